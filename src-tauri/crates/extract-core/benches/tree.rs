@@ -0,0 +1,44 @@
+//! 构建条目树的基准：`cargo bench -p extract-core --bench tree`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use extract_core::{build_entry_tree, ArchiveEntry};
+
+/// 模拟 node_modules 式的深层目录：每个包若干层子目录，只记录文件条目
+fn entries(packages: usize) -> Vec<ArchiveEntry> {
+    let mut entries = Vec::new();
+    for p in 0..packages {
+        for d in 0..4 {
+            for f in 0..10 {
+                let path = format!("node_modules/pkg{p}/lib/sub{d}/file{f}.js");
+                let (parent, name) = path.rsplit_once('/').unwrap();
+                entries.push(ArchiveEntry {
+                    name: name.to_string(),
+                    parent_path: Some(format!("{parent}/")),
+                    path,
+                    size: 1024,
+                    is_dir: false,
+                    modified: None,
+                    is_archive: false,
+                });
+            }
+        }
+    }
+    entries
+}
+
+fn bench_build_tree(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_entry_tree");
+    group.sample_size(10);
+    for packages in [250, 2500] {
+        let input = entries(packages);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(input.len()),
+            &input,
+            |b, input| b.iter(|| build_entry_tree(black_box(input.clone()))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_build_tree);
+criterion_main!(benches);
@@ -0,0 +1,418 @@
+//! 比较压缩包与目录
+//!
+//! 逐个读取压缩包条目并计算 SHA-256，与目录中对应的文件比较，用于确认解压出的目录与压缩包仍然一致。
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::convert::safe_name;
+use crate::restore::safe_join;
+use crate::writer::EntryKind;
+use crate::{open_handler, ArchiveError, ResultR};
+
+/// 修改时间的容差（秒），zip 的 DOS 时间精度为 2 秒
+pub(crate) const MTIME_TOLERANCE: i64 = 2;
+
+/// 比较结果，各列表按路径排序
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct CompareReport {
+    /// 已比较的条目数
+    pub compared: u64,
+    /// 压缩包中有、目录中没有
+    pub missing: Vec<String>,
+    /// 目录中有、压缩包中没有；不在压缩包中的目录只列出目录本身
+    pub extra: Vec<String>,
+    /// 类型、大小、内容或链接目标不同
+    pub modified: Vec<Modified>,
+    /// 内容相同，修改时间或权限位不同
+    pub metadata_changed: Vec<MetadataChange>,
+}
+
+impl CompareReport {
+    /// 目录与压缩包完全一致
+    pub fn is_same(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.modified.is_empty()
+            && self.metadata_changed.is_empty()
+    }
+}
+
+/// 内容不同的条目
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Modified {
+    pub path: String,
+    pub change: Change,
+}
+
+/// 不同之处，`archive` 为压缩包中的值，`disk` 为目录中的值
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Change {
+    /// 类型不同：`file`、`dir`、`symlink` 或 `other`
+    Kind {
+        archive: String,
+        disk: String,
+    },
+    Size {
+        archive: u64,
+        disk: u64,
+    },
+    /// 大小相同、内容不同，值为十六进制的 SHA-256
+    Content {
+        archive: String,
+        disk: String,
+    },
+    LinkTarget {
+        archive: String,
+        disk: String,
+    },
+    /// 读取目录中的文件失败
+    Unreadable {
+        error: String,
+    },
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Kind { archive, disk } => write!(f, "类型不同 {} -> {}", archive, disk),
+            Change::Size { archive, disk } => write!(f, "大小不同 {} -> {}", archive, disk),
+            Change::Content { archive, disk } => {
+                write!(f, "内容不同 sha256 {} -> {}", archive, disk)
+            }
+            Change::LinkTarget { archive, disk } => {
+                write!(f, "链接目标不同 {} -> {}", archive, disk)
+            }
+            Change::Unreadable { error } => write!(f, "无法读取: {}", error),
+        }
+    }
+}
+
+/// 元数据不同的条目，各字段为 `[压缩包中的值, 目录中的值]`，相同时为空
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct MetadataChange {
+    pub path: String,
+    /// 修改时间（unix 秒），只比较文件，压缩包未记录时间时不比较
+    pub mtime: Option<[i64; 2]>,
+    /// 权限位，只在 unix 上比较
+    pub mode: Option<[u32; 2]>,
+}
+
+/// 计算 `reader` 全部内容的 SHA-256
+pub(crate) fn sha256(reader: &mut dyn Read) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+pub(crate) fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn kind_name(kind: &EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Dir => "dir",
+        EntryKind::File => "file",
+        EntryKind::Symlink(_) => "symlink",
+    }
+}
+
+/// 比较 `archive`（可以是嵌套压缩包的虚拟路径）与 `directory`
+///
+/// 文件先比较大小，大小相同时比较 SHA-256；符号链接比较链接目标，不跟随链接。内容一致的条目再比较
+/// 修改时间与权限位（去掉 setuid 等特殊位），目录的修改时间随内容变化而只比较权限位，符号链接不比较
+/// 元数据。条目路径去掉 `./` 前缀后比较与报告，`.` 本身、不安全的路径与重复的条目被跳过。
+pub fn compare_archive(
+    archive: &str,
+    password: String,
+    directory: &Path,
+) -> ResultR<CompareReport> {
+    if !directory.is_dir() {
+        return Err(ArchiveError::MsgError(format!(
+            "目录不存在: {}",
+            directory.display()
+        )));
+    }
+    let mut handler = open_handler(archive, password)?;
+    let mut report = CompareReport::default();
+    // 压缩包中的路径及其上级目录
+    let mut seen = HashSet::new();
+    handler.for_each_entry(&mut |entry, data| {
+        // `./`、`./a.txt` 之类的名称按 `a.txt` 处理
+        let name = match safe_name(&entry.path) {
+            Some(name) => name,
+            // 只指向根目录的条目没有可比较的内容
+            None if !entry.path.split('/').any(|part| part == "..") => return Ok(true),
+            None => {
+                log::warn!("跳过不安全的路径: {}", entry.path);
+                return Ok(true);
+            }
+        };
+        let Some(path) = safe_join(directory, &name) else {
+            log::warn!("跳过不安全的路径: {}", entry.path);
+            return Ok(true);
+        };
+        if !seen.insert(name.clone()) {
+            log::warn!("跳过重复的条目: {}", entry.path);
+            return Ok(true);
+        }
+        let mut parent = name.as_str();
+        while let Some(i) = parent.rfind('/') {
+            parent = &parent[..i];
+            seen.insert(parent.to_string());
+        }
+        report.compared += 1;
+
+        let meta = match fs::symlink_metadata(&path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                report.missing.push(name.clone());
+                return Ok(true);
+            }
+            Err(e) => {
+                report.modified.push(Modified {
+                    path: name.clone(),
+                    change: Change::Unreadable {
+                        error: e.to_string(),
+                    },
+                });
+                return Ok(true);
+            }
+        };
+        let disk_kind = match meta.file_type() {
+            t if t.is_symlink() => "symlink",
+            t if t.is_dir() => "dir",
+            t if t.is_file() => "file",
+            _ => "other",
+        };
+        let change = if kind_name(&entry.kind) != disk_kind {
+            Some(Change::Kind {
+                archive: kind_name(&entry.kind).to_string(),
+                disk: disk_kind.to_string(),
+            })
+        } else {
+            match &entry.kind {
+                EntryKind::Dir => None,
+                EntryKind::Symlink(target) => {
+                    let disk = fs::read_link(&path)
+                        .map(|t| t.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    (disk != *target).then(|| Change::LinkTarget {
+                        archive: target.clone(),
+                        disk,
+                    })
+                }
+                EntryKind::File if meta.len() != entry.size => Some(Change::Size {
+                    archive: entry.size,
+                    disk: meta.len(),
+                }),
+                EntryKind::File => {
+                    let expected = sha256(&mut data.take(entry.size))
+                        .map_err(|e| ArchiveError::MsgError(format!("读取条目失败: {}", e)))?;
+                    match File::open(&path).and_then(|mut f| sha256(&mut f)) {
+                        Ok(actual) if actual == expected => None,
+                        Ok(actual) => Some(Change::Content {
+                            archive: hex(&expected),
+                            disk: hex(&actual),
+                        }),
+                        Err(e) => Some(Change::Unreadable {
+                            error: e.to_string(),
+                        }),
+                    }
+                }
+            }
+        };
+        if let Some(change) = change {
+            report.modified.push(Modified {
+                path: name.clone(),
+                change,
+            });
+            return Ok(true);
+        }
+        if matches!(entry.kind, EntryKind::Symlink(_)) {
+            return Ok(true);
+        }
+
+        let disk_mtime = filetime::FileTime::from_last_modification_time(&meta).unix_seconds();
+        let mtime = (entry.kind == EntryKind::File
+            && entry.attrs.mtime != 0
+            && (entry.attrs.mtime - disk_mtime).abs() >= MTIME_TOLERANCE)
+            .then_some([entry.attrs.mtime, disk_mtime]);
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            let (mode, disk_mode) = (entry.attrs.mode & 0o777, meta.permissions().mode() & 0o777);
+            (mode != disk_mode).then_some([mode, disk_mode])
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+        if mtime.is_some() || mode.is_some() {
+            report.metadata_changed.push(MetadataChange {
+                path: name.clone(),
+                mtime,
+                mode,
+            });
+        }
+        Ok(true)
+    })?;
+
+    extra_files(directory, "", &seen, &mut report.extra)
+        .map_err(|e| ArchiveError::MsgError(format!("读取目录失败: {}", e)))?;
+    report.missing.sort();
+    report.extra.sort();
+    report.modified.sort_by(|a, b| a.path.cmp(&b.path));
+    report.metadata_changed.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+/// 收集 `dir` 中不在 `seen` 里的路径，不跟随符号链接
+fn extra_files(
+    dir: &Path,
+    prefix: &str,
+    seen: &HashSet<String>,
+    extra: &mut Vec<String>,
+) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let name = format!("{}{}", prefix, item.file_name().to_string_lossy());
+        if !seen.contains(&name) {
+            extra.push(name);
+        } else if item.file_type()?.is_dir() {
+            extra_files(&item.path(), &format!("{}/", name), seen, extra)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::CreateOptions;
+    use crate::testutil::{temp_dir, write_raw_tar};
+    use crate::writer::{create_writer, EntryAttrs};
+    use crate::{ArchiveFormat, ExtractOptions};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_compare_archive() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let attrs = |mode| EntryAttrs {
+            mtime: 1_600_000_000,
+            mode,
+            uid: 0,
+            gid: 0,
+        };
+        let archive = dir.join("a.tar");
+        let mut writer = create_writer(
+            File::create(&archive).unwrap(),
+            ArchiveFormat::Tar,
+            &CreateOptions::default(),
+        )
+        .unwrap();
+        writer.add_dir("d", &attrs(0o755)).unwrap();
+        for (name, data) in [
+            ("d/same.txt", &b"same"[..]),
+            ("d/edited.txt", b"before"),
+            ("d/grown.txt", b"short"),
+            ("d/gone.txt", b"gone"),
+            ("d/touched.txt", b"touched"),
+        ] {
+            writer
+                .add_file(name, &attrs(0o644), data.len() as u64, &mut &data[..])
+                .unwrap();
+        }
+        writer
+            .add_symlink("d/link", &attrs(0o777), "same.txt")
+            .unwrap();
+        writer.finish().unwrap();
+
+        let out = dir.join("out");
+        let mut handler = crate::create_handler(&archive, ArchiveFormat::Tar, String::new());
+        handler.extract(&out, &ExtractOptions::default()).unwrap();
+        let archive = archive.to_string_lossy().to_string();
+        let report = compare_archive(&archive, String::new(), &out).unwrap();
+        assert!(report.is_same(), "{:?}", report);
+        assert_eq!(report.compared, 7);
+
+        let path = |name: &str| -> PathBuf { out.join(name) };
+        fs::write(path("d/edited.txt"), b"after!").unwrap();
+        fs::write(path("d/grown.txt"), b"longer").unwrap();
+        fs::remove_file(path("d/gone.txt")).unwrap();
+        fs::create_dir_all(path("d/new/deep")).unwrap();
+        fs::write(path("d/new/deep/x"), b"x").unwrap();
+        fs::write(path("top.txt"), b"top").unwrap();
+        filetime::set_file_mtime(
+            path("d/touched.txt"),
+            filetime::FileTime::from_unix_time(1_700_000_000, 0),
+        )
+        .unwrap();
+        fs::remove_file(path("d/link")).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("edited.txt", path("d/link")).unwrap();
+
+        let report = compare_archive(&archive, String::new(), &out).unwrap();
+        assert_eq!(report.missing, ["d/gone.txt"]);
+        assert_eq!(report.extra, ["d/new", "top.txt"]);
+        let changes: Vec<_> = report
+            .modified
+            .iter()
+            .map(|m| (m.path.as_str(), &m.change))
+            .collect();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].0, "d/edited.txt");
+        assert!(matches!(changes[0].1, Change::Content { .. }));
+        assert_eq!(
+            changes[1],
+            (
+                "d/grown.txt",
+                &Change::Size {
+                    archive: 5,
+                    disk: 6
+                }
+            )
+        );
+        assert_eq!(changes[2].0, "d/link");
+        assert_eq!(
+            report.metadata_changed,
+            [MetadataChange {
+                path: String::from("d/touched.txt"),
+                mtime: Some([1_600_000_000, 1_700_000_000]),
+                mode: None,
+            }]
+        );
+
+        let result = compare_archive(&archive, String::new(), &dir.join("none"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_compare_dot_prefixed_tar() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let archive = dir.join("a.tar");
+        write_raw_tar(
+            &archive,
+            &[("./", None), ("./d/", None), ("./d/a.txt", Some(b"a"))],
+        );
+
+        let out = dir.join("out");
+        let mut handler = crate::create_handler(&archive, ArchiveFormat::Tar, String::new());
+        handler.extract(&out, &ExtractOptions::default()).unwrap();
+        let archive = archive.to_string_lossy().to_string();
+        let report = compare_archive(&archive, String::new(), &out).unwrap();
+        assert!(report.is_same(), "{:?}", report);
+        assert_eq!(report.compared, 2);
+
+        fs::remove_file(out.join("d/a.txt")).unwrap();
+        let report = compare_archive(&archive, String::new(), &out).unwrap();
+        assert_eq!(report.missing, ["d/a.txt"]);
+        assert!(report.extra.is_empty());
+    }
+}
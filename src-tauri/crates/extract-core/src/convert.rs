@@ -0,0 +1,344 @@
+//! 格式转换
+//!
+//! 通过 [`ArchiveHandler::for_each_entry`](crate::ArchiveHandler::for_each_entry) 逐个读取源压缩包的条目，
+//! 边解压边写入 [`ArchiveWriter`](crate::writer::ArchiveWriter)，不把内容展开到磁盘。
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
+
+use crate::options::CreateOptions;
+use crate::writer::{self, CreateProgress, EntryAttrs, EntryKind, ProgressReader};
+use crate::{open_handler, ArchiveFormat, ResultR};
+
+/// 流式读取到的条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    /// 压缩包中的路径，使用 `/` 分隔，目录不带结尾的 `/`
+    pub path: String,
+    pub kind: EntryKind,
+    /// 压缩包未记录的属性取默认值：时间为 0，权限位为 0o644、0o755 或 0o777，属主为 0
+    pub attrs: EntryAttrs,
+    /// 文件内容的大小，目录与符号链接为 0
+    pub size: u64,
+}
+
+impl StreamEntry {
+    /// `mode` 可以带有文件类型位，写入前去掉
+    pub(crate) fn new(
+        path: &str,
+        kind: EntryKind,
+        size: u64,
+        mtime: Option<i64>,
+        mode: Option<u32>,
+    ) -> Self {
+        let default_mode = match kind {
+            EntryKind::Dir => 0o755,
+            EntryKind::File => 0o644,
+            EntryKind::Symlink(_) => 0o777,
+        };
+        StreamEntry {
+            path: path.trim_end_matches('/').to_string(),
+            size: match kind {
+                EntryKind::File => size,
+                _ => 0,
+            },
+            kind,
+            attrs: EntryAttrs {
+                mtime: mtime.unwrap_or(0),
+                mode: mode.map_or(default_mode, |m| m & 0o7777),
+                uid: 0,
+                gid: 0,
+            },
+        }
+    }
+}
+
+/// 把 `input`（可以是嵌套压缩包的虚拟路径）转换为 `format` 格式的 `output`
+///
+/// 条目按源压缩包中的顺序写入，保留路径、修改时间、权限位与符号链接；`password` 用于打开源压缩包，
+/// 输出是否加密由 `options.encryption` 决定。硬链接等写入器不支持的条目、不安全的路径与重复的条目被跳过。
+/// 可重复构建时去掉与主机相关的属性，但不重新排序。先写入同目录下的临时文件，成功后再替换 `output`；
+/// tar 类格式事先统计条目需要完整解压一遍，进度中的总数为 0。
+pub fn convert_archive(
+    input: &str,
+    password: String,
+    output: &Path,
+    format: ArchiveFormat,
+    options: &CreateOptions,
+    mut progress: impl FnMut(&CreateProgress),
+) -> ResultR<()> {
+    let mut handler = open_handler(input, password)?;
+    let mut state = CreateProgress::default();
+    if !matches!(
+        ArchiveFormat::from_path(Path::new(input)),
+        ArchiveFormat::Tar
+            | ArchiveFormat::TarGz
+            | ArchiveFormat::TarBz2
+            | ArchiveFormat::TarXz
+            | ArchiveFormat::TarZst
+            | ArchiveFormat::Gz
+            | ArchiveFormat::Bz2
+    ) {
+        for entry in handler.entries()? {
+            let entry = entry?;
+            state.entries_total += 1;
+            state.bytes_total += entry.size;
+        }
+    }
+    let epoch = match options.deterministic {
+        true => Some(writer::source_date_epoch(options)?),
+        false => None,
+    };
+
+    writer::write_output(output, format, options, |mut writer| {
+        let mut written = HashSet::new();
+        handler.for_each_entry(&mut |entry, data| {
+            let Some(name) = safe_name(&entry.path) else {
+                log::warn!("跳过不安全的路径: {}", entry.path);
+                return Ok(true);
+            };
+            if !written.insert(name.clone()) {
+                log::warn!("跳过重复的条目: {}", name);
+                return Ok(true);
+            }
+            let mut attrs = entry.attrs;
+            if let Some(mtime) = epoch {
+                attrs.normalize(&entry.kind, mtime);
+            }
+            state.entry = name.clone();
+            match &entry.kind {
+                EntryKind::Dir => writer.add_dir(&name, &attrs)?,
+                EntryKind::Symlink(target) => writer.add_symlink(&name, &attrs, target)?,
+                EntryKind::File => {
+                    let reported = state.bytes_done;
+                    let mut data = ProgressReader {
+                        inner: data.take(entry.size),
+                        reported,
+                        state: &mut state,
+                        progress: &mut progress,
+                    };
+                    writer.add_file(&name, &attrs, entry.size, &mut data)?
+                }
+            }
+            state.entries_done += 1;
+            progress(&state);
+            Ok(true)
+        })?;
+        writer.finish()
+    })
+}
+
+/// 去掉开头的 `/` 与路径中的 `.`，含有 `..` 的路径返回 `None`
+pub(crate) fn safe_name(path: &str) -> Option<String> {
+    let parts: Vec<&str> = path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    if parts.is_empty() || parts.contains(&"..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Encryption;
+    use crate::testutil::temp_dir;
+    use crate::writer::create_writer;
+    use crate::{create_handler, ArchiveError};
+    use std::fs::File;
+    use zeroize::Zeroizing;
+
+    fn read_all(path: &Path, password: &str) -> Vec<(StreamEntry, Vec<u8>)> {
+        let mut handler =
+            create_handler(path, ArchiveFormat::from_path(path), password.to_string());
+        let mut items = Vec::new();
+        handler
+            .for_each_entry(&mut |entry, data| {
+                let mut content = Vec::new();
+                data.read_to_end(&mut content).unwrap();
+                let mut entry = entry.clone();
+                (entry.attrs.uid, entry.attrs.gid) = (0, 0);
+                items.push((entry, content));
+                Ok(true)
+            })
+            .unwrap();
+        // 7z 把没有数据的条目放在最后
+        items.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+        items
+    }
+
+    #[test]
+    fn test_convert_archive() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let attrs = |mtime, mode| EntryAttrs {
+            mtime,
+            mode,
+            uid: 0,
+            gid: 0,
+        };
+
+        let source = dir.join("src.zip");
+        let mut writer = create_writer(
+            File::create(&source).unwrap(),
+            ArchiveFormat::Zip,
+            &CreateOptions::default(),
+        )
+        .unwrap();
+        writer.add_dir("d", &attrs(1_600_000_000, 0o755)).unwrap();
+        writer
+            .add_file(
+                "d/a.txt",
+                &attrs(1_600_000_100, 0o640),
+                5,
+                &mut &b"hello"[..],
+            )
+            .unwrap();
+        writer
+            .add_file(
+                "d/run.sh",
+                &attrs(1_600_000_200, 0o755),
+                9,
+                &mut &b"#!/bin/sh"[..],
+            )
+            .unwrap();
+        writer
+            .add_symlink("d/link", &attrs(1_600_000_300, 0o777), "a.txt")
+            .unwrap();
+        writer.finish().unwrap();
+        let expected = read_all(&source, "");
+        assert_eq!(expected.len(), 4);
+        assert_eq!(
+            expected[2].0.kind,
+            EntryKind::Symlink(String::from("a.txt"))
+        );
+        assert_eq!(expected[1].0.attrs, attrs(1_600_000_100, 0o640));
+
+        let encrypted = CreateOptions {
+            encryption: Some(Encryption {
+                password: Zeroizing::new(String::from("secret")),
+                encrypt_header: true,
+            }),
+            ..Default::default()
+        };
+        // 逐级转换，每一步都与源压缩包一致，7z 与 zip 重新加密
+        let mut input = (source, "");
+        for (name, options, password) in [
+            ("a.tar.zst", CreateOptions::default(), ""),
+            ("b.7z", encrypted.clone(), "secret"),
+            ("c.zip", encrypted, "secret"),
+            ("d.tar", CreateOptions::default(), ""),
+        ] {
+            let output = dir.join(name);
+            let format = ArchiveFormat::from_path(&output);
+            let mut last = CreateProgress::default();
+            convert_archive(
+                &input.0.to_string_lossy(),
+                input.1.to_string(),
+                &output,
+                format,
+                &options,
+                |p| last = p.clone(),
+            )
+            .unwrap();
+            assert_eq!(last.entries_done, 4, "{}", name);
+            assert_eq!(last.bytes_done, 14, "{}", name);
+            assert_eq!(read_all(&output, password), expected, "{}", name);
+            input = (output, password);
+        }
+
+        let secret = dir.join("b.7z").to_string_lossy().to_string();
+        let result = convert_archive(
+            &secret,
+            String::new(),
+            &dir.join("e.zip"),
+            ArchiveFormat::Zip,
+            &CreateOptions::default(),
+            |_| {},
+        );
+        assert!(matches!(result, Err(ArchiveError::MsgError(e)) if e == "密码错误"));
+        assert!(!dir.join("e.zip").exists());
+
+        // 可重复构建时去掉主机相关的属性
+        let options = CreateOptions {
+            deterministic: true,
+            source_date_epoch: Some(1_700_000_000),
+            ..Default::default()
+        };
+        let output = dir.join("f.tar");
+        let input = dir.join("c.zip").to_string_lossy().to_string();
+        convert_archive(
+            &input,
+            String::from("secret"),
+            &output,
+            ArchiveFormat::Tar,
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        let modes: Vec<_> = read_all(&output, "")
+            .iter()
+            .map(|(entry, _)| (entry.attrs.mtime, entry.attrs.mode))
+            .collect();
+        assert_eq!(
+            modes,
+            [
+                (1_700_000_000, 0o755),
+                (1_700_000_000, 0o644),
+                (1_700_000_000, 0o777),
+                (1_700_000_000, 0o755)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_tar_hardlink() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let source = dir.join("src.tar");
+        let mut builder = tar::Builder::new(File::create(&source).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "./a.txt", &b"hello"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        header.set_mode(0o644);
+        builder
+            .append_link(&mut header, "b.txt", "./a.txt")
+            .unwrap();
+        builder
+            .append_link(&mut header, "gone.txt", "missing.txt")
+            .unwrap();
+        builder.into_inner().unwrap();
+
+        // 硬链接作为内容相同的文件写出，目标不存在的被跳过
+        let output = dir.join("out.zip");
+        convert_archive(
+            &source.to_string_lossy(),
+            String::new(),
+            &output,
+            ArchiveFormat::Zip,
+            &CreateOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        let items: Vec<_> = read_all(&output, "")
+            .into_iter()
+            .map(|(entry, data)| (entry.path, entry.kind, data))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (String::from("a.txt"), EntryKind::File, b"hello".to_vec()),
+                (String::from("b.txt"), EntryKind::File, b"hello".to_vec()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,528 @@
+//! 比较两个压缩包
+//!
+//! 分别读取两个压缩包的全部条目并计算 CRC32 与 SHA-256，按路径对齐后分为新增、删除、内容不同与
+//! 只有元数据不同，结果组织为与文件列表相同的 [`TreeNode`] 树。两个压缩包的格式可以不同。
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::compare::{hex, kind_name, MTIME_TOLERANCE};
+use crate::convert::safe_name;
+use crate::options::DiffOptions;
+use crate::plustree::{natural_cmp, TreeNode};
+use crate::preview::decode_entry_text;
+use crate::writer::{EntryAttrs, EntryKind};
+use crate::{entry_name, open_handler, parent_dir, ArchiveError, ResultR};
+
+/// 条目的比较结果
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    #[default]
+    Unchanged,
+    /// 只在新压缩包中
+    Added,
+    /// 只在旧压缩包中
+    Removed,
+    /// 类型、大小、内容或链接目标不同
+    Changed,
+    /// 内容相同，修改时间或权限位不同
+    Metadata,
+}
+
+/// 条目在一个压缩包中的信息
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct EntryDigest {
+    /// `file`、`dir` 或 `symlink`
+    pub kind: String,
+    pub size: u64,
+    /// 目录与符号链接为 0
+    pub crc32: u32,
+    /// 十六进制的 SHA-256，目录与符号链接为空
+    pub sha256: String,
+    pub mtime: i64,
+    pub mode: u32,
+    pub link_target: Option<String>,
+}
+
+/// 差异树的节点
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct DiffEntry {
+    pub name: String,
+    /// 目录以 `/` 结尾
+    pub path: String,
+    /// 上级目录，以 `/` 结尾，顶层为 `/`
+    pub parent_path: String,
+    pub is_dir: bool,
+    pub status: DiffStatus,
+    /// 不同的属性：`kind`、`size`、`crc32`、`sha256`、`link_target`、`mtime`、`mode`
+    pub changes: Vec<String>,
+    /// 在旧压缩包中的信息，不存在或是未单独记录的上级目录时为空
+    pub old: Option<EntryDigest>,
+    pub new: Option<EntryDigest>,
+    /// 目录下有变化的条目
+    pub changed_below: bool,
+    /// 内容不同的文本条目的统一格式 diff，需要开启 [`DiffOptions::unified`]
+    pub unified: Option<String>,
+}
+
+/// 两个压缩包的差异
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ArchiveDiff {
+    pub added: u64,
+    pub removed: u64,
+    pub changed: u64,
+    pub metadata: u64,
+    pub unchanged: u64,
+    /// 同级条目目录在前，再按名称自然排序
+    pub tree: Vec<TreeNode<DiffEntry>>,
+}
+
+impl ArchiveDiff {
+    /// 两个压缩包的内容与元数据完全相同
+    pub fn is_same(&self) -> bool {
+        self.added + self.removed + self.changed + self.metadata == 0
+    }
+}
+
+/// 读取到的条目
+struct Side {
+    kind: EntryKind,
+    size: u64,
+    crc32: u32,
+    sha256: [u8; 32],
+    attrs: EntryAttrs,
+    /// 未单独记录、由子条目补齐的目录
+    implicit: bool,
+    text: Option<String>,
+}
+
+impl Side {
+    fn digest(&self) -> Option<EntryDigest> {
+        if self.implicit {
+            return None;
+        }
+        let file = self.kind == EntryKind::File;
+        Some(EntryDigest {
+            kind: kind_name(&self.kind).to_string(),
+            size: self.size,
+            crc32: self.crc32,
+            sha256: if file {
+                hex(&self.sha256)
+            } else {
+                String::new()
+            },
+            mtime: self.attrs.mtime,
+            mode: self.attrs.mode,
+            link_target: match &self.kind {
+                EntryKind::Symlink(target) => Some(target.clone()),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// 同时计算 CRC32 与 SHA-256
+#[derive(Default)]
+struct Hashes {
+    crc32: crc32fast::Hasher,
+    sha256: Sha256,
+}
+
+impl Write for Hashes {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc32.update(buf);
+        self.sha256.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 比较 `old` 与 `new`（都可以是嵌套压缩包的虚拟路径）
+///
+/// 重复的条目以第一次出现的为准；未单独记录的上级目录被补齐，与显式记录的目录视为相同。
+/// 修改时间允许 2 秒误差，任一方未记录时间（为 0）时不比较。
+pub fn diff_archives(
+    old: &str,
+    old_password: String,
+    new: &str,
+    new_password: String,
+    options: &DiffOptions,
+) -> ResultR<ArchiveDiff> {
+    let old = read_side(old, old_password, options)?;
+    let new = read_side(new, new_password, options)?;
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut diff = ArchiveDiff::default();
+    let mut entries = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (a, b) = (old.get(path), new.get(path));
+        let mut changes = Vec::new();
+        let status = match (a, b) {
+            (Some(_), None) => DiffStatus::Removed,
+            (None, Some(_)) => DiffStatus::Added,
+            (Some(a), Some(b)) => {
+                changes = content_changes(a, b);
+                if changes.is_empty() && !a.implicit && !b.implicit {
+                    changes = metadata_changes(&a.attrs, &b.attrs);
+                    match changes.is_empty() {
+                        true => DiffStatus::Unchanged,
+                        false => DiffStatus::Metadata,
+                    }
+                } else if changes.is_empty() {
+                    DiffStatus::Unchanged
+                } else {
+                    DiffStatus::Changed
+                }
+            }
+            (None, None) => continue,
+        };
+        match status {
+            DiffStatus::Unchanged => diff.unchanged += 1,
+            DiffStatus::Added => diff.added += 1,
+            DiffStatus::Removed => diff.removed += 1,
+            DiffStatus::Changed => diff.changed += 1,
+            DiffStatus::Metadata => diff.metadata += 1,
+        }
+        let unified = match (a, b) {
+            (Some(a), Some(b)) if status == DiffStatus::Changed => {
+                unified_diff(path, a, b, options)
+            }
+            _ => None,
+        };
+        let is_dir = b.or(a).is_some_and(|side| side.kind == EntryKind::Dir);
+        let path = match is_dir {
+            true => format!("{}/", path),
+            false => path.clone(),
+        };
+        entries.push(DiffEntry {
+            name: entry_name(&path).to_string(),
+            parent_path: parent_dir(&path),
+            path,
+            is_dir,
+            status,
+            changes: changes.into_iter().map(String::from).collect(),
+            old: a.and_then(Side::digest),
+            new: b.and_then(Side::digest),
+            changed_below: false,
+            unified,
+        });
+    }
+
+    // 把变化标记到所有上级目录
+    let index: HashMap<String, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.path.clone(), i))
+        .collect();
+    for i in 0..entries.len() {
+        if entries[i].status == DiffStatus::Unchanged {
+            continue;
+        }
+        let mut parent = entries[i].parent_path.clone();
+        while let Some(&p) = index.get(&parent) {
+            if std::mem::replace(&mut entries[p].changed_below, true) {
+                break;
+            }
+            parent = entries[p].parent_path.clone();
+        }
+    }
+
+    diff.tree = TreeNode::build_sorted_tree(
+        entries,
+        String::from("/"),
+        |e| e.path.clone(),
+        |e| e.parent_path.clone(),
+        |a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| natural_cmp(&a.name, &b.name))
+        },
+    );
+    Ok(diff)
+}
+
+/// 读取压缩包的全部条目，键为去掉 `./` 前缀与结尾 `/` 的路径，并补齐未单独记录的上级目录
+///
+/// `.` 本身与不安全的路径被跳过。
+fn read_side(
+    archive: &str,
+    password: String,
+    options: &DiffOptions,
+) -> ResultR<BTreeMap<String, Side>> {
+    let mut handler = open_handler(archive, password)?;
+    let mut sides = BTreeMap::new();
+    handler.for_each_entry(&mut |entry, data| {
+        let Some(name) = safe_name(&entry.path) else {
+            if entry.path.split('/').any(|part| part == "..") {
+                log::warn!("跳过不安全的路径: {}", entry.path);
+            }
+            return Ok(true);
+        };
+        if sides.contains_key(&name) {
+            log::warn!("跳过重复的条目: {}", entry.path);
+            return Ok(true);
+        }
+        let mut hashes = Hashes::default();
+        let mut text = None;
+        if entry.kind == EntryKind::File {
+            let mut data = data.take(entry.size);
+            let read = if options.unified && entry.size <= options.text_limit {
+                let mut content = Vec::with_capacity(entry.size as usize);
+                data.read_to_end(&mut content).map(|_| {
+                    hashes.write_all(&content).unwrap_or_default();
+                    text = decode_entry_text(&entry.path, &content);
+                })
+            } else {
+                io::copy(&mut data, &mut hashes).map(|_| ())
+            };
+            read.map_err(|e| ArchiveError::MsgError(format!("读取条目失败: {}", e)))?;
+        }
+        sides.insert(
+            name,
+            Side {
+                kind: entry.kind.clone(),
+                size: entry.size,
+                crc32: match entry.kind {
+                    EntryKind::File => hashes.crc32.finalize(),
+                    _ => 0,
+                },
+                sha256: hashes.sha256.finalize().into(),
+                attrs: entry.attrs,
+                implicit: false,
+                text,
+            },
+        );
+        Ok(true)
+    })?;
+
+    let mut dirs = Vec::new();
+    for path in sides.keys() {
+        let mut parent = path.as_str();
+        while let Some(i) = parent.rfind('/') {
+            parent = &parent[..i];
+            if sides.contains_key(parent) {
+                break;
+            }
+            dirs.push(parent.to_string());
+        }
+    }
+    for dir in dirs {
+        sides.entry(dir).or_insert_with(|| Side {
+            kind: EntryKind::Dir,
+            size: 0,
+            crc32: 0,
+            sha256: [0; 32],
+            attrs: EntryAttrs {
+                mtime: 0,
+                mode: 0o755,
+                uid: 0,
+                gid: 0,
+            },
+            implicit: true,
+            text: None,
+        });
+    }
+    Ok(sides)
+}
+
+/// 类型、大小与内容的不同
+fn content_changes(a: &Side, b: &Side) -> Vec<&'static str> {
+    if kind_name(&a.kind) != kind_name(&b.kind) {
+        return vec!["kind"];
+    }
+    match (&a.kind, &b.kind) {
+        (EntryKind::Symlink(x), EntryKind::Symlink(y)) if x != y => vec!["link_target"],
+        (EntryKind::File, EntryKind::File) => [
+            ("size", a.size != b.size),
+            ("crc32", a.crc32 != b.crc32),
+            ("sha256", a.sha256 != b.sha256),
+        ]
+        .into_iter()
+        .filter_map(|(name, differs)| differs.then_some(name))
+        .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn metadata_changes(a: &EntryAttrs, b: &EntryAttrs) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+    if a.mtime != 0 && b.mtime != 0 && (a.mtime - b.mtime).abs() >= MTIME_TOLERANCE {
+        changes.push("mtime");
+    }
+    if a.mode & 0o7777 != b.mode & 0o7777 {
+        changes.push("mode");
+    }
+    changes
+}
+
+fn unified_diff(path: &str, a: &Side, b: &Side, options: &DiffOptions) -> Option<String> {
+    let (old, new) = (a.text.as_deref()?, b.text.as_deref()?);
+    let patch = diffy::DiffOptions::new()
+        .set_context_len(options.context)
+        .set_original_filename(format!("a/{}", path))
+        .set_modified_filename(format!("b/{}", path))
+        .create_patch(old, new)
+        .to_string();
+    Some(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::CreateOptions;
+    use crate::testutil::{temp_dir, write_raw_tar};
+    use crate::writer::create_writer;
+    use crate::ArchiveFormat;
+    use std::fs::File;
+
+    fn write(path: &std::path::Path, entries: &[(&str, Option<&str>, i64, u32)]) {
+        let mut writer = create_writer(
+            File::create(path).unwrap(),
+            ArchiveFormat::from_path(path),
+            &CreateOptions::default(),
+        )
+        .unwrap();
+        for &(name, data, mtime, mode) in entries {
+            let attrs = EntryAttrs {
+                mtime,
+                mode,
+                uid: 0,
+                gid: 0,
+            };
+            match data {
+                Some(data) => writer
+                    .add_file(name, &attrs, data.len() as u64, &mut data.as_bytes())
+                    .unwrap(),
+                None => writer.add_dir(name, &attrs).unwrap(),
+            }
+        }
+        writer.finish().unwrap();
+    }
+
+    fn flatten<'a>(nodes: &'a [TreeNode<DiffEntry>], out: &mut Vec<&'a DiffEntry>) {
+        for node in nodes {
+            out.push(node.item());
+            flatten(node.children(), out);
+        }
+    }
+
+    #[test]
+    fn test_diff_archives() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let t = 1_600_000_000;
+        // 旧版本的 zip 单独记录目录，新版本的 tar.gz 没有目录条目
+        let v1 = dir.join("v1.zip");
+        write(
+            &v1,
+            &[
+                ("app", None, t, 0o755),
+                ("app/main.txt", Some("one\ntwo\nthree\n"), t, 0o644),
+                ("app/same.txt", Some("same"), t, 0o644),
+                ("app/old.txt", Some("old"), t, 0o644),
+                ("app/run.sh", Some("#!/bin/sh"), t, 0o644),
+                ("notes.bin", Some("\0\x01\x02"), t, 0o644),
+            ],
+        );
+        let v2 = dir.join("v2.tar.gz");
+        write(
+            &v2,
+            &[
+                ("app/main.txt", Some("one\n2\nthree\n"), t, 0o644),
+                ("app/same.txt", Some("same"), t, 0o644),
+                ("app/run.sh", Some("#!/bin/sh"), t + 60, 0o755),
+                ("app/lib/new.txt", Some("new"), t, 0o644),
+                ("notes.bin", Some("\0\x01\x03"), t, 0o644),
+            ],
+        );
+
+        let options = DiffOptions {
+            unified: true,
+            ..Default::default()
+        };
+        let diff = diff_archives(
+            &v1.to_string_lossy(),
+            String::new(),
+            &v2.to_string_lossy(),
+            String::new(),
+            &options,
+        )
+        .unwrap();
+        assert!(!diff.is_same());
+        let mut entries = Vec::new();
+        flatten(&diff.tree, &mut entries);
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.status, e.changes.join(",")))
+            .collect();
+        use DiffStatus::*;
+        assert_eq!(
+            summary,
+            [
+                ("app/", Unchanged, String::new()),
+                ("app/lib/", Added, String::new()),
+                ("app/lib/new.txt", Added, String::new()),
+                ("app/main.txt", Changed, String::from("size,crc32,sha256")),
+                ("app/old.txt", Removed, String::new()),
+                ("app/run.sh", Metadata, String::from("mtime,mode")),
+                ("app/same.txt", Unchanged, String::new()),
+                ("notes.bin", Changed, String::from("crc32,sha256")),
+            ]
+        );
+        assert_eq!(
+            (diff.added, diff.removed, diff.changed, diff.metadata),
+            (2, 1, 2, 1)
+        );
+        assert!(entries[0].changed_below);
+        assert!(entries[0].old.is_some() && entries[0].new.is_none());
+        assert_eq!(
+            entries[3].unified.as_deref(),
+            Some(
+                "--- a/app/main.txt\n+++ b/app/main.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+            )
+        );
+        assert_eq!(entries[7].unified, None);
+        assert_eq!(entries[6].new.as_ref().unwrap().size, 4);
+
+        let same = diff_archives(
+            &v2.to_string_lossy(),
+            String::new(),
+            &v2.to_string_lossy(),
+            String::new(),
+            &DiffOptions::default(),
+        )
+        .unwrap();
+        assert!(same.is_same());
+        assert_eq!(same.unchanged, 7);
+    }
+
+    #[test]
+    fn test_diff_dot_prefixed_tar() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let zip = dir.join("a.zip");
+        write(&zip, &[("a.txt", Some("a"), 1_600_000_000, 0o644)]);
+        let tar = dir.join("a.tar");
+        write_raw_tar(&tar, &[("./", None), ("./a.txt", Some(b"a"))]);
+
+        let diff = diff_archives(
+            &zip.to_string_lossy(),
+            String::new(),
+            &tar.to_string_lossy(),
+            String::new(),
+            &DiffOptions::default(),
+        )
+        .unwrap();
+        assert!(diff.is_same(), "{:?}", diff);
+        assert_eq!(diff.unchanged, 1);
+    }
+}
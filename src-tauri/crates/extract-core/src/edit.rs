@@ -0,0 +1,887 @@
+//! 修改压缩包
+//!
+//! 改动先写入同目录下的临时文件，全部成功后再替换原文件，失败时原压缩包保持不变。
+//! zip 中未改动的条目原样复制，不重新压缩；tar 类边读边写；7z 解压后重新压缩。
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use sevenz_rust::{Password, SevenZMethod, SevenZReader};
+use zeroize::Zeroizing;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::options::{CompressionLevel, CompressionOptions, CreateOptions, Encryption};
+use crate::spool::LinkTargets;
+use crate::volume::VolumeFile;
+use crate::writer::{
+    self, ArchiveWriter, EntryAttrs, EntryKind, Source, TarArchiveWriter, TarEncoder,
+    ZipArchiveWriter,
+};
+use crate::{convert, sevenz_unix_mode, xzindex, ArchiveError, ArchiveFormat, ResultR, TarHandler};
+
+fn io_error(e: io::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+fn zip_error(e: zip::result::ZipError) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+fn sevenz_error(e: sevenz_rust::Error) -> ArchiveError {
+    if e.to_string().contains("Password") {
+        ArchiveError::MsgError(String::from("密码错误"))
+    } else {
+        ArchiveError::MsgError(e.to_string())
+    }
+}
+
+fn not_found(entry: &str) -> ArchiveError {
+    ArchiveError::MsgError(format!("条目不存在: {}", entry))
+}
+
+/// 对压缩包的一项修改，压缩包内的路径使用 `/` 分隔
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum EditOp {
+    /// 把磁盘上的文件或文件夹加入 `dest` 目录（为空时为根目录），替换同名的条目
+    Add {
+        source: PathBuf,
+        #[serde(default)]
+        dest: String,
+    },
+    /// 用 `source` 的内容替换文件条目
+    Replace { entry: String, source: PathBuf },
+    /// 删除条目，目录连同其中的全部条目
+    Delete { entry: String },
+    /// 重命名或移动条目，目录连同其中的全部条目；目标已存在时被覆盖
+    Rename { from: String, to: String },
+}
+
+/// 用 `source` 的内容替换压缩包中的文件条目，保留其余条目不变，见 [`edit_archive`]
+pub fn replace_entry(
+    archive: &Path,
+    format: ArchiveFormat,
+    entry: &str,
+    source: &Path,
+) -> ResultR<()> {
+    let op = EditOp::Replace {
+        entry: entry.to_string(),
+        source: source.to_path_buf(),
+    };
+    edit_archive(archive, format, "", &[op])
+}
+
+/// 按顺序对压缩包执行 `ops`，任一修改找不到条目时整体失败
+///
+/// 支持 zip、tar 类格式与 7z。zip 中未改动的条目原样复制（包括加密的条目），
+/// 新加入或替换的文件在 `password` 不为空时用它加密；7z 用 `password` 解压后重新压缩，
+/// 保留原来的加密与固实方式。
+pub fn edit_archive(
+    archive: &Path,
+    format: ArchiveFormat,
+    password: &str,
+    ops: &[EditOp],
+) -> ResultR<()> {
+    let mut plan = Plan::new(ops, archive)?;
+    let encryption = (!password.is_empty()).then(|| Encryption {
+        password: Zeroizing::new(password.to_string()),
+        encrypt_header: false,
+    });
+    match format {
+        ArchiveFormat::Zip => writer::replace_output(archive, None, |out| {
+            let options = CreateOptions {
+                encryption,
+                ..Default::default()
+            };
+            let file = edit_zip(archive, &mut plan, &options, out)?;
+            plan.finish().map(|_| file)
+        }),
+        ArchiveFormat::Tar
+        | ArchiveFormat::TarGz
+        | ArchiveFormat::TarXz
+        | ArchiveFormat::TarBz2
+        | ArchiveFormat::TarZst => writer::replace_output(archive, None, |out| {
+            let handler = TarHandler {
+                archive_path: archive.to_path_buf(),
+                archive_format: format,
+                index: None,
+            };
+            let compression = tar_compression(archive, format);
+            let writer = TarArchiveWriter {
+                builder: tar::Builder::new(TarEncoder::new(out, format, &compression)?),
+            };
+            let file = edit_tar(&handler, &mut plan, writer)?;
+            plan.finish().map(|_| file)
+        }),
+        ArchiveFormat::SevenZip => writer::replace_output(archive, None, |out| {
+            let file = edit_sevenz(archive, password, &mut plan, out)?;
+            plan.finish().map(|_| file)
+        }),
+        _ => Err(ArchiveError::MsgError(String::from("暂不支持修改该格式"))),
+    }
+}
+
+/// 去掉开头的 `./` 与两端的 `/`
+fn clean(path: &str) -> &str {
+    path.trim_start_matches("./").trim_matches('/')
+}
+
+/// `path` 是否为 `prefix` 本身或位于其中
+fn within(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn check_path(path: &str) -> ResultR<()> {
+    match clean(path) {
+        "" => Err(ArchiveError::MsgError(format!("无效的条目路径: {}", path))),
+        _ => Ok(()),
+    }
+}
+
+/// 修改计划：每个条目按名称依次经过各项修改，决定保留、改名、替换内容或删除
+struct Plan<'a> {
+    ops: &'a [EditOp],
+    /// 每项 `Add` 收集到的磁盘条目，其余修改为空
+    added: Vec<Vec<Source>>,
+    /// 各项修改是否找到了条目
+    matched: Vec<bool>,
+}
+
+/// 条目经过全部修改后的结果
+struct Fate {
+    name: String,
+    /// 替换内容的文件
+    source: Option<PathBuf>,
+}
+
+impl<'a> Plan<'a> {
+    fn new(ops: &'a [EditOp], archive: &Path) -> ResultR<Self> {
+        // 加入的文件夹包含压缩包自身时跳过它
+        let output = writer::absolute(archive);
+        let mut added = Vec::with_capacity(ops.len());
+        for op in ops {
+            let mut items = Vec::new();
+            match op {
+                EditOp::Add { source, dest } => {
+                    let name = match (clean(dest), writer::source_name(source)?) {
+                        ("", name) => name,
+                        (dest, name) => format!("{}/{}", dest, name),
+                    };
+                    writer::collect(source, name, output.as_deref(), &mut items)?;
+                }
+                EditOp::Replace { entry, .. } | EditOp::Delete { entry } => check_path(entry)?,
+                EditOp::Rename { from, to } => {
+                    check_path(from)?;
+                    check_path(to)?;
+                    if within(clean(to), clean(from)) {
+                        return Err(ArchiveError::MsgError(format!(
+                            "不能把 {} 移动到自身之中",
+                            from
+                        )));
+                    }
+                }
+            }
+            added.push(items);
+        }
+        Ok(Plan {
+            ops,
+            added,
+            matched: vec![false; ops.len()],
+        })
+    }
+
+    /// 从第 `start` 项修改开始处理名为 `name` 的条目，返回 `None` 表示删除
+    fn fate(&mut self, name: &str, is_file: bool, start: usize) -> Option<Fate> {
+        let mut name = clean(name).to_string();
+        let mut source = None;
+        for (i, op) in self.ops.iter().enumerate().skip(start) {
+            match op {
+                // 被新加入的同名条目替换
+                EditOp::Add { .. } => {
+                    if self.added[i].iter().any(|item| item.name == name) {
+                        return None;
+                    }
+                }
+                EditOp::Replace { entry, source: s } => {
+                    if is_file && name == clean(entry) {
+                        self.matched[i] = true;
+                        source = Some(s.clone());
+                    }
+                }
+                EditOp::Delete { entry } => {
+                    if within(&name, clean(entry)) {
+                        self.matched[i] = true;
+                        return None;
+                    }
+                }
+                EditOp::Rename { from, to } => {
+                    let (from, to) = (clean(from), clean(to));
+                    if within(&name, from) {
+                        self.matched[i] = true;
+                        name = format!("{}{}", to, &name[from.len()..]);
+                    } else if within(&name, to) {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(Fate { name, source })
+    }
+
+    /// 同 [`Plan::fate`]，但不记录修改是否找到了条目，用于查询硬链接目标的去向
+    fn peek(&mut self, name: &str) -> Option<Fate> {
+        let matched = self.matched.clone();
+        let fate = self.fate(name, true, 0);
+        self.matched = matched;
+        fate
+    }
+
+    /// 写入各项 `Add` 加入的条目，它们只受之后的修改影响
+    fn write_added(&mut self, writer: &mut dyn ArchiveWriter) -> ResultR<()> {
+        for i in 0..self.added.len() {
+            let items = std::mem::take(&mut self.added[i]);
+            for item in &items {
+                let is_file = matches!(item.kind, EntryKind::File);
+                let Some(fate) = self.fate(&item.name, is_file, i + 1) else {
+                    continue;
+                };
+                match fate.source {
+                    Some(source) => write_replacement(writer, &fate.name, item.attrs, &source)?,
+                    None => writer::write_source(writer, item, &fate.name, |file| file)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 确认每项修改都找到了条目
+    fn finish(&self) -> ResultR<()> {
+        for (op, matched) in self.ops.iter().zip(&self.matched) {
+            let entry = match op {
+                EditOp::Add { .. } => continue,
+                EditOp::Replace { entry, .. } | EditOp::Delete { entry } => entry,
+                EditOp::Rename { from, .. } => from,
+            };
+            if !matched {
+                return Err(not_found(entry));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 以 `source` 的内容写入文件条目，修改时间取自 `source`
+fn write_replacement(
+    writer: &mut dyn ArchiveWriter,
+    name: &str,
+    attrs: EntryAttrs,
+    source: &Path,
+) -> ResultR<()> {
+    let file = File::open(source).map_err(io_error)?;
+    let meta = file.metadata().map_err(io_error)?;
+    let attrs = EntryAttrs {
+        mtime: EntryAttrs::from_metadata(&meta).mtime,
+        ..attrs
+    };
+    writer.add_file(name, &attrs, meta.len(), &mut file.take(meta.len()))
+}
+
+fn edit_zip(
+    archive: &Path,
+    plan: &mut Plan,
+    options: &CreateOptions,
+    out: VolumeFile,
+) -> ResultR<VolumeFile> {
+    let mut archive = ZipArchive::new(File::open(archive).map_err(io_error)?).map_err(zip_error)?;
+    let mut writer = ZipArchiveWriter::new(ZipWriter::new(out), options);
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(zip_error)?;
+        // 与 entries 一致，按原始文件名匹配
+        let name = String::from_utf8(file.name_raw().to_vec()).unwrap_or_default();
+        let is_dir = file.is_dir();
+        let Some(fate) = plan.fate(&name, !is_dir, 0) else {
+            continue;
+        };
+        if let Some(source) = fate.source {
+            if file.encrypted() && options.encryption.is_none() {
+                return Err(ArchiveError::MsgError(String::from("密码错误")));
+            }
+            let attrs = EntryAttrs {
+                mtime: 0,
+                mode: file.unix_mode().map_or(0o644, |mode| mode & 0o7777),
+                uid: 0,
+                gid: 0,
+            };
+            drop(file);
+            write_replacement(&mut writer, &fate.name, attrs, &source)?;
+        } else if fate.name == clean(&name) {
+            writer.writer.raw_copy_file(file).map_err(zip_error)?;
+        } else {
+            let name = match is_dir {
+                true => format!("{}/", fate.name),
+                false => fate.name,
+            };
+            writer
+                .writer
+                .raw_copy_file_rename(file, name)
+                .map_err(zip_error)?;
+        }
+    }
+    plan.write_added(&mut writer)?;
+    writer.writer.set_raw_comment(archive.comment().into());
+    Box::new(writer).finish()
+}
+
+/// 按原压缩包推断重新压缩 tar 时的压缩设置
+///
+/// gzip 头部只区分最快、最高与其余级别；bzip2 的块大小即压缩级别；xz 沿用第一个块的 LZMA2
+/// 字典大小，并按它选择级别；zstd 不记录级别，使用默认级别。
+fn tar_compression(archive: &Path, format: ArchiveFormat) -> CompressionOptions {
+    let mut head = Vec::new();
+    if let Ok(file) = File::open(archive) {
+        let _ = file.take(2048).read_to_end(&mut head);
+    }
+    // 数值不超过 `value` 的最高级别
+    let level = |value: u32| {
+        [
+            CompressionLevel::Ultra,
+            CompressionLevel::Maximum,
+            CompressionLevel::Normal,
+            CompressionLevel::Fast,
+        ]
+        .into_iter()
+        .find(|level| level.value() <= value)
+        .unwrap_or(CompressionLevel::Fastest)
+    };
+    let mut compression = CompressionOptions::default();
+    match format {
+        // XFL：2 为最高级别，4 为最快
+        ArchiveFormat::TarGz => match head.get(8) {
+            Some(2) => compression.level = CompressionLevel::Ultra,
+            Some(4) => compression.level = CompressionLevel::Fastest,
+            _ => {}
+        },
+        ArchiveFormat::TarBz2 => {
+            if let Some(digit @ b'1'..=b'9') = head.get(3) {
+                compression.level = level((digit - b'0') as u32);
+            }
+        }
+        ArchiveFormat::TarXz => {
+            if let Some(size) = xzindex::lzma2_dict_size(&head) {
+                // 各级预设的字典：1 为 1 MiB，3 为 4 MiB，5 为 8 MiB，7 为 16 MiB，9 为 64 MiB
+                compression.level = level(match size >> 20 {
+                    64.. => 9,
+                    16.. => 7,
+                    8.. => 5,
+                    4.. => 3,
+                    _ => 1,
+                });
+                compression.dictionary_size = Some(size);
+            }
+        }
+        _ => {}
+    }
+    compression
+}
+
+/// 逐个条目重写 tar 流，最后追加新加入的条目
+///
+/// 条目的 PAX 扩展（扩展属性、ACL、精确到纳秒的修改时间、长用户名等）随条目写回，路径由头部或
+/// GNU 长名称重新生成；替换内容的条目去掉原来的 `size` 与 `mtime`。稀疏文件无法原样写回，遇到时失败。
+/// 硬链接随目标改名；目标被删除时改为写入目标内容的普通文件。
+fn edit_tar(
+    handler: &TarHandler,
+    plan: &mut Plan,
+    mut writer: TarArchiveWriter,
+) -> ResultR<VolumeFile> {
+    let mut archive = tar::Archive::new(handler.open_reader()?);
+    // 目标被删除的硬链接需要目标的内容，首次遇到时才收集并保存
+    let mut targets: Option<LinkTargets> = None;
+    for (position, item) in archive.entries().map_err(io_error)?.enumerate() {
+        let mut item = item.map_err(io_error)?;
+        let mut pax = Vec::new();
+        if let Some(extensions) = item.pax_extensions().map_err(io_error)? {
+            for extension in extensions {
+                let extension = extension.map_err(io_error)?;
+                let key = extension
+                    .key()
+                    .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+                pax.push((key.to_string(), extension.value_bytes().to_vec()));
+            }
+        }
+        let path = item.path().map_err(io_error)?.into_owned();
+        let mut header = item.header().clone();
+        let kind = header.entry_type();
+        let name = path.to_string_lossy().to_string();
+        let fate = plan.fate(&name, kind.is_file(), 0);
+        // 之后的硬链接可能指向该条目，删除前也要保存副本
+        let mut data_copy = None;
+        if let Some(targets) = targets
+            .as_mut()
+            .filter(|_| kind.is_file() || kind.is_contiguous())
+        {
+            if let Some(target) = convert::safe_name(&name).filter(|n| targets.wants(n)) {
+                data_copy = Some(targets.save(&target, &mut item).map_err(io_error)?);
+            }
+        }
+        let Some(fate) = fate else {
+            continue;
+        };
+        if kind.is_gnu_sparse() || pax.iter().any(|(key, _)| key.starts_with("GNU.sparse.")) {
+            return Err(ArchiveError::MsgError(format!(
+                "不支持修改含稀疏文件的 tar: {}",
+                name
+            )));
+        }
+        let mut target = item.link_name().map_err(io_error)?.map(|t| t.into_owned());
+        let mut relinked = false;
+        if let Some(link) = target.as_ref().filter(|_| kind.is_hard_link()) {
+            let link = link.to_string_lossy().to_string();
+            match plan.peek(&link) {
+                Some(moved) => {
+                    relinked = moved.name != clean(&link);
+                    target = Some(PathBuf::from(moved.name));
+                }
+                None => {
+                    let targets = match &mut targets {
+                        Some(targets) => targets,
+                        None => targets.insert(handler.link_targets(position)?),
+                    };
+                    let saved = convert::safe_name(&link)
+                        .and_then(|link| targets.open(&link))
+                        .ok_or_else(|| {
+                            ArchiveError::MsgError(format!(
+                                "硬链接 {} 的目标 {} 已删除",
+                                name, link
+                            ))
+                        })?;
+                    let (file, size) = saved.map_err(io_error)?;
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_size(size);
+                    header.as_old_mut().linkname = [0; 100];
+                    target = None;
+                    data_copy = Some((file, size));
+                    relinked = true;
+                }
+            }
+        }
+        let replaced = fate.source.is_some();
+        let renamed = fate.name != clean(&name);
+        // 路径放得进头部且没有改名时原样写回头部，避免 `./` 等前缀被规范化掉
+        let raw = !renamed
+            && !relinked
+            && header.path_bytes() == item.path_bytes()
+            && header.link_name_bytes() == item.link_name_bytes();
+        let path = match (renamed, kind.is_dir()) {
+            (false, _) => path,
+            (true, true) => PathBuf::from(format!("{}/", fate.name)),
+            (true, false) => PathBuf::from(&fate.name),
+        };
+        let data: Box<dyn Read + '_> = match (fate.source, data_copy) {
+            (Some(source), _) => {
+                let data = File::open(source).map_err(io_error)?;
+                let meta = data.metadata().map_err(io_error)?;
+                header.set_size(meta.len());
+                header.set_mtime(EntryAttrs::from_metadata(&meta).mtime.max(0) as u64);
+                Box::new(data.take(meta.len()))
+            }
+            (None, Some((file, _))) => Box::new(file),
+            (None, None) => Box::new(&mut item),
+        };
+        let builder = &mut writer.builder;
+        let pax = pax.iter().filter(|(key, _)| match key.as_str() {
+            "path" | "linkpath" => false,
+            "size" | "mtime" => !replaced,
+            _ => true,
+        });
+        builder
+            .append_pax_extensions(pax.map(|(key, value)| (key.as_str(), value.as_slice())))
+            .map_err(io_error)?;
+        let result = if raw {
+            header.set_cksum();
+            builder.append(&header, data)
+        } else if let Some(target) = target.filter(|_| kind.is_symlink() || kind.is_hard_link()) {
+            builder.append_link(&mut header, &path, target)
+        } else {
+            builder.append_data(&mut header, &path, data)
+        };
+        result.map_err(io_error)?;
+    }
+    plan.write_added(&mut writer)?;
+    Box::new(writer).finish()
+}
+
+/// 解压 7z 的条目并重新压缩，保留原来的加密与固实方式
+fn edit_sevenz(
+    archive: &Path,
+    password: &str,
+    plan: &mut Plan,
+    out: VolumeFile,
+) -> ResultR<VolumeFile> {
+    // 密码移入 SevenZReader 后无法访问，释放时不会清零
+    let key = match password {
+        "" => Password::empty(),
+        password => Password::from(password),
+    };
+    let mut reader = SevenZReader::open(archive, key).map_err(sevenz_error)?;
+    let info = reader.archive();
+    let aes = SevenZMethod::AES256SHA256.id();
+    let encrypted = info
+        .folders
+        .iter()
+        .flat_map(|folder| folder.coders.iter())
+        .any(|coder| coder.decompression_method_id() == aes);
+    let solid = info.files.iter().filter(|f| f.has_stream()).count() > info.folders.len();
+    let options = CreateOptions {
+        compression: CompressionOptions {
+            solid_block_size: solid.then_some(u64::MAX),
+            ..Default::default()
+        },
+        // 不用密码无法打开时头部是加密的
+        encryption: (encrypted && !password.is_empty()).then(|| Encryption {
+            password: Zeroizing::new(password.to_string()),
+            encrypt_header: SevenZReader::open(archive, Password::empty()).is_err(),
+        }),
+        ..Default::default()
+    };
+
+    let mut writer = writer::create_writer(out, ArchiveFormat::SevenZip, &options)?;
+    let mut failed = None;
+    let result = reader.for_each_entries(|entry, data| {
+        let mode = sevenz_unix_mode(entry);
+        let is_link = mode.is_some_and(|m| m & 0o170000 == 0o120000);
+        let is_dir = entry.is_directory();
+        let Some(fate) = plan.fate(entry.name(), !is_dir && !is_link, 0) else {
+            return Ok(true);
+        };
+        let attrs = EntryAttrs {
+            mtime: match entry.has_last_modified_date {
+                true => entry.last_modified_date().to_unix_time(),
+                false => 0,
+            },
+            mode: mode.map_or(if is_dir { 0o755 } else { 0o644 }, |m| m & 0o7777),
+            uid: 0,
+            gid: 0,
+        };
+        let written = if is_dir {
+            writer.add_dir(&fate.name, &attrs)
+        } else if is_link {
+            let mut target = String::new();
+            data.read_to_string(&mut target)
+                .map_err(io_error)
+                .and_then(|_| writer.add_symlink(&fate.name, &attrs, &target))
+        } else if let Some(source) = fate.source {
+            write_replacement(writer.as_mut(), &fate.name, attrs, &source)
+        } else {
+            writer.add_file(&fate.name, &attrs, entry.size(), data)
+        };
+        match written {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                failed = Some(e);
+                Ok(false)
+            }
+        }
+    });
+    if let Some(e) = failed {
+        return Err(e);
+    }
+    result.map_err(sevenz_error)?;
+    plan.write_added(writer.as_mut())?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_handler;
+    use crate::preview::ByteRange;
+    use crate::testutil::temp_dir;
+    use std::fs;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_replace_entry() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let source = dir.join("new.txt");
+        fs::write(&source, "changed content").unwrap();
+
+        let zip_path = dir.join("t.zip");
+        let mut writer = ZipWriter::new(File::create(&zip_path).unwrap());
+        for (name, data) in [("a.txt", "aaa"), ("sub/b.txt", "bbb")] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let tar_path = dir.join("t.tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(&tar_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (name, data) in [("a.txt", "aaa"), ("sub/b.txt", "bbb")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o640);
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        for path in [&zip_path, &tar_path] {
+            let format = ArchiveFormat::from_path(path);
+            replace_entry(path, format, "sub/b.txt", &source).unwrap();
+            assert!(replace_entry(path, format, "nope", &source).is_err());
+
+            let mut handler = create_handler(path, format, String::new());
+            for (name, data) in [("a.txt", "aaa"), ("sub/b.txt", "changed content")] {
+                let mut out = Vec::new();
+                handler
+                    .read_entry(name, ByteRange::default(), &mut out)
+                    .unwrap();
+                assert_eq!(out, data.as_bytes(), "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn test_edit_archive() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "aaa").unwrap();
+        fs::write(src.join("b.txt"), "bbb").unwrap();
+        fs::write(src.join("sub/c.txt"), "ccc").unwrap();
+        let extra = dir.join("extra");
+        fs::create_dir_all(&extra).unwrap();
+        fs::write(extra.join("d.txt"), "ddd").unwrap();
+        let new = dir.join("new.txt");
+        fs::write(&new, "new content").unwrap();
+
+        let ops = [
+            EditOp::Add {
+                source: extra.clone(),
+                dest: String::from("src/"),
+            },
+            EditOp::Delete {
+                entry: String::from("src/a.txt"),
+            },
+            EditOp::Rename {
+                from: String::from("src/sub"),
+                to: String::from("src/moved"),
+            },
+            EditOp::Replace {
+                entry: String::from("src/b.txt"),
+                source: new.clone(),
+            },
+            EditOp::Rename {
+                from: String::from("src/extra/d.txt"),
+                to: String::from("d.txt"),
+            },
+        ];
+        for ext in ["zip", "tar.gz", "7z"] {
+            let path = dir.join(format!("t.{}", ext));
+            let format = ArchiveFormat::from_path(&path);
+            let sources = vec![src.clone()];
+            crate::writer::create_archive(&sources, &path, format, &Default::default(), |_| {})
+                .unwrap();
+
+            // 任一修改找不到条目时原压缩包不变
+            let before = fs::read(&path).unwrap();
+            let missing = EditOp::Delete {
+                entry: String::from("nope"),
+            };
+            assert!(edit_archive(&path, format, "", &[ops[0].clone(), missing]).is_err());
+            assert_eq!(fs::read(&path).unwrap(), before, "{}", ext);
+
+            edit_archive(&path, format, "", &ops).unwrap();
+            let mut handler = create_handler(&path, format, String::new());
+            let mut names = handler
+                .entries()
+                .unwrap()
+                .map(|e| e.unwrap().path.trim_end_matches('/').to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(
+                names,
+                [
+                    "d.txt",
+                    "src",
+                    "src/b.txt",
+                    "src/extra",
+                    "src/moved",
+                    "src/moved/c.txt"
+                ],
+                "{}",
+                ext
+            );
+            for (name, data) in [
+                ("src/b.txt", "new content"),
+                ("src/moved/c.txt", "ccc"),
+                ("d.txt", "ddd"),
+            ] {
+                let mut out = Vec::new();
+                handler
+                    .read_entry(name, ByteRange::default(), &mut out)
+                    .unwrap();
+                assert_eq!(out, data.as_bytes(), "{} {}", ext, name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_edit_tar_keeps_pax_extensions() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("t.tar");
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        builder
+            .append_pax_extensions([
+                ("SCHILY.xattr.user.tag", &b"blue"[..]),
+                ("mtime", b"1600000000.25"),
+            ])
+            .unwrap();
+        for name in ["a.txt", "b.txt"] {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(3);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, &b"abc"[..]).unwrap();
+        }
+        builder.into_inner().unwrap();
+
+        let delete = EditOp::Delete {
+            entry: String::from("b.txt"),
+        };
+        edit_archive(&path, ArchiveFormat::Tar, "", &[delete]).unwrap();
+        let mut archive = tar::Archive::new(File::open(&path).unwrap());
+        let mut entries = archive.entries().unwrap();
+        let mut item = entries.next().unwrap().unwrap();
+        assert_eq!(item.path().unwrap().to_str(), Some("a.txt"));
+        let pax: Vec<_> = item
+            .pax_extensions()
+            .unwrap()
+            .unwrap()
+            .map(|e| {
+                let e = e.unwrap();
+                (e.key().unwrap().to_string(), e.value().unwrap().to_string())
+            })
+            .collect();
+        assert_eq!(
+            pax,
+            [
+                (String::from("SCHILY.xattr.user.tag"), String::from("blue")),
+                (String::from("mtime"), String::from("1600000000.25")),
+            ]
+        );
+        assert!(entries.next().is_none());
+
+        // 稀疏文件无法原样写回
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::GNUSparse);
+        header.set_size(0);
+        header.as_gnu_mut().unwrap().set_real_size(0);
+        builder
+            .append_data(&mut header, "sparse", io::empty())
+            .unwrap();
+        builder.into_inner().unwrap();
+        let rename = EditOp::Rename {
+            from: String::from("sparse"),
+            to: String::from("moved"),
+        };
+        let result = edit_archive(&path, ArchiveFormat::Tar, "", &[rename]);
+        assert!(
+            matches!(&result, Err(ArchiveError::MsgError(e)) if e.contains("稀疏")),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_edit_tar_links_and_level() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("t.tar.bz2");
+        let encoder =
+            bzip2::write::BzEncoder::new(File::create(&path).unwrap(), bzip2::Compression::new(1));
+        let mut builder = tar::Builder::new(encoder);
+        for (name, data) in [("a.txt", "aaa"), ("b.txt", "bbb")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(3);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        for (name, target) in [("link-a", "a.txt"), ("link-b", "b.txt")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Link);
+            header.set_size(0);
+            header.set_mode(0o644);
+            builder.append_link(&mut header, name, target).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let ops = [
+            EditOp::Rename {
+                from: String::from("a.txt"),
+                to: String::from("renamed.txt"),
+            },
+            EditOp::Delete {
+                entry: String::from("b.txt"),
+            },
+        ];
+        edit_archive(&path, ArchiveFormat::TarBz2, "", &ops).unwrap();
+
+        // 沿用原来的 bzip2 级别
+        assert_eq!(&fs::read(&path).unwrap()[..4], b"BZh1");
+        let reader = bzip2::read::BzDecoder::new(File::open(&path).unwrap());
+        let mut archive = tar::Archive::new(reader);
+        let mut links = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let target = entry
+                .link_name()
+                .unwrap()
+                .map(|t| t.to_string_lossy().to_string());
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            links.push((name, entry.header().entry_type(), target, data));
+        }
+        assert_eq!(
+            links,
+            [
+                (
+                    String::from("renamed.txt"),
+                    tar::EntryType::Regular,
+                    None,
+                    String::from("aaa")
+                ),
+                (
+                    String::from("link-a"),
+                    tar::EntryType::Link,
+                    Some(String::from("renamed.txt")),
+                    String::new()
+                ),
+                // 目标被删除的硬链接改为普通文件
+                (
+                    String::from("link-b"),
+                    tar::EntryType::Regular,
+                    None,
+                    String::from("bbb")
+                ),
+            ]
+        );
+    }
+}
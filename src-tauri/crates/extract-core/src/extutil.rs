@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+    TarZst,
+    Gz,
+    Bz2,
+    SevenZip,
+    Rar,
+    #[default]
+    Unknown,
+}
+
+// -------------------------
+// 扩展名匹配逻辑
+// -------------------------
+
+/// 扩展名与格式的对应，复合扩展名在前，优先匹配
+const EXTENSIONS: [(&str, ArchiveFormat); 14] = [
+    (".tar.gz", ArchiveFormat::TarGz),
+    (".tar.bz2", ArchiveFormat::TarBz2),
+    (".tar.xz", ArchiveFormat::TarXz),
+    (".tar.zst", ArchiveFormat::TarZst),
+    (".tzst", ArchiveFormat::TarZst),
+    (".tgz", ArchiveFormat::TarGz),
+    (".tbz2", ArchiveFormat::TarBz2),
+    (".tbz", ArchiveFormat::TarBz2),
+    (".zip", ArchiveFormat::Zip),
+    (".tar", ArchiveFormat::Tar),
+    (".gz", ArchiveFormat::Gz),
+    (".bz2", ArchiveFormat::Bz2),
+    (".7z", ArchiveFormat::SevenZip),
+    (".rar", ArchiveFormat::Rar),
+];
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.file_name() {
+            Some(name) => Self::split(&name.to_string_lossy())
+                .map_or(ArchiveFormat::Unknown, |(_, format)| format),
+            None => ArchiveFormat::Unknown,
+        }
+    }
+
+    /// 去掉文件名中的压缩包扩展名（含 `.tar.gz` 等复合扩展名），不是压缩包时返回 `None`
+    pub fn strip_extension(name: &str) -> Option<&str> {
+        Self::split(name).map(|(stem, _)| stem)
+    }
+
+    /// 按扩展名（不区分大小写）拆成主名与格式，只有扩展名的文件（如 `.zip`）不算压缩包
+    fn split(name: &str) -> Option<(&str, ArchiveFormat)> {
+        EXTENSIONS.iter().find_map(|&(ext, format)| {
+            let stem = name.len().checked_sub(ext.len()).filter(|&len| len > 0)?;
+            // 扩展名均为 ASCII，匹配时 stem 必定落在字符边界上
+            name.as_bytes()[stem..]
+                .eq_ignore_ascii_case(ext.as_bytes())
+                .then(|| (&name[..stem], format))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_format_from_path() {
+        // 测试复合扩展名
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tar.gz.zip")),
+            ArchiveFormat::Zip
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tar.bz2")),
+            ArchiveFormat::TarBz2
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tar.xz")),
+            ArchiveFormat::TarXz
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tar.zst")),
+            ArchiveFormat::TarZst
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tgz")),
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tbz2")),
+            ArchiveFormat::TarBz2
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tbz")),
+            ArchiveFormat::TarBz2
+        );
+
+        // 测试单扩展名
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.zip")),
+            ArchiveFormat::Zip
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.tar")),
+            ArchiveFormat::Tar
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.gz")),
+            ArchiveFormat::Gz
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.bz2")),
+            ArchiveFormat::Bz2
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.7z")),
+            ArchiveFormat::SevenZip
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.rar")),
+            ArchiveFormat::Rar
+        );
+
+        // 测试未知格式和边界情况
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("test.unknown")),
+            ArchiveFormat::Unknown
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("")),
+            ArchiveFormat::Unknown
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new(".hidden")),
+            ArchiveFormat::Unknown
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new("TEST.TZST")),
+            ArchiveFormat::TarZst
+        );
+        assert_eq!(
+            ArchiveFormat::from_path(Path::new(".zip")),
+            ArchiveFormat::Unknown
+        );
+    }
+
+    #[test]
+    fn test_strip_extension() {
+        assert_eq!(ArchiveFormat::strip_extension("a.tar.zst"), Some("a"));
+        assert_eq!(ArchiveFormat::strip_extension("a.tzst"), Some("a"));
+        assert_eq!(ArchiveFormat::strip_extension("数据.TAR.GZ"), Some("数据"));
+        assert_eq!(ArchiveFormat::strip_extension("a.b.zip"), Some("a.b"));
+        assert_eq!(ArchiveFormat::strip_extension("a.txt"), None);
+        assert_eq!(ArchiveFormat::strip_extension(".7z"), None);
+    }
+}
@@ -0,0 +1,406 @@
+//! gzip 随机访问
+//!
+//! 解压时在 deflate 块边界按间隔记录检查点：压缩流中的位置（精确到位）、解压后的位置，
+//! 以及此前 32 KiB 的输出作为字典。之后可从最近的检查点继续解压，不必从头开始。
+//! deflate 块不按字节对齐，需要 zlib 的 `inflatePrime`，所以这里直接使用 zlib。
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use libz_sys as z;
+
+/// deflate 的回溯窗口大小
+const WINDOW: usize = 32 * 1024;
+/// 初始的检查点间隔（解压后字节数）
+const SPAN: u64 = 1 << 20;
+/// 检查点超过该数量时间隔加倍、丢弃一半，限制索引大小
+const MAX_CHECKPOINTS: usize = 1024;
+/// 自动识别 gzip 头
+const GZIP_BITS: i32 = 15 + 32;
+/// 原始 deflate 流，用于从检查点继续
+const RAW_BITS: i32 = -15;
+/// gzip 成员末尾的 CRC32 与长度
+const TRAILER: usize = 8;
+
+/// 一个可以继续解压的位置
+#[derive(Debug, Clone)]
+pub(crate) struct GzCheckpoint {
+    /// 压缩流中下一个完整字节的位置
+    pub input: u64,
+    /// 检查点位于 `input` 前一个字节中的位数（0 表示字节对齐）
+    pub bits: u8,
+    /// 解压后的位置
+    pub output: u64,
+    /// 此前最多 32 KiB 的输出
+    pub window: Vec<u8>,
+}
+
+unsafe extern "C" fn zalloc(_: z::voidpf, items: z::uInt, size: z::uInt) -> z::voidpf {
+    libc::calloc(items as usize, size as usize)
+}
+
+unsafe extern "C" fn zfree(_: z::voidpf, ptr: z::voidpf) {
+    libc::free(ptr)
+}
+
+/// zlib 解压状态，`z_stream` 放在堆上，初始化后地址不再变化
+struct Inflater {
+    strm: Box<z::z_stream>,
+}
+
+// z_stream 只被所属的读取器使用
+unsafe impl Send for Inflater {}
+
+impl Inflater {
+    fn new(window_bits: i32) -> io::Result<Self> {
+        let mut strm = Box::new(z::z_stream {
+            next_in: std::ptr::null_mut(),
+            avail_in: 0,
+            total_in: 0,
+            next_out: std::ptr::null_mut(),
+            avail_out: 0,
+            total_out: 0,
+            msg: std::ptr::null_mut(),
+            state: std::ptr::null_mut(),
+            zalloc,
+            zfree,
+            opaque: std::ptr::null_mut(),
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        });
+        let ret = unsafe {
+            z::inflateInit2_(
+                strm.as_mut(),
+                window_bits,
+                z::zlibVersion(),
+                std::mem::size_of::<z::z_stream>() as i32,
+            )
+        };
+        match ret {
+            z::Z_OK => Ok(Inflater { strm }),
+            _ => Err(zlib_error(ret, "inflateInit2")),
+        }
+    }
+
+    fn reset(&mut self, window_bits: i32) -> io::Result<()> {
+        match unsafe { z::inflateReset2(self.strm.as_mut(), window_bits) } {
+            z::Z_OK => Ok(()),
+            ret => Err(zlib_error(ret, "inflateReset2")),
+        }
+    }
+
+    fn prime(&mut self, bits: u8, value: u8) -> io::Result<()> {
+        let value = (value >> (8 - bits)) as i32;
+        match unsafe { z::inflatePrime(self.strm.as_mut(), bits as i32, value) } {
+            z::Z_OK => Ok(()),
+            ret => Err(zlib_error(ret, "inflatePrime")),
+        }
+    }
+
+    fn set_dictionary(&mut self, window: &[u8]) -> io::Result<()> {
+        if window.is_empty() {
+            return Ok(());
+        }
+        let ret = unsafe {
+            z::inflateSetDictionary(self.strm.as_mut(), window.as_ptr(), window.len() as z::uInt)
+        };
+        match ret {
+            z::Z_OK => Ok(()),
+            _ => Err(zlib_error(ret, "inflateSetDictionary")),
+        }
+    }
+
+    /// 解压一次，到块边界时也会返回；返回 (zlib 返回值, 消耗的输入, 产生的输出)
+    fn inflate(&mut self, input: &[u8], output: &mut [u8]) -> (i32, usize, usize) {
+        let strm = self.strm.as_mut();
+        strm.next_in = input.as_ptr() as *mut u8;
+        strm.avail_in = input.len().min(u32::MAX as usize) as z::uInt;
+        strm.next_out = output.as_mut_ptr();
+        strm.avail_out = output.len().min(u32::MAX as usize) as z::uInt;
+        let avail = (strm.avail_in, strm.avail_out);
+        let ret = unsafe { z::inflate(strm, z::Z_BLOCK) };
+        let strm = self.strm.as_ref();
+        (
+            ret,
+            (avail.0 - strm.avail_in) as usize,
+            (avail.1 - strm.avail_out) as usize,
+        )
+    }
+
+    fn data_type(&self) -> i32 {
+        self.strm.data_type
+    }
+}
+
+impl Drop for Inflater {
+    fn drop(&mut self) {
+        unsafe {
+            z::inflateEnd(self.strm.as_mut());
+        }
+    }
+}
+
+fn zlib_error(ret: i32, what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} 失败: {}", what, ret),
+    )
+}
+
+/// 记录检查点所需的状态
+struct Recorder {
+    span: u64,
+    /// 最近 32 KiB 输出的环形缓冲
+    history: Vec<u8>,
+    checkpoints: Vec<GzCheckpoint>,
+}
+
+impl Recorder {
+    fn push_output(&mut self, total_out: u64, data: &[u8]) {
+        // 只保留最后 WINDOW 字节
+        let data = &data[data.len().saturating_sub(WINDOW)..];
+        let start = (total_out - data.len() as u64) as usize % WINDOW;
+        let first = data.len().min(WINDOW - start);
+        self.history[start..start + first].copy_from_slice(&data[..first]);
+        self.history[..data.len() - first].copy_from_slice(&data[first..]);
+    }
+
+    fn checkpoint(&mut self, input: u64, bits: u8, output: u64) {
+        let due = match self.checkpoints.last() {
+            Some(last) => output - last.output >= self.span,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        let len = output.min(WINDOW as u64) as usize;
+        let end = output as usize % WINDOW;
+        let mut window = Vec::with_capacity(len);
+        if len == WINDOW {
+            window.extend_from_slice(&self.history[end..]);
+            window.extend_from_slice(&self.history[..end]);
+        } else {
+            window.extend_from_slice(&self.history[..len]);
+        }
+        self.checkpoints.push(GzCheckpoint {
+            input,
+            bits,
+            output,
+            window,
+        });
+
+        if self.checkpoints.len() > MAX_CHECKPOINTS {
+            let mut i = 0;
+            self.checkpoints.retain(|_| {
+                i += 1;
+                i % 2 == 1
+            });
+            self.span *= 2;
+        }
+    }
+}
+
+/// gzip 解压读取器，支持多成员；可在解压时记录检查点，或从检查点开始
+pub(crate) struct GzReader<R> {
+    inner: R,
+    inflater: Inflater,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    eof: bool,
+    /// 已消耗的压缩字节数（从文件开头算起）
+    total_in: u64,
+    total_out: u64,
+    /// 从检查点开始时为原始 deflate 模式，zlib 不处理当前成员的 gzip 尾部
+    raw: bool,
+    /// 成员结束后还需手动跳过的尾部字节
+    skip: usize,
+    /// 刚结束一个成员，之后的无效数据视为结尾填充
+    member_end: bool,
+    done: bool,
+    recorder: Option<Recorder>,
+}
+
+impl<R: Read> GzReader<R> {
+    /// 从头解压
+    pub fn new(inner: R) -> io::Result<Self> {
+        Ok(Self::with(inner, Inflater::new(GZIP_BITS)?, 0, 0))
+    }
+
+    /// 从头解压并记录检查点，读完后用 [`GzReader::into_checkpoints`] 取出
+    pub fn indexing(inner: R) -> io::Result<Self> {
+        let mut reader = Self::with(inner, Inflater::new(GZIP_BITS)?, 0, 0);
+        reader.recorder = Some(Recorder {
+            span: SPAN,
+            history: vec![0; WINDOW],
+            checkpoints: Vec::new(),
+        });
+        Ok(reader)
+    }
+
+    fn with(inner: R, inflater: Inflater, total_in: u64, total_out: u64) -> Self {
+        GzReader {
+            inner,
+            inflater,
+            buf: vec![0; 64 * 1024].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            eof: false,
+            total_in,
+            total_out,
+            raw: false,
+            skip: 0,
+            member_end: false,
+            done: false,
+            recorder: None,
+        }
+    }
+
+    pub fn into_checkpoints(self) -> Vec<GzCheckpoint> {
+        self.recorder.map(|r| r.checkpoints).unwrap_or_default()
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        if self.pos == self.len && !self.eof {
+            self.len = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+            self.eof = self.len == 0;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> GzReader<R> {
+    /// 从检查点继续解压
+    pub fn resume(mut inner: R, checkpoint: &GzCheckpoint) -> io::Result<Self> {
+        let mut inflater = Inflater::new(RAW_BITS)?;
+        let mut start = checkpoint.input;
+        if checkpoint.bits > 0 {
+            start -= 1;
+            inner.seek(SeekFrom::Start(start))?;
+            let mut byte = [0u8];
+            inner.read_exact(&mut byte)?;
+            inflater.prime(checkpoint.bits, byte[0])?;
+        } else {
+            inner.seek(SeekFrom::Start(start))?;
+        }
+        inflater.set_dictionary(&checkpoint.window)?;
+        let mut reader = Self::with(inner, inflater, checkpoint.input, checkpoint.output);
+        reader.raw = true;
+        Ok(reader)
+    }
+}
+
+impl<R: Read> Read for GzReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill()?;
+            if self.skip > 0 {
+                // 原始 deflate 模式不处理 gzip 尾部，手动跳过后按 gzip 解析下一个成员
+                let n = self.skip.min(self.len - self.pos);
+                if n == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                self.pos += n;
+                self.total_in += n as u64;
+                self.skip -= n;
+                if self.skip == 0 {
+                    self.inflater.reset(GZIP_BITS)?;
+                }
+                continue;
+            }
+            if self.pos == self.len {
+                if self.member_end {
+                    self.done = true;
+                    return Ok(0);
+                }
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            let (ret, used, produced) = self.inflater.inflate(&self.buf[self.pos..self.len], out);
+            self.pos += used;
+            self.total_in += used as u64;
+            self.total_out += produced as u64;
+            if produced > 0 {
+                self.member_end = false;
+            }
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.push_output(self.total_out, &out[..produced]);
+            }
+
+            match ret {
+                z::Z_OK | z::Z_BUF_ERROR => {
+                    let data_type = self.inflater.data_type();
+                    // 位于块边界且不是最后一个块之后
+                    if data_type & 128 != 0 && data_type & 64 == 0 {
+                        if let Some(recorder) = self.recorder.as_mut() {
+                            let bits = (data_type & 7) as u8;
+                            recorder.checkpoint(self.total_in, bits, self.total_out);
+                        }
+                    }
+                }
+                z::Z_STREAM_END => {
+                    self.member_end = true;
+                    if std::mem::replace(&mut self.raw, false) {
+                        self.skip = TRAILER;
+                    } else {
+                        self.inflater.reset(GZIP_BITS)?;
+                    }
+                }
+                _ if self.member_end && produced == 0 => {
+                    // 成员之后的填充数据
+                    self.done = true;
+                }
+                _ => return Err(zlib_error(ret, "inflate")),
+            }
+            if produced > 0 {
+                return Ok(produced);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        // 伪随机文本，压缩后仍有足够多的块
+        let mut seed = 1u64;
+        let data: Vec<u8> = (0..3 << 20)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                b"abcdefgh \n"[(seed >> 60) as usize % 10]
+            })
+            .collect();
+        // 两个成员拼接
+        let mut gz = Vec::new();
+        for part in data.chunks(2 << 20) {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            encoder.write_all(part).unwrap();
+            gz.extend(encoder.finish().unwrap());
+        }
+
+        let mut reader = GzReader::indexing(Cursor::new(&gz)).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert!(out == data);
+        let checkpoints = reader.into_checkpoints();
+        assert!(checkpoints.len() > 2);
+
+        for checkpoint in &checkpoints[1..] {
+            let mut reader = GzReader::resume(Cursor::new(&gz), checkpoint).unwrap();
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest).unwrap();
+            assert!(rest == data[checkpoint.output as usize..]);
+        }
+    }
+}
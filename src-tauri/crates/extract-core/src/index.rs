@@ -0,0 +1,256 @@
+//! 条目索引：一次读取全部条目后按目录分页浏览，避免一次性构建并传输整棵树
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::plustree::natural_cmp;
+use crate::{complete_dirs, entry_name, ArchiveEntry, ArchiveError, ResultR};
+
+/// 根目录的键
+const ROOT: &str = "/";
+
+/// 目录列表的排序字段，目录始终排在文件之前
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// 名称自然排序
+    #[default]
+    Name,
+    /// 大小，目录按其下所有文件的总大小
+    Size,
+    /// 修改时间
+    Modified,
+}
+
+/// 目录列表的排序方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct DirSort {
+    pub key: SortKey,
+    /// 是否倒序
+    pub descending: bool,
+}
+
+/// 目录中的一项
+#[derive(Debug, Serialize, Clone)]
+pub struct DirItem {
+    #[serde(flatten)]
+    pub entry: ArchiveEntry,
+    /// 直接子条目数，文件为 0
+    pub child_count: usize,
+    /// 其下所有文件数，文件为 1
+    pub file_count: u64,
+    /// 其下所有文件的总大小，文件为自身大小
+    pub total_size: u64,
+}
+
+/// `list_dir` 返回的一页
+#[derive(Debug, Serialize, Clone)]
+pub struct DirPage {
+    /// 目录路径，根目录为 `/`
+    pub path: String,
+    /// 目录中的条目总数
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<DirItem>,
+}
+
+#[derive(Debug, Default)]
+struct DirStats {
+    children: Vec<usize>,
+    file_count: u64,
+    total_size: u64,
+}
+
+/// 压缩包的条目索引，按目录组织
+#[derive(Debug, Default)]
+pub struct ArchiveIndex {
+    entries: Vec<ArchiveEntry>,
+    dirs: HashMap<String, DirStats>,
+}
+
+impl ArchiveIndex {
+    /// 由条目列表建立索引，补齐缺失的上级目录并统计每个目录的文件数与总大小
+    pub fn new(mut entries: Vec<ArchiveEntry>) -> Self {
+        complete_dirs(&mut entries);
+
+        let mut dirs: HashMap<String, DirStats> = HashMap::new();
+        dirs.insert(ROOT.to_string(), DirStats::default());
+        for entry in entries.iter().filter(|e| e.is_dir) {
+            dirs.entry(entry.path.clone()).or_default();
+        }
+
+        // 重复的路径只保留第一次出现的条目
+        let mut seen = std::collections::HashSet::new();
+        entries.retain(|e| seen.insert(e.path.clone()));
+
+        for (i, entry) in entries.iter().enumerate() {
+            let parent = entry.parent_path.as_deref().unwrap_or(ROOT);
+            let Some(stats) = dirs.get_mut(parent) else {
+                continue;
+            };
+            stats.children.push(i);
+            if entry.is_dir {
+                continue;
+            }
+            // 逐级向上累计
+            let mut parent = Some(parent);
+            while let Some(dir) = parent {
+                let Some(stats) = dirs.get_mut(dir) else {
+                    break;
+                };
+                stats.file_count += 1;
+                stats.total_size += entry.size;
+                parent = (dir != ROOT).then(|| parent_of(dir));
+            }
+        }
+
+        ArchiveIndex { entries, dirs }
+    }
+
+    /// 条目总数（含补齐的目录）
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 全部条目，顺序与压缩包中一致
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// 列出一个目录的直接子条目，`dir_path` 为空或 `/` 表示根目录
+    pub fn list_dir(
+        &self,
+        dir_path: &str,
+        offset: usize,
+        limit: usize,
+        sort: DirSort,
+    ) -> ResultR<DirPage> {
+        let path = match dir_path.trim_start_matches('/') {
+            "" => ROOT.to_string(),
+            p if p.ends_with('/') => p.to_string(),
+            p => format!("{}/", p),
+        };
+        let stats = self
+            .dirs
+            .get(&path)
+            .ok_or_else(|| ArchiveError::MsgError(format!("目录不存在: {}", dir_path)))?;
+
+        let mut children = stats.children.clone();
+        children.sort_by(|&a, &b| {
+            let (a, b) = (&self.entries[a], &self.entries[b]);
+            let ord = match sort.key {
+                SortKey::Name => Ordering::Equal,
+                SortKey::Size => self.total_size(a).cmp(&self.total_size(b)),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+            }
+            .then_with(|| natural_cmp(entry_name(&a.path), entry_name(&b.path)));
+            let ord = if sort.descending { ord.reverse() } else { ord };
+            b.is_dir.cmp(&a.is_dir).then(ord)
+        });
+
+        Ok(DirPage {
+            path,
+            total: children.len(),
+            offset,
+            items: children
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|i| self.item(i))
+                .collect(),
+        })
+    }
+
+    fn total_size(&self, entry: &ArchiveEntry) -> u64 {
+        match self.dirs.get(&entry.path).filter(|_| entry.is_dir) {
+            Some(stats) => stats.total_size,
+            None => entry.size,
+        }
+    }
+
+    fn item(&self, i: usize) -> DirItem {
+        let entry = &self.entries[i];
+        match self.dirs.get(&entry.path).filter(|_| entry.is_dir) {
+            Some(stats) => DirItem {
+                entry: entry.clone(),
+                child_count: stats.children.len(),
+                file_count: stats.file_count,
+                total_size: stats.total_size,
+            },
+            None => DirItem {
+                entry: entry.clone(),
+                child_count: 0,
+                file_count: 1,
+                total_size: entry.size,
+            },
+        }
+    }
+}
+
+/// 目录的上级目录，以 `/` 结尾，顶层为 `/`
+fn parent_of(dir: &str) -> &str {
+    match dir.trim_end_matches('/').rfind('/') {
+        Some(i) => &dir[..=i],
+        None => ROOT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> ArchiveEntry {
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        ArchiveEntry {
+            name: name.to_string(),
+            path: path.to_string(),
+            parent_path: Some(format!("{}/", parent)),
+            size,
+            is_dir: false,
+            modified: None,
+            is_archive: false,
+        }
+    }
+
+    #[test]
+    fn test_list_dir() {
+        let index = ArchiveIndex::new(vec![
+            file("b.txt", 5),
+            file("a/x/file10", 10),
+            file("a/x/file2", 20),
+            file("a/y.txt", 1),
+        ]);
+        let names = |page: &DirPage| -> Vec<String> {
+            page.items.iter().map(|i| i.entry.path.clone()).collect()
+        };
+
+        let root = index.list_dir("/", 0, 10, DirSort::default()).unwrap();
+        assert_eq!(names(&root), ["a/", "b.txt"]);
+        assert_eq!(root.items[0].child_count, 2);
+        assert_eq!(root.items[0].file_count, 3);
+        assert_eq!(root.items[0].total_size, 31);
+
+        let x = index.list_dir("a/x", 0, 10, DirSort::default()).unwrap();
+        assert_eq!(x.path, "a/x/");
+        assert_eq!(names(&x), ["a/x/file2", "a/x/file10"]);
+
+        let sort = DirSort {
+            key: SortKey::Size,
+            descending: true,
+        };
+        let page = index.list_dir("a/x/", 1, 1, sort).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(names(&page), ["a/x/file10"]);
+
+        assert!(index
+            .list_dir("missing", 0, 10, DirSort::default())
+            .is_err());
+    }
+}
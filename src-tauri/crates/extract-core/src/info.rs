@@ -0,0 +1,223 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::extutil::ArchiveFormat;
+
+/// 压缩包整体信息
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ArchiveInfo {
+    pub format: ArchiveFormat,
+    /// 压缩包注释（zip/rar 注释、gzip 头注释）
+    pub comment: Option<String>,
+    /// 条目数量（包含目录）
+    pub entry_count: u64,
+    /// 压缩包文件的大小，含头部与目录（多卷时为所有分卷之和）
+    pub packed_size: u64,
+    /// 解压后大小
+    pub unpacked_size: u64,
+    pub is_zip64: bool,
+    pub is_multi_volume: bool,
+    pub is_solid: bool,
+    pub is_locked: bool,
+    pub is_encrypted: bool,
+    /// 7z 数据块信息，其他格式为空
+    pub blocks: Vec<ArchiveBlock>,
+}
+
+/// 7z 数据块（folder）信息
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ArchiveBlock {
+    pub entry_count: u64,
+    /// 该块打包流的大小之和，不含头部
+    pub packed_size: u64,
+    pub unpacked_size: u64,
+    /// 压缩/过滤方法，例如 ["LZMA2", "BCJ_X86"]
+    pub methods: Vec<String>,
+}
+
+/// 完整性测试结果
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct TestReport {
+    /// 已测试的条目数
+    pub tested: u64,
+    pub failed: Vec<TestFailure>,
+}
+
+/// 未通过测试的条目
+#[derive(Debug, Serialize, Clone)]
+pub struct TestFailure {
+    pub path: String,
+    pub error: String,
+}
+
+impl TestReport {
+    pub fn fail(&mut self, path: impl Into<String>, error: impl std::fmt::Display) {
+        self.failed.push(TestFailure {
+            path: path.into(),
+            error: error.to_string(),
+        });
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+const EOCD_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+const ZIP64_LOCATOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+const EOCD_MIN_SIZE: u64 = 22;
+const ZIP64_LOCATOR_SIZE: u64 = 20;
+
+/// zip 尾部信息：(是否 ZIP64, 是否分卷)
+///
+/// 从文件末尾查找 EOCD，再检查其前方是否紧跟 ZIP64 定位记录。
+pub fn zip_tail_info(path: &Path) -> std::io::Result<(bool, bool)> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    // EOCD 注释最长 65535 字节
+    let window = len.min(EOCD_MIN_SIZE + ZIP64_LOCATOR_SIZE + u16::MAX as u64);
+    file.seek(SeekFrom::Start(len - window))?;
+    let mut buf = vec![0u8; window as usize];
+    file.read_exact(&mut buf)?;
+
+    let eocd = match (0..buf.len().saturating_sub(EOCD_MIN_SIZE as usize - 1))
+        .rev()
+        .find(|&i| buf[i..i + 4] == EOCD_SIGNATURE)
+    {
+        Some(pos) => pos,
+        None => return Ok((false, false)),
+    };
+
+    let disk_number = u16::from_le_bytes([buf[eocd + 4], buf[eocd + 5]]);
+    let cd_disk = u16::from_le_bytes([buf[eocd + 6], buf[eocd + 7]]);
+    let is_zip64 = eocd >= ZIP64_LOCATOR_SIZE as usize
+        && buf[eocd - ZIP64_LOCATOR_SIZE as usize..][..4] == ZIP64_LOCATOR_SIGNATURE;
+
+    Ok((is_zip64, disk_number != 0 || cd_disk != 0))
+}
+
+/// RAR 所有分卷路径（单卷时只有自身）
+pub fn rar_volumes(path: &Path) -> Vec<PathBuf> {
+    let archive = unrar::Archive::new(path);
+    if !archive.is_multipart() {
+        return vec![path.to_path_buf()];
+    }
+    (1..)
+        .map_while(|n| archive.nth_part(n).filter(|p| p.exists()))
+        .collect()
+}
+
+const RAR_COMMENT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 读取 RAR 注释
+///
+/// unrar crate 暂不支持注释，这里直接调用 DLL 接口。
+pub fn rar_comment(path: &Path) -> Option<String> {
+    use unrar_sys::{OpenArchiveDataEx, RARCloseArchive, RAROpenArchiveEx, RAR_OM_LIST, WCHAR};
+
+    let name = crate::rarstream::rar_path(path)?;
+
+    let mut buffer: Vec<WCHAR> = vec![0; RAR_COMMENT_BUFFER_SIZE];
+    let mut data = OpenArchiveDataEx::new(name.as_ptr() as *const _, RAR_OM_LIST);
+    data.comment_buffer_w = buffer.as_mut_ptr();
+    data.comment_buffer_size = buffer.len() as u32;
+
+    // SAFETY: data 与 buffer 在调用期间均有效，句柄在读取后立即关闭
+    let handle = unsafe { RAROpenArchiveEx(&mut data as *mut _) };
+    if handle.is_null() {
+        return None;
+    }
+    unsafe { RARCloseArchive(handle) };
+
+    // CmtState == 1 表示注释读取成功，其余（0 无注释、ERAR_SMALL_BUF 等）一律忽略
+    if data.comment_state != 1 || data.comment_size <= 1 {
+        return None;
+    }
+    let wide = &buffer[..data.comment_size as usize - 1];
+    Some(decode_wide(wide))
+}
+
+#[cfg(windows)]
+pub(crate) fn decode_wide(wide: &[u16]) -> String {
+    String::from_utf16_lossy(wide)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn decode_wide(wide: &[unrar_sys::WCHAR]) -> String {
+    wide.iter()
+        .map(|&c| char::from_u32(c as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_handler;
+    use crate::options::{CreateOptions, Encryption};
+    use crate::testutil::temp_dir;
+    use crate::writer::create_archive;
+    use std::fs;
+    use std::io::Write;
+
+    fn write_temp(dir: &Path, name: &str, bytes: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        File::create(&path).unwrap().write_all(bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_zip_tail_info() {
+        let tmp = temp_dir();
+        // 空 zip：只有 EOCD
+        let mut plain = EOCD_SIGNATURE.to_vec();
+        plain.extend_from_slice(&[0u8; 18]);
+        let path = write_temp(tmp.path(), "extract_info_plain.zip", &plain);
+        assert_eq!(zip_tail_info(&path).unwrap(), (false, false));
+
+        // ZIP64 定位记录 + EOCD，且 EOCD 声明为第 2 卷
+        let mut zip64 = ZIP64_LOCATOR_SIGNATURE.to_vec();
+        zip64.extend_from_slice(&[0u8; 16]);
+        zip64.extend_from_slice(&EOCD_SIGNATURE);
+        zip64.extend_from_slice(&[1, 0, 1, 0]);
+        zip64.extend_from_slice(&[0u8; 14]);
+        let path = write_temp(tmp.path(), "extract_info_zip64.zip", &zip64);
+        assert_eq!(zip_tail_info(&path).unwrap(), (true, true));
+    }
+
+    #[test]
+    fn test_archive_info() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let sources = [dir.join("a.txt"), dir.join("b.txt")];
+        fs::write(&sources[0], "aaa").unwrap();
+        fs::write(&sources[1], "bbbbb").unwrap();
+
+        for (name, password) in [("t.tar.gz", ""), ("t.7z", ""), ("s.7z", "secret")] {
+            let path = dir.join(name);
+            let format = ArchiveFormat::from_path(&path);
+            let options = CreateOptions {
+                encryption: (!password.is_empty()).then(|| Encryption {
+                    password: password.to_string().into(),
+                    encrypt_header: false,
+                }),
+                ..Default::default()
+            };
+            create_archive(&sources, &path, format, &options, |_| {}).unwrap();
+
+            let info = create_handler(&path, format, password.to_string())
+                .archive_info()
+                .unwrap();
+            assert_eq!(info.entry_count, 2, "{}", name);
+            assert_eq!(info.unpacked_size, 8, "{}", name);
+            assert_eq!(info.is_encrypted, !password.is_empty(), "{}", name);
+            assert_eq!(
+                info.packed_size,
+                fs::metadata(&path).unwrap().len(),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,2143 @@
+//! 压缩包查看与解压引擎
+//!
+//! 支持 zip、tar（gz、xz、bz2、zst）、7z 与 rar，不依赖 Tauri，可在服务端直接使用。
+//! 按扩展名识别格式，再通过 [`create_handler`] 得到对应的 [`ArchiveHandler`]：
+//!
+//! ```no_run
+//! use extract_core::{create_handler, ArchiveFormat, ExtractOptions};
+//! use std::path::Path;
+//!
+//! let path = Path::new("data.tar.gz");
+//! let mut handler = create_handler(path, ArchiveFormat::from_path(path), String::new());
+//! let info = handler.archive_info()?;
+//! println!("{} 个条目", info.entry_count);
+//! handler.extract(Path::new("out"), &ExtractOptions::default())?;
+//! # Ok::<(), extract_core::ArchiveError>(())
+//! ```
+//!
+//! 所有操作返回 [`ResultR`]，错误统一为 [`ArchiveError`]；密码缺失或错误时信息为 `密码错误`。
+
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use links::Links;
+use options::EntryFilter;
+use preview::copy_range;
+use rarstream::{RarLink, RarStream};
+use restore::{EntryMeta, Restorer};
+use spool::{LinkTargets, Spool};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::{fs::File, path::Path};
+use tar::Archive;
+use unrar::error::UnrarError;
+use writer::EntryKind;
+use xattrs::Xattrs;
+use xz2::read::XzDecoder;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+mod compare;
+mod convert;
+mod diff;
+mod edit;
+pub mod extutil;
+mod gzindex;
+pub mod index;
+pub mod info;
+mod links;
+pub mod nested;
+pub mod options;
+pub mod plustree;
+pub mod preview;
+mod rarstream;
+mod recursive;
+mod repair;
+mod restore;
+pub mod session;
+mod spool;
+pub mod tarindex;
+#[cfg(test)]
+mod testutil;
+pub mod volume;
+mod xattrs;
+mod xzindex;
+pub mod writer;
+
+pub use compare::{compare_archive, Change, CompareReport, MetadataChange, Modified};
+pub use convert::{convert_archive, StreamEntry};
+pub use diff::{diff_archives, ArchiveDiff, DiffEntry, DiffStatus, EntryDigest};
+pub use edit::{edit_archive, replace_entry, EditOp};
+pub use extutil::ArchiveFormat;
+pub use index::{ArchiveIndex, DirItem, DirPage, DirSort, SortKey};
+pub use info::{ArchiveBlock, ArchiveInfo, TestFailure, TestReport};
+pub use nested::{open_handler, NestedArchive, MAX_NESTED_DEPTH};
+pub use options::{
+    CompressionLevel, CompressionOptions, CreateOptions, DiffOptions, Encryption, ExtractOptions,
+    LinkPolicy, RecursiveExtract, SalvageOptions, SizeLimit, ZipMethod,
+};
+pub use plustree::TreeNode;
+pub use preview::{ByteRange, EntryPreview, PREVIEW_LIMIT};
+pub use repair::{salvage_archive, SalvageReport};
+pub use session::ArchiveSession;
+pub use spool::private_dir;
+pub use tarindex::{EntrySpan, TarIndex};
+
+/// 压缩包中的一个条目（文件或目录）
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub path: String,
+    pub parent_path: Option<String>,
+    pub size: u64,
+    pub is_dir: bool,
+    pub modified: Option<String>,
+    /// 条目本身是支持的压缩包，可用 `外层!/条目` 形式的虚拟路径进入
+    pub is_archive: bool,
+}
+
+/// 引擎统一的返回类型
+pub type ResultR<T, E = ArchiveError> = core::result::Result<T, E>;
+
+/// 逐个产出条目的迭代器，读取出错时产出 `Err`
+pub type Entries<'a> = Box<dyn Iterator<Item = ResultR<ArchiveEntry>> + 'a>;
+
+/// [`ArchiveHandler::for_each_entry`] 的回调，读取条目内容，返回 `false` 时停止
+pub type EntryVisitor<'a> = dyn FnMut(&StreamEntry, &mut dyn Read) -> ResultR<bool> + 'a;
+
+/// 后台线程与迭代器之间最多缓存的条目数
+const ENTRY_CHANNEL_BOUND: usize = 256;
+
+/// 以内容保存的链接目标最多读取的字节数
+const MAX_LINK_TARGET: usize = 4096;
+
+// -------------------------
+// 类型定义
+// -------------------------
+
+/// 统一错误类型
+#[derive(Debug, thiserror::Error, Serialize)]
+pub enum ArchiveError {
+    #[error("Error: {0}")]
+    MsgError(String),
+}
+
+// -------------------------
+// 核心 Trait 设计
+// -------------------------
+
+/// 单个压缩包的操作接口，由 [`create_handler`] 按格式创建
+///
+/// 处理器会缓存解析过的目录或头部，同一个处理器上的后续操作不再重复解析。
+pub trait ArchiveHandler: Send {
+    /// 按压缩包中的顺序逐个读取条目，不在内存中保留完整列表
+    fn entries(&mut self) -> ResultR<Entries<'_>>;
+    /// 获取文件列表（树形），由 `entries` 的结果构建
+    fn list_files(&mut self) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
+        let entries = self.entries()?.collect::<ResultR<Vec<_>>>()?;
+        Ok(build_entry_tree(entries))
+    }
+    /// 执行解压
+    fn extract(&mut self, target_dir: &std::path::Path, options: &ExtractOptions) -> ResultR<()>;
+    /// 把单个条目的内容（或其中一段）写入 `out`，返回条目的完整大小
+    fn read_entry(&mut self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64>;
+    /// 获取压缩包整体信息
+    fn archive_info(&mut self) -> ResultR<ArchiveInfo>;
+    /// 解压到空设备，校验所有条目
+    fn test(&mut self) -> ResultR<TestReport>;
+    /// 按压缩包中的顺序逐个读取条目及其内容，不解压到目录
+    ///
+    /// 目录与符号链接的内容为空，链接目标见 [`EntryKind::Symlink`](writer::EntryKind::Symlink)；
+    /// 硬链接作为内容与目标相同的普通文件给出，目标不存在时跳过；设备文件等其他类型的条目被跳过。
+    fn for_each_entry(&mut self, visit: &mut EntryVisitor<'_>) -> ResultR<()>;
+    /// 验证密码：读取第一个加密条目的开头，密码错误时返回 `密码错误`；没有加密的条目时直接返回
+    fn check_password(&mut self) -> ResultR<()> {
+        Ok(())
+    }
+}
+
+/// 验证密码时最多读取的字节数，较小的条目可以读完并校验 CRC
+const PASSWORD_CHECK_LIMIT: u64 = 64 << 10;
+
+/// 把条目列表构建为以 `/` 为根的树
+///
+/// 压缩包中未单独记录的上级目录会被补齐；同级条目目录在前，再按名称自然排序。
+pub fn build_entry_tree(mut entries: Vec<ArchiveEntry>) -> Vec<TreeNode<ArchiveEntry>> {
+    complete_dirs(&mut entries);
+    plustree::TreeNode::build_sorted_tree(
+        entries,
+        String::from("/"),
+        |i| i.path.clone(),
+        |i| i.parent_path.clone().unwrap_or_default(),
+        |a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| plustree::natural_cmp(entry_name(&a.path), entry_name(&b.path)))
+        },
+    )
+}
+
+/// 目录路径统一以 `/` 结尾，并补齐压缩包中未单独记录的上级目录
+pub(crate) fn complete_dirs(entries: &mut Vec<ArchiveEntry>) {
+    // 目录的键统一以 `/` 结尾，与子条目的 parent_path 对应
+    for entry in entries.iter_mut() {
+        if entry.is_dir && !entry.path.ends_with('/') {
+            entry.path.push('/');
+        }
+    }
+
+    // 构建缺失的文件夹
+    let mut known: HashSet<String> = entries.iter().map(|e| e.path.clone()).collect();
+    let mut missing = Vec::new();
+    for entry in entries.iter() {
+        let mut parent = entry.parent_path.clone();
+        while let Some(dir) = parent.filter(|d| d != "/" && !known.contains(d)) {
+            known.insert(dir.clone());
+            parent = Some(parent_dir(&dir));
+            missing.push(ArchiveEntry {
+                name: entry_name(&dir).to_string(),
+                path: dir,
+                parent_path: parent.clone(),
+                size: 0,
+                is_dir: true,
+                modified: None,
+                is_archive: false,
+            });
+        }
+    }
+    entries.extend(missing);
+}
+
+/// 条目路径的最后一段，目录去掉末尾的 `/`
+pub(crate) fn entry_name(path: &str) -> &str {
+    let path = path.trim_end_matches('/');
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// 上级目录路径，以 `/` 结尾，顶层为 `/`
+fn parent_dir(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(i) => path[..=i].to_string(),
+        None => String::from("/"),
+    }
+}
+
+/// 在后台线程中运行只能借用式遍历的读取过程，经有界通道逐个产出条目
+///
+/// `read` 每产出一个条目调用一次 `send`，返回 `false` 表示迭代器已被丢弃，应停止读取。
+fn spawn_entries<F>(read: F) -> Entries<'static>
+where
+    F: FnOnce(&mut dyn FnMut(ResultR<ArchiveEntry>) -> bool) + Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::sync_channel(ENTRY_CHANNEL_BOUND);
+    std::thread::spawn(move || read(&mut |entry| tx.send(entry).is_ok()));
+    Box::new(rx.into_iter())
+}
+
+// -------------------------
+// 具体类型实现（空结构体示例）
+// -------------------------
+
+pub struct ZipHandler {
+    // 内部状态存储
+    archive_path: PathBuf,
+
+    password: String,
+    /// 已解析中央目录的压缩包，第一次使用时打开
+    archive: Option<ZipArchive<File>>,
+}
+
+impl ArchiveHandler for ZipHandler {
+    fn entries(&mut self) -> ResultR<Entries<'_>> {
+        let custom_error = |e: ZipError| match e {
+            zip::result::ZipError::FileNotFound => {
+                ArchiveError::MsgError(String::from("文件未找到"))
+            }
+            zip::result::ZipError::InvalidPassword => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            _ => ArchiveError::MsgError(String::from("打开文件错误")),
+        };
+        let path = &self.archive_path;
+        let file = File::open(path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        let mut archive = ZipArchive::new(file).map_err(custom_error)?;
+        let password = self.password.clone();
+
+        Ok(Box::new((0..archive.len()).filter_map(move |i| {
+            let file = if password.is_empty() {
+                archive.by_index(i)
+            } else {
+                archive.by_index_decrypt(i, password.as_bytes())
+            };
+            let file = match file {
+                Ok(file) => file,
+                Err(e) => return Some(Err(custom_error(e))),
+            };
+
+            let full_path = String::from_utf8(file.name_raw().to_vec()).unwrap_or_default();
+            // 跳过 macOS 系统文件
+            if full_path.starts_with("__MACOSX") {
+                return None;
+            }
+            let is_dir = file.is_dir();
+            let modified = file.last_modified().map(|m| m.to_string());
+            let name = full_path.split('/').next_back().unwrap_or("").to_string();
+            // 获取目录路径
+            let parent_path = Path::new(&full_path).parent().map(|parent| {
+                parent
+                    .to_string_lossy()
+                    .to_string()
+                    .trim_end_matches('/')
+                    .to_string()
+                    + "/"
+            });
+            let is_archive = !is_dir && nested::is_nested_archive(&name);
+            Some(Ok(ArchiveEntry {
+                name,
+                path: full_path.clone(),
+                parent_path,
+                size: if is_dir { 0 } else { file.size() },
+                is_dir,
+                modified,
+                is_archive,
+            }))
+        })))
+    }
+
+    fn extract(&mut self, target_dir: &std::path::Path, options: &ExtractOptions) -> ResultR<()> {
+        let custom_error = |e: ZipError| match e {
+            zip::result::ZipError::FileNotFound => {
+                ArchiveError::MsgError(String::from("文件未找到"))
+            }
+            zip::result::ZipError::InvalidPassword => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            zip::result::ZipError::UnsupportedArchive(estr) => match estr {
+                zip::result::ZipError::PASSWORD_REQUIRED => {
+                    ArchiveError::MsgError(String::from("密码错误"))
+                }
+                _ => ArchiveError::MsgError(String::from("打开文件错误")),
+            },
+            _ => ArchiveError::MsgError(String::from("解压文件错误")),
+        };
+
+        let filter = EntryFilter::new(options)?;
+        let archive = cached_zip(&self.archive_path, &mut self.archive)?;
+
+        // 创建目标目录（如果不存在）
+        if !target_dir.exists() {
+            std::fs::create_dir_all(target_dir)
+                .map_err(|e| ArchiveError::MsgError(format!("创建目标目录失败: {}", e)))?;
+        }
+
+        let mut restorer = Restorer::new(options);
+        let mut links = Links::new(target_dir, options);
+        let mut xattrs = Xattrs::new(target_dir, options);
+
+        // 遍历并解压所有文件
+        for i in 0..archive.len() {
+            let mut file = if self.password.is_empty() {
+                archive.by_index(i).map_err(custom_error)?
+            } else {
+                archive
+                    .by_index_decrypt(i, self.password.as_bytes())
+                    .map_err(custom_error)?
+            };
+
+            let full_path = String::from_utf8(file.name_raw().to_vec()).unwrap_or_default();
+
+            // macOS 系统文件：开启扩展属性时合并 AppleDouble，否则跳过
+            if full_path.starts_with("__MACOSX") {
+                if let Some(target) = xattrs
+                    .companion_target(&full_path)
+                    .filter(|_| file.is_file())
+                {
+                    let mut data = Vec::new();
+                    file.read_to_end(&mut data)
+                        .map_err(|e| ArchiveError::MsgError(format!("读取文件失败: {}", e)))?;
+                    xattrs.companion(target, data);
+                }
+                continue;
+            }
+            if !filter.allows(&full_path) {
+                continue;
+            }
+
+            // 构建目标路径，跳过指向目标目录之外的条目
+            let outpath = match restore::safe_join(target_dir, &full_path) {
+                Some(outpath) => outpath,
+                None => {
+                    log::warn!("跳过不安全的路径: {}", full_path);
+                    continue;
+                }
+            };
+
+            let (mtime, atime) = zip_times(&file);
+            let (uid, gid) = file.extra_data().map(zip_owner).unwrap_or_default();
+            let meta = EntryMeta {
+                mtime,
+                atime,
+                mode: file.unix_mode(),
+                uid,
+                gid,
+            };
+
+            // 符号链接的内容即链接目标
+            if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target)
+                    .map_err(|e| ArchiveError::MsgError(format!("读取链接失败: {}", e)))?;
+                links.symlink(outpath, &target, meta);
+                continue;
+            }
+
+            // 创建目录结构
+            if (*file.name()).ends_with('/') {
+                std::fs::create_dir_all(&outpath)
+                    .map_err(|e| ArchiveError::MsgError(format!("创建目录失败: {}", e)))?;
+                restorer.dir(outpath, meta);
+            } else {
+                // 确保父目录存在
+                if let Some(parent) = outpath.parent() {
+                    if !parent.exists() {
+                        std::fs::create_dir_all(parent).map_err(|e| {
+                            ArchiveError::MsgError(format!("创建父目录失败: {}", e))
+                        })?;
+                    }
+                }
+
+                // 创建文件并写入内容
+                let mut outfile = std::fs::File::create(&outpath)
+                    .map_err(|e| ArchiveError::MsgError(format!("创建文件失败: {}", e)))?;
+
+                std::io::copy(&mut file, &mut options.limited(&mut outfile))
+                    .map_err(|e| ArchiveError::MsgError(format!("写入文件失败: {}", e)))?;
+                drop(outfile);
+
+                // 设置文件修改时间与权限
+                restorer.file(&outpath, &meta)?;
+            }
+        }
+
+        links.finish(&restorer)?;
+        xattrs.finish();
+        restorer.finish()
+    }
+
+    fn test(&mut self) -> ResultR<TestReport> {
+        let archive = cached_zip(&self.archive_path, &mut self.archive)?;
+
+        let mut report = TestReport::default();
+        for i in 0..archive.len() {
+            let file = if self.password.is_empty() {
+                archive.by_index(i)
+            } else {
+                archive.by_index_decrypt(i, self.password.as_bytes())
+            };
+            let mut file = match file {
+                Ok(file) => file,
+                Err(ZipError::InvalidPassword)
+                | Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+                    return Err(ArchiveError::MsgError(String::from("密码错误")))
+                }
+                Err(e) => {
+                    report.fail(format!("#{}", i), e);
+                    continue;
+                }
+            };
+            let name = file.name().to_string();
+            report.tested += 1;
+            // 读到末尾时校验 CRC
+            if let Err(e) = std::io::copy(&mut file, &mut std::io::sink()) {
+                report.fail(name, e);
+            }
+        }
+        Ok(report)
+    }
+
+    fn read_entry(&mut self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64> {
+        let archive = cached_zip(&self.archive_path, &mut self.archive)?;
+        // 与 entries 一致，按原始文件名匹配
+        let index = (0..archive.len())
+            .find(|&i| {
+                archive.by_index_raw(i).is_ok_and(|f| {
+                    String::from_utf8(f.name_raw().to_vec()).unwrap_or_default() == entry
+                })
+            })
+            .ok_or_else(|| ArchiveError::MsgError(format!("条目不存在: {}", entry)))?;
+        let file = if self.password.is_empty() {
+            archive.by_index(index)
+        } else {
+            archive.by_index_decrypt(index, self.password.as_bytes())
+        };
+        let mut file = match file {
+            Ok(file) => file,
+            Err(ZipError::InvalidPassword)
+            | Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+                return Err(ArchiveError::MsgError(String::from("密码错误")))
+            }
+            Err(e) => return Err(ArchiveError::MsgError(e.to_string())),
+        };
+        copy_range(&mut file, range, out).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        Ok(file.size())
+    }
+
+    fn archive_info(&mut self) -> ResultR<ArchiveInfo> {
+        let path = &self.archive_path;
+        let packed_size = std::fs::metadata(path)
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+            .len();
+        let archive = cached_zip(path, &mut self.archive)?;
+        let (is_zip64, is_multi_volume) = info::zip_tail_info(path)
+            .map_err(|e| ArchiveError::MsgError(format!("读取文件错误: {}", e)))?;
+
+        let comment = String::from_utf8_lossy(archive.comment()).to_string();
+        let mut info = ArchiveInfo {
+            format: ArchiveFormat::Zip,
+            comment: (!comment.is_empty()).then_some(comment),
+            entry_count: archive.len() as u64,
+            packed_size,
+            is_zip64,
+            is_multi_volume,
+            ..Default::default()
+        };
+
+        // 只读取头部信息，无需密码
+        for i in 0..archive.len() {
+            let file = archive
+                .by_index_raw(i)
+                .map_err(|e| ArchiveError::MsgError(format!("读取文件错误: {}", e)))?;
+            info.unpacked_size += file.size();
+            info.is_encrypted |= file.encrypted();
+        }
+        Ok(info)
+    }
+
+    fn for_each_entry(&mut self, visit: &mut EntryVisitor<'_>) -> ResultR<()> {
+        let custom_error = |e: ZipError| match e {
+            ZipError::InvalidPassword
+            | ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            _ => ArchiveError::MsgError(format!("打开文件错误: {}", e)),
+        };
+        let archive = cached_zip(&self.archive_path, &mut self.archive)?;
+
+        for i in 0..archive.len() {
+            let mut file = if self.password.is_empty() {
+                archive.by_index(i)
+            } else {
+                archive.by_index_decrypt(i, self.password.as_bytes())
+            }
+            .map_err(custom_error)?;
+            let path = file.name().to_string();
+            // 符号链接的内容即链接目标
+            let kind = if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target)
+                    .map_err(|e| ArchiveError::MsgError(format!("读取链接失败: {}", e)))?;
+                EntryKind::Symlink(target)
+            } else if file.is_dir() {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+            let (mtime, _) = zip_times(&file);
+            let (uid, gid) = file.extra_data().map(zip_owner).unwrap_or_default();
+            let mut entry = StreamEntry::new(&path, kind, file.size(), mtime, file.unix_mode());
+            entry.attrs.uid = uid.unwrap_or(0) as u64;
+            entry.attrs.gid = gid.unwrap_or(0) as u64;
+            if !visit(&entry, &mut file)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_password(&mut self) -> ResultR<()> {
+        let archive = cached_zip(&self.archive_path, &mut self.archive)?;
+        let Some(index) =
+            (0..archive.len()).find(|&i| archive.by_index_raw(i).is_ok_and(|f| f.encrypted()))
+        else {
+            return Ok(());
+        };
+        // 打开时按加密头部中的校验值验证密码
+        match archive.by_index_decrypt(index, self.password.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(ZipError::InvalidPassword)
+            | Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+                Err(ArchiveError::MsgError(String::from("密码错误")))
+            }
+            Err(e) => Err(ArchiveError::MsgError(e.to_string())),
+        }
+    }
+}
+
+/// 返回缓存的压缩包，第一次调用时打开并解析中央目录
+fn cached_zip<'a>(
+    path: &Path,
+    cache: &'a mut Option<ZipArchive<File>>,
+) -> ResultR<&'a mut ZipArchive<File>> {
+    let archive = match cache.take() {
+        Some(archive) => archive,
+        None => {
+            let file = File::open(path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+            ZipArchive::new(file)
+                .map_err(|e| ArchiveError::MsgError(format!("打开文件错误: {}", e)))?
+        }
+    };
+    Ok(cache.insert(archive))
+}
+
+/// 读取 zip 条目的 (修改时间, 访问时间)
+///
+/// 优先使用扩展时间戳（UTC），否则回退到 DOS 时间（本地时区）。
+fn zip_times(file: &zip::read::ZipFile) -> (Option<i64>, Option<i64>) {
+    let mut times = (None, None);
+    for field in file.extra_data_fields() {
+        let zip::extra_fields::ExtraField::ExtendedTimestamp(ts) = field;
+        times = (
+            ts.mod_time().map(|t| t as i64),
+            ts.ac_time().map(|t| t as i64),
+        );
+    }
+    if times.0.is_none() {
+        times.0 = file.last_modified().and_then(|dt| {
+            restore::dos_time_to_unix(((dt.datepart() as u32) << 16) | dt.timepart() as u32)
+        });
+    }
+    times
+}
+
+/// 从 Info-ZIP unix 扩展字段（0x7875）读取 (uid, gid)
+fn zip_owner(extra: &[u8]) -> (Option<u32>, Option<u32>) {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        let data = match rest.get(4..4 + len) {
+            Some(data) => data,
+            None => break,
+        };
+        // 版本(1) uid长度(1) uid gid长度(1) gid
+        if id == 0x7875 && data.first() == Some(&1) {
+            let read = |bytes: &[u8]| -> Option<u32> {
+                match bytes.len() {
+                    1..=4 => {
+                        let mut buf = [0u8; 4];
+                        buf[..bytes.len()].copy_from_slice(bytes);
+                        Some(u32::from_le_bytes(buf))
+                    }
+                    _ => None,
+                }
+            };
+            let uid_len = *data.get(1).unwrap_or(&0) as usize;
+            let uid = data.get(2..2 + uid_len).and_then(read);
+            let gid_len = *data.get(2 + uid_len).unwrap_or(&0) as usize;
+            let gid = data.get(3 + uid_len..3 + uid_len + gid_len).and_then(read);
+            return (uid, gid);
+        }
+        rest = &rest[4 + len..];
+    }
+    (None, None)
+}
+
+// 其他格式类似
+
+pub struct TarHandler {
+    // 内部状态存储
+    // tar 本身不支持加密，无需密码
+    archive_path: std::path::PathBuf,
+    archive_format: ArchiveFormat,
+    /// 会话建立的随机访问索引，读取单个条目或只解压部分条目时从最近的检查点开始
+    index: Option<Arc<TarIndex>>,
+}
+
+impl ArchiveHandler for TarHandler {
+    fn entries(&mut self) -> ResultR<Entries<'_>> {
+        // 根据不同格式创建对应的解码器
+        let reader = self.open_reader()?;
+
+        // tar 的条目迭代器借用 Archive，放到后台线程中读取
+        Ok(spawn_entries(move |send| {
+            let mut archive = Archive::new(reader);
+            let entries = match archive.entries() {
+                Ok(entries) => entries,
+                Err(e) => {
+                    send(Err(ArchiveError::MsgError(e.to_string())));
+                    return;
+                }
+            };
+            for entry in entries {
+                let entry = entry
+                    .map_err(|e| ArchiveError::MsgError(e.to_string()))
+                    .and_then(|entry| tar_entry(&entry));
+                let keep_going = match entry {
+                    Ok(None) => true,
+                    Ok(Some(entry)) => send(Ok(entry)),
+                    Err(e) => {
+                        send(Err(e));
+                        false
+                    }
+                };
+                if !keep_going {
+                    return;
+                }
+            }
+        }))
+    }
+
+    fn extract(&mut self, target_dir: &std::path::Path, options: &ExtractOptions) -> ResultR<()> {
+        let filter = EntryFilter::new(options)?;
+        let mut archive = Archive::new(self.open_selection(options, &filter)?);
+        // 时间与权限统一由 Restorer 处理
+        archive.set_preserve_mtime(false);
+        archive.set_preserve_permissions(false);
+
+        std::fs::create_dir_all(target_dir)
+            .map_err(|e| ArchiveError::MsgError(format!("创建目标目录失败: {}", e)))?;
+
+        let mut restorer = Restorer::new(options);
+        let mut links = Links::new(target_dir, options);
+        let mut xattrs = Xattrs::new(target_dir, options);
+        for entry in archive
+            .entries()
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+        {
+            let mut entry = entry.map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+            let full_path = entry
+                .path()
+                .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+                .to_string_lossy()
+                .to_string();
+
+            // macOS 的 `._name` 附属文件：开启扩展属性时合并到对应条目
+            let mut companion_data = None;
+            if let Some(target) = xattrs
+                .companion_target(&full_path)
+                .filter(|_| entry.header().entry_type().is_file())
+            {
+                let mut data = Vec::new();
+                entry
+                    .read_to_end(&mut data)
+                    .map_err(|e| ArchiveError::MsgError(format!("读取文件失败: {}", e)))?;
+                match xattrs.companion(target, data) {
+                    None => continue,
+                    Some(data) => companion_data = Some(data),
+                }
+            }
+
+            // 跳过 macOS 系统文件与未选中的条目
+            if full_path.starts_with("__MACOSX") || !filter.allows(&full_path) {
+                continue;
+            }
+            let outpath = match restore::safe_join(target_dir, &full_path) {
+                Some(outpath) => outpath,
+                None => {
+                    log::warn!("跳过不安全的路径: {}", full_path);
+                    continue;
+                }
+            };
+
+            let atime = tar_atime(&mut entry);
+            let attrs = match entry.pax_extensions() {
+                Ok(Some(extensions)) if xattrs.enabled() => xattrs::from_pax(
+                    extensions
+                        .flatten()
+                        .filter_map(|ext| Some((ext.key().ok()?, ext.value_bytes()))),
+                ),
+                _ => Vec::new(),
+            };
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            let meta = EntryMeta {
+                mtime: header.mtime().ok().map(|t| t as i64),
+                atime,
+                mode: header.mode().ok(),
+                uid: header.uid().ok().map(|id| id as u32),
+                gid: header.gid().ok().map(|id| id as u32),
+            };
+
+            if entry_type.is_symlink() || entry_type.is_hard_link() {
+                let target = entry
+                    .link_name()
+                    .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if entry_type.is_symlink() {
+                    links.symlink(outpath, &target, meta);
+                } else {
+                    links.hardlink(outpath, &target, meta);
+                }
+                continue;
+            }
+
+            // tar 条目的数据长度即头部中的大小，写入前计入上限
+            if entry_type.is_file() || entry_type.is_contiguous() {
+                let size = entry.header().size().unwrap_or(0);
+                options
+                    .charge(size)
+                    .map_err(|e| ArchiveError::MsgError(format!("写入文件失败: {}", e)))?;
+            }
+
+            // 不是 AppleDouble 的 `._name` 已读出，按普通文件写入
+            if let Some(data) = companion_data {
+                if let Some(parent) = outpath.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        ArchiveError::MsgError(format!("创建父目录失败: {}", e))
+                    })?;
+                }
+                std::fs::write(&outpath, data)
+                    .map_err(|e| ArchiveError::MsgError(format!("写入文件失败: {}", e)))?;
+            } else if !entry
+                .unpack_in(target_dir)
+                .map_err(|e| ArchiveError::MsgError(format!("解压文件错误: {}", e)))?
+            {
+                continue;
+            }
+
+            xattrs.apply(&outpath, &attrs);
+            if entry_type.is_dir() {
+                restorer.dir(outpath, meta);
+            } else if entry_type.is_file() || entry_type.is_contiguous() {
+                restorer.file(&outpath, &meta)?;
+            }
+        }
+
+        links.finish(&restorer)?;
+        xattrs.finish();
+        restorer.finish()
+    }
+
+    fn test(&mut self) -> ResultR<TestReport> {
+        let mut archive = Archive::new(self.open_reader()?);
+        let mut report = TestReport::default();
+        for entry in archive
+            .entries()
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+        {
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report.fail("", e);
+                    return Ok(report);
+                }
+            };
+            let path = entry
+                .path()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            report.tested += 1;
+            // 流式格式出错后无法继续定位后续条目
+            if let Err(e) = std::io::copy(&mut entry, &mut std::io::sink()) {
+                report.fail(path, e);
+                return Ok(report);
+            }
+        }
+        // 读完剩余数据，让 gzip/xz 校验尾部
+        if let Err(e) = std::io::copy(&mut archive.into_inner(), &mut std::io::sink()) {
+            report.fail("", e);
+        }
+        Ok(report)
+    }
+
+    fn read_entry(&mut self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64> {
+        if let Some(index) = &self.index {
+            return index.copy_entry(&self.archive_path, entry, range, out);
+        }
+        let mut archive = Archive::new(self.open_reader()?);
+        for item in archive
+            .entries()
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+        {
+            let mut item = item.map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+            let path = item
+                .path()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if path == entry {
+                copy_range(&mut item, range, out)
+                    .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+                return Ok(item.size());
+            }
+        }
+        Err(ArchiveError::MsgError(format!("条目不存在: {}", entry)))
+    }
+
+    fn archive_info(&mut self) -> ResultR<ArchiveInfo> {
+        let file =
+            File::open(&self.archive_path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        let packed_size = file
+            .metadata()
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+            .len();
+
+        let mut info = ArchiveInfo {
+            format: self.archive_format,
+            packed_size,
+            // 整体流式压缩，等同于固实压缩
+            is_solid: self.archive_format != ArchiveFormat::Tar,
+            ..Default::default()
+        };
+
+        let mut count_entries = |reader: &mut dyn Read| -> ResultR<()> {
+            let mut archive = Archive::new(reader);
+            for entry in archive
+                .entries()
+                .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+            {
+                let entry = entry.map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+                info.entry_count += 1;
+                info.unpacked_size += entry.header().size().unwrap_or(0);
+            }
+            Ok(())
+        };
+
+        // gzip 头部可能带有注释
+        let mut comment = None;
+        match self.archive_format {
+            ArchiveFormat::TarGz | ArchiveFormat::Gz => {
+                let mut decoder = GzDecoder::new(file);
+                count_entries(&mut decoder)?;
+                comment = decoder
+                    .header()
+                    .and_then(|h| h.comment())
+                    .map(|c| String::from_utf8_lossy(c).to_string());
+            }
+            ArchiveFormat::TarXz => count_entries(&mut XzDecoder::new(file))?,
+            ArchiveFormat::TarZst => count_entries(
+                &mut zstd::Decoder::new(file).map_err(|e| ArchiveError::MsgError(e.to_string()))?,
+            )?,
+            ArchiveFormat::TarBz2 | ArchiveFormat::Bz2 => count_entries(&mut BzDecoder::new(file))?,
+            ArchiveFormat::Tar => count_entries(&mut BufReader::new(file))?,
+            _ => return Err(ArchiveError::MsgError("不支持的格式".to_string())),
+        }
+        info.comment = comment;
+        Ok(info)
+    }
+
+    fn for_each_entry(&mut self, visit: &mut EntryVisitor<'_>) -> ResultR<()> {
+        let io_err = |e: std::io::Error| ArchiveError::MsgError(format!("读取条目失败: {}", e));
+        let mut archive = Archive::new(self.open_reader()?);
+        // 第一次遇到硬链接时建立，之后出现的目标条目随读随存
+        let mut targets: Option<LinkTargets> = None;
+        for (position, item) in archive
+            .entries()
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+            .enumerate()
+        {
+            let mut item = item.map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+            let path = item
+                .path()
+                .map(|p| p.to_string_lossy().to_string())
+                .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+            let header = item.header();
+            let entry_type = header.entry_type();
+            let kind = if entry_type.is_dir() {
+                EntryKind::Dir
+            } else if entry_type.is_symlink() {
+                let target = item
+                    .link_name()
+                    .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                EntryKind::Symlink(target)
+            } else if entry_type.is_file()
+                || entry_type.is_contiguous()
+                || entry_type.is_hard_link()
+            {
+                EntryKind::File
+            } else {
+                log::warn!("跳过不支持的条目: {}", path);
+                continue;
+            };
+            let header = item.header();
+            let mut entry = StreamEntry::new(
+                &path,
+                kind,
+                item.size(),
+                header.mtime().ok().map(|t| t as i64),
+                header.mode().ok(),
+            );
+            entry.attrs.uid = header.uid().unwrap_or(0);
+            entry.attrs.gid = header.gid().unwrap_or(0);
+            let more = if entry_type.is_hard_link() {
+                let target = item
+                    .link_name()
+                    .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                match self.visit_hardlink(entry, &target, position, &mut targets, visit)? {
+                    Some(more) => more,
+                    None => {
+                        log::warn!("跳过目标不存在的硬链接: {} -> {}", path, target);
+                        true
+                    }
+                }
+            } else if let Some((targets, name)) = targets
+                .as_mut()
+                .filter(|_| entry.kind == EntryKind::File)
+                .and_then(|t| {
+                    let name = convert::safe_name(&path).filter(|n| t.wants(n))?;
+                    Some((t, name))
+                })
+            {
+                let (mut file, _) = targets.save(&name, &mut item).map_err(io_err)?;
+                visit(&entry, &mut file)?
+            } else {
+                visit(&entry, &mut item)?
+            };
+            if !more {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TarHandler {
+    /// 带随机访问索引的处理器，`index` 须由同一个压缩包建立
+    pub(crate) fn with_index(path: &Path, format: ArchiveFormat, index: Arc<TarIndex>) -> Self {
+        TarHandler {
+            archive_path: path.to_path_buf(),
+            archive_format: format,
+            index: Some(index),
+        }
+    }
+
+    /// 解压用的 tar 流：只解压部分条目时借助索引从第一个选中的条目开始，读到最后一个为止
+    ///
+    /// 开启扩展属性时 AppleDouble 附属文件不在选中范围内，仍从头读取。
+    fn open_selection(
+        &self,
+        options: &ExtractOptions,
+        filter: &EntryFilter,
+    ) -> ResultR<Box<dyn Read + Send>> {
+        let selection = self
+            .index
+            .as_ref()
+            .filter(|_| !options.include.is_empty() && !options.restore_xattrs)
+            .and_then(|index| Some((index, index.selection(|path| filter.allows(path))?)));
+        match selection {
+            Some((index, range)) => Ok(Box::new(
+                index
+                    .open_at(&self.archive_path, range.start)?
+                    .take(range.end - range.start),
+            )),
+            None => self.open_reader(),
+        }
+    }
+
+    /// 硬链接没有数据，以目标的内容访问 `entry`，`position` 为链接在压缩包中的序号
+    ///
+    /// 有索引时直接定位目标的数据；否则第一次遇到硬链接时建立 `targets`，之后从副本读取。
+    /// 返回 `visit` 的结果；目标不存在或不是普通文件时返回 `None`。
+    fn visit_hardlink(
+        &self,
+        mut entry: StreamEntry,
+        target: &str,
+        position: usize,
+        targets: &mut Option<LinkTargets>,
+        visit: &mut EntryVisitor<'_>,
+    ) -> ResultR<Option<bool>> {
+        let io_err = |e: std::io::Error| ArchiveError::MsgError(format!("读取条目失败: {}", e));
+        let Some(target) = convert::safe_name(target) else {
+            return Ok(None);
+        };
+        if let Some(index) = &self.index {
+            let span = index
+                .entries()
+                .iter()
+                .find(|e| !e.is_dir && convert::safe_name(&e.path).as_ref() == Some(&target))
+                .and_then(|e| index.span(&e.path));
+            let Some(span) = span else {
+                return Ok(None);
+            };
+            entry.size = span.size;
+            let mut data = index
+                .open_at(&self.archive_path, span.data)?
+                .take(span.size);
+            return visit(&entry, &mut data).map(Some);
+        }
+
+        let targets = match targets {
+            Some(targets) => targets,
+            None => targets.insert(self.link_targets(position)?),
+        };
+        match targets.open(&target) {
+            Some(saved) => {
+                let (mut file, size) = saved.map_err(io_err)?;
+                entry.size = size;
+                visit(&entry, &mut file).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 收集所有硬链接的目标，并保存前 `before` 个条目中已经错过的目标
+    ///
+    /// 先读一遍条目头找出全部目标，再从头读到当前位置补存副本；之后的目标由调用方随读随存。
+    fn link_targets(&self, before: usize) -> ResultR<LinkTargets> {
+        let tar_err = |e: std::io::Error| ArchiveError::MsgError(e.to_string());
+        let mut wanted = HashSet::new();
+        let mut archive = Archive::new(self.open_reader()?);
+        for item in archive.entries().map_err(tar_err)? {
+            let item = item.map_err(tar_err)?;
+            if !item.header().entry_type().is_hard_link() {
+                continue;
+            }
+            if let Some(target) = item.link_name().map_err(tar_err)? {
+                wanted.extend(convert::safe_name(&target.to_string_lossy()));
+            }
+        }
+
+        let mut targets = LinkTargets::new(wanted);
+        let mut archive = Archive::new(self.open_reader()?);
+        for item in archive.entries().map_err(tar_err)?.take(before) {
+            let mut item = item.map_err(tar_err)?;
+            let entry_type = item.header().entry_type();
+            if !entry_type.is_file() && !entry_type.is_contiguous() {
+                continue;
+            }
+            let path = item.path().map_err(tar_err)?;
+            if let Some(name) = convert::safe_name(&path.to_string_lossy()) {
+                if targets.wants(&name) {
+                    targets.save(&name, &mut item).map_err(tar_err)?;
+                }
+            }
+        }
+        Ok(targets)
+    }
+
+    /// 按格式打开解压后的 tar 数据流
+    fn open_reader(&self) -> ResultR<Box<dyn Read + Send>> {
+        let file =
+            File::open(&self.archive_path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        Ok(match self.archive_format {
+            ArchiveFormat::TarGz | ArchiveFormat::Gz => Box::new(GzDecoder::new(file)),
+            ArchiveFormat::TarXz => Box::new(XzDecoder::new(file)),
+            ArchiveFormat::TarZst => Box::new(
+                zstd::Decoder::new(file).map_err(|e| ArchiveError::MsgError(e.to_string()))?,
+            ),
+            ArchiveFormat::TarBz2 | ArchiveFormat::Bz2 => Box::new(BzDecoder::new(file)),
+            ArchiveFormat::Tar => Box::new(file),
+            _ => return Err(ArchiveError::MsgError("不支持的格式".to_string())),
+        })
+    }
+}
+
+/// 把 tar 条目转换为 `ArchiveEntry`，macOS 系统文件返回 `None`
+pub(crate) fn tar_entry<R: Read>(entry: &tar::Entry<R>) -> ResultR<Option<ArchiveEntry>> {
+    let path = entry
+        .path()
+        .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+    let full_path = path.to_string_lossy().to_string();
+
+    // 跳过 macOS 系统文件
+    if full_path.starts_with("__MACOSX") {
+        return Ok(None);
+    }
+
+    let is_dir = entry.header().entry_type().is_dir();
+    let name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let parent_path = path.parent().map(|parent| {
+        parent
+            .to_string_lossy()
+            .to_string()
+            .trim_end_matches('/')
+            .to_string()
+            + "/"
+    });
+
+    let is_archive = !is_dir && nested::is_nested_archive(&name);
+    Ok(Some(ArchiveEntry {
+        name,
+        path: full_path.clone(),
+        parent_path,
+        size: entry.header().size().unwrap_or(0),
+        is_dir,
+        modified: entry.header().mtime().ok().and_then(|m| {
+            chrono::DateTime::from_timestamp(m as i64, 0)
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        }),
+        is_archive,
+    }))
+}
+
+/// 读取 tar 条目的访问时间：PAX `atime` 优先，其次 GNU 头部
+fn tar_atime<R: Read>(entry: &mut tar::Entry<R>) -> Option<i64> {
+    if let Ok(Some(extensions)) = entry.pax_extensions() {
+        for ext in extensions.flatten() {
+            if ext.key() == Ok("atime") {
+                if let Some(t) = ext.value().ok().and_then(|v| v.parse::<f64>().ok()) {
+                    return Some(t as i64);
+                }
+            }
+        }
+    }
+    entry
+        .header()
+        .as_gnu()
+        .and_then(|gnu| gnu.atime().ok())
+        .filter(|&t| t > 0)
+        .map(|t| t as i64)
+}
+
+pub struct SevenZipHandler {
+    // 内部状态存储
+    archive_path: std::path::PathBuf,
+    password: String,
+    /// 已解析（及解密）的头部，第一次使用时读取
+    archive: Option<sevenz_rust::Archive>,
+}
+
+#[test]
+fn testf() {
+    let path = std::path::Path::new(
+        "/Users/apple/Downloads/Compressed/时间序列分析——基于R（第2版）案例数据.zip",
+    );
+    let mut handle = ZipHandler {
+        archive_path: path.to_path_buf(),
+        password: String::from("3"),
+        archive: None,
+    };
+
+    let ff = handle.list_files();
+    println!("{:#?}", ff);
+}
+
+impl ArchiveHandler for SevenZipHandler {
+    fn entries(&mut self) -> ResultR<Entries<'_>> {
+        // 只读取头部信息，不解压数据
+        let archive = cached_sevenz(&self.archive_path, &self.password, &mut self.archive)?;
+
+        Ok(Box::new(archive.files.clone().into_iter().map(|entry| {
+            let full_path = entry.name().to_string();
+            let path = Path::new(&full_path);
+            let is_dir = entry.is_directory();
+            let name = path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let parent_path = path.parent().map(|parent| {
+                parent
+                    .to_string_lossy()
+                    .to_string()
+                    .trim_end_matches('/')
+                    .to_string()
+                    + "/"
+            });
+
+            // 获取修改时间
+            let modified =
+                chrono::DateTime::from_timestamp(entry.last_modified_date().to_unix_time(), 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+
+            let is_archive = !is_dir && nested::is_nested_archive(&name);
+            Ok(ArchiveEntry {
+                name,
+                path: if is_dir {
+                    full_path.clone() + "/"
+                } else {
+                    full_path.clone()
+                },
+                parent_path,
+                size: entry.size(),
+                is_dir,
+                modified,
+                is_archive,
+            })
+        })))
+    }
+
+    fn extract(&mut self, target_dir: &std::path::Path, options: &ExtractOptions) -> ResultR<()> {
+        let custom_err = |e: sevenz_rust::Error| {
+            if e.to_string().contains("Password") {
+                ArchiveError::MsgError(String::from("密码错误"))
+            } else {
+                ArchiveError::MsgError(format!("打开文件错误: {}", e))
+            }
+        };
+
+        let filter = EntryFilter::new(options)?;
+        let mut sz = self.reader()?;
+        std::fs::create_dir_all(target_dir)
+            .map_err(|e| ArchiveError::MsgError(format!("创建目标目录失败: {}", e)))?;
+        let mut restorer = Restorer::new(options);
+        let mut links = Links::new(target_dir, options);
+        let mut xattrs = Xattrs::new(target_dir, options);
+        sz.for_each_entries(|entry, reader| {
+            if let Some(target) = xattrs
+                .companion_target(entry.name())
+                .filter(|_| entry.name().starts_with("__MACOSX") && !entry.is_directory())
+            {
+                let mut data = Vec::new();
+                reader
+                    .read_to_end(&mut data)
+                    .map_err(sevenz_rust::Error::io)?;
+                xattrs.companion(target, data);
+                return Ok(true);
+            }
+            if !filter.allows(entry.name()) {
+                return Ok(true);
+            }
+            let outpath = match restore::safe_join(target_dir, entry.name()) {
+                Some(outpath) => outpath,
+                None => {
+                    log::warn!("跳过不安全的路径: {}", entry.name());
+                    return Ok(true);
+                }
+            };
+            let meta = EntryMeta {
+                mtime: entry
+                    .has_last_modified_date
+                    .then(|| entry.last_modified_date().to_unix_time()),
+                atime: entry
+                    .has_access_date
+                    .then(|| entry.access_date().to_unix_time()),
+                mode: sevenz_unix_mode(entry),
+                ..Default::default()
+            };
+
+            if meta.mode.is_some_and(|m| m & 0o170000 == 0o120000) {
+                let mut target = String::new();
+                reader
+                    .read_to_string(&mut target)
+                    .map_err(sevenz_rust::Error::io)?;
+                links.symlink(outpath, &target, meta);
+                return Ok(true);
+            }
+
+            if entry.is_directory() {
+                std::fs::create_dir_all(&outpath).map_err(sevenz_rust::Error::io)?;
+                restorer.dir(outpath, meta);
+            } else {
+                if let Some(parent) = outpath.parent() {
+                    std::fs::create_dir_all(parent).map_err(sevenz_rust::Error::io)?;
+                }
+                let mut outfile = File::create(&outpath).map_err(sevenz_rust::Error::io)?;
+                std::io::copy(reader, &mut options.limited(&mut outfile))
+                    .map_err(sevenz_rust::Error::io)?;
+                drop(outfile);
+                restorer
+                    .file(&outpath, &meta)
+                    .map_err(|e| sevenz_rust::Error::other(e.to_string()))?;
+            }
+            Ok(true)
+        })
+        .map_err(custom_err)?;
+        links.finish(&restorer)?;
+        xattrs.finish();
+        restorer.finish()
+    }
+
+    fn test(&mut self) -> ResultR<TestReport> {
+        let custom_err = |e: sevenz_rust::Error| {
+            if e.to_string().contains("Password") {
+                ArchiveError::MsgError(String::from("密码错误"))
+            } else {
+                ArchiveError::MsgError(format!("打开文件错误: {}", e))
+            }
+        };
+        let mut sz = self.reader()?;
+
+        let mut report = TestReport::default();
+        let mut current = String::new();
+        let result = sz.for_each_entries(|entry, reader| {
+            current = entry.name().to_string();
+            report.tested += 1;
+            std::io::copy(reader, &mut std::io::sink()).map_err(sevenz_rust::Error::io)?;
+            Ok(true)
+        });
+        if let Err(e) = result {
+            if e.to_string().contains("Password") {
+                return Err(custom_err(e));
+            }
+            report.fail(current, e);
+        }
+        Ok(report)
+    }
+
+    fn read_entry(&mut self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64> {
+        let custom_err = |e: sevenz_rust::Error| {
+            if e.to_string().contains("Password") {
+                ArchiveError::MsgError(String::from("密码错误"))
+            } else {
+                ArchiveError::MsgError(format!("打开文件错误: {}", e))
+            }
+        };
+        let mut sz = self.reader()?;
+
+        // 固实压缩包只能顺序解压到目标条目
+        let mut size = None;
+        sz.for_each_entries(|item, reader| {
+            if item.name() != entry {
+                return Ok(true);
+            }
+            copy_range(reader, range, out).map_err(sevenz_rust::Error::io)?;
+            size = Some(item.size());
+            Ok(false)
+        })
+        .map_err(custom_err)?;
+        size.ok_or_else(|| ArchiveError::MsgError(format!("条目不存在: {}", entry)))
+    }
+
+    fn archive_info(&mut self) -> ResultR<ArchiveInfo> {
+        let archive = cached_sevenz(&self.archive_path, &self.password, &mut self.archive)?;
+
+        let mut blocks: Vec<ArchiveBlock> = archive
+            .folders
+            .iter()
+            .map(|folder| ArchiveBlock {
+                unpacked_size: folder.get_unpack_size(),
+                methods: folder
+                    .coders
+                    .iter()
+                    .map(|coder| {
+                        sevenz_rust::SevenZMethod::by_id(coder.decompression_method_id())
+                            .map(|m| m.name().to_string())
+                            .unwrap_or_else(|| format!("{:02x?}", coder.decompression_method_id()))
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .collect();
+
+        // 每个数据块的压缩大小为其所有打包流之和
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let first = archive.stream_map.folder_first_pack_stream_index[i];
+            let count = archive.folders[i].packed_streams.len();
+            block.packed_size = archive.pack_sizes[first..first + count].iter().sum();
+        }
+        for folder_index in archive.stream_map.file_folder_index.iter().flatten() {
+            blocks[*folder_index].entry_count += 1;
+        }
+
+        // 与其他格式一致，整体的压缩后大小为文件大小，含头部；各数据块只计打包流
+        let packed_size = std::fs::metadata(&self.archive_path)
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
+            .len();
+        Ok(ArchiveInfo {
+            format: ArchiveFormat::SevenZip,
+            entry_count: archive.files.len() as u64,
+            packed_size,
+            unpacked_size: archive.files.iter().map(|f| f.size()).sum(),
+            is_solid: blocks.iter().any(|b| b.entry_count > 1),
+            is_encrypted: blocks.iter().any(|b| {
+                b.methods
+                    .iter()
+                    .any(|m| m == sevenz_rust::SevenZMethod::AES256SHA256.name())
+            }),
+            blocks,
+            ..Default::default()
+        })
+    }
+
+    fn for_each_entry(&mut self, visit: &mut EntryVisitor<'_>) -> ResultR<()> {
+        let custom_err = |e: sevenz_rust::Error| {
+            if e.to_string().contains("Password") {
+                ArchiveError::MsgError(String::from("密码错误"))
+            } else {
+                ArchiveError::MsgError(format!("打开文件错误: {}", e))
+            }
+        };
+        let mut sz = self.reader()?;
+
+        // 回调出错时先停止解压，再返回原始错误
+        let mut failed = None;
+        sz.for_each_entries(|item, reader| {
+            let mode = sevenz_unix_mode(item);
+            let kind = if mode.is_some_and(|m| m & 0o170000 == 0o120000) {
+                let mut target = String::new();
+                reader
+                    .read_to_string(&mut target)
+                    .map_err(sevenz_rust::Error::io)?;
+                EntryKind::Symlink(target)
+            } else if item.is_directory() {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+            let mtime = item
+                .has_last_modified_date
+                .then(|| item.last_modified_date().to_unix_time());
+            let entry = StreamEntry::new(item.name(), kind, item.size(), mtime, mode);
+            visit(&entry, reader).or_else(|e| {
+                failed = Some(e);
+                Ok(false)
+            })
+        })
+        .map_err(custom_err)?;
+        failed.map_or(Ok(()), Err)
+    }
+
+    fn check_password(&mut self) -> ResultR<()> {
+        let password = sevenz_password(&self.password);
+        let archive = cached_sevenz(&self.archive_path, &self.password, &mut self.archive)?;
+        let aes = Some(sevenz_rust::SevenZMethod::AES256SHA256);
+        let Some(block) = archive.folders.iter().position(|folder| {
+            folder.coders.iter().any(|coder| {
+                sevenz_rust::SevenZMethod::by_id(coder.decompression_method_id()) == aes
+            })
+        }) else {
+            return Ok(());
+        };
+        // 只解压第一个加密数据块的开头；密码错误时解密出的数据无法解压或校验失败
+        let mut file =
+            File::open(&self.archive_path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        sevenz_rust::BlockDecoder::new(block, archive, password.as_slice(), &mut file)
+            .for_each_entries(&mut |entry, reader| {
+                if entry.size() == 0 {
+                    return Ok(true);
+                }
+                std::io::copy(&mut reader.take(PASSWORD_CHECK_LIMIT), &mut std::io::sink())
+                    .map_err(sevenz_rust::Error::io)?;
+                Ok(false)
+            })
+            .map_err(|e| {
+                if e.to_string().contains("Password") {
+                    ArchiveError::MsgError(String::from("密码错误"))
+                } else {
+                    ArchiveError::MsgError(format!("打开文件错误: {}", e))
+                }
+            })?;
+        Ok(())
+    }
+}
+
+impl SevenZipHandler {
+    /// 以缓存的头部创建读取器，只重新打开文件，不再读取和解密头部
+    fn reader(&mut self) -> ResultR<sevenz_rust::SevenZReader<File>> {
+        let archive = cached_sevenz(&self.archive_path, &self.password, &mut self.archive)?;
+        let file =
+            File::open(&self.archive_path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        Ok(sevenz_rust::SevenZReader::from_archive(
+            archive.clone(),
+            file,
+            sevenz_password(&self.password),
+        ))
+    }
+}
+
+/// 返回缓存的头部，第一次调用时读取；头部加密时同时验证密码
+fn cached_sevenz<'a>(
+    path: &Path,
+    password: &str,
+    cache: &'a mut Option<sevenz_rust::Archive>,
+) -> ResultR<&'a sevenz_rust::Archive> {
+    let archive = match cache.take() {
+        Some(archive) => archive,
+        None => sevenz_rust::Archive::open_with_password(path, &sevenz_password(password))
+            .map_err(|e| {
+                if e.to_string().contains("Password") {
+                    ArchiveError::MsgError(String::from("密码错误"))
+                } else {
+                    ArchiveError::MsgError(format!("打开文件错误: {}", e))
+                }
+            })?,
+    };
+    Ok(cache.insert(archive))
+}
+
+fn sevenz_password(password: &str) -> sevenz_rust::Password {
+    password
+        .is_empty()
+        .then(sevenz_rust::Password::empty)
+        .unwrap_or(sevenz_rust::Password::from(password))
+}
+
+/// 7z 属性高 16 位在设置了 FILE_ATTRIBUTE_UNIX_EXTENSION 时保存 unix 权限
+pub(crate) fn sevenz_unix_mode(entry: &sevenz_rust::SevenZArchiveEntry) -> Option<u32> {
+    (entry.has_windows_attributes && entry.windows_attributes() & 0x8000 != 0)
+        .then(|| entry.windows_attributes() >> 16)
+}
+
+pub struct RarHandler {
+    // 内部状态存储
+    archive_path: std::path::PathBuf,
+    password: String,
+}
+
+impl ArchiveHandler for RarHandler {
+    fn entries(&mut self) -> ResultR<Entries<'_>> {
+        // 使用unrar库打开RAR文件
+        let archive = if self.password.is_empty() {
+            unrar::Archive::new(&self.archive_path)
+        } else {
+            unrar::Archive::with_password(&self.archive_path, &self.password)
+        };
+
+        // 列出所有文件
+        let list = match archive.open_for_listing() {
+            Ok(list) => list,
+            Err(e) => match e.code {
+                unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
+                    return Err(ArchiveError::MsgError(String::from("密码错误")))
+                }
+                _ => return Err(ArchiveError::MsgError(format!("发生错误: {}", e,))),
+            },
+        };
+
+        Ok(Box::new(list.filter_map(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(ArchiveError::MsgError(e.to_string()))),
+            };
+            let full_path = &entry.filename;
+
+            // 跳过 macOS 系统文件
+            if full_path.starts_with("__MACOSX") {
+                return None;
+            }
+
+            let path = Path::new(&full_path);
+            let is_dir = entry.is_directory();
+            let name = path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let parent_path = path.parent().map(|parent| {
+                parent
+                    .to_string_lossy()
+                    .to_string()
+                    .trim_end_matches('/')
+                    .to_string()
+                    + "/"
+            });
+
+            // 转换时间格式
+            let modified = restore::dos_time_to_unix(entry.file_time)
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+
+            let is_archive = !is_dir && nested::is_nested_archive(&name);
+            Some(Ok(ArchiveEntry {
+                name,
+                path: format!(
+                    "{}{}",
+                    full_path.to_string_lossy(),
+                    if is_dir { "/" } else { "" }
+                ),
+                parent_path,
+                size: entry.unpacked_size,
+                is_dir,
+                modified,
+                is_archive,
+            }))
+        })))
+    }
+
+    fn extract(&mut self, target_dir: &std::path::Path, options: &ExtractOptions) -> ResultR<()> {
+        // 数据经由回调写出，文件与链接都由这里创建，不让 DLL 直接写入目标目录
+        let io_err = |e: std::io::Error| ArchiveError::MsgError(format!("写入文件失败: {}", e));
+        let filter = EntryFilter::new(options)?;
+        let mut archive = RarStream::open(&self.archive_path, &self.password).map_err(rar_error)?;
+        let mut restorer = Restorer::new(options);
+        let mut links = Links::new(target_dir, options);
+        let mut xattrs = Xattrs::new(target_dir, options);
+        while let Some(header) = archive.next_header().map_err(rar_error)? {
+            let fname = header.path.clone();
+            if let Some(target) = xattrs.companion_target(&fname).filter(|_| {
+                fname.starts_with("__MACOSX") && !header.is_dir && header.link.is_none()
+            }) {
+                let mut data = Vec::new();
+                archive
+                    .read_to(&mut data)
+                    .map_err(rar_error)?
+                    .map_err(io_err)?;
+                xattrs.companion(target, data);
+                continue;
+            }
+            let meta = EntryMeta {
+                mtime: restore::dos_time_to_unix(header.file_time),
+                mode: rar_unix_mode(header.file_attr),
+                ..Default::default()
+            };
+
+            // 跳过 macOS 系统文件、未选中的条目与不安全的路径
+            let outpath = match restore::safe_join(target_dir, &fname) {
+                Some(outpath) if !fname.starts_with("__MACOSX") && filter.allows(&fname) => outpath,
+                _ => {
+                    archive.skip().map_err(rar_error)?;
+                    continue;
+                }
+            };
+
+            match header.link {
+                _ if header.is_dir => {
+                    archive.skip().map_err(rar_error)?;
+                    std::fs::create_dir_all(&outpath)
+                        .map_err(|e| ArchiveError::MsgError(format!("创建目录失败: {}", e)))?;
+                    restorer.dir(outpath, meta);
+                }
+                // RAR5 的链接目标记录在头部中
+                Some(RarLink::Symlink(target)) => {
+                    archive.skip().map_err(rar_error)?;
+                    links.symlink(outpath, &target, meta);
+                }
+                Some(RarLink::Hardlink(target)) => {
+                    archive.skip().map_err(rar_error)?;
+                    links.hardlink(outpath, &target, meta);
+                }
+                // RAR3 以内容保存链接目标
+                None if meta.mode.is_some_and(|m| m & 0o170000 == 0o120000) => {
+                    let target = archive.read_head(MAX_LINK_TARGET).map_err(rar_error)?;
+                    if target.is_empty() {
+                        log::warn!("读取链接失败: {}", fname);
+                        continue;
+                    }
+                    links.symlink(outpath, &String::from_utf8_lossy(&target), meta);
+                }
+                None => {
+                    if let Some(parent) = outpath.parent() {
+                        std::fs::create_dir_all(parent).map_err(|e| {
+                            ArchiveError::MsgError(format!("创建父目录失败: {}", e))
+                        })?;
+                    }
+                    let mut file = std::fs::File::create(&outpath)
+                        .map_err(|e| ArchiveError::MsgError(format!("创建文件失败: {}", e)))?;
+                    archive
+                        .read_to(&mut options.limited(&mut file))
+                        .map_err(rar_error)?
+                        .map_err(io_err)?;
+                    drop(file);
+                    restorer.file(&outpath, &meta)?;
+                }
+            }
+        }
+        links.finish(&restorer)?;
+        xattrs.finish();
+        restorer.finish()
+    }
+
+    fn test(&mut self) -> ResultR<TestReport> {
+        let archive = if self.password.is_empty() {
+            unrar::Archive::new(&self.archive_path)
+        } else {
+            unrar::Archive::with_password(&self.archive_path, &self.password)
+        };
+        let merr = |e: UnrarError| match e.code {
+            unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            _ => ArchiveError::MsgError(format!("发生错误: {}", e)),
+        };
+
+        let mut archive = archive.open_for_processing().map_err(merr)?;
+        let mut report = TestReport::default();
+        loop {
+            let header = match archive.read_header() {
+                Ok(Some(header)) => header,
+                Ok(None) => break,
+                Err(e) => {
+                    report.fail("", merr(e));
+                    break;
+                }
+            };
+            let fname = header.entry().filename.to_string_lossy().to_string();
+            report.tested += 1;
+            archive = match header.test() {
+                Ok(archive) => archive,
+                Err(e) => match e.code {
+                    unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
+                        return Err(merr(e))
+                    }
+                    _ => {
+                        report.fail(fname, merr(e));
+                        break;
+                    }
+                },
+            };
+        }
+        Ok(report)
+    }
+
+    fn read_entry(&mut self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64> {
+        let merr = |e: UnrarError| match e.code {
+            unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            _ => ArchiveError::MsgError(format!("发生错误: {}", e)),
+        };
+
+        let mut archive = RarStream::open(&self.archive_path, &self.password).map_err(merr)?;
+        while let Some(header) = archive.next_header().map_err(merr)? {
+            if header.path != entry {
+                archive.skip().map_err(merr)?;
+                continue;
+            }
+            // 逐块丢弃 offset 之前的数据，写满 length 后停止解压
+            let (mut skip, mut left) = (range.offset, range.length.unwrap_or(u64::MAX));
+            let mut failed = None;
+            archive
+                .read(&mut |data| {
+                    let skipped = skip.min(data.len() as u64);
+                    skip -= skipped;
+                    let data = &data[skipped as usize..];
+                    let data = &data[..left.min(data.len() as u64) as usize];
+                    if let Err(e) = out.write_all(data) {
+                        failed = Some(e);
+                        return false;
+                    }
+                    left -= data.len() as u64;
+                    left > 0
+                })
+                .map_err(merr)?;
+            if let Some(e) = failed {
+                return Err(ArchiveError::MsgError(e.to_string()));
+            }
+            return Ok(header.size);
+        }
+        Err(ArchiveError::MsgError(format!("条目不存在: {}", entry)))
+    }
+
+    fn archive_info(&mut self) -> ResultR<ArchiveInfo> {
+        let archive = if self.password.is_empty() {
+            unrar::Archive::new(&self.archive_path)
+        } else {
+            unrar::Archive::with_password(&self.archive_path, &self.password)
+        };
+
+        let list = archive.open_for_listing().map_err(|e| match e.code {
+            unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
+                ArchiveError::MsgError(String::from("密码错误"))
+            }
+            _ => ArchiveError::MsgError(format!("发生错误: {}", e)),
+        })?;
+
+        let mut info = ArchiveInfo {
+            format: ArchiveFormat::Rar,
+            comment: info::rar_comment(&self.archive_path),
+            packed_size: info::rar_volumes(&self.archive_path)
+                .iter()
+                .filter_map(|p| std::fs::metadata(p).ok())
+                .map(|m| m.len())
+                .sum(),
+            is_multi_volume: !matches!(list.volume_info(), unrar::VolumeInfo::None),
+            is_solid: list.is_solid(),
+            is_locked: list.is_locked(),
+            is_encrypted: list.has_encrypted_headers(),
+            ..Default::default()
+        };
+
+        for entry in list {
+            let entry = entry.map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+            info.entry_count += 1;
+            info.unpacked_size += entry.unpacked_size;
+            info.is_encrypted |= entry.is_encrypted();
+        }
+        Ok(info)
+    }
+
+    fn for_each_entry(&mut self, visit: &mut EntryVisitor<'_>) -> ResultR<()> {
+        let io_err = |e: std::io::Error| ArchiveError::MsgError(format!("读取条目失败: {}", e));
+        let mut archive = RarStream::open(&self.archive_path, &self.password).map_err(rar_error)?;
+        // 条目内容先解压到临时文件，大条目不必整体读入内存
+        let mut spool = Spool::new("rar").map_err(io_err)?;
+        let mut empty = std::io::empty();
+        // 第一次遇到硬链接时建立，之后出现的目标条目随读随存
+        let mut targets: Option<LinkTargets> = None;
+        let mut saved: Option<File> = None;
+        let mut count = 0;
+        while let Some(header) = archive.next_header().map_err(rar_error)? {
+            let position = count;
+            count += 1;
+            let mtime = restore::dos_time_to_unix(header.file_time);
+            let mode = rar_unix_mode(header.file_attr);
+            let (kind, size) = match header.link {
+                _ if header.is_dir => {
+                    archive.skip().map_err(rar_error)?;
+                    (EntryKind::Dir, 0)
+                }
+                Some(RarLink::Symlink(target)) => {
+                    archive.skip().map_err(rar_error)?;
+                    (EntryKind::Symlink(target), 0)
+                }
+                // 硬链接没有数据，读取目标的副本
+                Some(RarLink::Hardlink(target)) => {
+                    archive.skip().map_err(rar_error)?;
+                    let targets = match &mut targets {
+                        Some(targets) => targets,
+                        None => targets.insert(self.link_targets(position)?),
+                    };
+                    match targets.open(&target).transpose().map_err(io_err)? {
+                        Some((file, size)) => {
+                            saved = Some(file);
+                            (EntryKind::File, size)
+                        }
+                        None => {
+                            log::warn!("跳过目标不存在的硬链接: {} -> {}", header.path, target);
+                            continue;
+                        }
+                    }
+                }
+                // RAR3 以内容保存链接目标
+                None if mode.is_some_and(|m| m & 0o170000 == 0o120000) => {
+                    let target = archive.read_head(MAX_LINK_TARGET).map_err(rar_error)?;
+                    if target.is_empty() {
+                        log::warn!("跳过无法读取目标的链接: {}", header.path);
+                        continue;
+                    }
+                    (
+                        EntryKind::Symlink(String::from_utf8_lossy(&target).to_string()),
+                        0,
+                    )
+                }
+                None => {
+                    let file = spool.clear().map_err(io_err)?;
+                    archive.read_to(file).map_err(rar_error)?.map_err(io_err)?;
+                    let size = spool.rewind().map_err(io_err)?;
+                    if let Some(targets) = targets.as_mut().filter(|t| t.wants(&header.path)) {
+                        let (file, _) = targets
+                            .save(&header.path, &mut spool.file)
+                            .map_err(io_err)?;
+                        saved = Some(file);
+                    }
+                    (EntryKind::File, size)
+                }
+            };
+            let data: &mut dyn Read = match (&kind, &mut saved) {
+                (EntryKind::File, Some(file)) => file,
+                (EntryKind::File, None) => &mut spool.file,
+                _ => &mut empty,
+            };
+            let entry = StreamEntry::new(&header.path, kind, size, mtime, mode);
+            let more = visit(&entry, data)?;
+            saved = None;
+            if !more {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_password(&mut self) -> ResultR<()> {
+        let mut archive = RarStream::open(&self.archive_path, &self.password).map_err(rar_error)?;
+        while let Some(header) = archive.next_header().map_err(rar_error)? {
+            if !header.is_encrypted || header.is_dir || header.size == 0 {
+                archive.skip().map_err(rar_error)?;
+                continue;
+            }
+            // RAR5 按头部中的校验值验证，RAR3 只能在读完后按 CRC 判断，加密条目的 CRC 错误即密码错误
+            let mut read = 0;
+            archive
+                .read(&mut |data| {
+                    read += data.len() as u64;
+                    read < PASSWORD_CHECK_LIMIT
+                })
+                .map_err(|e| match e.code {
+                    unrar::error::Code::BadData => ArchiveError::MsgError(String::from("密码错误")),
+                    _ => rar_error(e),
+                })?;
+            break;
+        }
+        Ok(())
+    }
+}
+
+impl RarHandler {
+    /// 收集所有硬链接的目标，并保存前 `before` 个条目中已经错过的目标
+    ///
+    /// 先读一遍条目头找出全部目标，再从头读到当前位置补存副本；之后的目标由调用方随读随存。
+    fn link_targets(&self, before: usize) -> ResultR<LinkTargets> {
+        let io_err = |e: std::io::Error| ArchiveError::MsgError(format!("读取条目失败: {}", e));
+        let mut wanted = HashSet::new();
+        let mut archive = RarStream::open(&self.archive_path, &self.password).map_err(rar_error)?;
+        while let Some(header) = archive.next_header().map_err(rar_error)? {
+            if let Some(RarLink::Hardlink(target)) = header.link {
+                wanted.insert(target);
+            }
+            archive.skip().map_err(rar_error)?;
+        }
+
+        let mut targets = LinkTargets::new(wanted);
+        let mut spool = Spool::new("rar").map_err(io_err)?;
+        let mut archive = RarStream::open(&self.archive_path, &self.password).map_err(rar_error)?;
+        for _ in 0..before {
+            let Some(header) = archive.next_header().map_err(rar_error)? else {
+                break;
+            };
+            let mode = rar_unix_mode(header.file_attr);
+            if !targets.wants(&header.path)
+                || header.is_dir
+                || header.link.is_some()
+                || mode.is_some_and(|m| m & 0o170000 == 0o120000)
+            {
+                archive.skip().map_err(rar_error)?;
+                continue;
+            }
+            let file = spool.clear().map_err(io_err)?;
+            archive.read_to(file).map_err(rar_error)?.map_err(io_err)?;
+            spool.rewind().map_err(io_err)?;
+            targets
+                .save(&header.path, &mut spool.file)
+                .map_err(io_err)?;
+        }
+        Ok(targets)
+    }
+}
+
+fn rar_error(e: UnrarError) -> ArchiveError {
+    match e.code {
+        unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
+            ArchiveError::MsgError(String::from("密码错误"))
+        }
+        _ => ArchiveError::MsgError(format!("发生错误: {}", e)),
+    }
+}
+
+/// RAR 在 unix 下创建时属性字段即为 st_mode，通过文件类型位判断
+fn rar_unix_mode(attr: u32) -> Option<u32> {
+    matches!(attr & 0o170000, 0o100000 | 0o040000 | 0o120000).then_some(attr)
+}
+
+// -------------------------
+// 工厂模式设计
+// -------------------------
+
+/// 按格式创建处理器，`password` 为空表示不使用密码
+///
+/// 格式通常由 [`ArchiveFormat::from_path`] 得到；未知格式按 zip 处理。
+pub fn create_handler(
+    path: &std::path::Path,
+    format: ArchiveFormat,
+    password: String,
+) -> Box<dyn ArchiveHandler> {
+    match format {
+        ArchiveFormat::Zip => Box::new(ZipHandler {
+            archive_path: path.to_path_buf(),
+            password,
+            archive: None,
+        }),
+        ArchiveFormat::Tar
+        | ArchiveFormat::TarXz
+        | ArchiveFormat::TarZst
+        | ArchiveFormat::TarGz
+        | ArchiveFormat::TarBz2
+        | ArchiveFormat::Gz
+        | ArchiveFormat::Bz2 => Box::new(TarHandler {
+            archive_path: path.to_path_buf(),
+            archive_format: format,
+            index: None,
+        }),
+        ArchiveFormat::SevenZip => Box::new(SevenZipHandler {
+            archive_path: path.to_path_buf(),
+            password,
+            archive: None,
+        }),
+        ArchiveFormat::Rar => Box::new(RarHandler {
+            archive_path: path.to_path_buf(),
+            password,
+        }),
+        _ => Box::new(ZipHandler {
+            archive_path: path.to_path_buf(),
+            password,
+            archive: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, is_dir: bool) -> ArchiveEntry {
+        ArchiveEntry {
+            name: entry_name(path).to_string(),
+            path: path.to_string(),
+            parent_path: Some(parent_dir(path)),
+            size: 0,
+            is_dir,
+            modified: None,
+            is_archive: false,
+        }
+    }
+
+    #[test]
+    fn test_build_entry_tree() {
+        // 只记录了文件，上级目录需要补齐；目录在前，名称自然排序
+        let tree = build_entry_tree(vec![
+            entry("b.txt", false),
+            entry("a/x/file10", false),
+            entry("a/x/file2", false),
+            entry("a/y.txt", false),
+            entry("c", true),
+        ]);
+        fn paths(nodes: &[TreeNode<ArchiveEntry>]) -> Vec<&str> {
+            nodes.iter().map(|n| n.item().path.as_str()).collect()
+        }
+        assert_eq!(paths(&tree), ["a/", "c/", "b.txt"]);
+        let a = tree[0].children();
+        assert_eq!(paths(a), ["a/x/", "a/y.txt"]);
+        assert_eq!(a[0].item().name, "x");
+        assert_eq!(paths(a[0].children()), ["a/x/file2", "a/x/file10"]);
+    }
+
+    #[test]
+    fn test_tar_hardlinks() {
+        let dir = testutil::temp_dir();
+        let path = dir.path().join("links.tar");
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        for (name, data, target) in [
+            ("a.txt", &b"first"[..], None),
+            ("b.txt", b"", Some("./a.txt")),
+            // 第一个硬链接之后才出现的目标，读到时保存副本
+            ("c.txt", b"second", None),
+            ("d.txt", b"", Some("c.txt")),
+            ("e.txt", b"", Some("a.txt")),
+            ("gone.txt", b"", Some("missing.txt")),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            match target {
+                Some(target) => {
+                    header.set_entry_type(tar::EntryType::Link);
+                    builder.append_link(&mut header, name, target).unwrap();
+                }
+                None => builder.append_data(&mut header, name, data).unwrap(),
+            }
+        }
+        builder.into_inner().unwrap();
+
+        let index = Arc::new(TarIndex::build(&path, ArchiveFormat::Tar).unwrap());
+        let handlers = [
+            create_handler(&path, ArchiveFormat::Tar, String::new()),
+            Box::new(TarHandler::with_index(&path, ArchiveFormat::Tar, index)),
+        ];
+        for mut handler in handlers {
+            let mut items = Vec::new();
+            handler
+                .for_each_entry(&mut |entry, data| {
+                    let mut content = String::new();
+                    data.read_to_string(&mut content).unwrap();
+                    assert_eq!(entry.size, content.len() as u64);
+                    items.push(format!("{}={}", entry.path, content));
+                    Ok(true)
+                })
+                .unwrap();
+            assert_eq!(
+                items,
+                [
+                    "a.txt=first",
+                    "b.txt=first",
+                    "c.txt=second",
+                    "d.txt=second",
+                    "e.txt=first"
+                ]
+            );
+        }
+    }
+}
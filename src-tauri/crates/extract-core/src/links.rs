@@ -0,0 +1,378 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::options::{ExtractOptions, LinkPolicy, SizeLimit};
+use super::restore::{self, EntryMeta, Restorer};
+use super::{ArchiveError, ResultR};
+
+enum LinkKind {
+    /// 符号链接，保存压缩包中记录的目标
+    Symbolic(PathBuf),
+    /// 硬链接，保存压缩包内的目标路径
+    Hard(String),
+}
+
+struct PendingLink {
+    path: PathBuf,
+    kind: LinkKind,
+    meta: EntryMeta,
+}
+
+enum Outcome {
+    Done,
+    /// 目标尚未写出，等待下一轮
+    Waiting,
+}
+
+/// 按 `LinkPolicy` 处理符号链接与硬链接
+///
+/// 所有链接都推迟到普通条目写完后再创建，避免后续条目经由压缩包中的链接写到目标目录之外。
+/// 被拒绝的链接不会中断解压，其余条目照常写出。
+pub struct Links {
+    root: PathBuf,
+    policy: LinkPolicy,
+    pending: Vec<PendingLink>,
+    /// `CopyTarget` 复制出的内容同样计入写入上限
+    size_limit: Option<SizeLimit>,
+}
+
+impl Links {
+    pub fn new(root: &Path, options: &ExtractOptions) -> Self {
+        Links {
+            root: root.to_path_buf(),
+            policy: options.links,
+            pending: Vec::new(),
+            size_limit: options.size_limit.clone(),
+        }
+    }
+
+    /// 记录符号链接
+    pub fn symlink(&mut self, path: PathBuf, target: &str, meta: EntryMeta) {
+        if self.policy != LinkPolicy::Skip {
+            self.pending.push(PendingLink {
+                path,
+                kind: LinkKind::Symbolic(PathBuf::from(target)),
+                meta,
+            });
+        }
+    }
+
+    /// 记录硬链接，`target` 为压缩包内路径
+    pub fn hardlink(&mut self, path: PathBuf, target: &str, meta: EntryMeta) {
+        if self.policy != LinkPolicy::Skip {
+            self.pending.push(PendingLink {
+                path,
+                kind: LinkKind::Hard(target.to_string()),
+                meta,
+            });
+        }
+    }
+
+    /// 创建所有链接；目标尚未出现的链接（链接指向链接）会在后续轮次重试
+    pub fn finish(self, restorer: &Restorer) -> ResultR<()> {
+        let mut pending = self.pending.iter().collect::<Vec<_>>();
+        let mut dangling = Vec::new();
+        while !pending.is_empty() {
+            let mut waiting = Vec::new();
+            for link in &pending {
+                if let Outcome::Waiting = self.create(link, restorer, &mut dangling)? {
+                    waiting.push(*link);
+                }
+            }
+            if waiting.len() == pending.len() {
+                for link in waiting {
+                    log::warn!("链接目标不存在，已跳过: {}", link.path.display());
+                }
+                break;
+            }
+            pending = waiting;
+        }
+        Ok(())
+    }
+
+    /// `dangling` 记录创建时悬空的符号链接，后续链接可能让它们变得可解析，需要重新核对
+    fn create(
+        &self,
+        link: &PendingLink,
+        restorer: &Restorer,
+        dangling: &mut Vec<PathBuf>,
+    ) -> ResultR<Outcome> {
+        let io_err = |e: std::io::Error| {
+            ArchiveError::MsgError(format!("创建链接失败 {}: {}", link.path.display(), e))
+        };
+
+        match &link.kind {
+            LinkKind::Symbolic(target) => {
+                let resolved = self.resolve(&link.path, target);
+                match self.policy {
+                    LinkPolicy::Materialize => {}
+                    LinkPolicy::RefuseEscaping if resolved.is_none() => {
+                        return Ok(self.refuse(link, target));
+                    }
+                    LinkPolicy::RefuseEscaping => {}
+                    LinkPolicy::CopyTarget => {
+                        let source = match resolved {
+                            Some(source) => source,
+                            None => return Ok(self.refuse(link, target)),
+                        };
+                        if !source.exists() {
+                            return Ok(Outcome::Waiting);
+                        }
+                        let source = match self.real_source(&source, &link.path) {
+                            Some(source) => source,
+                            None => return Ok(self.refuse(link, target)),
+                        };
+                        prepare(&link.path).map_err(io_err)?;
+                        copy_all(&source, &link.path, self.size_limit.as_ref()).map_err(io_err)?;
+                        return Ok(Outcome::Done);
+                    }
+                    LinkPolicy::Skip => return Ok(Outcome::Done),
+                }
+
+                prepare(&link.path).map_err(io_err)?;
+                make_symlink(target, &link.path).map_err(io_err)?;
+                // 逐个链接的词法检查无法覆盖链接串联的情况，创建后再按真实路径核对
+                if self.policy == LinkPolicy::RefuseEscaping {
+                    match self.contains_real(&link.path) {
+                        Some(false) => {
+                            let _ = std::fs::remove_file(&link.path);
+                            return Ok(self.refuse(link, target));
+                        }
+                        Some(true) => {}
+                        None => dangling.push(link.path.clone()),
+                    }
+                    self.recheck(dangling);
+                }
+                restorer.link(&link.path, &link.meta)?;
+            }
+            LinkKind::Hard(target) => {
+                // 硬链接的目标是压缩包内路径，任何策略下都不能指向目标目录之外；
+                // 词法检查之后再按真实路径核对，防止经由中间的符号链接跳出
+                let source = match restore::safe_join(&self.root, target) {
+                    Some(source) => source,
+                    None => return Ok(self.refuse(link, Path::new(target))),
+                };
+                if !source.exists() {
+                    return Ok(Outcome::Waiting);
+                }
+                let source = match self.real_source(&source, &link.path) {
+                    Some(source) => source,
+                    None => return Ok(self.refuse(link, Path::new(target))),
+                };
+                prepare(&link.path).map_err(io_err)?;
+                if self.policy == LinkPolicy::CopyTarget {
+                    copy_all(&source, &link.path, self.size_limit.as_ref()).map_err(io_err)?;
+                } else {
+                    std::fs::hard_link(&source, &link.path).map_err(io_err)?;
+                }
+            }
+        }
+        Ok(Outcome::Done)
+    }
+
+    /// 拒绝指向目标目录之外的链接：不创建，只记录警告
+    fn refuse(&self, link: &PendingLink, target: &Path) -> Outcome {
+        log::warn!(
+            "拒绝指向目标目录之外的链接: {} -> {}",
+            link.path.display(),
+            target.display()
+        );
+        Outcome::Done
+    }
+
+    /// 按词法解析链接目标，结果不在目标目录内时返回 `None`
+    fn resolve(&self, link: &Path, target: &Path) -> Option<PathBuf> {
+        if target.has_root() {
+            return None;
+        }
+        let mut resolved = link.parent()?.to_path_buf();
+        for component in target.components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return None;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        resolved.starts_with(&self.root).then_some(resolved)
+    }
+
+    /// 链接可解析时，返回真实路径是否仍在目标目录内；悬空链接返回 `None`
+    fn contains_real(&self, path: &Path) -> Option<bool> {
+        let root = std::fs::canonicalize(&self.root).ok()?;
+        let real = std::fs::canonicalize(path).ok()?;
+        Some(real.starts_with(root))
+    }
+
+    /// 重新核对此前悬空的链接，已变得可解析且指向目标目录之外的予以删除
+    fn recheck(&self, dangling: &mut Vec<PathBuf>) {
+        dangling.retain(|path| match self.contains_real(path) {
+            None => true,
+            Some(true) => false,
+            Some(false) => {
+                log::warn!("拒绝指向目标目录之外的链接: {}", path.display());
+                let _ = std::fs::remove_file(path);
+                false
+            }
+        });
+    }
+
+    /// 按真实路径核对链接源：源必须在目标目录内，且链接不能位于源之内，
+    /// 否则指向自身或上级目录的链接会把目录树复制进自身
+    fn real_source(&self, source: &Path, link: &Path) -> Option<PathBuf> {
+        let root = std::fs::canonicalize(&self.root).ok()?;
+        let source = std::fs::canonicalize(source).ok()?;
+        // 链接自身可能是同名的旧链接，只解析其父目录
+        let link = real_path(link.parent()?)?.join(link.file_name()?);
+        (source.starts_with(&root) && !link.starts_with(&source)).then_some(source)
+    }
+}
+
+/// 解析路径中已存在的部分，其余部分原样拼接（尚不存在的部分不可能是链接）
+fn real_path(path: &Path) -> Option<PathBuf> {
+    let mut rest = Vec::new();
+    let mut base = path;
+    loop {
+        match std::fs::canonicalize(base) {
+            Ok(mut real) => {
+                real.extend(rest.iter().rev());
+                return Some(real);
+            }
+            Err(_) => {
+                rest.push(base.file_name()?);
+                base = base.parent()?;
+            }
+        }
+    }
+}
+
+/// 创建父目录，并移除同名的旧文件或旧链接
+fn prepare(path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if !meta.is_dir() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+fn copy_all(source: &Path, dest: &Path, limit: Option<&SizeLimit>) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir_all(dest)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &dest.join(entry.file_name()), limit)?;
+        }
+        Ok(())
+    } else {
+        if let Some(limit) = limit {
+            limit.charge(std::fs::metadata(source)?.len())?;
+        }
+        std::fs::copy(source, dest).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn make_symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn make_symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    let is_dir = path.parent().map(|p| p.join(target).is_dir()).unwrap_or(false);
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+
+    #[test]
+    fn test_resolve() {
+        let options = ExtractOptions::default();
+        let links = Links::new(Path::new("/out"), &options);
+        let link = Path::new("/out/a/link");
+        assert_eq!(
+            links.resolve(link, Path::new("../b/c")),
+            Some(PathBuf::from("/out/b/c"))
+        );
+        assert_eq!(
+            links.resolve(link, Path::new("./x")),
+            Some(PathBuf::from("/out/a/x"))
+        );
+        assert_eq!(links.resolve(link, Path::new("../../etc/passwd")), None);
+        assert_eq!(links.resolve(link, Path::new("/etc/passwd")), None);
+    }
+
+    #[cfg(unix)]
+    fn finish(root: &Path, policy: LinkPolicy, add: impl FnOnce(&mut Links)) {
+        let options = ExtractOptions {
+            links: policy,
+            ..Default::default()
+        };
+        let mut links = Links::new(root, &options);
+        add(&mut links);
+        links.finish(&Restorer::new(&options)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_target_refuses_ancestor() {
+        let dir = temp_dir();
+        let root = dir.path();
+        std::fs::create_dir(root.join("a")).unwrap();
+        std::fs::write(root.join("a/f"), b"x").unwrap();
+        finish(root, LinkPolicy::CopyTarget, |links| {
+            links.symlink(root.join("a/self"), "..", EntryMeta::default());
+            links.symlink(root.join("a/dot"), ".", EntryMeta::default());
+            links.symlink(root.join("g"), "a/f", EntryMeta::default());
+        });
+        assert!(!root.join("a/self").exists());
+        assert!(!root.join("a/dot").exists());
+        assert_eq!(std::fs::read(root.join("g")).unwrap(), b"x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlink_through_symlink() {
+        let dir = temp_dir();
+        let outside = dir.path().join("outside");
+        let root = dir.path().join("out");
+        std::fs::create_dir(&outside).unwrap();
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(outside.join("secret"), b"s").unwrap();
+        finish(&root, LinkPolicy::Materialize, |links| {
+            links.symlink(
+                root.join("l"),
+                outside.to_str().unwrap(),
+                EntryMeta::default(),
+            );
+            links.hardlink(root.join("h"), "l/secret", EntryMeta::default());
+        });
+        assert!(root.join("l").exists());
+        assert!(!root.join("h").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_link_rechecked() {
+        let dir = temp_dir();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("d1/d2")).unwrap();
+        finish(root, LinkPolicy::RefuseEscaping, |links| {
+            links.symlink(root.join("d1/d2/y"), "x/..", EntryMeta::default());
+            links.symlink(root.join("d1/d2/x"), "../..", EntryMeta::default());
+        });
+        assert!(std::fs::symlink_metadata(root.join("d1/d2/x")).is_ok());
+        assert!(std::fs::symlink_metadata(root.join("d1/d2/y")).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+//! 嵌套压缩包：用 `outer.zip!/inner.tar.gz!/file.txt` 形式的虚拟路径进入压缩包中的压缩包
+//!
+//! 处理器只能读取磁盘上的文件，所以每一层内层压缩包都会先解压到临时文件，
+//! 最内层关闭时一并删除。
+
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use tempfile::TempDir;
+
+use crate::preview::ByteRange;
+use crate::spool::private_dir;
+use crate::{
+    create_handler, ArchiveError, ArchiveFormat, ArchiveHandler, ArchiveInfo, Entries,
+    EntryVisitor, ExtractOptions, ResultR, TestReport,
+};
+
+/// 虚拟路径中分隔压缩包与其中条目的标记
+pub const NESTED_SEPARATOR: &str = "!/";
+
+/// 默认最多进入的嵌套层数
+pub const MAX_NESTED_DEPTH: usize = 4;
+
+/// 每一层内层压缩包解压到临时文件的最大字节数
+pub const MAX_NESTED_SIZE: u64 = 8 << 30;
+
+/// 按文件名判断条目本身是否是可以打开的压缩包
+pub fn is_nested_archive(name: &str) -> bool {
+    ArchiveFormat::from_path(Path::new(name)) != ArchiveFormat::Unknown
+}
+
+/// 拆分虚拟路径的最后一层，返回 (压缩包, 其中的条目)
+///
+/// `a.zip!/b.tar.gz!/c.txt` 拆为 `("a.zip!/b.tar.gz", Some("c.txt"))`，
+/// 不含分隔标记的路径返回 `(path, None)`。
+pub fn split_virtual(path: &str) -> (&str, Option<&str>) {
+    match path.rsplit_once(NESTED_SEPARATOR) {
+        Some((archive, entry)) => (archive, Some(entry)),
+        None => (path, None),
+    }
+}
+
+/// 是否是指向嵌套压缩包的虚拟路径；磁盘上真实存在的路径按原样处理
+pub fn is_virtual(path: &str) -> bool {
+    path.contains(NESTED_SEPARATOR) && !Path::new(path).exists()
+}
+
+/// 打开的（可能是嵌套的）压缩包
+#[derive(Debug)]
+pub struct NestedArchive {
+    /// 最内层压缩包在磁盘上的位置
+    path: PathBuf,
+    format: ArchiveFormat,
+    /// 从外到内存放临时文件的私有目录，随之删除
+    spools: Vec<TempDir>,
+}
+
+impl NestedArchive {
+    /// 打开虚拟路径指向的压缩包，每一层都使用 `password`
+    ///
+    /// 嵌套层数超过 `max_depth`、或内层压缩包超过 [`MAX_NESTED_SIZE`] 时返回错误。
+    pub fn open(path: &str, password: &str, max_depth: usize) -> ResultR<Self> {
+        Self::open_limited(path, password, max_depth, MAX_NESTED_SIZE)
+    }
+
+    fn open_limited(path: &str, password: &str, max_depth: usize, max_size: u64) -> ResultR<Self> {
+        if !is_virtual(path) {
+            let path = PathBuf::from(path);
+            let format = ArchiveFormat::from_path(&path);
+            return Ok(NestedArchive {
+                path,
+                format,
+                spools: Vec::new(),
+            });
+        }
+        let mut layers = path.split(NESTED_SEPARATOR);
+        let outer = PathBuf::from(layers.next().unwrap_or_default());
+        let layers: Vec<&str> = layers.collect();
+        if layers.len() > max_depth {
+            return Err(ArchiveError::MsgError(format!(
+                "嵌套层数超过限制: {}",
+                max_depth
+            )));
+        }
+
+        let mut archive = NestedArchive {
+            format: ArchiveFormat::from_path(&outer),
+            path: outer,
+            spools: Vec::new(),
+        };
+        for layer in layers {
+            archive.descend(layer, password, max_size)?;
+        }
+        Ok(archive)
+    }
+
+    /// 最内层压缩包在磁盘上的位置，嵌套时为临时文件
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+
+    /// 嵌套的层数，普通压缩包为 0
+    pub fn depth(&self) -> usize {
+        self.spools.len()
+    }
+
+    pub fn handler(&self, password: String) -> Box<dyn ArchiveHandler> {
+        create_handler(&self.path, self.format, password)
+    }
+
+    /// 转为处理器，临时文件随处理器一起删除
+    pub fn into_handler(self, password: String) -> Box<dyn ArchiveHandler> {
+        if self.spools.is_empty() {
+            return self.handler(password);
+        }
+        Box::new(NestedHandler {
+            inner: self.handler(password),
+            _archive: self,
+        })
+    }
+
+    /// 把当前压缩包中的 `entry` 解压到临时文件，并进入这一层
+    ///
+    /// 解密后的内容写在 [`private_dir`] 创建的目录中，其他用户无法读取或预先放置链接。
+    fn descend(&mut self, entry: &str, password: &str, max_size: u64) -> ResultR<()> {
+        let format = ArchiveFormat::from_path(Path::new(entry));
+        if format == ArchiveFormat::Unknown || entry.ends_with('/') {
+            return Err(ArchiveError::MsgError(format!("不是压缩包: {}", entry)));
+        }
+        let name = Path::new(entry)
+            .file_name()
+            .ok_or_else(|| ArchiveError::MsgError(format!("不是压缩包: {}", entry)))?;
+        let dir = private_dir(&std::env::temp_dir(), "extract-nested-")
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        // 保留文件名，之后仍按扩展名识别格式
+        let path = dir.path().join(name);
+        // 先登记，出错时也能删除临时目录
+        self.spools.push(dir);
+
+        let mut out = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        // 多读一个字节用于判断是否超过限制，超过时不必解压剩余部分
+        self.handler(password.to_string()).read_entry(
+            entry,
+            ByteRange::new(0, Some(max_size.saturating_add(1))),
+            &mut out,
+        )?;
+        let size = out
+            .stream_position()
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        if size > max_size {
+            return Err(ArchiveError::MsgError(format!(
+                "内层压缩包超过大小限制: {}",
+                entry
+            )));
+        }
+        self.path = path;
+        self.format = format;
+        Ok(())
+    }
+}
+
+/// 打开虚拟路径或普通路径指向的压缩包，嵌套层数使用默认限制
+pub fn open_handler(path: &str, password: String) -> ResultR<Box<dyn ArchiveHandler>> {
+    Ok(NestedArchive::open(path, &password, MAX_NESTED_DEPTH)?.into_handler(password))
+}
+
+/// 持有临时文件的处理器
+struct NestedHandler {
+    inner: Box<dyn ArchiveHandler>,
+    _archive: NestedArchive,
+}
+
+impl ArchiveHandler for NestedHandler {
+    fn entries(&mut self) -> ResultR<Entries<'_>> {
+        self.inner.entries()
+    }
+
+    fn extract(&mut self, target_dir: &Path, options: &ExtractOptions) -> ResultR<()> {
+        self.inner.extract(target_dir, options)
+    }
+
+    fn read_entry(&mut self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64> {
+        self.inner.read_entry(entry, range, out)
+    }
+
+    fn archive_info(&mut self) -> ResultR<ArchiveInfo> {
+        self.inner.archive_info()
+    }
+
+    fn test(&mut self) -> ResultR<TestReport> {
+        self.inner.test()
+    }
+
+    fn for_each_entry(&mut self, visit: &mut EntryVisitor<'_>) -> ResultR<()> {
+        self.inner.for_each_entry(visit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+    use std::fs;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_nested_archive() {
+        assert_eq!(
+            split_virtual("a.zip!/b.tar.gz!/c.txt"),
+            ("a.zip!/b.tar.gz", Some("c.txt"))
+        );
+        assert_eq!(split_virtual("a.zip"), ("a.zip", None));
+        assert!(is_nested_archive("dist/app.tar.gz"));
+        assert!(!is_nested_archive("readme.txt"));
+
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        // inner.tar 放在 outer.zip 中
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "file.txt", &b"hello"[..])
+            .unwrap();
+        let inner = builder.into_inner().unwrap();
+        let outer = dir.join("outer.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&outer).unwrap());
+        writer
+            .start_file("pkg/inner.tar", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&inner).unwrap();
+        writer.finish().unwrap();
+
+        let path = format!("{}!/pkg/inner.tar", outer.display());
+        let archive = NestedArchive::open(&path, "", MAX_NESTED_DEPTH).unwrap();
+        assert_eq!(archive.depth(), 1);
+        assert_eq!(archive.format(), ArchiveFormat::Tar);
+        let mut out = Vec::new();
+        archive
+            .handler(String::new())
+            .read_entry("file.txt", ByteRange::default(), &mut out)
+            .unwrap();
+        assert_eq!(out, b"hello");
+
+        let spool = archive.path().to_path_buf();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(spool.parent().unwrap())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let mut handler = archive.into_handler(String::new());
+        assert_eq!(handler.entries().unwrap().count(), 1);
+        drop(handler);
+        assert!(!spool.exists());
+        assert!(NestedArchive::open(&path, "", 0).is_err());
+        assert!(NestedArchive::open(&format!("{}!/nope.tar", outer.display()), "", 4).is_err());
+        // 超过大小限制的内层压缩包不会留下临时文件
+        let spools = || {
+            fs::read_dir(std::env::temp_dir())
+                .unwrap()
+                .filter(|e| {
+                    let name = e.as_ref().unwrap().file_name();
+                    name.to_string_lossy().starts_with("extract-nested-")
+                })
+                .count()
+        };
+        let before = spools();
+        assert!(matches!(
+            NestedArchive::open_limited(&path, "", MAX_NESTED_DEPTH, 512),
+            Err(ArchiveError::MsgError(e)) if e.contains("大小限制")
+        ));
+        assert_eq!(spools(), before);
+    }
+}
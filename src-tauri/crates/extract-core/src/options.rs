@@ -0,0 +1,408 @@
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+use super::{ArchiveError, ResultR};
+
+/// 解压选项
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExtractOptions {
+    /// 恢复修改时间，关闭时使用解压时刻
+    pub restore_mtime: bool,
+    /// 恢复访问时间（压缩包中存在时）
+    pub restore_atime: bool,
+    /// 恢复权限位，关闭时使用 0o666/0o777 减去 umask
+    pub restore_permissions: bool,
+    /// 应用于权限位的掩码
+    pub umask: u32,
+    /// 保留 setuid/setgid/sticky 位，默认去除
+    pub keep_special_bits: bool,
+    /// 恢复所有者，仅在 root 身份运行时生效
+    pub restore_ownership: bool,
+    /// 符号链接与硬链接的处理方式
+    pub links: LinkPolicy,
+    /// 恢复扩展属性与 POSIX ACL，并把 macOS 的 AppleDouble（`._name`）合并为扩展属性
+    pub restore_xattrs: bool,
+    /// 同时恢复 `trusted.`、`security.` 等特权命名空间的属性；默认只恢复 `user.` 属性与 ACL，
+    /// 避免以 root 身份解压时写入 `security.capability` 等来自压缩包的属性
+    pub restore_privileged_xattrs: bool,
+    /// 只解压匹配的条目（glob），为空时解压全部
+    pub include: Vec<String>,
+    /// 跳过匹配的条目（glob），优先于 `include`
+    pub exclude: Vec<String>,
+    /// 把解压出的压缩包继续解压到同名目录，为空时不递归
+    pub recursive: Option<RecursiveExtract>,
+    /// 写入的总大小上限，按实际写出的字节计数，超出时中止解压并报错；为空时不限制
+    #[serde(skip)]
+    pub size_limit: Option<SizeLimit>,
+}
+
+/// 递归解压的限制
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RecursiveExtract {
+    /// 最多继续解压的层数
+    pub max_depth: usize,
+    /// 递归解压出的内容总大小上限（字节），按实际写入计数；超出时删除该压缩包已解压的部分，保留压缩包
+    pub max_size: u64,
+    /// 解压成功后删除中间的压缩包
+    pub delete_archives: bool,
+}
+
+impl Default for RecursiveExtract {
+    fn default() -> Self {
+        RecursiveExtract {
+            max_depth: 4,
+            max_size: 8 << 30,
+            delete_archives: false,
+        }
+    }
+}
+
+/// 链接处理策略
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPolicy {
+    /// 按压缩包记录原样创建，包括指向目标目录之外的符号链接；硬链接仍只能指向目标目录内
+    Materialize,
+    /// 不创建任何链接
+    Skip,
+    /// 复制链接目标的内容，目标不在目标目录内时跳过
+    CopyTarget,
+    /// 创建链接，但跳过指向目标目录之外的链接
+    #[default]
+    RefuseEscaping,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            restore_mtime: true,
+            restore_atime: false,
+            restore_permissions: true,
+            umask: 0o022,
+            keep_special_bits: false,
+            restore_ownership: false,
+            links: LinkPolicy::default(),
+            restore_xattrs: false,
+            restore_privileged_xattrs: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            recursive: None,
+            size_limit: None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// 按 [`ExtractOptions::size_limit`] 计数的写入端
+    pub(crate) fn limited<T>(&self, inner: T) -> Limited<'_, T> {
+        Limited {
+            inner,
+            limit: self.size_limit.as_ref(),
+        }
+    }
+
+    /// 计入即将写入的 `n` 个字节，用于无法包装写入端的场合
+    pub(crate) fn charge(&self, n: u64) -> io::Result<()> {
+        self.size_limit
+            .as_ref()
+            .map_or(Ok(()), |limit| limit.charge(n))
+    }
+}
+
+/// 解压写入的总大小上限，克隆出的副本共用同一计数
+#[derive(Debug, Clone)]
+pub struct SizeLimit(Arc<LimitState>);
+
+#[derive(Debug)]
+struct LimitState {
+    limit: u64,
+    used: AtomicU64,
+    /// 同时计入的外层上限
+    outer: Option<SizeLimit>,
+}
+
+impl SizeLimit {
+    pub fn new(limit: u64) -> Self {
+        Self::within(limit, None)
+    }
+
+    /// 计入自身的同时计入 `outer`
+    pub(crate) fn within(limit: u64, outer: Option<SizeLimit>) -> Self {
+        SizeLimit(Arc::new(LimitState {
+            limit,
+            used: AtomicU64::new(0),
+            outer,
+        }))
+    }
+
+    /// 已写入的字节数
+    pub fn used(&self) -> u64 {
+        self.0.used.load(Ordering::Relaxed)
+    }
+
+    /// 计入 `n` 个字节，超出自身或外层的上限时返回错误
+    pub(crate) fn charge(&self, n: u64) -> io::Result<()> {
+        let used = self
+            .0
+            .used
+            .fetch_add(n, Ordering::Relaxed)
+            .saturating_add(n);
+        if used > self.0.limit {
+            return Err(io::Error::other(format!("超出大小限制: {}", self.0.limit)));
+        }
+        match &self.0.outer {
+            Some(outer) => outer.charge(n),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 写入时计入 [`SizeLimit`]，未设置上限时直接转发
+pub(crate) struct Limited<'a, T> {
+    inner: T,
+    limit: Option<&'a SizeLimit>,
+}
+
+impl<T: Write> Write for Limited<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 先计数再写入，超出上限的数据不会落盘
+        if let Some(limit) = self.limit {
+            limit.charge(buf.len() as u64)?;
+        }
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 创建压缩包的选项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CreateOptions {
+    /// 压缩级别、方式等，各格式只使用自己支持的部分
+    pub compression: CompressionOptions,
+    /// 可重复构建：相同的输入总是得到相同的字节。条目按名称排序，修改时间统一为
+    /// `source_date_epoch`，属主清零，权限规范为 755 / 644，zip 时间按 UTC 保存
+    pub deterministic: bool,
+    /// 可重复构建时所有条目的修改时间（unix 秒）；为空时读取环境变量 `SOURCE_DATE_EPOCH`，
+    /// 仍为空时使用 1980-01-01
+    pub source_date_epoch: Option<i64>,
+    /// 加密，为空时不加密；只有 zip 与 7z 支持。密码不会被序列化
+    #[serde(skip_serializing)]
+    pub encryption: Option<Encryption>,
+    /// 分卷大小（字节），为空时不分卷。输出按 7-Zip 的方式命名为 `output.001`、`output.002`……
+    pub volume_size: Option<u64>,
+}
+
+/// 修复损坏压缩包的选项，都为空时只检查哪些条目完好
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SalvageOptions {
+    /// 把完好的条目解压到该目录
+    pub extract_to: Option<PathBuf>,
+    /// 把完好的条目写入新的压缩包，格式与原压缩包相同
+    pub rebuild: Option<PathBuf>,
+}
+
+/// 比较两个压缩包的选项
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// 为内容不同的文本条目生成统一格式的 diff
+    pub unified: bool,
+    /// diff 的上下文行数
+    pub context: usize,
+    /// 超过该大小（字节）的条目不生成 diff
+    pub text_limit: u64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            unified: false,
+            context: 3,
+            text_limit: 1 << 20,
+        }
+    }
+}
+
+/// 加密选项：zip 使用 WinZip AES-256，7z 使用 AES-256 + SHA-256
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Encryption {
+    /// 释放时清零
+    pub password: Zeroizing<String>,
+    /// 7z 同时加密文件名等头部信息，其余格式忽略
+    pub encrypt_header: bool,
+}
+
+impl std::fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Encryption")
+            .field("password", &"***")
+            .field("encrypt_header", &self.encrypt_header)
+            .finish()
+    }
+}
+
+/// 压缩选项，格式支持哪些选项见 [`format_capabilities`](crate::writer::format_capabilities)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct CompressionOptions {
+    pub level: CompressionLevel,
+    /// zip 条目的压缩方式，其余格式忽略
+    pub method: ZipMethod,
+    /// 7z 与 xz 的 LZMA2 字典大小（字节），为空时按压缩级别
+    pub dictionary_size: Option<u32>,
+    /// 7z 固实块的大小（字节），为空时不使用固实压缩
+    pub solid_block_size: Option<u64>,
+    /// xz 与 zstd 的压缩线程数，0 表示按 CPU 核数
+    pub threads: u32,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        CompressionOptions {
+            level: CompressionLevel::default(),
+            method: ZipMethod::default(),
+            dictionary_size: None,
+            solid_block_size: None,
+            threads: 1,
+        }
+    }
+}
+
+impl CompressionOptions {
+    /// 实际使用的线程数
+    pub fn threads(&self) -> u32 {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get() as u32),
+            n => n,
+        }
+    }
+}
+
+/// 压缩级别预设，与 7-Zip 的“仅存储”到“极限压缩”对应
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionLevel {
+    /// 不压缩，只有 zip 与 gzip 支持
+    Store,
+    Fastest,
+    Fast,
+    #[default]
+    Normal,
+    Maximum,
+    Ultra,
+}
+
+impl CompressionLevel {
+    /// 对应 deflate、bzip2、xz 与 LZMA2 的 0–9 级
+    pub fn value(self) -> u32 {
+        match self {
+            CompressionLevel::Store => 0,
+            CompressionLevel::Fastest => 1,
+            CompressionLevel::Fast => 3,
+            CompressionLevel::Normal => 5,
+            CompressionLevel::Maximum => 7,
+            CompressionLevel::Ultra => 9,
+        }
+    }
+
+    /// zstd 的级别，范围比其他算法大
+    pub fn zstd(self) -> i32 {
+        match self {
+            CompressionLevel::Store | CompressionLevel::Fastest => 1,
+            CompressionLevel::Fast => 2,
+            CompressionLevel::Normal => 3,
+            CompressionLevel::Maximum => 12,
+            CompressionLevel::Ultra => 19,
+        }
+    }
+}
+
+/// zip 条目的压缩方式；zip 库不能写入 deflate64 与 lzma，解压时仍然支持
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ZipMethod {
+    #[default]
+    Deflate,
+    /// 不压缩
+    Store,
+    Bzip2,
+    Zstd,
+}
+
+/// 按 `include` / `exclude` 过滤条目
+///
+/// 模式匹配条目路径或其任一上级目录，`docs` 即可选中 `docs/` 下的全部条目。
+pub struct EntryFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl EntryFilter {
+    pub fn new(options: &ExtractOptions) -> ResultR<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| {
+                    Pattern::new(p.trim_end_matches('/'))
+                        .map_err(|e| ArchiveError::MsgError(format!("无效的匹配模式 {}: {}", p, e)))
+                })
+                .collect::<ResultR<Vec<_>>>()
+        };
+        Ok(EntryFilter {
+            include: compile(&options.include)?,
+            exclude: compile(&options.exclude)?,
+        })
+    }
+
+    /// 条目是否需要解压
+    pub fn allows(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./").trim_matches('/');
+        let candidates = path
+            .match_indices('/')
+            .map(|(i, _)| &path[..i])
+            .chain(std::iter::once(path))
+            .collect::<Vec<_>>();
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|p| candidates.iter().any(|c| p.matches(c)))
+        };
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_filter() {
+        let options = ExtractOptions {
+            include: vec!["docs".to_string(), "*.md".to_string()],
+            exclude: vec!["docs/private/".to_string()],
+            ..Default::default()
+        };
+        let filter = EntryFilter::new(&options).unwrap();
+        assert!(filter.allows("docs/a.txt"));
+        assert!(filter.allows("docs/"));
+        assert!(filter.allows("src/README.md"));
+        assert!(!filter.allows("src/main.rs"));
+        assert!(!filter.allows("docs/private/key.pem"));
+        assert!(EntryFilter::new(&ExtractOptions::default())
+            .unwrap()
+            .allows("any/path"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::option::Option;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TreeNode<T>
+where
+    T: Clone + Default + Debug,
+{
+    item: T,
+    children: Option<Vec<TreeNode<T>>>,
+}
+
+impl<T> TreeNode<T>
+where
+    T: Clone + Default + Debug,
+{
+    pub fn item(&self) -> &T {
+        &self.item
+    }
+
+    pub fn children(&self) -> &[TreeNode<T>] {
+        self.children.as_deref().unwrap_or_default()
+    }
+
+    /// 按父子关系构建树，同级节点保持输入顺序
+    ///
+    /// 父节点为 `parent_key` 的条目作为根，找不到父节点的条目会被丢弃。
+    pub fn build_tree<F1, F2>(
+        items: Vec<T>,
+        parent_key: String,
+        get_key: F1,
+        get_parent_key: F2,
+    ) -> Vec<TreeNode<T>>
+    where
+        F1: Fn(&T) -> String + Clone,
+        F2: Fn(&T) -> String + Clone,
+    {
+        Self::build_sorted_tree(items, parent_key, get_key, get_parent_key, |_, _| {
+            Ordering::Equal
+        })
+    }
+
+    /// 按父子关系构建树，同级节点按 `compare` 排序（稳定排序）
+    ///
+    /// 先用哈希表建立 键 → 下标 的索引，再一次遍历挂接子节点，整体为线性时间（不含排序）。
+    pub fn build_sorted_tree<F1, F2, C>(
+        items: Vec<T>,
+        parent_key: String,
+        get_key: F1,
+        get_parent_key: F2,
+        compare: C,
+    ) -> Vec<TreeNode<T>>
+    where
+        F1: Fn(&T) -> String,
+        F2: Fn(&T) -> String,
+        C: Fn(&T, &T) -> Ordering,
+    {
+        // 重复的键以第一次出现的条目为准
+        let mut index: HashMap<String, usize> = HashMap::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            index.entry(get_key(item)).or_insert(i);
+        }
+
+        let mut roots = Vec::new();
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); items.len()];
+        for (i, item) in items.iter().enumerate() {
+            let parent = get_parent_key(item);
+            if parent == parent_key {
+                roots.push(i);
+            } else if let Some(&p) = index.get(&parent) {
+                if p != i {
+                    children[p].push(i);
+                }
+            }
+        }
+
+        roots.sort_by(|&a, &b| compare(&items[a], &items[b]));
+        for list in children.iter_mut() {
+            list.sort_by(|&a, &b| compare(&items[a], &items[b]));
+        }
+
+        // 先序遍历得到访问顺序，再逆序组装，子节点总是先于父节点完成，避免深层递归
+        let mut order = Vec::with_capacity(items.len());
+        let mut visited = vec![false; items.len()];
+        let mut stack: Vec<usize> = roots.iter().rev().copied().collect();
+        while let Some(i) = stack.pop() {
+            if std::mem::replace(&mut visited[i], true) {
+                continue;
+            }
+            order.push(i);
+            stack.extend(children[i].iter().rev());
+        }
+
+        let mut items: Vec<Option<T>> = items.into_iter().map(Some).collect();
+        let mut built: Vec<Option<TreeNode<T>>> = (0..items.len()).map(|_| None).collect();
+        for &i in order.iter().rev() {
+            let nodes = children[i]
+                .iter()
+                .filter_map(|&c| built[c].take())
+                .collect();
+            built[i] = items[i].take().map(|item| TreeNode {
+                item,
+                children: Some(nodes),
+            });
+        }
+        roots.iter().filter_map(|&i| built[i].take()).collect()
+    }
+}
+
+/// 自然排序：数字部分按数值比较（`file2` 排在 `file10` 之前），其余部分忽略大小写
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let (x, y) = (take_digits(&mut a_chars), take_digits(&mut b_chars));
+                let (xt, yt) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ord = xt
+                    .len()
+                    .cmp(&yt.len())
+                    .then_with(|| xt.cmp(yt))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+    // 仅大小写不同时按原始字符串区分，保证排序结果稳定
+    a.cmp(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_sorted_tree() {
+        // (键, 父键)
+        let items = [
+            ("a/b/", "a/"),
+            ("z", "/"),
+            ("a/", "/"),
+            ("a/b/c", "a/b/"),
+            ("orphan", "missing/"),
+            ("a/10", "a/"),
+            ("a/2", "a/"),
+        ];
+        let tree = TreeNode::build_sorted_tree(
+            items
+                .iter()
+                .map(|(k, p)| (k.to_string(), p.to_string()))
+                .collect(),
+            "/".to_string(),
+            |i: &(String, String)| i.0.clone(),
+            |i| i.1.clone(),
+            |a, b| natural_cmp(&a.0, &b.0),
+        );
+
+        fn keys(nodes: &[TreeNode<(String, String)>]) -> Vec<String> {
+            nodes.iter().map(|n| n.item.0.clone()).collect()
+        }
+        assert_eq!(keys(&tree), ["a/", "z"]);
+        let a = tree[0].children.as_deref().unwrap();
+        assert_eq!(keys(a), ["a/2", "a/10", "a/b/"]);
+        assert_eq!(keys(a[2].children.as_deref().unwrap()), ["a/b/c"]);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["file10.txt", "File2.txt", "file1.txt", "a", "file02.txt"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["a", "file1.txt", "File2.txt", "file02.txt", "file10.txt"]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! 条目预览：读取条目内容的一段，识别类型并安全地解码文本
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::ResultR;
+
+/// 单次预览最多返回的字节数
+pub const PREVIEW_LIMIT: u64 = 1 << 20;
+
+/// 条目内容中的一段
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ByteRange {
+    pub offset: u64,
+    /// 长度，`None` 表示到末尾
+    pub length: Option<u64>,
+}
+
+impl ByteRange {
+    pub fn new(offset: u64, length: Option<u64>) -> Self {
+        ByteRange { offset, length }
+    }
+}
+
+/// 预览结果，文本放在 `text` 中，其余内容以 base64 放在 `data` 中
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct EntryPreview {
+    pub path: String,
+    /// 条目的完整大小
+    pub size: u64,
+    pub offset: u64,
+    /// 本次返回的字节数
+    pub length: u64,
+    pub mime: String,
+    pub text: Option<String>,
+    /// 文本的编码，如 `utf-8`、`utf-16le`
+    pub encoding: Option<String>,
+    pub data: Option<String>,
+    /// 之后还有内容未返回
+    pub truncated: bool,
+}
+
+/// 跳过 `range.offset` 字节后，把最多 `range.length` 字节复制到 `out`
+pub(crate) fn copy_range(
+    reader: &mut dyn Read,
+    range: ByteRange,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    io::copy(&mut reader.take(range.offset), &mut io::sink())?;
+    io::copy(&mut reader.take(range.length.unwrap_or(u64::MAX)), out)?;
+    Ok(())
+}
+
+/// 读取并识别一段内容，长度不超过 `limit`
+///
+/// `read` 把指定范围写入缓冲区并返回条目的完整大小，通常由 [`crate::ArchiveHandler::read_entry`] 实现。
+pub fn preview<F>(path: &str, range: ByteRange, limit: u64, read: F) -> ResultR<EntryPreview>
+where
+    F: FnOnce(ByteRange, &mut dyn Write) -> ResultR<u64>,
+{
+    let length = range.length.unwrap_or(u64::MAX).min(limit);
+    let mut buf = Vec::new();
+    let size = read(ByteRange::new(range.offset, Some(length)), &mut buf)?;
+    let end = range.offset + buf.len() as u64;
+
+    let mut preview = EntryPreview {
+        path: path.to_string(),
+        size,
+        offset: range.offset,
+        length: buf.len() as u64,
+        mime: detect_mime(path, &buf),
+        truncated: end < size,
+        ..Default::default()
+    };
+    // 文本从中间开始时不能依赖 BOM 与类型识别
+    let textual = preview.mime.starts_with("text/") || is_text_mime(&preview.mime);
+    match decode_text(&buf, range.offset == 0, textual) {
+        Some((text, encoding)) => {
+            // 末尾被截断的字符不计入本次长度，继续读取时从该字符开始
+            if encoding == "utf-8" {
+                preview.length -= partial_utf8_tail(&buf) as u64;
+                preview.truncated = range.offset + preview.length < size;
+            }
+            preview.text = Some(text);
+            preview.encoding = Some(encoding.to_string());
+            if !textual {
+                preview.mime = String::from("text/plain");
+            }
+        }
+        None => {
+            preview.data = Some(base64::engine::general_purpose::STANDARD.encode(&buf));
+        }
+    }
+    Ok(preview)
+}
+
+/// 把条目的完整内容解码为文本，不是文本时返回 `None`
+pub(crate) fn decode_entry_text(path: &str, data: &[u8]) -> Option<String> {
+    let mime = detect_mime(path, data);
+    let textual = mime.starts_with("text/") || is_text_mime(&mime);
+    decode_text(data, true, textual).map(|(text, _)| text)
+}
+
+/// 先按内容特征识别，再按扩展名
+fn detect_mime(path: &str, data: &[u8]) -> String {
+    if let Some(kind) = infer::get(data) {
+        return kind.mime_type().to_string();
+    }
+    let ext = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "txt" | "log" | "ini" | "cfg" | "conf" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" => "text/javascript",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+fn is_text_mime(mime: &str) -> bool {
+    matches!(
+        mime,
+        "application/json"
+            | "application/xml"
+            | "image/svg+xml"
+            | "application/yaml"
+            | "application/toml"
+    )
+}
+
+/// 解码文本，不是文本时返回 `None`
+///
+/// 识别 UTF-8 与带 BOM 的 UTF-16；截断处不完整的字符会被丢弃，
+/// 含 NUL 或大量无效字节的内容视为二进制。
+fn decode_text(data: &[u8], at_start: bool, textual: bool) -> Option<(String, &'static str)> {
+    if at_start {
+        if let Some(rest) = data.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            return Some((
+                String::from_utf8_lossy(trim_partial_utf8(rest)).into_owned(),
+                "utf-8",
+            ));
+        }
+        if let Some(rest) = data.strip_prefix(&[0xFF, 0xFE]) {
+            return Some((decode_utf16(rest, u16::from_le_bytes), "utf-16le"));
+        }
+        if let Some(rest) = data.strip_prefix(&[0xFE, 0xFF]) {
+            return Some((decode_utf16(rest, u16::from_be_bytes), "utf-16be"));
+        }
+    }
+    // 从中间开始时，跳过开头不完整的字符
+    let skip = if at_start {
+        0
+    } else {
+        data.iter()
+            .take(3)
+            .take_while(|&&b| b & 0xC0 == 0x80)
+            .count()
+    };
+    let data = trim_partial_utf8(&data[skip..]);
+    if !textual && data.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) => Some((text.to_string(), "utf-8")),
+        Err(_) => {
+            let text = String::from_utf8_lossy(data);
+            let invalid = text
+                .chars()
+                .filter(|&c| c == char::REPLACEMENT_CHARACTER)
+                .count();
+            // 少量无效字节按文本处理，其余视为二进制
+            (textual || invalid * 100 < text.chars().count()).then(|| (text.into_owned(), "utf-8"))
+        }
+    }
+}
+
+/// 去掉末尾被截断的多字节字符
+fn trim_partial_utf8(data: &[u8]) -> &[u8] {
+    match std::str::from_utf8(data) {
+        Err(e) if e.error_len().is_none() => &data[..e.valid_up_to()],
+        _ => data,
+    }
+}
+
+/// 末尾不完整的 UTF-8 字符的字节数
+fn partial_utf8_tail(data: &[u8]) -> usize {
+    for i in 1..=data.len().min(3) {
+        let byte = data[data.len() - i];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let need = match byte {
+            0xF0.. => 4,
+            0xE0.. => 3,
+            0xC0.. => 2,
+            _ => 1,
+        };
+        return if need > i { i } else { 0 };
+    }
+    0
+}
+
+fn decode_utf16(data: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = data.chunks_exact(2).map(|c| from_bytes([c[0], c[1]]));
+    let mut text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    // 截断处的半个代理对
+    if text.ends_with(char::REPLACEMENT_CHARACTER) {
+        text.pop();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(path: &str, content: &[u8], range: ByteRange, limit: u64) -> EntryPreview {
+        preview(path, range, limit, |range, out| {
+            copy_range(&mut &content[..], range, out).unwrap();
+            Ok(content.len() as u64)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_preview() {
+        let text = "说明 readme".as_bytes();
+        let p = run("README.md", text, ByteRange::default(), 4);
+        // 截断在多字节字符中间
+        assert_eq!(p.text.as_deref(), Some("说"));
+        assert_eq!(p.mime, "text/markdown");
+        assert_eq!(p.length, 3);
+        assert!(p.truncated);
+
+        let p = run("a.txt", text, ByteRange::new(4, None), PREVIEW_LIMIT);
+        assert_eq!(p.text.as_deref(), Some(" readme"));
+        assert!(!p.truncated);
+
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let p = run("a.bin", png, ByteRange::default(), PREVIEW_LIMIT);
+        assert_eq!(p.mime, "image/png");
+        assert!(p.text.is_none() && p.data.is_some());
+
+        let utf16 = [0xFF, 0xFE, b'h', 0, b'i', 0];
+        let p = run("a.txt", &utf16, ByteRange::default(), PREVIEW_LIMIT);
+        assert_eq!(p.text.as_deref(), Some("hi"));
+        assert_eq!(p.encoding.as_deref(), Some("utf-16le"));
+    }
+}
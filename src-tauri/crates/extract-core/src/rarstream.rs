@@ -0,0 +1,272 @@
+//! 直接调用 unrar DLL 逐块读取 RAR 条目
+//!
+//! unrar crate 只能把条目整体读入内存，或者交给 DLL 写到磁盘（链接也由 DLL 创建）。这里通过
+//! `UCM_PROCESSDATA` 回调把解压出的数据逐块交给调用方，读到需要的位置即可停止；RAR5 链接的目标
+//! 从头部的 `RedirName` 读取。
+
+use std::io::{self, Write};
+use std::os::raw::c_int;
+use std::path::Path;
+use std::ptr::NonNull;
+
+use unrar::error::{Code, UnrarError, When};
+use unrar_sys as native;
+use zeroize::Zeroizing;
+
+use crate::info::decode_wide;
+
+/// RAR5 的链接类型，对应 DLL 的 `FSREDIR_*`
+const FSREDIR_UNIXSYMLINK: u32 = 1;
+const FSREDIR_WINSYMLINK: u32 = 2;
+const FSREDIR_JUNCTION: u32 = 3;
+const FSREDIR_HARDLINK: u32 = 4;
+const FSREDIR_FILECOPY: u32 = 5;
+
+/// 文件名与链接目标的最大长度（宽字符），与 DLL 一致
+const RAR_NAME_SIZE: usize = 2048;
+
+/// 条目头部中的目录标记
+const RHDF_DIRECTORY: u32 = 0x20;
+/// 条目头部中的加密标记
+const RHDF_ENCRYPTED: u32 = 0x04;
+
+/// DLL 接口使用的压缩包路径：linux 上为字节串，其余平台为宽字符串
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+pub(crate) type RarPath = std::ffi::CString;
+#[cfg(not(any(target_os = "linux", target_os = "netbsd")))]
+pub(crate) type RarPath = Vec<native::WCHAR>;
+
+/// 转换为 DLL 接口使用的以 0 结尾的路径
+pub(crate) fn rar_path(path: &Path) -> Option<RarPath> {
+    #[cfg(any(target_os = "linux", target_os = "netbsd"))]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::ffi::CString::new(path.as_os_str().as_bytes()).ok()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        Some(
+            path.as_os_str()
+                .encode_wide()
+                .chain(std::iter::once(0))
+                .collect(),
+        )
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "netbsd", windows)))]
+    {
+        Some(
+            path.to_string_lossy()
+                .chars()
+                .map(|c| c as native::WCHAR)
+                .chain(std::iter::once(0))
+                .collect(),
+        )
+    }
+}
+
+/// 条目头部
+#[derive(Debug)]
+pub(crate) struct RarHeader {
+    pub path: String,
+    pub size: u64,
+    pub file_time: u32,
+    pub file_attr: u32,
+    pub is_dir: bool,
+    pub is_encrypted: bool,
+    /// RAR5 在头部中记录的链接；RAR3 的符号链接目标保存在数据中，这里为 `None`
+    pub link: Option<RarLink>,
+}
+
+#[derive(Debug)]
+pub(crate) enum RarLink {
+    Symlink(String),
+    /// 硬链接或文件副本，目标为压缩包内路径
+    Hardlink(String),
+}
+
+/// 以解压模式打开的 RAR，依次调用 [`RarStream::next_header`] 与 [`RarStream::skip`] 或
+/// [`RarStream::read`]
+pub(crate) struct RarStream {
+    handle: NonNull<native::Handle>,
+}
+
+impl RarStream {
+    pub fn open(path: &Path, password: &str) -> Result<Self, UnrarError> {
+        let name = rar_path(path).ok_or_else(|| error(native::ERAR_EOPEN, When::Open))?;
+        let mut data =
+            native::OpenArchiveDataEx::new(name.as_ptr() as *const _, native::RAR_OM_EXTRACT);
+        // SAFETY: data 与 name 在调用期间均有效
+        let handle = unsafe { native::RAROpenArchiveEx(&mut data as *mut _) };
+        let Some(handle) = NonNull::new(handle as *mut native::Handle) else {
+            return Err(error(data.open_result as c_int, When::Open));
+        };
+        let stream = RarStream { handle };
+        if data.open_result as c_int != native::ERAR_SUCCESS {
+            return Err(error(data.open_result as c_int, When::Open));
+        }
+        if !password.is_empty() {
+            let mut bytes = Zeroizing::new(Vec::with_capacity(password.len() + 1));
+            bytes.extend_from_slice(password.as_bytes());
+            bytes.push(0);
+            // SAFETY: 以 0 结尾，DLL 在调用时复制密码
+            unsafe { native::RARSetPassword(handle.as_ptr(), bytes.as_ptr() as *const _) };
+        }
+        Ok(stream)
+    }
+
+    /// 读取下一个条目的头部，到达末尾时返回 `None`
+    pub fn next_header(&mut self) -> Result<Option<RarHeader>, UnrarError> {
+        self.set_callback(0);
+        let mut redir_name: Vec<native::WCHAR> = vec![0; RAR_NAME_SIZE];
+        let mut header = native::HeaderDataEx {
+            redir_name: redir_name.as_mut_ptr(),
+            redir_name_size: redir_name.len() as u32,
+            ..Default::default()
+        };
+        // SAFETY: header 与 redir_name 在调用期间均有效
+        let code = unsafe { native::RARReadHeaderEx(self.handle.as_ptr(), &mut header as *mut _) };
+        match code {
+            native::ERAR_SUCCESS => {}
+            native::ERAR_END_ARCHIVE => return Ok(None),
+            code => return Err(error(code, When::Read)),
+        }
+
+        let target = wide_str(&redir_name);
+        let link = match header.redir_type {
+            _ if target.is_empty() => None,
+            FSREDIR_UNIXSYMLINK | FSREDIR_WINSYMLINK | FSREDIR_JUNCTION => {
+                Some(RarLink::Symlink(target))
+            }
+            FSREDIR_HARDLINK | FSREDIR_FILECOPY => Some(RarLink::Hardlink(target)),
+            _ => None,
+        };
+        Ok(Some(RarHeader {
+            path: wide_str(&header.filename_w),
+            size: ((header.unp_size_high as u64) << 32) | header.unp_size as u64,
+            file_time: header.file_time,
+            file_attr: header.file_attr,
+            is_dir: header.flags & RHDF_DIRECTORY != 0,
+            is_encrypted: header.flags & RHDF_ENCRYPTED != 0,
+            link,
+        }))
+    }
+
+    /// 跳过当前条目
+    pub fn skip(&mut self) -> Result<(), UnrarError> {
+        self.process(native::RAR_SKIP, 0)
+    }
+
+    /// 解压当前条目，数据逐块交给 `write`
+    ///
+    /// `write` 返回 `false` 时停止解压并返回 `Ok(false)`，之后不能再读取后续条目。
+    pub fn read(&mut self, write: &mut dyn FnMut(&[u8]) -> bool) -> Result<bool, UnrarError> {
+        let mut sink = Sink {
+            write,
+            stopped: false,
+        };
+        let result = self.process(native::RAR_TEST, &mut sink as *mut Sink as native::LPARAM);
+        match result {
+            _ if sink.stopped => Ok(false),
+            Ok(()) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 解压当前条目并写入 `out`，外层为解压的错误，内层为写入的错误
+    pub fn read_to(&mut self, out: &mut dyn Write) -> Result<io::Result<()>, UnrarError> {
+        let mut failed = None;
+        self.read(&mut |data| match out.write_all(data) {
+            Ok(()) => true,
+            Err(e) => {
+                failed = Some(e);
+                false
+            }
+        })?;
+        Ok(failed.map_or(Ok(()), Err))
+    }
+
+    /// 解压当前条目，只保留前 `limit` 个字节，用于读取以内容保存的链接目标
+    pub fn read_head(&mut self, limit: usize) -> Result<Vec<u8>, UnrarError> {
+        let mut head = Vec::new();
+        self.read(&mut |data| {
+            let room = limit - head.len();
+            head.extend_from_slice(&data[..room.min(data.len())]);
+            true
+        })?;
+        Ok(head)
+    }
+
+    fn process(&mut self, operation: c_int, user_data: native::LPARAM) -> Result<(), UnrarError> {
+        self.set_callback(user_data);
+        // SAFETY: 不指定输出路径，数据只经由回调交出；user_data 在调用期间有效
+        let code = unsafe {
+            native::RARProcessFile(
+                self.handle.as_ptr(),
+                operation,
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        self.set_callback(0);
+        match code {
+            native::ERAR_SUCCESS => Ok(()),
+            code => Err(error(code, When::Process)),
+        }
+    }
+
+    fn set_callback(&mut self, user_data: native::LPARAM) {
+        // SAFETY: 句柄在 self 存续期间有效
+        unsafe { native::RARSetCallback(self.handle.as_ptr(), Some(callback), user_data) };
+    }
+}
+
+impl Drop for RarStream {
+    fn drop(&mut self) {
+        // SAFETY: 句柄只在这里关闭
+        unsafe { native::RARCloseArchive(self.handle.as_ptr()) };
+    }
+}
+
+/// 回调的用户数据
+struct Sink<'a> {
+    write: &'a mut dyn FnMut(&[u8]) -> bool,
+    stopped: bool,
+}
+
+extern "C" fn callback(
+    msg: native::UINT,
+    user_data: native::LPARAM,
+    p1: native::LPARAM,
+    p2: native::LPARAM,
+) -> c_int {
+    match msg {
+        // 找不到下一个分卷时停止，与 unrar crate 一致
+        native::UCM_CHANGEVOLUMEW if p2 == native::RAR_VOL_ASK => -1,
+        native::UCM_PROCESSDATA if user_data != 0 => {
+            // SAFETY: user_data 指向 RarStream::read 中的 Sink，p1、p2 为 DLL 提供的数据块
+            let sink = unsafe { &mut *(user_data as *mut Sink) };
+            let data = unsafe { std::slice::from_raw_parts(p1 as *const u8, p2 as usize) };
+            if (sink.write)(data) {
+                0
+            } else {
+                sink.stopped = true;
+                -1
+            }
+        }
+        _ => 0,
+    }
+}
+
+fn error(code: c_int, when: When) -> UnrarError {
+    UnrarError {
+        code: Code::from(code).unwrap_or(Code::Unknown),
+        when,
+    }
+}
+
+/// 以 0 结尾的宽字符串
+fn wide_str(wide: &[native::WCHAR]) -> String {
+    let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+    decode_wide(&wide[..len])
+}
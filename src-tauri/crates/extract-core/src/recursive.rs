@@ -0,0 +1,277 @@
+//! 递归解压：解压完成后，把写出的压缩包就地解压到同名目录
+//!
+//! 单文件的 `.gz` / `.bz2` 直接解压为去掉扩展名的文件，其余格式解压到去掉扩展名的目录。
+//! 内层压缩包不带密码打开，无法解压时保留原文件并记录警告。
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use bzip2::read::BzDecoder;
+use flate2::read::MultiGzDecoder;
+
+use crate::options::{ExtractOptions, RecursiveExtract, SizeLimit};
+use crate::{create_handler, ArchiveError, ArchiveFormat, ResultR};
+
+/// 依次解压 `archives`，每个压缩包解压出的内容再由其自身的解压过程继续递归
+///
+/// 这一层及更深层写出的内容共用 [`RecursiveExtract::max_size`] 的预算，按实际写入的字节计数，
+/// 同时计入外层的 [`ExtractOptions::size_limit`]。
+pub(crate) fn extract_nested(archives: Vec<PathBuf>, options: &ExtractOptions) -> ResultR<()> {
+    let Some(recursive) = options.recursive.as_ref().filter(|r| r.max_depth > 0) else {
+        return Ok(());
+    };
+    let budget = SizeLimit::within(recursive.max_size, options.size_limit.clone());
+    for archive in archives {
+        match extract_one(&archive, options, recursive, &budget) {
+            Ok(()) => {
+                if recursive.delete_archives {
+                    fs::remove_file(&archive)
+                        .map_err(|e| ArchiveError::MsgError(format!("删除压缩包失败: {}", e)))?;
+                }
+            }
+            Err(ArchiveError::MsgError(e)) => {
+                log::warn!("跳过内层压缩包 {}: {}", archive.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 解压一个内层压缩包，写出的内容（含更深层）计入 `budget`，失败时删除已写出的部分
+fn extract_one(
+    archive: &Path,
+    options: &ExtractOptions,
+    recursive: &RecursiveExtract,
+    budget: &SizeLimit,
+) -> ResultR<()> {
+    let format = ArchiveFormat::from_path(archive);
+    // 下一层的选项：少一层，写入计入预算，不再按条目过滤
+    let next = ExtractOptions {
+        include: Vec::new(),
+        exclude: Vec::new(),
+        recursive: Some(RecursiveExtract {
+            max_depth: recursive.max_depth - 1,
+            ..recursive.clone()
+        }),
+        size_limit: Some(budget.clone()),
+        ..options.clone()
+    };
+
+    if matches!(format, ArchiveFormat::Gz | ArchiveFormat::Bz2) && !is_tar(archive, format) {
+        let file = File::open(archive).map_err(io_error)?;
+        let reader: Box<dyn Read> = match format {
+            ArchiveFormat::Gz => Box::new(MultiGzDecoder::new(file)),
+            _ => Box::new(BzDecoder::new(file)),
+        };
+        let target = available(&strip_extension(archive));
+        copy_limited(reader, &target, &next)?;
+        // 解压出的文件本身可能还是压缩包，如 `.tar.gz.gz`
+        if !crate::nested::is_nested_archive(&target.to_string_lossy()) {
+            return Ok(());
+        }
+        return extract_nested(vec![target], &next);
+    }
+
+    let target = available(&strip_extension(archive));
+    let result = create_handler(archive, format, String::new()).extract(&target, &next);
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&target);
+    }
+    result
+}
+
+/// `.gz` / `.bz2` 是否其实是未用复合扩展名的 tar
+fn is_tar(archive: &Path, format: ArchiveFormat) -> bool {
+    let Ok(file) = File::open(archive) else {
+        return false;
+    };
+    let mut reader: Box<dyn Read> = match format {
+        ArchiveFormat::Gz => Box::new(MultiGzDecoder::new(file)),
+        _ => Box::new(BzDecoder::new(file)),
+    };
+    let mut header = [0u8; 512];
+    reader.read_exact(&mut header).is_ok() && &header[257..262] == b"ustar"
+}
+
+/// 复制到 `target`，写入计入 `options` 的上限，超出时删除并报错
+fn copy_limited(mut reader: impl Read, target: &Path, options: &ExtractOptions) -> ResultR<()> {
+    let mut out = File::create(target).map_err(io_error)?;
+    let written = io::copy(&mut reader, &mut options.limited(&mut out));
+    drop(out);
+    written.map(|_| ()).map_err(|e| {
+        let _ = fs::remove_file(target);
+        io_error(e)
+    })
+}
+
+/// 去掉压缩包扩展名（含 `.tar.gz` 等复合扩展名），与 [`ArchiveFormat::from_path`] 识别的一致
+fn strip_extension(archive: &Path) -> PathBuf {
+    let name = archive
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match ArchiveFormat::strip_extension(&name) {
+        Some(stem) => archive.with_file_name(stem),
+        None => archive.to_path_buf(),
+    }
+}
+
+/// 目标已存在时依次尝试 `name (1)`、`name (2)`……
+fn available(path: &Path) -> PathBuf {
+    if fs::symlink_metadata(path).is_err() {
+        return path.to_path_buf();
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    (1..)
+        .map(|i| path.with_file_name(format!("{} ({})", name, i)))
+        .find(|p| fs::symlink_metadata(p).is_err())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn io_error(e: io::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+    use std::io::Write;
+
+    #[test]
+    fn test_recursive_extract() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+
+        // bundle.zip 中有 day1.tar.gz，其中有 app.log.gz
+        let mut log = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        log.write_all(b"log line\n").unwrap();
+        let log = log.finish().unwrap();
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(log.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "app.log.gz", &log[..])
+            .unwrap();
+        let day = builder.into_inner().unwrap().finish().unwrap();
+        let bundle = dir.join("bundle.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&bundle).unwrap());
+        writer
+            .start_file("logs/day1.tar.gz", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&day).unwrap();
+        writer.finish().unwrap();
+
+        let extract = |name: &str, recursive: RecursiveExtract| {
+            let out = dir.join(name);
+            let options = ExtractOptions {
+                recursive: Some(recursive),
+                ..Default::default()
+            };
+            create_handler(&bundle, ArchiveFormat::Zip, String::new())
+                .extract(&out, &options)
+                .unwrap();
+            out
+        };
+
+        let out = extract(
+            "all",
+            RecursiveExtract {
+                delete_archives: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            fs::read(out.join("logs/day1/app.log")).unwrap(),
+            b"log line\n"
+        );
+        assert!(!out.join("logs/day1.tar.gz").exists());
+        assert!(!out.join("logs/day1/app.log.gz").exists());
+
+        // 只递归一层，保留中间文件
+        let out = extract(
+            "one",
+            RecursiveExtract {
+                max_depth: 1,
+                ..Default::default()
+            },
+        );
+        assert!(out.join("logs/day1.tar.gz").exists());
+        assert!(out.join("logs/day1/app.log.gz").exists());
+        assert!(!out.join("logs/day1/app.log").exists());
+
+        // 超出大小限制时不解压
+        let out = extract(
+            "small",
+            RecursiveExtract {
+                max_size: 4,
+                ..Default::default()
+            },
+        );
+        assert!(out.join("logs/day1.tar.gz").exists());
+        assert!(!out.join("logs/day1").exists());
+
+        assert_eq!(strip_extension(Path::new("a/x.TAR.GZ")), Path::new("a/x"));
+        assert_eq!(strip_extension(Path::new("a/x.tar.zst")), Path::new("a/x"));
+        assert_eq!(strip_extension(Path::new("a/x.tzst")), Path::new("a/x"));
+        assert_eq!(strip_extension(Path::new(".zip")), Path::new(".zip"));
+    }
+
+    #[test]
+    fn test_size_limit_counts_written_bytes() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+
+        // inner.zip 的头部声称条目只有 1 字节，实际解压出 6000 字节
+        let mut inner = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        inner
+            .start_file("big.txt", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        inner.write_all("hello ".repeat(1000).as_bytes()).unwrap();
+        let mut inner = inner.finish().unwrap().into_inner();
+        let central = inner.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        inner[22..26].copy_from_slice(&1u32.to_le_bytes());
+        inner[central + 24..central + 28].copy_from_slice(&1u32.to_le_bytes());
+
+        let bundle = dir.join("bundle.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&bundle).unwrap());
+        let stored = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("inner.zip", stored).unwrap();
+        writer.write_all(&inner).unwrap();
+        writer.finish().unwrap();
+
+        let out = dir.join("out");
+        let options = ExtractOptions {
+            recursive: Some(RecursiveExtract {
+                max_size: 100,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        create_handler(&bundle, ArchiveFormat::Zip, String::new())
+            .extract(&out, &options)
+            .unwrap();
+        assert!(out.join("inner.zip").exists());
+        assert!(!out.join("inner").exists());
+
+        // 直接设置的上限同样按写入计数
+        let limit = SizeLimit::new(100);
+        let options = ExtractOptions {
+            size_limit: Some(limit.clone()),
+            ..Default::default()
+        };
+        let result = create_handler(&out.join("inner.zip"), ArchiveFormat::Zip, String::new())
+            .extract(&dir.join("direct"), &options);
+        assert!(result.is_err());
+        assert!(limit.used() > 100);
+    }
+}
@@ -0,0 +1,1052 @@
+//! 修复损坏的压缩包
+//!
+//! 下载不完整或局部损坏的压缩包往往无法正常打开。修复模式尽量找回仍然完好的条目：zip 的中央目录
+//! 缺失或损坏时扫描本地文件头并重建目录，tar 遇到损坏的头部时跳到下一个校验和正确的头部。
+//! 每个条目先完整读出并校验，完好的条目再解压到目录或写入重建的压缩包。
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use zeroize::Zeroizing;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::convert::{safe_name, StreamEntry};
+use crate::info::TestFailure;
+use crate::links::Links;
+use crate::options::{CreateOptions, Encryption, ExtractOptions, SalvageOptions};
+use crate::restore::{self, EntryMeta, Restorer};
+use crate::spool::Spool;
+use crate::writer::{self, ArchiveWriter, EntryKind};
+use crate::{zip_owner, zip_times, ArchiveError, ArchiveFormat, ResultR, TarHandler};
+
+const LOCAL_HEADER: [u8; 4] = *b"PK\x03\x04";
+const CENTRAL_HEADER: [u8; 4] = *b"PK\x01\x02";
+const END_OF_DIRECTORY: [u8; 4] = *b"PK\x05\x06";
+const DATA_DESCRIPTOR: [u8; 4] = *b"PK\x07\x08";
+const ZIP64_END_OF_DIRECTORY: [u8; 4] = *b"PK\x06\x06";
+const ZIP64_LOCATOR: [u8; 4] = *b"PK\x06\x07";
+/// zip64 扩展信息的标记
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// tar 的 pax 或 GNU 长名称头部最多读取的大小
+const MAX_EXTENSION_SIZE: u64 = 1 << 20;
+
+/// 修复结果
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SalvageReport {
+    /// 完好的条目数
+    pub recovered: u64,
+    /// 损坏的条目；头部损坏、无法得知名称时路径为空，错误中给出偏移
+    pub damaged: Vec<TestFailure>,
+    /// zip 的中央目录缺失或损坏，条目由扫描本地文件头得到
+    pub rebuilt_directory: bool,
+    /// 不属于任何完好条目而被跳过的字节数
+    pub skipped_bytes: u64,
+}
+
+impl SalvageReport {
+    fn fail(&mut self, path: impl Into<String>, error: impl std::fmt::Display) {
+        self.damaged.push(TestFailure {
+            path: path.into(),
+            error: error.to_string(),
+        });
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.damaged.is_empty()
+    }
+}
+
+/// 从损坏的 `archive` 中找回完好的条目，按 `options` 解压或写入重建的压缩包
+///
+/// 支持 zip 与 tar（gz、bz2、xz、zst）。重建的压缩包与原压缩包格式相同，zip 使用原密码重新加密；
+/// 扫描得到的 zip 条目没有中央目录中的权限信息，按默认权限写出。`options` 都为空时只检查。
+pub fn salvage_archive(
+    archive: &Path,
+    password: &str,
+    options: &SalvageOptions,
+) -> ResultR<SalvageReport> {
+    let format = ArchiveFormat::from_path(archive);
+    if !matches!(
+        format,
+        ArchiveFormat::Zip
+            | ArchiveFormat::Tar
+            | ArchiveFormat::TarGz
+            | ArchiveFormat::TarBz2
+            | ArchiveFormat::TarXz
+            | ArchiveFormat::TarZst
+    ) {
+        return Err(ArchiveError::MsgError(String::from("暂不支持修复该格式")));
+    }
+
+    let extract_options = ExtractOptions::default();
+    let mut extractor = match &options.extract_to {
+        Some(dir) => Some(Extractor::new(dir, &extract_options)?),
+        None => None,
+    };
+    let mut report = SalvageReport::default();
+    match &options.rebuild {
+        Some(output) => {
+            let create = CreateOptions {
+                encryption: (format == ArchiveFormat::Zip && !password.is_empty()).then(|| {
+                    Encryption {
+                        password: Zeroizing::new(password.to_string()),
+                        encrypt_header: false,
+                    }
+                }),
+                ..Default::default()
+            };
+            writer::write_output(output, format, &create, |mut writer| {
+                let outputs = Outputs {
+                    extractor: extractor.as_mut(),
+                    writer: Some(writer.as_mut()),
+                    names: HashSet::new(),
+                };
+                salvage(archive, format, password, &mut report, outputs)?;
+                writer.finish()
+            })?
+        }
+        None => {
+            let outputs = Outputs {
+                extractor: extractor.as_mut(),
+                writer: None,
+                names: HashSet::new(),
+            };
+            salvage(archive, format, password, &mut report, outputs)?
+        }
+    }
+    if let Some(extractor) = extractor {
+        extractor.finish()?;
+    }
+    Ok(report)
+}
+
+fn salvage(
+    archive: &Path,
+    format: ArchiveFormat,
+    password: &str,
+    report: &mut SalvageReport,
+    mut outputs: Outputs,
+) -> ResultR<()> {
+    let mut spool = Spool::new("salvage").map_err(io_error)?;
+    if format == ArchiveFormat::Zip {
+        return salvage_zip(archive, password, &mut spool, report, &mut outputs);
+    }
+    let reader = TarHandler {
+        archive_path: archive.to_path_buf(),
+        archive_format: format,
+        index: None,
+    }
+    .open_reader()?;
+    salvage_tar(reader, &mut spool, report, &mut outputs)
+}
+
+fn io_error(e: io::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+/// 完好条目的去向
+struct Outputs<'a, 'b> {
+    extractor: Option<&'a mut Extractor<'b>>,
+    writer: Option<&'a mut dyn ArchiveWriter>,
+    /// 已写入重建压缩包的名称，重复的条目只保留第一个
+    names: HashSet<String>,
+}
+
+impl Outputs<'_, '_> {
+    /// `data` 为内容所在的临时文件，位于开头
+    fn add(&mut self, entry: &StreamEntry, data: &mut File) -> ResultR<()> {
+        if let Some(extractor) = self.extractor.as_mut() {
+            extractor.add(entry, data)?;
+            data.rewind().map_err(io_error)?;
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        let Some(name) = safe_name(&entry.path) else {
+            log::warn!("跳过不安全的路径: {}", entry.path);
+            return Ok(());
+        };
+        if !self.names.insert(name.clone()) {
+            log::warn!("跳过重复的条目: {}", name);
+            return Ok(());
+        }
+        match &entry.kind {
+            EntryKind::Dir => writer.add_dir(&name, &entry.attrs),
+            EntryKind::Symlink(target) => writer.add_symlink(&name, &entry.attrs, target),
+            EntryKind::File => {
+                writer.add_file(&name, &entry.attrs, entry.size, &mut data.take(entry.size))
+            }
+        }
+    }
+}
+
+/// 把完好的条目解压到目录，与正常解压一样恢复时间与权限
+struct Extractor<'a> {
+    root: PathBuf,
+    restorer: Restorer<'a>,
+    links: Links,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(root: &Path, options: &'a ExtractOptions) -> ResultR<Self> {
+        fs::create_dir_all(root)
+            .map_err(|e| ArchiveError::MsgError(format!("创建目标目录失败: {}", e)))?;
+        Ok(Extractor {
+            root: root.to_path_buf(),
+            restorer: Restorer::new(options),
+            links: Links::new(root, options),
+        })
+    }
+
+    fn add(&mut self, entry: &StreamEntry, data: &mut File) -> ResultR<()> {
+        let Some(path) = restore::safe_join(&self.root, &entry.path) else {
+            log::warn!("跳过不安全的路径: {}", entry.path);
+            return Ok(());
+        };
+        let meta = EntryMeta {
+            mtime: Some(entry.attrs.mtime),
+            mode: Some(entry.attrs.mode),
+            ..Default::default()
+        };
+        match &entry.kind {
+            EntryKind::Dir => {
+                fs::create_dir_all(&path)
+                    .map_err(|e| ArchiveError::MsgError(format!("创建目录失败: {}", e)))?;
+                self.restorer.dir(path, meta);
+            }
+            EntryKind::Symlink(target) => self.links.symlink(path, target, meta),
+            EntryKind::File => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| ArchiveError::MsgError(format!("创建父目录失败: {}", e)))?;
+                }
+                let mut out = File::create(&path)
+                    .map_err(|e| ArchiveError::MsgError(format!("创建文件失败: {}", e)))?;
+                io::copy(&mut data.take(entry.size), &mut out)
+                    .map_err(|e| ArchiveError::MsgError(format!("写入文件失败: {}", e)))?;
+                drop(out);
+                self.restorer.file(&path, &meta)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> ResultR<()> {
+        self.links.finish(&self.restorer)?;
+        self.restorer.finish()
+    }
+}
+
+fn salvage_zip(
+    archive: &Path,
+    password: &str,
+    spool: &mut Spool,
+    report: &mut SalvageReport,
+    outputs: &mut Outputs,
+) -> ResultR<()> {
+    let file = File::open(archive).map_err(io_error)?;
+    match ZipArchive::new(file) {
+        Ok(zip) => salvage_zip_entries(zip, password, spool, report, outputs),
+        Err(e) => {
+            log::warn!("中央目录损坏，扫描本地文件头: {}", e);
+            report.rebuilt_directory = true;
+            let mut file = File::open(archive).map_err(io_error)?;
+            let directory = scan_local_headers(&mut file, report).map_err(io_error)?;
+            let patched = Patched::new(file, directory).map_err(io_error)?;
+            let zip = ZipArchive::new(patched)
+                .map_err(|e| ArchiveError::MsgError(format!("重建中央目录失败: {}", e)))?;
+            salvage_zip_entries(zip, password, spool, report, outputs)
+        }
+    }
+}
+
+fn salvage_zip_entries<R: Read + Seek>(
+    mut zip: ZipArchive<R>,
+    password: &str,
+    spool: &mut Spool,
+    report: &mut SalvageReport,
+    outputs: &mut Outputs,
+) -> ResultR<()> {
+    for i in 0..zip.len() {
+        let name = zip
+            .name_for_index(i)
+            .map_or_else(|| format!("#{}", i), str::to_string);
+        let file = if password.is_empty() {
+            zip.by_index(i)
+        } else {
+            zip.by_index_decrypt(i, password.as_bytes())
+        };
+        let mut file = match file {
+            Ok(file) => file,
+            Err(ZipError::InvalidPassword)
+            | Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+                return Err(ArchiveError::MsgError(String::from("密码错误")))
+            }
+            Err(e) => {
+                report.fail(name, e);
+                continue;
+            }
+        };
+        // 完整读出内容，zip 在读到结尾时校验 CRC
+        let read = if file.is_symlink() {
+            let mut target = String::new();
+            file.read_to_string(&mut target)
+                .map(|_| (EntryKind::Symlink(target), 0))
+        } else if file.is_dir() {
+            Ok((EntryKind::Dir, 0))
+        } else {
+            spool.fill(&mut file).map(|size| (EntryKind::File, size))
+        };
+        let (kind, size) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                report.fail(name, e);
+                continue;
+            }
+        };
+        let (mtime, _) = zip_times(&file);
+        let (uid, gid) = file.extra_data().map(zip_owner).unwrap_or_default();
+        let mut entry = StreamEntry::new(&name, kind, size, mtime, file.unix_mode());
+        entry.attrs.uid = uid.unwrap_or(0) as u64;
+        entry.attrs.gid = gid.unwrap_or(0) as u64;
+        drop(file);
+        report.recovered += 1;
+        outputs.add(&entry, &mut spool.file)?;
+    }
+    Ok(())
+}
+
+/// 从 `from` 开始查找第一个出现的签名
+fn find_signature(
+    file: &mut File,
+    from: u64,
+    signatures: &[[u8; 4]],
+) -> io::Result<Option<(u64, [u8; 4])>> {
+    let mut buf = vec![0u8; 64 << 10];
+    let mut base = from;
+    loop {
+        file.seek(SeekFrom::Start(base))?;
+        let n = read_full(file, &mut buf)?;
+        if n < 4 {
+            return Ok(None);
+        }
+        for i in 0..=n - 4 {
+            if buf[i] != b'P' || buf[i + 1] != b'K' {
+                continue;
+            }
+            let signature = [buf[i], buf[i + 1], buf[i + 2], buf[i + 3]];
+            if signatures.contains(&signature) {
+                return Ok(Some((base + i as u64, signature)));
+            }
+        }
+        if n < buf.len() {
+            return Ok(None);
+        }
+        // 保留末尾 3 字节，签名可能跨越两次读取
+        base += (n - 3) as u64;
+    }
+}
+
+/// 读满 `buf` 或读到文件结尾，返回读取的字节数
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+fn le16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap_or_default())
+}
+
+/// 扫描得到的完整条目
+struct LocalEntry {
+    offset: u64,
+    /// 本地文件头的固定部分
+    header: [u8; 30],
+    name: Vec<u8>,
+    extra: Vec<u8>,
+    crc: u32,
+    compressed: u64,
+    uncompressed: u64,
+    /// 数据（含数据描述符）结束的位置
+    end: u64,
+}
+
+/// 扫描本地文件头，返回追加在文件之后的中央目录与目录结束记录
+fn scan_local_headers(file: &mut File, report: &mut SalvageReport) -> io::Result<Vec<u8>> {
+    let len = file.metadata()?.len();
+    let mut directory = Vec::new();
+    let mut count = 0u64;
+    let mut pos = 0;
+    while let Some((start, _)) = find_signature(file, pos, &[LOCAL_HEADER])? {
+        match read_local(file, start, len)? {
+            Some(Ok(entry)) => {
+                report.skipped_bytes += start - pos;
+                central_record(&entry, &mut directory);
+                count += 1;
+                pos = entry.end;
+            }
+            Some(Err((name, error))) => {
+                report.fail(name, error);
+                report.skipped_bytes += start + 4 - pos;
+                pos = start + 4;
+            }
+            // 数据中恰好出现的签名
+            None => {
+                report.skipped_bytes += start + 4 - pos;
+                pos = start + 4;
+            }
+        }
+    }
+    report.skipped_bytes += len - pos.min(len);
+    end_of_directory(&mut directory, count, len);
+    Ok(directory)
+}
+
+/// 读取 `offset` 处的本地文件头；不像文件头时返回 `None`，数据不完整时返回错误与条目名
+fn read_local(
+    file: &mut File,
+    offset: u64,
+    len: u64,
+) -> io::Result<Option<Result<LocalEntry, (String, String)>>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut header = [0u8; 30];
+    if read_full(file, &mut header)? < header.len() {
+        return Ok(None);
+    }
+    let (version, flags) = (le16(&header[4..]), le16(&header[6..]));
+    let name_len = le16(&header[26..]) as usize;
+    let extra_len = le16(&header[28..]) as usize;
+    if name_len == 0 || version & 0xff > 63 {
+        return Ok(None);
+    }
+    let mut name = vec![0u8; name_len];
+    let mut extra = vec![0u8; extra_len];
+    if read_full(file, &mut name)? < name_len || read_full(file, &mut extra)? < extra_len {
+        return Ok(None);
+    }
+    let display = String::from_utf8_lossy(&name).to_string();
+    let incomplete = || Ok(Some(Err((display.clone(), String::from("数据不完整")))));
+
+    let mut crc = le32(&header[14..]);
+    let mut compressed = le32(&header[18..]) as u64;
+    let mut uncompressed = le32(&header[22..]) as u64;
+    let zip64 = compressed == u32::MAX as u64 || uncompressed == u32::MAX as u64;
+    if zip64 {
+        // 本地文件头的 zip64 扩展信息总是同时包含两个大小
+        if let Some(data) = extra_field(&extra, ZIP64_EXTRA).filter(|d| d.len() >= 16) {
+            uncompressed = le64(data);
+            compressed = le64(&data[8..]);
+        }
+    }
+    let data_start = offset + (30 + name_len + extra_len) as u64;
+    let end = if flags & 0x08 != 0 {
+        match find_descriptor(file, data_start, zip64)? {
+            Some(descriptor) => {
+                (crc, compressed, uncompressed) = (descriptor.1, descriptor.2, descriptor.3);
+                descriptor.0
+            }
+            None => return incomplete(),
+        }
+    } else {
+        data_start + compressed
+    };
+    if end > len {
+        return incomplete();
+    }
+    Ok(Some(Ok(LocalEntry {
+        offset,
+        header,
+        name,
+        extra,
+        crc,
+        compressed,
+        uncompressed,
+        end,
+    })))
+}
+
+/// 查找数据描述符，返回 (结束位置, crc, 压缩大小, 原始大小)
+///
+/// 带签名的描述符紧跟在数据之后；不带签名时描述符位于下一个头部之前。压缩大小与位置相符才认为找到。
+fn find_descriptor(
+    file: &mut File,
+    data_start: u64,
+    zip64: bool,
+) -> io::Result<Option<(u64, u32, u64, u64)>> {
+    let fields = if zip64 { 20 } else { 12 };
+    let signatures = [
+        DATA_DESCRIPTOR,
+        LOCAL_HEADER,
+        CENTRAL_HEADER,
+        END_OF_DIRECTORY,
+    ];
+    let mut from = data_start;
+    while let Some((at, signature)) = find_signature(file, from, &signatures)? {
+        from = at + 1;
+        let (descriptor, size, end) = match signature {
+            DATA_DESCRIPTOR => (at + 4, at - data_start, at + 4 + fields),
+            _ if at >= data_start + fields => (at - fields, at - fields - data_start, at),
+            _ => continue,
+        };
+        file.seek(SeekFrom::Start(descriptor))?;
+        let mut buf = [0u8; 20];
+        if read_full(file, &mut buf[..fields as usize])? < fields as usize {
+            continue;
+        }
+        let (compressed, uncompressed) = match zip64 {
+            true => (le64(&buf[4..]), le64(&buf[12..])),
+            false => (le32(&buf[4..]) as u64, le32(&buf[8..]) as u64),
+        };
+        if compressed == size {
+            return Ok(Some((end, le32(&buf), compressed, uncompressed)));
+        }
+    }
+    Ok(None)
+}
+
+/// 扩展字段中 `id` 的数据
+fn extra_field(extra: &[u8], id: u16) -> Option<&[u8]> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let len = le16(&rest[2..]) as usize;
+        let data = rest.get(4..4 + len)?;
+        if le16(rest) == id {
+            return Some(data);
+        }
+        rest = &rest[4 + len..];
+    }
+    None
+}
+
+/// 按本地文件头生成中央目录记录，超出 32 位的值写入 zip64 扩展信息
+fn central_record(entry: &LocalEntry, out: &mut Vec<u8>) {
+    let mut extra = Vec::new();
+    let mut rest = entry.extra.as_slice();
+    while rest.len() >= 4 {
+        let len = (le16(&rest[2..]) as usize + 4).min(rest.len());
+        if le16(rest) != ZIP64_EXTRA {
+            extra.extend_from_slice(&rest[..len]);
+        }
+        rest = &rest[len..];
+    }
+    let mut zip64 = Vec::new();
+    let mut saturate = |value: u64| -> u32 {
+        if value < u32::MAX as u64 {
+            return value as u32;
+        }
+        zip64.extend(value.to_le_bytes());
+        u32::MAX
+    };
+    let uncompressed = saturate(entry.uncompressed);
+    let compressed = saturate(entry.compressed);
+    let offset = saturate(entry.offset);
+    if !zip64.is_empty() {
+        extra.extend(ZIP64_EXTRA.to_le_bytes());
+        extra.extend((zip64.len() as u16).to_le_bytes());
+        extra.extend(zip64);
+    }
+
+    let header = &entry.header;
+    out.extend(CENTRAL_HEADER);
+    // 由 unix 创建；外部属性为 0，即没有权限信息
+    out.extend([header[4].max(20), 3]);
+    // 所需版本、标志、压缩方式与时间
+    out.extend(&header[4..14]);
+    out.extend(entry.crc.to_le_bytes());
+    out.extend(compressed.to_le_bytes());
+    out.extend(uncompressed.to_le_bytes());
+    out.extend((entry.name.len() as u16).to_le_bytes());
+    out.extend((extra.len() as u16).to_le_bytes());
+    // 注释长度、起始分卷、内部属性、外部属性
+    out.extend([0u8; 10]);
+    out.extend(offset.to_le_bytes());
+    out.extend(&entry.name);
+    out.extend(extra);
+}
+
+/// 追加目录结束记录，条目数或偏移超出范围时先写入 zip64 记录
+fn end_of_directory(out: &mut Vec<u8>, count: u64, offset: u64) {
+    let size = out.len() as u64;
+    if count >= u16::MAX as u64 || offset >= u32::MAX as u64 || size >= u32::MAX as u64 {
+        let position = offset + size;
+        out.extend(ZIP64_END_OF_DIRECTORY);
+        out.extend(44u64.to_le_bytes());
+        out.extend([45, 3, 45, 0]);
+        out.extend([0u8; 8]);
+        out.extend(count.to_le_bytes());
+        out.extend(count.to_le_bytes());
+        out.extend(size.to_le_bytes());
+        out.extend(offset.to_le_bytes());
+        out.extend(ZIP64_LOCATOR);
+        out.extend(0u32.to_le_bytes());
+        out.extend(position.to_le_bytes());
+        out.extend(1u32.to_le_bytes());
+    }
+    out.extend(END_OF_DIRECTORY);
+    out.extend([0u8; 4]);
+    let count = count.min(u16::MAX as u64) as u16;
+    out.extend(count.to_le_bytes());
+    out.extend(count.to_le_bytes());
+    out.extend((size.min(u32::MAX as u64) as u32).to_le_bytes());
+    out.extend((offset.min(u32::MAX as u64) as u32).to_le_bytes());
+    out.extend([0u8; 2]);
+}
+
+/// 原文件之后接上重建的中央目录
+struct Patched {
+    file: File,
+    len: u64,
+    tail: Vec<u8>,
+    pos: u64,
+}
+
+impl Patched {
+    fn new(file: File, tail: Vec<u8>) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(Patched {
+            file,
+            len,
+            tail,
+            pos: 0,
+        })
+    }
+}
+
+impl Read for Patched {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = if self.pos < self.len {
+            let room = (self.len - self.pos).min(buf.len() as u64) as usize;
+            self.file.seek(SeekFrom::Start(self.pos))?;
+            self.file.read(&mut buf[..room])?
+        } else {
+            let start = ((self.pos - self.len) as usize).min(self.tail.len());
+            let mut tail = &self.tail[start..];
+            tail.read(buf)?
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for Patched {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let total = self.len + self.tail.len() as u64;
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(n) => total.checked_add_signed(n),
+        };
+        self.pos = target
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "定位到文件开头之前"))?;
+        Ok(self.pos)
+    }
+}
+
+/// 记录已读取字节数的读取器，用于报告损坏的位置
+struct Counted<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+/// pax 与 GNU 扩展头部为下一个条目提供的信息
+#[derive(Default)]
+struct Extensions {
+    path: Option<String>,
+    link: Option<String>,
+    mtime: Option<i64>,
+    size: Option<u64>,
+    uid: Option<u64>,
+    gid: Option<u64>,
+}
+
+impl Extensions {
+    /// 解析 `长度 键=值\n` 形式的 pax 记录
+    fn pax(&mut self, data: &[u8]) {
+        let mut rest = data;
+        while let Some(space) = rest.iter().position(|&b| b == b' ') {
+            let Some(len) = std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n > space && n <= rest.len())
+            else {
+                break;
+            };
+            let record = &rest[space + 1..len];
+            let record = record.strip_suffix(b"\n").unwrap_or(record);
+            if let Some(eq) = record.iter().position(|&b| b == b'=') {
+                let value = String::from_utf8_lossy(&record[eq + 1..]).to_string();
+                match &record[..eq] {
+                    b"path" => self.path = Some(value),
+                    b"linkpath" => self.link = Some(value),
+                    b"mtime" => self.mtime = value.parse::<f64>().ok().map(|t| t as i64),
+                    b"size" => self.size = value.parse().ok(),
+                    b"uid" => self.uid = value.parse().ok(),
+                    b"gid" => self.gid = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            rest = &rest[len..];
+        }
+    }
+}
+
+/// 头部的校验和是否正确，兼容按有符号字节计算的旧实现
+fn checksum_ok(block: &[u8; 512]) -> bool {
+    let header = tar::Header::from_byte_slice(block);
+    let Ok(expected) = header.cksum() else {
+        return false;
+    };
+    let field = 148..156;
+    let unsigned: u32 = block
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| if field.contains(&i) { 32 } else { b as u32 })
+        .sum();
+    let signed: i64 = block
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if field.contains(&i) {
+                32
+            } else {
+                b as i8 as i64
+            }
+        })
+        .sum();
+    expected == unsigned || expected as i64 == signed
+}
+
+/// 逐块读取 tar 数据流，校验和错误的块被跳过，直到下一个有效的头部
+fn salvage_tar(
+    reader: impl Read,
+    spool: &mut Spool,
+    report: &mut SalvageReport,
+    outputs: &mut Outputs,
+) -> ResultR<()> {
+    let mut reader = Counted {
+        inner: reader,
+        offset: 0,
+    };
+    let mut extensions = Extensions::default();
+    // 连续损坏区域的起始偏移
+    let mut damaged_from = None;
+    let mut block = [0u8; 512];
+    loop {
+        let offset = reader.offset;
+        match read_full(&mut reader, &mut block) {
+            Ok(512) => {}
+            Ok(0) => break,
+            Ok(n) => {
+                report.skipped_bytes += n as u64;
+                report.fail("", format!("偏移 {}: 数据不完整", offset));
+                break;
+            }
+            Err(e) => {
+                report.fail("", format!("偏移 {}: {}", offset, e));
+                break;
+            }
+        }
+        if block.iter().all(|&b| b == 0) {
+            continue;
+        }
+        let header = tar::Header::from_byte_slice(&block);
+        let size = match header.entry_size() {
+            Ok(size) if checksum_ok(&block) => size,
+            _ => {
+                damaged_from.get_or_insert(offset);
+                report.skipped_bytes += 512;
+                continue;
+            }
+        };
+        if let Some(start) = damaged_from.take() {
+            report.fail(
+                "",
+                format!(
+                    "偏移 {} 处的 {} 字节无法识别，已跳过",
+                    start,
+                    offset - start
+                ),
+            );
+        }
+
+        let entry_type = header.entry_type();
+        let padding = (512 - size % 512) % 512;
+        if entry_type.is_pax_local_extensions()
+            || entry_type.is_pax_global_extensions()
+            || entry_type.is_gnu_longname()
+            || entry_type.is_gnu_longlink()
+        {
+            if size > MAX_EXTENSION_SIZE {
+                damaged_from.get_or_insert(offset);
+                continue;
+            }
+            let mut data = vec![0u8; size as usize];
+            let read = read_full(&mut reader, &mut data)
+                .and_then(|_| io::copy(&mut (&mut reader).take(padding), &mut io::sink()));
+            if let Err(e) = read {
+                report.fail("", format!("偏移 {}: {}", offset, e));
+                break;
+            }
+            let value = || {
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                String::from_utf8_lossy(&data[..end]).to_string()
+            };
+            if entry_type.is_pax_local_extensions() {
+                extensions.pax(&data);
+            } else if entry_type.is_gnu_longname() {
+                extensions.path = Some(value());
+            } else if entry_type.is_gnu_longlink() {
+                extensions.link = Some(value());
+            }
+            continue;
+        }
+
+        let extensions = std::mem::take(&mut extensions);
+        let path = extensions
+            .path
+            .unwrap_or_else(|| String::from_utf8_lossy(&header.path_bytes()).to_string());
+        let size = extensions.size.unwrap_or(size);
+        let padding = (512 - size % 512) % 512;
+        let kind = if entry_type.is_dir() {
+            EntryKind::Dir
+        } else if entry_type.is_symlink() {
+            let target = extensions.link.unwrap_or_else(|| {
+                header
+                    .link_name_bytes()
+                    .map(|l| String::from_utf8_lossy(&l).to_string())
+                    .unwrap_or_default()
+            });
+            EntryKind::Symlink(target)
+        } else if entry_type.is_file() || entry_type.is_contiguous() {
+            EntryKind::File
+        } else {
+            log::warn!("跳过不支持的条目: {}", path);
+            if let Err(e) = io::copy(&mut (&mut reader).take(size + padding), &mut io::sink()) {
+                report.fail(path, e);
+                break;
+            }
+            continue;
+        };
+
+        let read = match kind {
+            EntryKind::File => spool.fill(&mut (&mut reader).take(size)),
+            _ => Ok(0),
+        };
+        match read {
+            Ok(n) if n == size || kind != EntryKind::File => {}
+            Ok(_) => {
+                report.fail(path, "数据不完整");
+                break;
+            }
+            // 压缩流出错后无法继续定位后续条目
+            Err(e) => {
+                report.fail(path, e);
+                break;
+            }
+        }
+        if kind == EntryKind::File {
+            // 最后一个条目的填充可能缺失，内容已完整
+            let _ = io::copy(&mut (&mut reader).take(padding), &mut io::sink());
+        }
+        let mut entry = StreamEntry::new(
+            &path,
+            kind,
+            size,
+            extensions
+                .mtime
+                .or_else(|| header.mtime().ok().map(|t| t as i64)),
+            header.mode().ok(),
+        );
+        entry.attrs.uid = extensions.uid.or(header.uid().ok()).unwrap_or(0);
+        entry.attrs.gid = extensions.gid.or(header.gid().ok()).unwrap_or(0);
+        report.recovered += 1;
+        outputs.add(&entry, &mut spool.file)?;
+    }
+    if let Some(start) = damaged_from {
+        report.fail(
+            "",
+            format!(
+                "偏移 {} 处的 {} 字节无法识别，已跳过",
+                start,
+                reader.offset - start
+            ),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn failures(report: &SalvageReport) -> Vec<&str> {
+        report.damaged.iter().map(|f| f.path.as_str()).collect()
+    }
+
+    /// 把名为 `name` 的条目改为流式写入的形式：本地文件头中的 CRC 与大小清零，数据之后跟随描述符
+    fn with_descriptor(data: &[u8], name: &[u8]) -> Vec<u8> {
+        let at = data.windows(name.len()).position(|w| w == name).unwrap() - 30;
+        let mut header = data[at..at + 30].to_vec();
+        let size = le32(&header[18..]) as usize;
+        let end = at + 30 + name.len() + le16(&header[28..]) as usize + size;
+        let mut out = data[..at].to_vec();
+        header[6] |= 0x08;
+        let descriptor: Vec<u8> = header[14..26].to_vec();
+        header[14..26].fill(0);
+        out.extend(header);
+        out.extend(&data[at + 30..end]);
+        out.extend(DATA_DESCRIPTOR);
+        out.extend(descriptor);
+        out.extend(&data[end..]);
+        out
+    }
+
+    #[test]
+    fn test_salvage_archive() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let stored =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+        // 截掉中央目录与最后一个条目的结尾，其中一个条目改为使用数据描述符
+        let truncated = dir.join("truncated.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&truncated).unwrap());
+        zip.add_directory("d/", SimpleFileOptions::default())
+            .unwrap();
+        zip.start_file("d/a.txt", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&b"hello ".repeat(100)).unwrap();
+        zip.start_file("b.txt", stored).unwrap();
+        zip.write_all(b"world").unwrap();
+        zip.start_file("c.txt", stored).unwrap();
+        zip.write_all(&[7; 1000]).unwrap();
+        zip.finish().unwrap();
+        let data = fs::read(&truncated).unwrap();
+        let cut = data.windows(4).position(|w| w == CENTRAL_HEADER).unwrap();
+        fs::write(&truncated, with_descriptor(&data[..cut - 500], b"d/a.txt")).unwrap();
+
+        let out = dir.join("out");
+        let rebuilt = dir.join("rebuilt.zip");
+        let options = SalvageOptions {
+            extract_to: Some(out.clone()),
+            rebuild: Some(rebuilt.clone()),
+        };
+        let report = salvage_archive(&truncated, "", &options).unwrap();
+        assert!(report.rebuilt_directory);
+        assert_eq!(report.recovered, 3);
+        assert_eq!(failures(&report), ["c.txt"]);
+        assert_eq!(
+            fs::read(out.join("d/a.txt")).unwrap(),
+            b"hello ".repeat(100)
+        );
+        assert_eq!(fs::read(out.join("b.txt")).unwrap(), b"world");
+        assert!(!out.join("c.txt").exists());
+        let mut zip = ZipArchive::new(File::open(&rebuilt).unwrap()).unwrap();
+        assert_eq!(zip.len(), 3);
+        let mut content = String::new();
+        zip.by_name("b.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "world");
+
+        // 中央目录完好，单个条目的数据损坏
+        let corrupt = dir.join("corrupt.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&corrupt).unwrap());
+        for (name, byte) in [("x.bin", b'x'), ("y.bin", b'y')] {
+            zip.start_file(name, stored).unwrap();
+            zip.write_all(&[byte; 100]).unwrap();
+        }
+        zip.finish().unwrap();
+        let mut data = fs::read(&corrupt).unwrap();
+        let at = data.iter().position(|&b| b == b'x').unwrap() + 50;
+        data[at] = 0;
+        fs::write(&corrupt, &data).unwrap();
+        let report = salvage_archive(&corrupt, "", &SalvageOptions::default()).unwrap();
+        assert!(!report.rebuilt_directory);
+        assert_eq!(report.recovered, 1);
+        assert_eq!(failures(&report), ["x.bin"]);
+
+        // tar：第二个头部损坏，最后一个条目被截断
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, size) in [
+            ("a.txt", 100),
+            ("b.txt", 600),
+            ("c.txt", 10),
+            ("d.txt", 2000),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(size as u64);
+            header.set_mode(0o600);
+            header.set_mtime(1_600_000_000);
+            builder
+                .append_data(&mut header, name, &vec![b'z'; size][..])
+                .unwrap();
+        }
+        let mut data = builder.into_inner().unwrap();
+        data[512 + 512] ^= 0xff;
+        data.truncate(512 * 8 + 100);
+        let damaged = dir.join("damaged.tar.gz");
+        let mut gz = GzEncoder::new(File::create(&damaged).unwrap(), Default::default());
+        gz.write_all(&data).unwrap();
+        gz.finish().unwrap();
+
+        let out = dir.join("tar");
+        let rebuilt = dir.join("rebuilt.tar.gz");
+        let options = SalvageOptions {
+            extract_to: Some(out.clone()),
+            rebuild: Some(rebuilt.clone()),
+        };
+        let report = salvage_archive(&damaged, "", &options).unwrap();
+        assert_eq!(report.recovered, 2);
+        assert_eq!(failures(&report), ["", "d.txt"]);
+        assert!(report.damaged[0].error.contains("1024"), "{:?}", report);
+        assert_eq!(report.skipped_bytes, 512 * 3);
+        assert_eq!(fs::read(out.join("c.txt")).unwrap(), b"zzzzzzzzzz");
+        let mut names = Vec::new();
+        let mut archive =
+            tar::Archive::new(flate2::read::GzDecoder::new(File::open(&rebuilt).unwrap()));
+        for entry in archive.entries().unwrap() {
+            let entry = entry.unwrap();
+            names.push(entry.path().unwrap().to_string_lossy().to_string());
+            assert_eq!(entry.header().mode().unwrap(), 0o600);
+        }
+        assert_eq!(names, ["a.txt", "c.txt"]);
+
+        let result = salvage_archive(&dir.join("a.rar"), "", &SalvageOptions::default());
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use filetime::FileTime;
+use std::cell::RefCell;
+use std::path::{Component, Path, PathBuf};
+
+use super::options::ExtractOptions;
+use super::{nested, recursive, ArchiveError, ResultR};
+
+/// 条目元数据，缺失的字段保持默认行为
+#[derive(Debug, Clone, Default)]
+pub struct EntryMeta {
+    /// 修改时间（unix 秒）
+    pub mtime: Option<i64>,
+    /// 访问时间（unix 秒）
+    pub atime: Option<i64>,
+    /// unix 权限位
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+/// 在写入条目后恢复时间、权限与所有者
+///
+/// 文件在写入后立即处理；目录推迟到 `finish` 时由深到浅处理，
+/// 避免写入子项时覆盖目录时间，或因只读权限导致后续写入失败。
+/// 开启递归解压时，写出的压缩包也在 `finish` 时解压。
+pub struct Restorer<'a> {
+    options: &'a ExtractOptions,
+    is_root: bool,
+    started: FileTime,
+    dirs: Vec<(PathBuf, EntryMeta)>,
+    /// 写出的压缩包，等待递归解压
+    archives: RefCell<Vec<PathBuf>>,
+}
+
+impl<'a> Restorer<'a> {
+    pub fn new(options: &'a ExtractOptions) -> Self {
+        Restorer {
+            options,
+            is_root: is_root(),
+            started: FileTime::now(),
+            dirs: Vec::new(),
+            archives: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 处理已写入的文件，符号链接保持原样
+    pub fn file(&self, path: &Path, meta: &EntryMeta) -> ResultR<()> {
+        if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Ok(());
+        }
+        if self.options.recursive.is_some() && nested::is_nested_archive(&path.to_string_lossy()) {
+            self.archives.borrow_mut().push(path.to_path_buf());
+        }
+        self.apply(path, meta, false)
+            .map_err(|e| ArchiveError::MsgError(format!("恢复文件属性失败: {}", e)))
+    }
+
+    /// 处理已创建的符号链接：只恢复所有者与时间，不跟随链接
+    pub fn link(&self, path: &Path, meta: &EntryMeta) -> ResultR<()> {
+        #[cfg(unix)]
+        if self.options.restore_ownership
+            && self.is_root
+            && (meta.uid.is_some() || meta.gid.is_some())
+        {
+            std::os::unix::fs::lchown(path, meta.uid, meta.gid)
+                .map_err(|e| ArchiveError::MsgError(format!("恢复链接属性失败: {}", e)))?;
+        }
+        let mtime = meta
+            .mtime
+            .filter(|_| self.options.restore_mtime)
+            .map_or(self.started, |t| FileTime::from_unix_time(t, 0));
+        filetime::set_symlink_file_times(path, self.started, mtime)
+            .map_err(|e| ArchiveError::MsgError(format!("恢复链接属性失败: {}", e)))
+    }
+
+    /// 记录目录，稍后处理
+    pub fn dir(&mut self, path: PathBuf, meta: EntryMeta) {
+        self.dirs.push((path, meta));
+    }
+
+    /// 递归解压写出的压缩包，再处理所有目录，子目录先于父目录
+    pub fn finish(mut self) -> ResultR<()> {
+        recursive::extract_nested(self.archives.take(), self.options)?;
+        self.dirs
+            .sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+        for (path, meta) in &self.dirs {
+            self.apply(path, meta, true)
+                .map_err(|e| ArchiveError::MsgError(format!("恢复目录属性失败: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    fn apply(&self, path: &Path, meta: &EntryMeta, is_dir: bool) -> std::io::Result<()> {
+        // chown 会清除 setuid/setgid，因此先于 chmod
+        #[cfg(unix)]
+        if self.options.restore_ownership
+            && self.is_root
+            && (meta.uid.is_some() || meta.gid.is_some())
+        {
+            std::os::unix::fs::chown(path, meta.uid, meta.gid)?;
+        }
+
+        let mode = self.mode(meta, is_dir);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        if !is_dir {
+            let mut perms = std::fs::metadata(path)?.permissions();
+            perms.set_readonly(mode & 0o200 == 0);
+            std::fs::set_permissions(path, perms)?;
+        }
+
+        let mtime = meta
+            .mtime
+            .filter(|_| self.options.restore_mtime)
+            .map_or(self.started, |t| FileTime::from_unix_time(t, 0));
+        let atime = meta
+            .atime
+            .filter(|_| self.options.restore_atime)
+            .map_or(self.started, |t| FileTime::from_unix_time(t, 0));
+        filetime::set_file_times(path, atime, mtime)
+    }
+
+    fn mode(&self, meta: &EntryMeta, is_dir: bool) -> u32 {
+        let default = if is_dir { 0o777 } else { 0o666 };
+        let mode = match meta.mode {
+            Some(mode) if self.options.restore_permissions => mode & 0o7777,
+            _ => default,
+        };
+        let special = if self.options.keep_special_bits {
+            0o7777
+        } else {
+            0o1777
+        };
+        mode & special & !self.options.umask
+    }
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    // SAFETY: geteuid 没有副作用，总是成功
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+/// 把压缩包内路径拼接到目标目录下
+///
+/// 去掉开头的 `/` 与 `.`，包含 `..` 或盘符的路径返回 `None`，防止写到目标目录之外。
+pub fn safe_join(base: &Path, name: &str) -> Option<PathBuf> {
+    let mut path = base.to_path_buf();
+    for part in name.split(['/', '\\']) {
+        match Path::new(part).components().next() {
+            None | Some(Component::CurDir) => continue,
+            Some(Component::Normal(p)) if Path::new(p) == Path::new(part) => path.push(p),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// DOS 时间（高 16 位日期，低 16 位时间，本地时区）转 unix 秒
+pub fn dos_time_to_unix(dos: u32) -> Option<i64> {
+    let (date, time) = (dos >> 16, dos & 0xffff);
+    NaiveDate::from_ymd_opt(
+        ((date >> 9) & 0x7f) as i32 + 1980,
+        (date >> 5) & 0x0f,
+        date & 0x1f,
+    )?
+    .and_hms_opt(time >> 11, (time >> 5) & 0x3f, (time & 0x1f) * 2)
+    .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+    .map(|dt| dt.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_join() {
+        let base = Path::new("/tmp/out");
+        assert_eq!(
+            safe_join(base, "a/b.txt"),
+            Some(PathBuf::from("/tmp/out/a/b.txt"))
+        );
+        assert_eq!(
+            safe_join(base, "/etc/./passwd"),
+            Some(PathBuf::from("/tmp/out/etc/passwd"))
+        );
+        assert_eq!(safe_join(base, "dir/"), Some(PathBuf::from("/tmp/out/dir")));
+        assert_eq!(safe_join(base, "../escape"), None);
+        assert_eq!(safe_join(base, "a\\..\\..\\escape"), None);
+    }
+
+    #[test]
+    fn test_mode() {
+        let options = ExtractOptions::default();
+        let restorer = Restorer::new(&options);
+        let meta = EntryMeta {
+            mode: Some(0o104777),
+            ..Default::default()
+        };
+        // 去除 setuid，应用 umask 022
+        assert_eq!(restorer.mode(&meta, false), 0o755);
+        assert_eq!(restorer.mode(&EntryMeta::default(), true), 0o755);
+        assert_eq!(restorer.mode(&EntryMeta::default(), false), 0o644);
+
+        let options = ExtractOptions {
+            keep_special_bits: true,
+            umask: 0,
+            ..Default::default()
+        };
+        assert_eq!(Restorer::new(&options).mode(&meta, false), 0o4777);
+    }
+
+    #[test]
+    fn test_dos_time_to_unix() {
+        // 2024-03-05 12:34:56 本地时间
+        let dos = ((2024 - 1980) << 25) | (3 << 21) | (5 << 16) | (12 << 11) | (34 << 5) | 28;
+        let expected = Local
+            .with_ymd_and_hms(2024, 3, 5, 12, 34, 56)
+            .unwrap()
+            .timestamp();
+        assert_eq!(dos_time_to_unix(dos), Some(expected));
+        assert_eq!(dos_time_to_unix(0), None);
+    }
+}
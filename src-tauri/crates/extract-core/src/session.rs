@@ -0,0 +1,313 @@
+//! 压缩包会话：打开时读取一次全部条目并建立索引，之后的浏览不再读取压缩包
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, TryLockError};
+
+use crate::index::{ArchiveIndex, DirPage, DirSort};
+use crate::nested::NestedArchive;
+use crate::preview::{self, ByteRange, EntryPreview, PREVIEW_LIMIT};
+use crate::tarindex::TarIndex;
+use crate::{
+    create_handler, ArchiveError, ArchiveFormat, ArchiveHandler, ArchiveInfo, EditOp, ResultR,
+    TarHandler,
+};
+
+/// 一个已打开的压缩包
+pub struct ArchiveSession {
+    path: PathBuf,
+    format: ArchiveFormat,
+    /// 已验证可用的密码
+    password: RwLock<String>,
+    index: ArchiveIndex,
+    /// tar、tar.gz、tar.xz 的随机访问索引，由会话创建的处理器共用
+    tar_index: Option<Arc<TarIndex>>,
+    info: OnceLock<ArchiveInfo>,
+    /// 嵌套压缩包的临时文件，随会话删除
+    nested: Option<NestedArchive>,
+    /// 复用的处理器，保留解析过的目录与头部
+    handler: Mutex<CachedHandler>,
+}
+
+/// 会话缓存的处理器及创建它时使用的密码
+struct CachedHandler {
+    password: String,
+    handler: Box<dyn ArchiveHandler>,
+}
+
+impl std::fmt::Debug for ArchiveSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveSession")
+            .field("path", &self.path)
+            .field("format", &self.format)
+            .field("index", &self.index)
+            .field("tar_index", &self.tar_index)
+            .field("info", &self.info)
+            .field("nested", &self.nested)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArchiveSession {
+    /// 打开压缩包并建立条目索引；传入密码时随即验证，密码错误时返回 `密码错误`
+    pub fn open(path: &Path, password: String) -> ResultR<Self> {
+        Self::open_cached(path, password, None)
+    }
+
+    /// 同 [`ArchiveSession::open`]，tar 类压缩包的随机访问索引缓存在 `cache_dir` 中
+    pub fn open_cached(path: &Path, password: String, cache_dir: Option<&Path>) -> ResultR<Self> {
+        let format = ArchiveFormat::from_path(path);
+        if format == ArchiveFormat::Unknown {
+            return Err(ArchiveError::MsgError(format!(
+                "不支持的格式: {}",
+                path.display()
+            )));
+        }
+        // tar 类在列出条目的同时建立随机访问索引，只读取一遍
+        let tar_index = if TarIndex::supports(format) {
+            Some(Arc::new(TarIndex::load_or_build(path, format, cache_dir)?))
+        } else {
+            None
+        };
+        let mut handler = new_handler(path, format, &tar_index, password.clone());
+        let entries = match &tar_index {
+            Some(tar_index) => tar_index.entries().to_vec(),
+            None => handler.entries()?.collect::<ResultR<Vec<_>>>()?,
+        };
+        // 列出条目通常不需要密码，在这里验证一次，之后的读取不必各自发现密码错误
+        if !password.is_empty() {
+            handler.check_password()?;
+        }
+        Ok(ArchiveSession {
+            path: path.to_path_buf(),
+            format,
+            password: RwLock::new(password.clone()),
+            index: ArchiveIndex::new(entries),
+            tar_index,
+            info: OnceLock::new(),
+            nested: None,
+            handler: Mutex::new(CachedHandler { password, handler }),
+        })
+    }
+
+    /// 打开虚拟路径（如 `outer.zip!/inner.tar.gz`）指向的压缩包，最多进入 `max_depth` 层
+    ///
+    /// 内层压缩包解压到临时文件，不缓存随机访问索引。
+    pub fn open_nested(
+        path: &str,
+        password: String,
+        cache_dir: Option<&Path>,
+        max_depth: usize,
+    ) -> ResultR<Self> {
+        let nested = NestedArchive::open(path, &password, max_depth)?;
+        if nested.depth() == 0 {
+            return Self::open_cached(nested.path(), password, cache_dir);
+        }
+        let mut session = Self::open_cached(nested.path(), password, None)?;
+        session.nested = Some(nested);
+        Ok(session)
+    }
+
+    /// 压缩包在磁盘上的位置，嵌套时为临时文件
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_nested(&self) -> bool {
+        self.nested.is_some()
+    }
+
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+
+    pub fn index(&self) -> &ArchiveIndex {
+        &self.index
+    }
+
+    pub fn tar_index(&self) -> Option<&TarIndex> {
+        self.tar_index.as_deref()
+    }
+
+    /// 分页列出一个目录，见 [`ArchiveIndex::list_dir`]
+    pub fn list_dir(
+        &self,
+        dir_path: &str,
+        offset: usize,
+        limit: usize,
+        sort: DirSort,
+    ) -> ResultR<DirPage> {
+        self.index.list_dir(dir_path, offset, limit, sort)
+    }
+
+    /// 压缩包信息，首次读取后缓存
+    pub fn info(&self) -> ResultR<ArchiveInfo> {
+        if let Some(info) = self.info.get() {
+            return Ok(info.clone());
+        }
+        let info = self.cached(|handler| handler.archive_info())?;
+        Ok(self.info.get_or_init(|| info).clone())
+    }
+
+    /// 读取条目的内容或其中一段，返回条目的完整大小
+    ///
+    /// tar 类压缩包借助随机访问索引从最近的检查点开始读取。
+    pub fn read_entry(&self, entry: &str, range: ByteRange, out: &mut dyn Write) -> ResultR<u64> {
+        self.cached(|handler| handler.read_entry(entry, range, out))
+    }
+
+    /// 预览条目，见 [`preview::preview`]
+    pub fn preview(&self, entry: &str, range: ByteRange) -> ResultR<EntryPreview> {
+        preview::preview(entry, range, PREVIEW_LIMIT, |range, out| {
+            self.read_entry(entry, range, out)
+        })
+    }
+
+    /// 以会话的密码用 `source` 的内容替换条目，见 [`ArchiveSession::edit`]
+    ///
+    /// 会话的索引不会更新，替换后应以 [`ArchiveSession::reopen`] 重新打开。
+    pub fn replace_entry(&self, entry: &str, source: &Path) -> ResultR<()> {
+        self.edit(&[EditOp::Replace {
+            entry: entry.to_string(),
+            source: source.to_path_buf(),
+        }])
+    }
+
+    /// 以会话的密码修改压缩包，见 [`crate::edit_archive`]
+    ///
+    /// 与 [`ArchiveSession::replace_entry`] 相同，修改后应重新打开。
+    pub fn edit(&self, ops: &[EditOp]) -> ResultR<()> {
+        if self.is_nested() {
+            return Err(ArchiveError::MsgError(String::from(
+                "暂不支持修改嵌套压缩包中的条目",
+            )));
+        }
+        crate::edit_archive(&self.path, self.format, &self.password(), ops)
+    }
+
+    /// 以相同的密码重新打开压缩包
+    pub fn reopen(&self, cache_dir: Option<&Path>) -> ResultR<Self> {
+        if self.is_nested() {
+            return Err(ArchiveError::MsgError(String::from(
+                "嵌套压缩包不能重新打开",
+            )));
+        }
+        Self::open_cached(&self.path, self.password(), cache_dir)
+    }
+
+    /// 以会话的密码新建处理器，与会话缓存的处理器互不影响
+    ///
+    /// tar 类处理器带有会话的随机访问索引，读取单个条目、只解压部分条目时不必从头解压。
+    pub fn handler(&self) -> Box<dyn ArchiveHandler> {
+        new_handler(&self.path, self.format, &self.tar_index, self.password())
+    }
+
+    /// 用会话缓存的处理器执行操作，会话的密码变化后先换成新密码的处理器
+    ///
+    /// 缓存的处理器正被其他操作（如解压）占用时，改用新建的处理器，不等待其完成。
+    fn cached<R>(&self, op: impl FnOnce(&mut dyn ArchiveHandler) -> ResultR<R>) -> ResultR<R> {
+        let mut cached = match self.handler.try_lock() {
+            Ok(cached) => cached,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return op(self.handler().as_mut()),
+        };
+        let password = self.password();
+        if cached.password != password {
+            cached.handler =
+                new_handler(&self.path, self.format, &self.tar_index, password.clone());
+            cached.password = password;
+        }
+        op(cached.handler.as_mut())
+    }
+
+    /// 用会话缓存的处理器执行一次操作
+    ///
+    /// 传入 `password` 时改用该密码，操作成功后替换会话缓存的密码，
+    /// 用于列表不需要密码、解压时才需要的压缩包。
+    pub fn with_handler<R>(
+        &self,
+        password: Option<String>,
+        op: impl FnOnce(&mut dyn ArchiveHandler) -> ResultR<R>,
+    ) -> ResultR<R> {
+        let Some(password) = password.filter(|p| *p != self.password()) else {
+            return self.cached(op);
+        };
+        let mut handler = new_handler(&self.path, self.format, &self.tar_index, password.clone());
+        let result = op(handler.as_mut())?;
+        *self.password.write().unwrap_or_else(|e| e.into_inner()) = password.clone();
+        // 已验证的处理器直接留作缓存；缓存正被占用时由下次使用时重建
+        if let Ok(mut cached) = self.handler.try_lock() {
+            *cached = CachedHandler { password, handler };
+        }
+        Ok(result)
+    }
+
+    fn password(&self) -> String {
+        self.password
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// 创建处理器，tar 类带上会话的随机访问索引
+fn new_handler(
+    path: &Path,
+    format: ArchiveFormat,
+    tar_index: &Option<Arc<TarIndex>>,
+    password: String,
+) -> Box<dyn ArchiveHandler> {
+    match tar_index {
+        Some(index) => Box::new(TarHandler::with_index(path, format, index.clone())),
+        None => create_handler(path, format, password),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{CreateOptions, Encryption};
+    use crate::testutil::temp_dir;
+    use crate::writer::create_archive;
+    use std::fs;
+
+    #[test]
+    fn test_password_checked_at_open() {
+        let dir = temp_dir();
+        let sources = [dir.path().join("a.txt")];
+        fs::write(&sources[0], "hello ".repeat(1000)).unwrap();
+        let options = CreateOptions {
+            encryption: Some(Encryption {
+                password: String::from("secret").into(),
+                encrypt_header: false,
+            }),
+            ..Default::default()
+        };
+        for ext in ["zip", "7z"] {
+            let path = dir.path().join(format!("out.{}", ext));
+            let format = ArchiveFormat::from_path(&path);
+            create_archive(&sources, &path, format, &options, |_| {}).unwrap();
+
+            // 7z 列出条目不需要密码，错误的密码也在打开时即被发现
+            let err = ArchiveSession::open(&path, String::from("wrong")).unwrap_err();
+            assert!(
+                matches!(err, ArchiveError::MsgError(ref e) if e == "密码错误"),
+                "{} {:?}",
+                ext,
+                err
+            );
+
+            // 缓存的处理器在多次读取之间复用
+            let session = ArchiveSession::open(&path, String::from("secret")).unwrap();
+            for _ in 0..2 {
+                let mut out = Vec::new();
+                let size = session
+                    .read_entry("a.txt", ByteRange::new(0, Some(5)), &mut out)
+                    .unwrap();
+                assert_eq!((size, out.as_slice()), (6000, &b"hello"[..]));
+            }
+            let info = session.info().unwrap();
+            assert_eq!((info.entry_count, info.is_encrypted), (1, true));
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! 条目内容的临时文件
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tempfile::TempDir;
+
+/// 在 `parent` 中创建名称随机、只有当前用户可以访问（unix 上为 0700）的目录，Drop 时连同内容删除
+///
+/// 名称不可预测且以独占方式创建，共享的临时目录中其他用户无法预先放置同名的目录或链接，
+/// 也无法读取其中的内容；多个进程各自使用自己的目录，互不影响。
+pub fn private_dir(parent: &Path, prefix: &str) -> io::Result<TempDir> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(prefix);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o700));
+    }
+    builder.tempdir_in(parent)
+}
+
+/// 临时文件的序号
+static SPOOL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 条目内容的临时文件，可反复清空重用，Drop 时删除
+///
+/// 以 `create_new` 创建，临时目录中已有同名的文件或链接时失败，不会跟随他人预先放置的链接。
+pub(crate) struct Spool {
+    pub file: File,
+    path: PathBuf,
+}
+
+impl Spool {
+    /// 在临时目录中创建 `extract-<name>-<pid>-<序号>`
+    pub fn new(name: &str) -> io::Result<Self> {
+        let id = SPOOL_ID.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("extract-{}-{}-{}", name, std::process::id(), id));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Spool { file, path })
+    }
+
+    /// 清空内容，返回用于写入的文件，写完后调用 [`Spool::rewind`]
+    pub fn clear(&mut self) -> io::Result<&mut File> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        Ok(&mut self.file)
+    }
+
+    /// 回到开头，返回已写入的字节数
+    pub fn rewind(&mut self) -> io::Result<u64> {
+        let n = self.file.stream_position()?;
+        self.file.rewind()?;
+        Ok(n)
+    }
+
+    /// 读出 `data` 的全部内容，返回字节数；之后文件位于开头
+    pub fn fill(&mut self, data: &mut dyn Read) -> io::Result<u64> {
+        io::copy(data, self.clear()?)?;
+        self.rewind()
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// 硬链接目标的内容副本
+///
+/// tar 与 RAR 的硬链接没有数据，目标总在链接之前出现。按顺序读取时，会被链接指向的条目
+/// 在出现时保存一份副本，之后的硬链接直接读取副本，不必为每个链接从头解压一遍。
+pub(crate) struct LinkTargets {
+    /// 被硬链接指向的条目
+    wanted: HashSet<String>,
+    /// 副本所在的目录，第一次保存时创建
+    dir: Option<TempDir>,
+    saved: HashMap<String, PathBuf>,
+}
+
+impl LinkTargets {
+    pub fn new(wanted: HashSet<String>) -> Self {
+        LinkTargets {
+            wanted,
+            dir: None,
+            saved: HashMap::new(),
+        }
+    }
+
+    /// 条目是否被硬链接指向，需要保存副本
+    pub fn wants(&self, name: &str) -> bool {
+        self.wanted.contains(name)
+    }
+
+    /// 把 `data` 的全部内容保存为 `name` 的副本，替换已有的副本；返回位于开头的副本与大小
+    pub fn save(&mut self, name: &str, data: &mut dyn Read) -> io::Result<(File, u64)> {
+        let path = match self.saved.get(name) {
+            Some(path) => path.clone(),
+            None => {
+                let dir = match &self.dir {
+                    Some(dir) => dir,
+                    None => self
+                        .dir
+                        .insert(private_dir(&std::env::temp_dir(), "extract-links-")?),
+                };
+                let path = dir.path().join(self.saved.len().to_string());
+                self.saved.insert(name.to_string(), path.clone());
+                path
+            }
+        };
+        // 目录只有当前用户可以访问，其中的文件不会被他人替换
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let size = io::copy(data, &mut file)?;
+        file.rewind()?;
+        Ok((file, size))
+    }
+
+    /// 打开 `name` 的副本，返回文件与大小；没有保存过时返回 `None`
+    pub fn open(&self, name: &str) -> Option<io::Result<(File, u64)>> {
+        let path = self.saved.get(name)?;
+        Some(File::open(path).and_then(|file| {
+            let size = file.metadata()?.len();
+            Ok((file, size))
+        }))
+    }
+}
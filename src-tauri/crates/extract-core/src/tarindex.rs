@@ -0,0 +1,745 @@
+//! tar 包的随机访问索引
+//!
+//! 列出条目的同时记录每个条目在解压后 tar 流中的位置，以及解压器的检查点
+//! （gzip 的 32 KiB 窗口、xz 的块边界）。之后读取单个条目时从最近的检查点开始解压，
+//! 不必从头读取整个流。索引可以缓存到磁盘，以文件大小、修改时间与内容摘要为键。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use sha2::{Digest, Sha256};
+use xz2::read::XzDecoder;
+
+use crate::gzindex::{GzCheckpoint, GzReader};
+use crate::preview::ByteRange;
+use crate::xzindex::{XzBlock, XzLayout};
+use crate::{nested, tar_entry, ArchiveEntry, ArchiveError, ArchiveFormat, ResultR};
+
+/// 缓存文件格式标识，格式变化时递增
+const CACHE_MAGIC: &[u8; 8] = b"EXTTIX01";
+/// 计算内容摘要时读取文件首尾的字节数
+const SAMPLE: u64 = 64 * 1024;
+
+/// 条目在解压后 tar 流中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntrySpan {
+    /// 条目头的位置
+    pub header: u64,
+    /// 数据的位置
+    pub data: u64,
+    pub size: u64,
+}
+
+/// 压缩包文件的身份：大小、修改时间与首尾内容的摘要
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CacheKey {
+    size: u64,
+    mtime: u128,
+    digest: [u8; 32],
+}
+
+impl CacheKey {
+    fn of(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let meta = file.metadata()?;
+        let size = meta.len();
+        let mtime = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(size.to_le_bytes());
+        let mut buf = vec![0u8; SAMPLE.min(size) as usize];
+        file.read_exact(&mut buf)?;
+        hasher.update(&buf);
+        if size > SAMPLE {
+            let tail = SAMPLE.min(size - SAMPLE);
+            file.seek(SeekFrom::Start(size - tail))?;
+            buf.resize(tail as usize, 0);
+            file.read_exact(&mut buf)?;
+            hasher.update(&buf);
+        }
+        Ok(CacheKey {
+            size,
+            mtime,
+            digest: hasher.finalize().into(),
+        })
+    }
+
+    /// 缓存文件名
+    fn file_name(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.size.to_le_bytes());
+        hasher.update(self.mtime.to_le_bytes());
+        hasher.update(self.digest);
+        let hash = hasher.finalize();
+        let hex: String = hash[..16].iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}.tix", hex)
+    }
+}
+
+/// tar、tar.gz、tar.xz 的随机访问索引
+#[derive(Debug, Clone)]
+pub struct TarIndex {
+    format: ArchiveFormat,
+    entries: Vec<ArchiveEntry>,
+    spans: HashMap<String, EntrySpan>,
+    gzip: Vec<GzCheckpoint>,
+    xz: Option<XzLayout>,
+}
+
+impl TarIndex {
+    /// 是否支持该格式
+    pub fn supports(format: ArchiveFormat) -> bool {
+        matches!(
+            format,
+            ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarXz
+        )
+    }
+
+    /// 完整读取一遍压缩包，建立索引
+    pub fn build(path: &Path, format: ArchiveFormat) -> ResultR<Self> {
+        let mut file = File::open(path).map_err(io_error)?;
+        let mut index = TarIndex {
+            format,
+            entries: Vec::new(),
+            spans: HashMap::new(),
+            gzip: Vec::new(),
+            xz: None,
+        };
+        match format {
+            ArchiveFormat::Tar => {
+                index.read_entries(file)?;
+            }
+            ArchiveFormat::TarGz => {
+                let reader = index.read_entries(GzReader::indexing(file).map_err(io_error)?)?;
+                index.gzip = reader.into_checkpoints();
+            }
+            ArchiveFormat::TarXz => {
+                index.xz = XzLayout::read(&mut file)
+                    .map_err(io_error)?
+                    .filter(|layout| layout.blocks.len() > 1);
+                let file = File::open(path).map_err(io_error)?;
+                index.read_entries(XzDecoder::new(BufReader::new(file)))?;
+            }
+            _ => return Err(ArchiveError::MsgError("不支持的格式".to_string())),
+        }
+        Ok(index)
+    }
+
+    /// 优先读取 `cache_dir` 中的缓存，不存在或已过期时重新建立并写入缓存
+    pub fn load_or_build(
+        path: &Path,
+        format: ArchiveFormat,
+        cache_dir: Option<&Path>,
+    ) -> ResultR<Self> {
+        let Some(cache_dir) = cache_dir else {
+            return Self::build(path, format);
+        };
+        let key = CacheKey::of(path).map_err(io_error)?;
+        let cache = cache_dir.join(key.file_name());
+        match Self::load(&cache, &key) {
+            Ok(Some(index)) if index.format == format => return Ok(index),
+            Ok(_) => {}
+            Err(e) => log::debug!("读取索引缓存失败 {}: {}", cache.display(), e),
+        }
+
+        let index = Self::build(path, format)?;
+        if let Err(e) = index.save(&cache, &key) {
+            log::debug!("写入索引缓存失败 {}: {}", cache.display(), e);
+        }
+        Ok(index)
+    }
+
+    /// 按压缩包中顺序的全部条目
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// 条目的位置，`path` 与 [`ArchiveEntry::path`] 一致
+    pub fn span(&self, path: &str) -> Option<EntrySpan> {
+        self.spans.get(path).copied()
+    }
+
+    /// 可以开始解压的位置数（gzip 检查点或 xz 块）
+    pub fn checkpoint_count(&self) -> usize {
+        match &self.xz {
+            Some(layout) => layout.blocks.len(),
+            None => self.gzip.len(),
+        }
+    }
+
+    /// 打开解压后 tar 流中 `offset` 处开始的数据，从最近的检查点解压并跳过之前的部分
+    pub fn open_at(&self, path: &Path, offset: u64) -> ResultR<Box<dyn Read + Send>> {
+        let file = File::open(path).map_err(io_error)?;
+        let (mut reader, start): (Box<dyn Read + Send>, u64) = match self.format {
+            ArchiveFormat::Tar => {
+                let mut file = file;
+                file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+                (Box::new(file), offset)
+            }
+            ArchiveFormat::TarGz => {
+                let i = self.gzip.partition_point(|c| c.output <= offset);
+                match self.gzip[..i].last() {
+                    Some(checkpoint) => (
+                        Box::new(GzReader::resume(file, checkpoint).map_err(io_error)?),
+                        checkpoint.output,
+                    ),
+                    None => (Box::new(GzReader::new(file).map_err(io_error)?), 0),
+                }
+            }
+            ArchiveFormat::TarXz => {
+                match self
+                    .xz
+                    .as_ref()
+                    .and_then(|l| Some((l, l.block_for(offset)?)))
+                {
+                    Some((layout, block)) => (
+                        Box::new(XzDecoder::new(
+                            layout.stream_from(file, block).map_err(io_error)?,
+                        )),
+                        block.output,
+                    ),
+                    None => (Box::new(XzDecoder::new(BufReader::new(file))), 0),
+                }
+            }
+            _ => return Err(ArchiveError::MsgError("不支持的格式".to_string())),
+        };
+        io::copy(&mut (&mut reader).take(offset - start), &mut io::sink()).map_err(io_error)?;
+        Ok(reader)
+    }
+
+    /// 读取单个条目的数据
+    pub fn read_entry(&self, path: &Path, entry: &str) -> ResultR<Box<dyn Read + Send>> {
+        let span = self
+            .span(entry)
+            .ok_or_else(|| ArchiveError::MsgError(format!("条目不存在: {}", entry)))?;
+        Ok(Box::new(self.open_at(path, span.data)?.take(span.size)))
+    }
+
+    /// 把条目的内容或其中一段写入 `out`，返回条目的完整大小
+    pub fn copy_entry(
+        &self,
+        path: &Path,
+        entry: &str,
+        range: ByteRange,
+        out: &mut dyn Write,
+    ) -> ResultR<u64> {
+        let span = self
+            .span(entry)
+            .ok_or_else(|| ArchiveError::MsgError(format!("条目不存在: {}", entry)))?;
+        let offset = range.offset.min(span.size);
+        let length = range.length.unwrap_or(u64::MAX).min(span.size - offset);
+        let mut reader = self.open_at(path, span.data + offset)?.take(length);
+        io::copy(&mut reader, out).map_err(io_error)?;
+        Ok(span.size)
+    }
+
+    /// `select` 选中的条目在解压后 tar 流中所占的范围，从这里开始读取即可得到全部选中的条目
+    ///
+    /// 范围的起点是之前最近一个条目数据的结尾，因此包含条目前面的 PAX、GNU 长文件名等扩展头。
+    /// 没有选中任何条目、或选中了没有记录位置的条目（稀疏文件）时返回 `None`。
+    pub fn selection(&self, select: impl Fn(&str) -> bool) -> Option<Range<u64>> {
+        let mut ends: Vec<u64> = self
+            .spans
+            .values()
+            .map(|span| span.data + pad_block(span.size))
+            .collect();
+        ends.sort_unstable();
+
+        let mut selection: Option<Range<u64>> = None;
+        for entry in self.entries.iter().filter(|entry| select(&entry.path)) {
+            let span = self.span(&entry.path)?;
+            let i = ends.partition_point(|&end| end <= span.header);
+            let start = i.checked_sub(1).map_or(0, |i| ends[i]);
+            let end = span.data + pad_block(span.size);
+            selection = Some(match selection {
+                Some(range) => range.start.min(start)..range.end.max(end),
+                None => start..end,
+            });
+        }
+        selection
+    }
+
+    /// 读取所有条目并记录位置，返回底层读取器以便取出检查点
+    fn read_entries<R: Read>(&mut self, reader: R) -> ResultR<R> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let Some(item) = tar_entry(&entry)? else {
+                continue;
+            };
+            // 稀疏文件的数据不连续，无法按位置直接读取
+            if !entry.header().entry_type().is_gnu_sparse() {
+                self.spans.insert(
+                    item.path.clone(),
+                    EntrySpan {
+                        header: entry.raw_header_position(),
+                        data: entry.raw_file_position(),
+                        size: entry.size(),
+                    },
+                );
+            }
+            self.entries.push(item);
+        }
+        Ok(archive.into_inner())
+    }
+
+    fn load(cache: &Path, key: &CacheKey) -> io::Result<Option<Self>> {
+        if !cache.exists() {
+            return Ok(None);
+        }
+        let mut r = BufReader::new(File::open(cache)?);
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != CACHE_MAGIC {
+            return Ok(None);
+        }
+        let stored = CacheKey {
+            size: read_u64(&mut r)?,
+            mtime: read_u64(&mut r)? as u128 | (read_u64(&mut r)? as u128) << 64,
+            digest: {
+                let mut digest = [0u8; 32];
+                r.read_exact(&mut digest)?;
+                digest
+            },
+        };
+        if stored != *key {
+            return Ok(None);
+        }
+
+        let format = match read_u8(&mut r)? {
+            0 => ArchiveFormat::Tar,
+            1 => ArchiveFormat::TarGz,
+            2 => ArchiveFormat::TarXz,
+            _ => return Ok(None),
+        };
+        let mut entries = Vec::new();
+        let mut spans = HashMap::new();
+        for _ in 0..read_u64(&mut r)? {
+            let mut entry = ArchiveEntry {
+                name: read_str(&mut r)?,
+                path: read_str(&mut r)?,
+                parent_path: read_opt_str(&mut r)?,
+                size: read_u64(&mut r)?,
+                is_dir: read_u8(&mut r)? != 0,
+                modified: read_opt_str(&mut r)?,
+                is_archive: false,
+            };
+            // 由名称推出，不写入缓存
+            entry.is_archive = !entry.is_dir && nested::is_nested_archive(&entry.name);
+            if read_u8(&mut r)? != 0 {
+                let span = EntrySpan {
+                    header: read_u64(&mut r)?,
+                    data: read_u64(&mut r)?,
+                    size: read_u64(&mut r)?,
+                };
+                spans.insert(entry.path.clone(), span);
+            }
+            entries.push(entry);
+        }
+
+        let mut gzip = Vec::new();
+        for _ in 0..read_u64(&mut r)? {
+            let input = read_u64(&mut r)?;
+            let bits = read_u8(&mut r)?;
+            let output = read_u64(&mut r)?;
+            let mut window = Vec::new();
+            DeflateDecoder::new(read_bytes(&mut r)?.as_slice()).read_to_end(&mut window)?;
+            gzip.push(GzCheckpoint {
+                input,
+                bits,
+                output,
+                window,
+            });
+        }
+
+        let xz = match read_u8(&mut r)? {
+            0 => None,
+            _ => {
+                let mut header = [0u8; 12];
+                r.read_exact(&mut header)?;
+                let mut blocks = Vec::new();
+                for _ in 0..read_u64(&mut r)? {
+                    blocks.push(XzBlock {
+                        offset: read_u64(&mut r)?,
+                        unpadded: read_u64(&mut r)?,
+                        output: read_u64(&mut r)?,
+                        uncompressed: read_u64(&mut r)?,
+                    });
+                }
+                Some(XzLayout { header, blocks })
+            }
+        };
+
+        Ok(Some(TarIndex {
+            format,
+            entries,
+            spans,
+            gzip,
+            xz,
+        }))
+    }
+
+    /// 先写到临时文件再改名，避免并发读取到不完整的缓存
+    fn save(&self, cache: &Path, key: &CacheKey) -> io::Result<()> {
+        let dir = cache.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        let tmp: PathBuf = cache.with_extension(format!("tmp{}", std::process::id()));
+        let result = self
+            .write_cache(&tmp, key)
+            .and_then(|_| std::fs::rename(&tmp, cache));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn write_cache(&self, tmp: &Path, key: &CacheKey) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(tmp)?);
+        w.write_all(CACHE_MAGIC)?;
+        write_u64(&mut w, key.size)?;
+        write_u64(&mut w, key.mtime as u64)?;
+        write_u64(&mut w, (key.mtime >> 64) as u64)?;
+        w.write_all(&key.digest)?;
+
+        w.write_all(&[match self.format {
+            ArchiveFormat::TarGz => 1,
+            ArchiveFormat::TarXz => 2,
+            _ => 0,
+        }])?;
+        write_u64(&mut w, self.entries.len() as u64)?;
+        for entry in &self.entries {
+            write_str(&mut w, &entry.name)?;
+            write_str(&mut w, &entry.path)?;
+            write_opt_str(&mut w, entry.parent_path.as_deref())?;
+            write_u64(&mut w, entry.size)?;
+            w.write_all(&[entry.is_dir as u8])?;
+            write_opt_str(&mut w, entry.modified.as_deref())?;
+            match self.spans.get(&entry.path) {
+                Some(span) => {
+                    w.write_all(&[1])?;
+                    write_u64(&mut w, span.header)?;
+                    write_u64(&mut w, span.data)?;
+                    write_u64(&mut w, span.size)?;
+                }
+                None => w.write_all(&[0])?,
+            }
+        }
+
+        write_u64(&mut w, self.gzip.len() as u64)?;
+        for checkpoint in &self.gzip {
+            write_u64(&mut w, checkpoint.input)?;
+            w.write_all(&[checkpoint.bits])?;
+            write_u64(&mut w, checkpoint.output)?;
+            let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(&checkpoint.window)?;
+            write_bytes(&mut w, &encoder.finish()?)?;
+        }
+
+        match &self.xz {
+            None => w.write_all(&[0])?,
+            Some(layout) => {
+                w.write_all(&[1])?;
+                w.write_all(&layout.header)?;
+                write_u64(&mut w, layout.blocks.len() as u64)?;
+                for block in &layout.blocks {
+                    write_u64(&mut w, block.offset)?;
+                    write_u64(&mut w, block.unpadded)?;
+                    write_u64(&mut w, block.output)?;
+                    write_u64(&mut w, block.uncompressed)?;
+                }
+            }
+        }
+        w.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+}
+
+/// tar 以 512 字节为块，数据末尾补齐到整块
+fn pad_block(size: u64) -> u64 {
+    size.div_ceil(512) * 512
+}
+
+fn io_error(e: io::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = read_u64(r)?;
+    let mut buf = Vec::new();
+    r.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+fn read_str(r: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(r)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_opt_str(r: &mut impl Read) -> io::Result<Option<String>> {
+    match read_u8(r)? {
+        0 => Ok(None),
+        _ => read_str(r).map(Some),
+    }
+}
+
+fn write_u64(w: &mut impl Write, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_bytes(w: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    write_u64(w, data.len() as u64)?;
+    w.write_all(data)
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_bytes(w, s.as_bytes())
+}
+
+fn write_opt_str(w: &mut impl Write, s: Option<&str>) -> io::Result<()> {
+    match s {
+        None => w.write_all(&[0]),
+        Some(s) => {
+            w.write_all(&[1])?;
+            write_str(w, s)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ExtractOptions;
+    use crate::testutil::temp_dir;
+    use crate::{ArchiveHandler, TarHandler};
+    use std::fs;
+    use std::sync::Arc;
+
+    /// 伪随机文本，压缩后仍有足够多的检查点
+    fn text(seed: u64, len: usize) -> Vec<u8> {
+        let mut seed = seed;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                b"abcdefgh \n"[(seed >> 60) as usize % 10]
+            })
+            .collect()
+    }
+
+    /// 四个 1 MiB 的文件与一个超过 100 字节、需要扩展头的长文件名
+    fn tar_data() -> (Vec<u8>, Vec<(String, Vec<u8>)>) {
+        let mut files: Vec<(String, Vec<u8>)> = (0..4)
+            .map(|i| (format!("dir/file{}.txt", i), text(i + 1, 1 << 20)))
+            .collect();
+        files.push((
+            format!("dir/{}.txt", "long".repeat(40)),
+            b"long name".to_vec(),
+        ));
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in &files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, data.as_slice())
+                .unwrap();
+        }
+        (builder.into_inner().unwrap(), files)
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_all(index: &TarIndex, path: &Path, entry: &str, range: ByteRange) -> Vec<u8> {
+        let mut out = Vec::new();
+        index.copy_entry(path, entry, range, &mut out).unwrap();
+        out
+    }
+
+    fn assert_entries(index: &TarIndex, path: &Path, files: &[(String, Vec<u8>)]) {
+        for (name, data) in files {
+            assert!(read_all(index, path, name, ByteRange::default()) == *data);
+            let part = read_all(index, path, name, ByteRange::new(3, Some(5)));
+            assert_eq!(part, data[3..8]);
+        }
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let tmp = temp_dir();
+        let (tar, files) = tar_data();
+        let path = tmp.path().join("a.tar.gz");
+        fs::write(&path, gzip(&tar)).unwrap();
+        let cache_dir = tmp.path().join("cache");
+
+        let built = TarIndex::load_or_build(&path, ArchiveFormat::TarGz, Some(&cache_dir)).unwrap();
+        assert!(built.checkpoint_count() > 2);
+        let key = CacheKey::of(&path).unwrap();
+        let cache = cache_dir.join(key.file_name());
+        let loaded = TarIndex::load(&cache, &key).unwrap().unwrap();
+        assert_eq!(loaded.format, ArchiveFormat::TarGz);
+        assert_eq!(loaded.entries.len(), files.len());
+        assert_eq!(loaded.spans, built.spans);
+        assert_eq!(loaded.checkpoint_count(), built.checkpoint_count());
+        for (a, b) in loaded.gzip.iter().zip(&built.gzip) {
+            assert_eq!((a.input, a.bits, a.output), (b.input, b.bits, b.output));
+            assert!(a.window == b.window);
+        }
+        assert_entries(&loaded, &path, &files);
+
+        // 再次打开时读取缓存
+        let cached =
+            TarIndex::load_or_build(&path, ArchiveFormat::TarGz, Some(&cache_dir)).unwrap();
+        assert_eq!(cached.spans, built.spans);
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_stale_cache() {
+        let tmp = temp_dir();
+        let path = tmp.path().join("a.tar");
+        let cache_dir = tmp.path().join("cache");
+        let write = |data: &[u8]| {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, "a.txt", data).unwrap();
+            fs::write(&path, builder.into_inner().unwrap()).unwrap();
+        };
+
+        write(b"old");
+        let old_key = CacheKey::of(&path).unwrap();
+        TarIndex::load_or_build(&path, ArchiveFormat::Tar, Some(&cache_dir)).unwrap();
+
+        // 大小不变、修改时间相同时也能由内容摘要发现变化
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        write(b"new");
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        let key = CacheKey::of(&path).unwrap();
+        assert_ne!(key, old_key);
+        let old_cache = cache_dir.join(old_key.file_name());
+        assert!(TarIndex::load(&old_cache, &key).unwrap().is_none());
+
+        let index = TarIndex::load_or_build(&path, ArchiveFormat::Tar, Some(&cache_dir)).unwrap();
+        assert_eq!(
+            read_all(&index, &path, "a.txt", ByteRange::default()),
+            b"new"
+        );
+        assert!(cache_dir.join(key.file_name()).exists());
+    }
+
+    #[test]
+    fn test_open_at_later_xz_block() {
+        let tmp = temp_dir();
+        let (tar, files) = tar_data();
+        let stream = xz2::stream::MtStreamBuilder::new()
+            .threads(2)
+            .block_size(256 << 10)
+            .preset(1)
+            .encoder()
+            .unwrap();
+        let mut encoder = xz2::write::XzEncoder::new_stream(Vec::new(), stream);
+        encoder.write_all(&tar).unwrap();
+        let path = tmp.path().join("a.tar.xz");
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let index = TarIndex::build(&path, ArchiveFormat::TarXz).unwrap();
+        let layout = index.xz.as_ref().unwrap();
+        assert!(index.checkpoint_count() > 4);
+        // 最后一个文件从后面的块开始解压
+        let (name, _) = &files[3];
+        let span = index.span(name).unwrap();
+        assert!(layout.block_for(span.data).unwrap().output > 0);
+        assert_entries(&index, &path, &files);
+
+        let mut reader = index.open_at(&path, span.header).unwrap();
+        let mut header = [0u8; 512];
+        reader.read_exact(&mut header).unwrap();
+        assert_eq!(&header[..name.len()], name.as_bytes());
+    }
+
+    #[test]
+    fn test_multi_member_gzip() {
+        let tmp = temp_dir();
+        let (tar, files) = tar_data();
+        // 在文件中间切开，分成两个 gzip 成员
+        let mut gz = Vec::new();
+        for part in tar.chunks(tar.len() / 2 + 1000) {
+            gz.extend(gzip(part));
+        }
+        let path = tmp.path().join("a.tar.gz");
+        fs::write(&path, gz).unwrap();
+
+        let index = TarIndex::build(&path, ArchiveFormat::TarGz).unwrap();
+        assert_eq!(index.entries().len(), files.len());
+        assert!(index.gzip.iter().any(|c| c.output > tar.len() as u64 / 2));
+        assert_entries(&index, &path, &files);
+    }
+
+    #[test]
+    fn test_extract_selection() {
+        let tmp = temp_dir();
+        let (tar, files) = tar_data();
+        let path = tmp.path().join("a.tar.gz");
+        fs::write(&path, gzip(&tar)).unwrap();
+        let index = Arc::new(TarIndex::build(&path, ArchiveFormat::TarGz).unwrap());
+
+        // 长文件名的扩展头在范围内
+        let (long, data) = &files[4];
+        let range = index.selection(|p| p == long).unwrap();
+        let span = index.span(long).unwrap();
+        assert!(range.start < span.header - 512 && range.end > span.data);
+        assert!(index.selection(|p| p == "none").is_none());
+
+        let out = tmp.path().join("out");
+        let options = ExtractOptions {
+            include: vec![long.clone(), files[2].0.clone()],
+            ..Default::default()
+        };
+        TarHandler::with_index(&path, ArchiveFormat::TarGz, index.clone())
+            .extract(&out, &options)
+            .unwrap();
+        assert_eq!(fs::read(out.join(long)).unwrap(), *data);
+        assert!(fs::read(out.join(&files[2].0)).unwrap() == files[2].1);
+        assert!(!out.join(&files[0].0).exists());
+        assert!(!out.join(&files[3].0).exists());
+
+        let mut part = Vec::new();
+        let size = TarHandler::with_index(&path, ArchiveFormat::TarGz, index)
+            .read_entry(&files[1].0, ByteRange::new(10, Some(4)), &mut part)
+            .unwrap();
+        assert_eq!(size, 1 << 20);
+        assert_eq!(part, files[1].1[10..14]);
+    }
+}
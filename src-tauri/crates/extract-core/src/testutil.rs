@@ -0,0 +1,35 @@
+//! 测试共用的辅助函数
+
+use std::fs::File;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+/// 测试用的临时目录，离开作用域时连同内容一起删除
+pub(crate) fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("extract-test-")
+        .tempdir()
+        .unwrap()
+}
+
+/// 按原样写入条目名称的 tar，`None` 为目录
+///
+/// `tar::Builder` 会去掉 `./` 前缀，`tar -C src -cf a.tar .` 生成的 `./a.txt` 之类的名称只能直接写入头部。
+pub(crate) fn write_raw_tar(path: &Path, entries: &[(&str, Option<&[u8]>)]) {
+    let mut builder = tar::Builder::new(File::create(path).unwrap());
+    for &(name, data) in entries {
+        let mut header = tar::Header::new_ustar();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(match data {
+            Some(_) => tar::EntryType::Regular,
+            None => tar::EntryType::Directory,
+        });
+        header.set_mode(if data.is_some() { 0o644 } else { 0o755 });
+        header.set_mtime(1_600_000_000);
+        header.set_size(data.map_or(0, |d| d.len() as u64));
+        header.set_cksum();
+        builder.append(&header, data.unwrap_or_default()).unwrap();
+    }
+    builder.into_inner().unwrap();
+}
@@ -0,0 +1,234 @@
+//! 分卷输出
+//!
+//! [`VolumeFile`] 把写入的字节按固定大小切分到 `name.001`、`name.002`……，与 7-Zip 的分卷方式一致：
+//! 各卷直接拼接即为完整的压缩包，7-Zip 与 WinRAR 打开第一卷即可解压。写入器需要回写头部，
+//! 因此同样支持读取与定位。
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// 可跨越多个分卷读写的输出文件，不分卷时即为单个文件
+pub struct VolumeFile {
+    /// 分卷的基础路径，不分卷时为空
+    base: Option<PathBuf>,
+    volume_size: u64,
+    volumes: Vec<File>,
+    pos: u64,
+    len: u64,
+}
+
+impl From<File> for VolumeFile {
+    fn from(file: File) -> Self {
+        let len = file.metadata().map_or(0, |m| m.len());
+        VolumeFile {
+            base: None,
+            volume_size: u64::MAX,
+            volumes: vec![file],
+            pos: 0,
+            len,
+        }
+    }
+}
+
+/// 第 `index` 卷（从 0 开始）的路径：`base.001`、`base.002`……，超过 999 卷时位数随之增加
+pub fn volume_path(base: &Path, index: usize) -> PathBuf {
+    let mut name = base.as_os_str().to_owned();
+    name.push(format!(".{:03}", index + 1));
+    PathBuf::from(name)
+}
+
+impl VolumeFile {
+    /// 创建第一卷 `base.001`，之后按需创建后续分卷
+    pub fn create(base: &Path, volume_size: u64) -> io::Result<Self> {
+        if volume_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "无效的分卷大小",
+            ));
+        }
+        let mut file = VolumeFile {
+            base: Some(base.to_path_buf()),
+            volume_size,
+            volumes: Vec::new(),
+            pos: 0,
+            len: 0,
+        };
+        file.volume(0)?;
+        Ok(file)
+    }
+
+    /// 已创建的分卷路径，不分卷时为空
+    pub fn paths(&self) -> Vec<PathBuf> {
+        match &self.base {
+            Some(base) => (0..self.volumes.len())
+                .map(|i| volume_path(base, i))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// 所有分卷的总长度
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 截断或扩展到 `len` 字节，删除不再需要的分卷
+    pub fn set_len(&mut self, len: u64) -> io::Result<()> {
+        let needed = match len {
+            0 => 1,
+            len => len.div_ceil(self.volume_size).max(1) as usize,
+        };
+        if needed > self.volumes.len() {
+            self.volume(needed - 1)?;
+        }
+        let paths = self.paths();
+        self.volumes.truncate(needed);
+        for path in paths.iter().skip(needed) {
+            fs::remove_file(path)?;
+        }
+        let last = needed - 1;
+        self.volumes[last].set_len(len - last as u64 * self.volume_size)?;
+        self.len = len;
+        Ok(())
+    }
+
+    pub fn sync_all(&self) -> io::Result<()> {
+        self.volumes.iter().try_for_each(File::sync_all)
+    }
+
+    /// 打开第 `index` 卷，中间缺少的分卷补齐为完整大小
+    fn volume(&mut self, index: usize) -> io::Result<&mut File> {
+        while self.volumes.len() <= index {
+            if let Some(last) = self.volumes.last() {
+                if last.metadata()?.len() < self.volume_size {
+                    last.set_len(self.volume_size)?;
+                }
+            }
+            let base = self
+                .base
+                .as_deref()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "单个文件不能分卷"))?;
+            // 写入器需要读回头部，以读写方式打开
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(volume_path(base, self.volumes.len()))?;
+            self.volumes.push(file);
+        }
+        Ok(&mut self.volumes[index])
+    }
+
+    /// 当前位置所在的分卷与卷内偏移
+    fn locate(&self) -> (usize, u64) {
+        (
+            (self.pos / self.volume_size) as usize,
+            self.pos % self.volume_size,
+        )
+    }
+}
+
+impl Write for VolumeFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let (index, offset) = self.locate();
+        let room = (self.volume_size - offset).min(buf.len() as u64) as usize;
+        let file = self.volume(index)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let n = file.write(&buf[..room])?;
+        self.pos += n as u64;
+        self.len = self.len.max(self.pos);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.volumes.iter_mut().try_for_each(File::flush)
+    }
+}
+
+impl Read for VolumeFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let (index, offset) = self.locate();
+        let room = (self.volume_size - offset)
+            .min(self.len - self.pos)
+            .min(buf.len() as u64) as usize;
+        let file = &mut self.volumes[index];
+        file.seek(SeekFrom::Start(offset))?;
+        let n = file.read(&mut buf[..room])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for VolumeFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+            SeekFrom::End(n) => self.len.checked_add_signed(n),
+        };
+        self.pos = target
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "定位到文件开头之前"))?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_dir;
+
+    #[test]
+    fn test_volume_file() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let base = dir.join("out.zip");
+        let data: Vec<u8> = (0..250u32).map(|i| i as u8).collect();
+
+        let mut file = VolumeFile::create(&base, 100).unwrap();
+        file.write_all(&data).unwrap();
+        assert_eq!(file.len(), 250);
+        let names: Vec<_> = file
+            .paths()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["out.zip.001", "out.zip.002", "out.zip.003"]);
+        assert_eq!(fs::metadata(volume_path(&base, 1)).unwrap().len(), 100);
+
+        // 跨卷回写与读取
+        file.seek(SeekFrom::Start(95)).unwrap();
+        file.write_all(&[0xff; 10]).unwrap();
+        file.seek(SeekFrom::End(-160)).unwrap();
+        let mut read = vec![0; 20];
+        file.read_exact(&mut read).unwrap();
+        assert_eq!(read[..5], data[90..95]);
+        assert_eq!(read[5..15], [0xff; 10]);
+        assert_eq!(read[15..], data[105..110]);
+
+        file.set_len(150).unwrap();
+        assert_eq!(file.paths().len(), 2);
+        assert!(!volume_path(&base, 2).exists());
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut all = Vec::new();
+        file.read_to_end(&mut all).unwrap();
+        assert_eq!(all.len(), 150);
+        assert_eq!(all[110..], data[110..150]);
+
+        assert_eq!(
+            volume_path(Path::new("a.7z"), 999),
+            PathBuf::from("a.7z.1000")
+        );
+    }
+}
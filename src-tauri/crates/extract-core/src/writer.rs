@@ -0,0 +1,1513 @@
+//! 创建压缩包
+//!
+//! [`ArchiveWriter`] 与 [`ArchiveHandler`](crate::ArchiveHandler) 相对，逐个写入目录、文件与符号链接；
+//! [`create_archive`] 把磁盘上的文件与文件夹打包为 zip、tar（gz、bz2、xz、zst）或 7z。
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bzip2::write::BzEncoder;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use sevenz_rust::lzma::LZMA2Options;
+use sevenz_rust::{
+    AesEncoderOptions, Password, SeqReader, SevenZArchiveEntry, SevenZMethod, SevenZWriter,
+    SourceReader,
+};
+use xz2::write::XzEncoder;
+use zeroize::Zeroizing;
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, CompressionMethod, ZipWriter};
+
+use crate::options::{CompressionLevel, CompressionOptions, CreateOptions, ZipMethod};
+use crate::volume::{self, VolumeFile};
+use crate::{ArchiveError, ArchiveFormat, ResultR};
+
+/// 读取大文件时两次进度通知之间至少写入的字节数
+const PROGRESS_STEP: u64 = 4 << 20;
+
+/// LZMA2 字典大小的范围，与 7-Zip 一致
+const MIN_DICTIONARY_SIZE: u32 = 4 << 10;
+const MAX_DICTIONARY_SIZE: u32 = 1536 << 20;
+
+/// 可重复构建时默认的修改时间：1980-01-01 UTC，zip 能表示的最早时间
+const DEFAULT_SOURCE_DATE_EPOCH: i64 = 315_532_800;
+
+/// 固实块临时文件的序号
+static SOLID_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 写入条目时使用的属性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryAttrs {
+    /// 修改时间，unix 秒
+    pub mtime: i64,
+    /// 权限位，不含文件类型
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+}
+
+impl EntryAttrs {
+    /// 读取磁盘上文件的属性，不跟随符号链接
+    pub fn from_metadata(meta: &fs::Metadata) -> Self {
+        let mtime = meta
+            .modified()
+            .map_or(0, |t| match t.duration_since(UNIX_EPOCH) {
+                Ok(d) => d.as_secs() as i64,
+                Err(e) => -(e.duration().as_secs() as i64),
+            });
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            EntryAttrs {
+                mtime,
+                mode: meta.mode() & 0o7777,
+                uid: meta.uid() as u64,
+                gid: meta.gid() as u64,
+            }
+        }
+        #[cfg(not(unix))]
+        EntryAttrs {
+            mtime,
+            mode: match meta.is_dir() {
+                true => 0o755,
+                false if meta.permissions().readonly() => 0o444,
+                false => 0o644,
+            },
+            uid: 0,
+            gid: 0,
+        }
+    }
+
+    fn modified(&self) -> SystemTime {
+        match u64::try_from(self.mtime) {
+            Ok(secs) => UNIX_EPOCH + Duration::from_secs(secs),
+            Err(_) => UNIX_EPOCH - Duration::from_secs(self.mtime.unsigned_abs()),
+        }
+    }
+}
+
+/// 单个新压缩包的写入接口，由 [`create_writer`] 按格式创建
+///
+/// 条目名使用 `/` 分隔，目录不带结尾的 `/`；上级目录需要先于其中的条目写入。
+pub trait ArchiveWriter {
+    fn add_dir(&mut self, name: &str, attrs: &EntryAttrs) -> ResultR<()>;
+    /// 写入文件，`data` 应正好产出 `size` 字节
+    fn add_file(
+        &mut self,
+        name: &str,
+        attrs: &EntryAttrs,
+        size: u64,
+        data: &mut dyn Read,
+    ) -> ResultR<()>;
+    fn add_symlink(&mut self, name: &str, attrs: &EntryAttrs, target: &str) -> ResultR<()>;
+    /// 写完目录等结尾数据，返回输出文件
+    fn finish(self: Box<Self>) -> ResultR<VolumeFile>;
+}
+
+/// 按格式创建写入器，写入 `out`（文件或 [`VolumeFile`] 分卷）；7z 需要以读写方式打开 `out`
+///
+/// 设置了加密时，zip 使用 WinZip AES-256 加密文件内容（目录与符号链接不加密），
+/// 7z 使用 AES-256 加密数据，可选同时加密头部；tar 类格式不支持加密。
+/// 格式不支持的压缩选项被忽略，见 [`format_capabilities`]。可重复构建时不能加密，
+/// 因为加密使用随机的盐。
+pub fn create_writer(
+    out: impl Into<VolumeFile>,
+    format: ArchiveFormat,
+    options: &CreateOptions,
+) -> ResultR<Box<dyn ArchiveWriter>> {
+    let out = out.into();
+    let encryption = options.encryption.as_ref();
+    if encryption.is_some_and(|e| e.password.is_empty()) {
+        return Err(ArchiveError::MsgError(String::from("密码不能为空")));
+    }
+    if options.deterministic && encryption.is_some() {
+        return Err(ArchiveError::MsgError(String::from("可重复构建不支持加密")));
+    }
+    let compression = &options.compression;
+    if compression
+        .dictionary_size
+        .is_some_and(|size| !(MIN_DICTIONARY_SIZE..=MAX_DICTIONARY_SIZE).contains(&size))
+    {
+        return Err(ArchiveError::MsgError(String::from("无效的字典大小")));
+    }
+    if compression.solid_block_size == Some(0) {
+        return Err(ArchiveError::MsgError(String::from("无效的固实块大小")));
+    }
+    Ok(match format {
+        ArchiveFormat::Zip => Box::new(ZipArchiveWriter::new(ZipWriter::new(out), options)),
+        _ if encryption.is_some() && format != ArchiveFormat::SevenZip => {
+            return Err(ArchiveError::MsgError(String::from("该格式不支持加密")));
+        }
+        ArchiveFormat::Tar
+        | ArchiveFormat::TarGz
+        | ArchiveFormat::TarBz2
+        | ArchiveFormat::TarXz
+        | ArchiveFormat::TarZst => Box::new(TarArchiveWriter {
+            builder: tar::Builder::new(TarEncoder::new(out, format, compression)?),
+        }),
+        ArchiveFormat::SevenZip => {
+            let mut writer = SevenZWriter::new(out).map_err(sevenz_error)?;
+            let mut methods = Vec::new();
+            let mut header_password = None;
+            if let Some(encryption) = encryption {
+                // 密码移入 sevenz-rust 的内容编码方法，无法在结束后清零，见 SevenZipArchiveWriter
+                let password = Password::from(encryption.password.as_str());
+                methods.push(AesEncoderOptions::new(password).into());
+                if encryption.encrypt_header {
+                    header_password = Some(encryption.password.clone());
+                }
+            }
+            let mut lzma2 = LZMA2Options::with_preset(compression.level.value());
+            if let Some(size) = compression.dictionary_size {
+                lzma2.dict_size = size;
+            }
+            methods.push(lzma2.into());
+            writer.set_content_methods(methods);
+            writer.set_encrypt_header(header_password.is_some());
+            let solid = match compression.solid_block_size {
+                Some(limit) => Some(SolidBlock::new(limit)?),
+                None => None,
+            };
+            Box::new(SevenZipArchiveWriter {
+                writer,
+                header_password,
+                solid,
+            })
+        }
+        _ => return Err(unsupported()),
+    })
+}
+
+/// 创建某种格式时可用的选项，界面据此生成表单
+#[derive(Debug, Serialize, Clone)]
+pub struct FormatCapabilities {
+    pub format: ArchiveFormat,
+    /// 默认扩展名，不含开头的 `.`
+    pub extension: &'static str,
+    /// 可选的压缩级别，为空时不压缩
+    pub levels: Vec<CompressionLevel>,
+    /// 可选的压缩方式，只有 zip 不为空
+    pub methods: Vec<ZipMethod>,
+    pub dictionary_size: bool,
+    pub solid_block_size: bool,
+    pub threads: bool,
+    pub encryption: bool,
+    pub encrypt_header: bool,
+}
+
+/// 可以创建的格式及各自支持的选项，按界面展示的顺序排列
+pub fn format_capabilities() -> Vec<FormatCapabilities> {
+    use CompressionLevel::*;
+    let all = vec![Store, Fastest, Fast, Normal, Maximum, Ultra];
+    let compressed = all[1..].to_vec();
+    let base = |format, extension, levels| FormatCapabilities {
+        format,
+        extension,
+        levels,
+        methods: Vec::new(),
+        dictionary_size: false,
+        solid_block_size: false,
+        threads: false,
+        encryption: false,
+        encrypt_header: false,
+    };
+    vec![
+        FormatCapabilities {
+            methods: vec![
+                ZipMethod::Deflate,
+                ZipMethod::Store,
+                ZipMethod::Bzip2,
+                ZipMethod::Zstd,
+            ],
+            encryption: true,
+            ..base(ArchiveFormat::Zip, "zip", all.clone())
+        },
+        FormatCapabilities {
+            dictionary_size: true,
+            solid_block_size: true,
+            encryption: true,
+            encrypt_header: true,
+            ..base(ArchiveFormat::SevenZip, "7z", compressed.clone())
+        },
+        base(ArchiveFormat::Tar, "tar", Vec::new()),
+        base(ArchiveFormat::TarGz, "tar.gz", all),
+        base(ArchiveFormat::TarBz2, "tar.bz2", compressed.clone()),
+        FormatCapabilities {
+            dictionary_size: true,
+            threads: true,
+            ..base(ArchiveFormat::TarXz, "tar.xz", compressed.clone())
+        },
+        FormatCapabilities {
+            threads: true,
+            ..base(ArchiveFormat::TarZst, "tar.zst", compressed)
+        },
+    ]
+}
+
+/// `archive-create-progress` 事件等使用的创建进度
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct CreateProgress {
+    /// 正在写入的条目
+    pub entry: String,
+    pub entries_done: u64,
+    pub entries_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// 待写入的磁盘文件
+pub(crate) struct Source {
+    pub(crate) path: PathBuf,
+    pub(crate) name: String,
+    pub(crate) kind: EntryKind,
+    pub(crate) attrs: EntryAttrs,
+    pub(crate) size: u64,
+}
+
+/// 条目类型，符号链接带有链接目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Dir,
+    File,
+    Symlink(String),
+}
+
+impl EntryAttrs {
+    /// 去掉与主机相关的属性：时间统一为 `mtime`，属主清零，只保留是否可执行
+    pub(crate) fn normalize(&mut self, kind: &EntryKind, mtime: i64) {
+        let mode = match kind {
+            EntryKind::Symlink(_) => 0o777,
+            EntryKind::Dir => 0o755,
+            EntryKind::File if self.mode & 0o111 != 0 => 0o755,
+            EntryKind::File => 0o644,
+        };
+        *self = EntryAttrs {
+            mtime,
+            mode,
+            uid: 0,
+            gid: 0,
+        };
+    }
+}
+
+/// 可重复构建使用的修改时间，见 [`CreateOptions::source_date_epoch`]
+pub(crate) fn source_date_epoch(options: &CreateOptions) -> ResultR<i64> {
+    if let Some(epoch) = options.source_date_epoch {
+        return Ok(epoch);
+    }
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| ArchiveError::MsgError(format!("无效的 SOURCE_DATE_EPOCH: {}", value))),
+        Err(_) => Ok(DEFAULT_SOURCE_DATE_EPOCH),
+    }
+}
+
+/// 把 `sources`（文件或文件夹）打包为 `output`
+///
+/// 每个来源以自身的名称放在压缩包根目录下，符号链接按链接保存。先写入同目录下的临时文件，
+/// 成功后再替换 `output`；每写完一个条目或一段数据调用一次 `progress`。
+pub fn create_archive(
+    sources: &[PathBuf],
+    output: &Path,
+    format: ArchiveFormat,
+    options: &CreateOptions,
+    mut progress: impl FnMut(&CreateProgress),
+) -> ResultR<()> {
+    let output_path = absolute(output);
+    let mut items = Vec::new();
+    for source in sources {
+        collect(
+            source,
+            source_name(source)?,
+            output_path.as_deref(),
+            &mut items,
+        )?;
+    }
+    if options.deterministic {
+        let mtime = source_date_epoch(options)?;
+        // 上级目录的名称是子条目的前缀，排序后仍先于子条目
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        items
+            .iter_mut()
+            .for_each(|item| item.attrs.normalize(&item.kind, mtime));
+    }
+
+    write_output(output, format, options, |writer| {
+        write_items(writer, &items, &mut progress)
+    })
+}
+
+/// 先由 `write` 写入同目录下的临时文件，成功后再替换 `output`，失败时删除临时文件
+///
+/// 设置了分卷大小时依次写入 `output.001`、`output.002`……，并删除上次留下的多余分卷。
+pub(crate) fn write_output(
+    output: &Path,
+    format: ArchiveFormat,
+    options: &CreateOptions,
+    write: impl FnOnce(Box<dyn ArchiveWriter>) -> ResultR<VolumeFile>,
+) -> ResultR<()> {
+    if options.volume_size == Some(0) {
+        return Err(ArchiveError::MsgError(String::from("无效的分卷大小")));
+    }
+    replace_output(output, options.volume_size, |file| {
+        create_writer(file, format, options).and_then(write)
+    })
+}
+
+/// 同 [`write_output`]，由 `write` 自行在临时文件上创建写入器；不分卷时沿用 `output` 原有的权限
+pub(crate) fn replace_output(
+    output: &Path,
+    volume_size: Option<u64>,
+    write: impl FnOnce(VolumeFile) -> ResultR<VolumeFile>,
+) -> ResultR<()> {
+    let name = output
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| ArchiveError::MsgError(format!("无效的路径: {}", output.display())))?;
+    let tmp = output.with_file_name(format!(".{}.tmp{}", name, std::process::id()));
+    let out = match volume_size {
+        Some(size) => VolumeFile::create(&tmp, size),
+        // 7z 写完后需要读回头部，以读写方式打开
+        None => fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp)
+            .map(VolumeFile::from),
+    };
+    let result = out
+        .map_err(io_error)
+        .and_then(write)
+        .and_then(|file| file.sync_all().map(|_| file.paths()).map_err(io_error))
+        .and_then(|volumes| match volumes.is_empty() {
+            true => keep_permissions(output, &tmp)
+                .and_then(|_| fs::rename(&tmp, output))
+                .map_err(io_error),
+            false => rename_volumes(&volumes, output).map_err(io_error),
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        remove_volumes(&tmp, 0);
+    }
+    result
+}
+
+/// 替换已有的文件时，让新文件沿用原来的权限
+fn keep_permissions(output: &Path, tmp: &Path) -> io::Result<()> {
+    match fs::metadata(output) {
+        Ok(meta) => fs::set_permissions(tmp, meta.permissions()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// 把临时分卷依次改名为 `output` 的分卷
+fn rename_volumes(volumes: &[PathBuf], output: &Path) -> io::Result<()> {
+    for (i, volume) in volumes.iter().enumerate() {
+        fs::rename(volume, volume::volume_path(output, i))?;
+    }
+    // 上次留下的后续分卷会被当作同一组
+    remove_volumes(output, volumes.len());
+    Ok(())
+}
+
+/// 删除 `base` 从第 `start` 卷开始的连续分卷
+fn remove_volumes(base: &Path, start: usize) {
+    (start..)
+        .map(|i| volume::volume_path(base, i))
+        .take_while(|path| fs::remove_file(path).is_ok())
+        .for_each(drop);
+}
+
+fn write_items(
+    mut writer: Box<dyn ArchiveWriter>,
+    items: &[Source],
+    progress: &mut dyn FnMut(&CreateProgress),
+) -> ResultR<VolumeFile> {
+    let mut state = CreateProgress {
+        entries_total: items.len() as u64,
+        bytes_total: items.iter().map(|i| i.size).sum(),
+        ..Default::default()
+    };
+    for item in items {
+        state.entry = item.name.clone();
+        let reported = state.bytes_done;
+        write_source(writer.as_mut(), item, &item.name, |file| ProgressReader {
+            inner: file,
+            reported,
+            state: &mut state,
+            progress: &mut *progress,
+        })?;
+        state.entries_done += 1;
+        progress(&state);
+    }
+    writer.finish()
+}
+
+/// 以 `name` 写入一个磁盘条目，文件内容经 `wrap` 包装后读取
+pub(crate) fn write_source<R: Read>(
+    writer: &mut dyn ArchiveWriter,
+    item: &Source,
+    name: &str,
+    wrap: impl FnOnce(io::Take<File>) -> R,
+) -> ResultR<()> {
+    match &item.kind {
+        EntryKind::Dir => writer.add_dir(name, &item.attrs),
+        EntryKind::Symlink(target) => writer.add_symlink(name, &item.attrs, target),
+        EntryKind::File => {
+            let file = File::open(&item.path).map_err(io_error)?;
+            // 文件在打包过程中变长时只写入开始时的大小
+            let mut data = wrap(file.take(item.size));
+            writer.add_file(name, &item.attrs, item.size, &mut data)
+        }
+    }
+}
+
+/// 读取时累计字节数，每读取 [`PROGRESS_STEP`] 字节通知一次
+pub(crate) struct ProgressReader<'a, R> {
+    pub(crate) inner: R,
+    pub(crate) state: &'a mut CreateProgress,
+    pub(crate) progress: &'a mut dyn FnMut(&CreateProgress),
+    pub(crate) reported: u64,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.state.bytes_done += n as u64;
+        if self.state.bytes_done - self.reported >= PROGRESS_STEP {
+            self.reported = self.state.bytes_done;
+            (self.progress)(self.state);
+        }
+        Ok(n)
+    }
+}
+
+/// 来源在压缩包中的名称，即其文件名；`.` 等路径取规范化后的名称
+pub(crate) fn source_name(source: &Path) -> ResultR<String> {
+    source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .or_else(|| {
+            let path = fs::canonicalize(source).ok()?;
+            Some(path.file_name()?.to_string_lossy().to_string())
+        })
+        .ok_or_else(|| ArchiveError::MsgError(format!("无效的路径: {}", source.display())))
+}
+
+/// 递归收集 `path` 下的条目，同一目录中按名称排序；跳过路径为 `output` 的文件
+pub(crate) fn collect(
+    path: &Path,
+    name: String,
+    output: Option<&Path>,
+    items: &mut Vec<Source>,
+) -> ResultR<()> {
+    let meta = fs::symlink_metadata(path).map_err(io_error)?;
+    let attrs = EntryAttrs::from_metadata(&meta);
+    let file_type = meta.file_type();
+    if file_type.is_symlink() {
+        let target = fs::read_link(path).map_err(io_error)?;
+        let target = target.to_string_lossy().replace('\\', "/");
+        items.push(Source {
+            path: path.to_path_buf(),
+            name,
+            kind: EntryKind::Symlink(target),
+            attrs,
+            size: 0,
+        });
+    } else if file_type.is_dir() {
+        items.push(Source {
+            path: path.to_path_buf(),
+            name: name.clone(),
+            kind: EntryKind::Dir,
+            attrs,
+            size: 0,
+        });
+        let mut children = fs::read_dir(path)
+            .map_err(io_error)?
+            .map(|entry| entry.map(|e| e.file_name()))
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(io_error)?;
+        children.sort();
+        for child in children {
+            let child_name = format!("{}/{}", name, child.to_string_lossy());
+            collect(&path.join(child), child_name, output, items)?;
+        }
+    } else if file_type.is_file() {
+        // 输出文件位于来源目录中时跳过，避免把自身打包进去
+        if output.is_some() && absolute(path).as_deref() == output {
+            return Ok(());
+        }
+        items.push(Source {
+            path: path.to_path_buf(),
+            name,
+            kind: EntryKind::File,
+            attrs,
+            size: meta.len(),
+        });
+    } else {
+        log::warn!("跳过特殊文件: {}", path.display());
+    }
+    Ok(())
+}
+
+/// 上级目录规范化后的路径，文件本身可以不存在
+pub(crate) fn absolute(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
+
+/// xz 编码器，线程数大于 1 时使用多线程分块压缩
+fn xz_stream(compression: &CompressionOptions) -> ResultR<xz2::stream::Stream> {
+    use xz2::stream::{Check, Filters, LzmaOptions, MtStreamBuilder, Stream};
+    let mut lzma = LzmaOptions::new_preset(compression.level.value()).map_err(xz_error)?;
+    if let Some(size) = compression.dictionary_size {
+        lzma.dict_size(size);
+    }
+    let mut filters = Filters::new();
+    filters.lzma2(&lzma);
+    match compression.threads() {
+        1 => Stream::new_stream_encoder(&filters, Check::Crc64),
+        threads => MtStreamBuilder::new()
+            .threads(threads)
+            .filters(filters)
+            .check(Check::Crc64)
+            .encoder(),
+    }
+    .map_err(xz_error)
+}
+
+/// tar 数据流外层的压缩
+pub(crate) enum TarEncoder<W: Write> {
+    Plain(W),
+    Gz(GzEncoder<W>),
+    Bz2(BzEncoder<W>),
+    Xz(XzEncoder<W>),
+    Zst(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> TarEncoder<W> {
+    pub(crate) fn new(
+        out: W,
+        format: ArchiveFormat,
+        compression: &CompressionOptions,
+    ) -> ResultR<Self> {
+        let level = compression.level;
+        Ok(match format {
+            ArchiveFormat::Tar => TarEncoder::Plain(out),
+            // 头部不写文件名与时间，系统字节固定为 255（未知），与运行的主机无关
+            ArchiveFormat::TarGz => TarEncoder::Gz(
+                flate2::GzBuilder::new()
+                    .mtime(0)
+                    .operating_system(255)
+                    .write(out, flate2::Compression::new(level.value())),
+            ),
+            ArchiveFormat::TarBz2 => TarEncoder::Bz2(BzEncoder::new(
+                out,
+                bzip2::Compression::new(level.value().max(1)),
+            )),
+            ArchiveFormat::TarXz => {
+                TarEncoder::Xz(XzEncoder::new_stream(out, xz_stream(compression)?))
+            }
+            ArchiveFormat::TarZst => {
+                let mut encoder = zstd::Encoder::new(out, level.zstd()).map_err(io_error)?;
+                let threads = compression.threads();
+                if threads > 1 {
+                    encoder.multithread(threads).map_err(io_error)?;
+                }
+                TarEncoder::Zst(encoder)
+            }
+            _ => return Err(unsupported()),
+        })
+    }
+
+    /// 写完压缩流的结尾，返回内层的输出
+    pub(crate) fn finish(self) -> io::Result<W> {
+        match self {
+            TarEncoder::Plain(w) => Ok(w),
+            TarEncoder::Gz(w) => w.finish(),
+            TarEncoder::Bz2(w) => w.finish(),
+            TarEncoder::Xz(w) => w.finish(),
+            TarEncoder::Zst(w) => w.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for TarEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TarEncoder::Plain(w) => w.write(buf),
+            TarEncoder::Gz(w) => w.write(buf),
+            TarEncoder::Bz2(w) => w.write(buf),
+            TarEncoder::Xz(w) => w.write(buf),
+            TarEncoder::Zst(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TarEncoder::Plain(w) => w.flush(),
+            TarEncoder::Gz(w) => w.flush(),
+            TarEncoder::Bz2(w) => w.flush(),
+            TarEncoder::Xz(w) => w.flush(),
+            TarEncoder::Zst(w) => w.flush(),
+        }
+    }
+}
+
+/// zip 的压缩方式与级别；“仅存储”级别时不压缩
+fn zip_method(compression: &CompressionOptions) -> (CompressionMethod, Option<i64>) {
+    let level = compression.level;
+    match compression.method {
+        _ if level == CompressionLevel::Store => (CompressionMethod::Stored, None),
+        ZipMethod::Store => (CompressionMethod::Stored, None),
+        ZipMethod::Deflate => (CompressionMethod::Deflated, Some(level.value() as i64)),
+        ZipMethod::Bzip2 => (CompressionMethod::Bzip2, Some(level.value() as i64)),
+        ZipMethod::Zstd => (CompressionMethod::Zstd, Some(level.zstd() as i64)),
+    }
+}
+
+pub(crate) struct ZipArchiveWriter {
+    pub(crate) writer: ZipWriter<VolumeFile>,
+    method: CompressionMethod,
+    level: Option<i64>,
+    /// 按 UTC 保存时间，不受主机时区影响
+    utc: bool,
+    password: Option<Zeroizing<String>>,
+}
+
+impl ZipArchiveWriter {
+    /// 在 `writer` 已有的条目之后继续写入，修改压缩包时用于先原样复制未改动的条目
+    pub(crate) fn new(writer: ZipWriter<VolumeFile>, options: &CreateOptions) -> Self {
+        let (method, level) = zip_method(&options.compression);
+        ZipArchiveWriter {
+            writer,
+            method,
+            level,
+            utc: options.deterministic,
+            password: options.encryption.as_ref().map(|e| e.password.clone()),
+        }
+    }
+
+    fn options(&self, attrs: &EntryAttrs) -> SimpleFileOptions {
+        let options = SimpleFileOptions::default()
+            .compression_method(self.method)
+            .compression_level(self.level)
+            .unix_permissions(attrs.mode);
+        let time = match self.utc {
+            true => zip_datetime(chrono::DateTime::<chrono::Utc>::from(attrs.modified())),
+            false => zip_time(attrs.modified()),
+        };
+        match time {
+            Some(time) => options.last_modified_time(time),
+            // 不设置时 zip 使用当前时间，可重复构建时改用最早的时间
+            None if self.utc => options.last_modified_time(zip::DateTime::default()),
+            None => options,
+        }
+    }
+}
+
+impl ArchiveWriter for ZipArchiveWriter {
+    fn add_dir(&mut self, name: &str, attrs: &EntryAttrs) -> ResultR<()> {
+        let options = self
+            .options(attrs)
+            .compression_method(CompressionMethod::Stored)
+            .compression_level(None);
+        self.writer.add_directory(name, options).map_err(zip_error)
+    }
+
+    fn add_file(
+        &mut self,
+        name: &str,
+        attrs: &EntryAttrs,
+        size: u64,
+        data: &mut dyn Read,
+    ) -> ResultR<()> {
+        let mut options = self.options(attrs).large_file(size >= u32::MAX as u64);
+        if let Some(password) = &self.password {
+            options = options.with_aes_encryption(AesMode::Aes256, password);
+        }
+        self.writer.start_file(name, options).map_err(zip_error)?;
+        io::copy(data, &mut self.writer).map_err(io_error)?;
+        Ok(())
+    }
+
+    fn add_symlink(&mut self, name: &str, attrs: &EntryAttrs, target: &str) -> ResultR<()> {
+        let options = self
+            .options(attrs)
+            .compression_method(CompressionMethod::Stored)
+            .compression_level(None);
+        self.writer
+            .add_symlink(name, target, options)
+            .map_err(zip_error)
+    }
+
+    fn finish(self: Box<Self>) -> ResultR<VolumeFile> {
+        self.writer.finish().map_err(zip_error)
+    }
+}
+
+/// zip 使用本地时间，超出 1980–2107 年时返回 `None`
+pub(crate) fn zip_time(time: SystemTime) -> Option<zip::DateTime> {
+    zip_datetime(chrono::DateTime::<chrono::Local>::from(time))
+}
+
+fn zip_datetime<Tz: chrono::TimeZone>(time: chrono::DateTime<Tz>) -> Option<zip::DateTime> {
+    use chrono::{Datelike, Timelike};
+    zip::DateTime::from_date_and_time(
+        time.year().try_into().ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+pub(crate) struct TarArchiveWriter {
+    pub(crate) builder: tar::Builder<TarEncoder<VolumeFile>>,
+}
+
+fn tar_header(kind: tar::EntryType, attrs: &EntryAttrs, size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(kind);
+    header.set_mode(attrs.mode);
+    header.set_mtime(attrs.mtime.max(0) as u64);
+    header.set_uid(attrs.uid);
+    header.set_gid(attrs.gid);
+    header.set_size(size);
+    header
+}
+
+impl ArchiveWriter for TarArchiveWriter {
+    fn add_dir(&mut self, name: &str, attrs: &EntryAttrs) -> ResultR<()> {
+        let mut header = tar_header(tar::EntryType::Directory, attrs, 0);
+        self.builder
+            .append_data(&mut header, format!("{}/", name), io::empty())
+            .map_err(io_error)
+    }
+
+    fn add_file(
+        &mut self,
+        name: &str,
+        attrs: &EntryAttrs,
+        size: u64,
+        data: &mut dyn Read,
+    ) -> ResultR<()> {
+        let mut header = tar_header(tar::EntryType::Regular, attrs, size);
+        self.builder
+            .append_data(&mut header, name, data)
+            .map_err(io_error)
+    }
+
+    fn add_symlink(&mut self, name: &str, attrs: &EntryAttrs, target: &str) -> ResultR<()> {
+        let mut header = tar_header(tar::EntryType::Symlink, attrs, 0);
+        self.builder
+            .append_link(&mut header, name, target)
+            .map_err(io_error)
+    }
+
+    fn finish(self: Box<Self>) -> ResultR<VolumeFile> {
+        self.builder
+            .into_inner()
+            .and_then(TarEncoder::finish)
+            .map_err(io_error)
+    }
+}
+
+/// 7z 属性：FILE_ATTRIBUTE_UNIX_EXTENSION 时高 16 位保存 unix 模式（含文件类型）
+const SEVENZ_UNIX_EXTENSION: u32 = 0x8000;
+const SEVENZ_DIRECTORY: u32 = 0x10;
+const SEVENZ_ARCHIVE: u32 = 0x20;
+
+/// 7z 写入器
+///
+/// 内容加密用的密码保存在 `SevenZWriter` 私有的编码方法列表中，sevenz-rust 在派生密钥时也会复制，
+/// 这些副本释放时不会清零，只能清零这里自己持有的密码。
+struct SevenZipArchiveWriter {
+    writer: SevenZWriter<VolumeFile>,
+    /// 需要加密头部时使用的密码，结束时才生成密钥
+    header_password: Option<Zeroizing<String>>,
+    /// 固实压缩时尚未写入的块
+    solid: Option<SolidBlock>,
+}
+
+/// 固实块：文件数据先写入临时文件，达到大小上限或结束时作为一个数据流压缩
+struct SolidBlock {
+    path: PathBuf,
+    spool: File,
+    entries: Vec<SevenZArchiveEntry>,
+    sizes: Vec<u64>,
+    size: u64,
+    limit: u64,
+}
+
+impl SolidBlock {
+    fn new(limit: u64) -> ResultR<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "extract-solid-{}-{}",
+            std::process::id(),
+            SOLID_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let spool = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(io_error)?;
+        Ok(SolidBlock {
+            path,
+            spool,
+            entries: Vec::new(),
+            sizes: Vec::new(),
+            size: 0,
+            limit,
+        })
+    }
+
+    fn push(
+        &mut self,
+        writer: &mut SevenZWriter<VolumeFile>,
+        mut entry: SevenZArchiveEntry,
+        data: &mut dyn Read,
+    ) -> ResultR<()> {
+        let size = io::copy(data, &mut self.spool).map_err(io_error)?;
+        entry.has_stream = true;
+        self.entries.push(entry);
+        self.sizes.push(size);
+        self.size += size;
+        if self.size >= self.limit {
+            self.flush(writer)?;
+        }
+        Ok(())
+    }
+
+    /// 压缩块中的全部条目，并清空临时文件
+    fn flush(&mut self, writer: &mut SevenZWriter<VolumeFile>) -> ResultR<()> {
+        use io::{Seek, SeekFrom};
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        self.spool.seek(SeekFrom::Start(0)).map_err(io_error)?;
+        let spool = &self.spool;
+        let readers = self
+            .sizes
+            .drain(..)
+            .map(|size| SourceReader::new(spool.take(size)))
+            .collect();
+        writer
+            .push_archive_entries(std::mem::take(&mut self.entries), SeqReader::new(readers))
+            .map_err(sevenz_error)?;
+        self.spool.set_len(0).map_err(io_error)?;
+        self.spool.seek(SeekFrom::Start(0)).map_err(io_error)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Drop for SolidBlock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn sevenz_entry(name: &str, attrs: &EntryAttrs, kind: u32) -> SevenZArchiveEntry {
+    let dos = match kind {
+        0o040000 => SEVENZ_DIRECTORY,
+        _ => SEVENZ_ARCHIVE,
+    };
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = name.to_string();
+    entry.is_directory = kind == 0o040000;
+    entry.has_windows_attributes = true;
+    entry.windows_attributes = dos | SEVENZ_UNIX_EXTENSION | ((kind | attrs.mode) << 16);
+    if let Ok(time) = sevenz_rust::nt_time::FileTime::from_unix_time(attrs.mtime) {
+        entry.last_modified_date = time;
+        entry.has_last_modified_date = true;
+    }
+    entry
+}
+
+impl ArchiveWriter for SevenZipArchiveWriter {
+    fn add_dir(&mut self, name: &str, attrs: &EntryAttrs) -> ResultR<()> {
+        let entry = sevenz_entry(name, attrs, 0o040000);
+        self.writer
+            .push_archive_entry::<&[u8]>(entry, None)
+            .map_err(sevenz_error)?;
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        name: &str,
+        attrs: &EntryAttrs,
+        size: u64,
+        data: &mut dyn Read,
+    ) -> ResultR<()> {
+        let entry = sevenz_entry(name, attrs, 0o100000);
+        if let (Some(solid), true) = (&mut self.solid, size > 0) {
+            return solid.push(&mut self.writer, entry, data);
+        }
+        // 空文件不写数据流，与 7-Zip 一致
+        let data = (size > 0).then_some(data);
+        self.writer
+            .push_archive_entry(entry, data)
+            .map_err(sevenz_error)?;
+        Ok(())
+    }
+
+    fn add_symlink(&mut self, name: &str, attrs: &EntryAttrs, target: &str) -> ResultR<()> {
+        // 与 p7zip 一致，链接目标作为内容保存
+        let entry = sevenz_entry(name, attrs, 0o120000);
+        if let Some(solid) = &mut self.solid {
+            return solid.push(&mut self.writer, entry, &mut target.as_bytes());
+        }
+        self.writer
+            .push_archive_entry(entry, Some(target.as_bytes()))
+            .map_err(sevenz_error)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> ResultR<VolumeFile> {
+        let mut this = *self;
+        if let Some(solid) = &mut this.solid {
+            solid.flush(&mut this.writer)?;
+        }
+        let mut file = this.writer.finish().map_err(io_error)?;
+        if let Some(password) = this.header_password {
+            let key = AesEncoderOptions::new(Password::from(password.as_str()));
+            let result = encrypt_raw_header(&mut file, &key);
+            // to_vec 取出 Password 内部的缓冲区本身，就地清零后释放
+            drop(Zeroizing::new(key.password.to_vec()));
+            result.map_err(io_error)?;
+        }
+        Ok(file)
+    }
+}
+
+/// 7z 头部的属性标记
+const K_END: u8 = 0x00;
+const K_HEADER: u8 = 0x01;
+const K_PACK_INFO: u8 = 0x06;
+const K_UNPACK_INFO: u8 = 0x07;
+const K_SIZE: u8 = 0x09;
+const K_CRC: u8 = 0x0a;
+const K_FOLDER: u8 = 0x0b;
+const K_CODERS_UNPACK_SIZE: u8 = 0x0c;
+const K_ENCODED_HEADER: u8 = 0x17;
+
+/// sevenz-rust 在压缩后的头部没有变小时会写入未加密的原始头部（文件很少时常见），
+/// 此时改为只用 AES 编码头部，保证文件名不以明文保存
+fn encrypt_raw_header(file: &mut VolumeFile, key: &AesEncoderOptions) -> io::Result<()> {
+    use io::{Seek, SeekFrom};
+    use sevenz_rust::lzma::CountingWriter;
+
+    let mut start = [0u8; 32];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut start)?;
+    let offset = u64::from_le_bytes(start[12..20].try_into().unwrap_or_default());
+    let size = u64::from_le_bytes(start[20..28].try_into().unwrap_or_default());
+    let mut raw = Zeroizing::new(vec![0u8; size as usize]);
+    file.seek(SeekFrom::Start(32 + offset))?;
+    file.read_exact(&mut raw)?;
+    if raw.first() != Some(&K_HEADER) {
+        return Ok(());
+    }
+
+    let mut packed = Vec::with_capacity(raw.len() + 16);
+    let mut encoder = sevenz_rust::Aes256Sha256Encoder::new(CountingWriter::new(&mut packed), key)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    encoder.write_all(&raw)?;
+    // 写入空数据表示结束，补齐最后一块
+    let _ = encoder.write(&[])?;
+    drop(encoder);
+
+    // 编码后的头部：一个只含 AES 编码器的 folder，数据紧接在原头部的位置
+    let mut header = vec![K_ENCODED_HEADER, K_PACK_INFO];
+    write_number(&mut header, offset);
+    write_number(&mut header, 1);
+    header.push(K_SIZE);
+    write_number(&mut header, packed.len() as u64);
+    header.extend([K_END, K_UNPACK_INFO, K_FOLDER]);
+    write_number(&mut header, 1);
+    // 非外部数据、一个编码器，id 长 4 字节且带属性
+    header.extend([0, 1, 0x24]);
+    header.extend(SevenZMethod::AES256SHA256.id());
+    let props = key.properties();
+    header.push(props.len() as u8);
+    header.extend(props);
+    header.push(K_CODERS_UNPACK_SIZE);
+    write_number(&mut header, raw.len() as u64);
+    header.extend([K_CRC, 1]);
+    header.extend(crc32fast::hash(&raw).to_le_bytes());
+    header.extend([K_END, K_END]);
+
+    file.seek(SeekFrom::Start(32 + offset))?;
+    file.write_all(&packed)?;
+    file.write_all(&header)?;
+    file.set_len(32 + offset + (packed.len() + header.len()) as u64)?;
+
+    start[12..20].copy_from_slice(&(offset + packed.len() as u64).to_le_bytes());
+    start[20..28].copy_from_slice(&(header.len() as u64).to_le_bytes());
+    start[28..32].copy_from_slice(&crc32fast::hash(&header).to_le_bytes());
+    let crc = crc32fast::hash(&start[12..32]);
+    start[8..12].copy_from_slice(&crc.to_le_bytes());
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&start)?;
+    file.seek(SeekFrom::End(0))?;
+    Ok(())
+}
+
+/// 7z 的变长整数：首字节高位的 1 的个数表示后续字节数
+fn write_number(out: &mut Vec<u8>, value: u64) {
+    let mut first = 0u64;
+    let mut mask = 0x80u64;
+    let mut len = 0;
+    while len < 8 {
+        if value < 1u64 << (7 * (len + 1)) {
+            first |= value >> (8 * len);
+            break;
+        }
+        first |= mask;
+        mask >>= 1;
+        len += 1;
+    }
+    out.push(first as u8);
+    out.extend(&value.to_le_bytes()[..len]);
+}
+
+fn unsupported() -> ArchiveError {
+    ArchiveError::MsgError(String::from("暂不支持创建该格式"))
+}
+
+fn io_error(e: io::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+fn zip_error(e: zip::result::ZipError) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+fn sevenz_error(e: sevenz_rust::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+fn xz_error(e: xz2::stream::Error) -> ArchiveError {
+    ArchiveError::MsgError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_handler;
+    use crate::preview::ByteRange;
+    use crate::testutil::temp_dir;
+
+    #[test]
+    fn test_create_archive() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "hello").unwrap();
+        fs::write(src.join("empty"), "").unwrap();
+        fs::write(src.join("sub/b.txt"), "world\n".repeat(100)).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("../a.txt", src.join("sub/link")).unwrap();
+
+        let sources = vec![src.clone()];
+        let method = |method| CompressionOptions {
+            method,
+            ..Default::default()
+        };
+        let level = |level| CompressionOptions {
+            level,
+            ..Default::default()
+        };
+        let solid = |size| CompressionOptions {
+            solid_block_size: Some(size),
+            ..Default::default()
+        };
+        let threads = CompressionOptions {
+            threads: 0,
+            dictionary_size: Some(1 << 20),
+            ..Default::default()
+        };
+        for (ext, compression) in [
+            ("zip", CompressionOptions::default()),
+            ("store.zip", method(ZipMethod::Store)),
+            ("bzip2.zip", method(ZipMethod::Bzip2)),
+            ("zstd.zip", method(ZipMethod::Zstd)),
+            ("level.zip", level(CompressionLevel::Store)),
+            ("tar", CompressionOptions::default()),
+            ("tar.gz", level(CompressionLevel::Ultra)),
+            ("tar.bz2", level(CompressionLevel::Fastest)),
+            ("tar.xz", CompressionOptions::default()),
+            ("mt.tar.xz", threads),
+            ("tar.zst", level(CompressionLevel::Maximum)),
+            ("mt.tar.zst", threads),
+            ("7z", level(CompressionLevel::Fastest)),
+            ("solid.7z", solid(1)),
+            ("block.7z", solid(1 << 20)),
+        ] {
+            let output = dir.join(format!("out.{}", ext));
+            let format = ArchiveFormat::from_path(&output);
+            let options = CreateOptions {
+                compression,
+                ..Default::default()
+            };
+            let mut last = CreateProgress::default();
+            create_archive(&sources, &output, format, &options, |p| last = p.clone()).unwrap();
+            assert_eq!(last.entries_done, last.entries_total, "{}", ext);
+            assert_eq!(last.bytes_done, 605, "{}", ext);
+
+            let mut handler = create_handler(&output, format, String::new());
+            let mut names = handler
+                .entries()
+                .unwrap()
+                .map(|e| e.unwrap().path.trim_end_matches('/').to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+            let mut expected = vec!["src", "src/a.txt", "src/empty", "src/sub", "src/sub/b.txt"];
+            #[cfg(unix)]
+            expected.push("src/sub/link");
+            expected.sort();
+            assert_eq!(names, expected, "{}", ext);
+            for (name, data) in [("src/a.txt", "hello"), ("src/empty", "")] {
+                let mut out = Vec::new();
+                handler
+                    .read_entry(name, ByteRange::default(), &mut out)
+                    .unwrap();
+                assert_eq!(out, data.as_bytes(), "{}", ext);
+            }
+
+            let target = dir.join(format!("x-{}", ext));
+            handler
+                .extract(&target, &crate::ExtractOptions::default())
+                .unwrap();
+            assert_eq!(
+                fs::read_to_string(target.join("src/sub/b.txt")).unwrap(),
+                "world\n".repeat(100)
+            );
+            #[cfg(unix)]
+            assert_eq!(
+                fs::read_link(target.join("src/sub/link")).unwrap(),
+                Path::new("../a.txt"),
+                "{}",
+                ext
+            );
+        }
+
+        // 压缩级别生效；同一固实块中的条目共用一个数据流
+        let size = |ext: &str| {
+            fs::metadata(dir.join(format!("out.{}", ext)))
+                .unwrap()
+                .len()
+        };
+        assert!(size("level.zip") > size("zip"));
+        assert!(size("block.7z") < size("solid.7z"));
+        for (dictionary_size, solid_block_size) in [(Some(1024), None), (None, Some(0))] {
+            let options = CreateOptions {
+                compression: CompressionOptions {
+                    dictionary_size,
+                    solid_block_size,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let output = dir.join("invalid.7z");
+            let result =
+                create_archive(&sources, &output, ArchiveFormat::SevenZip, &options, |_| {});
+            assert!(result.is_err());
+        }
+        let capabilities = format_capabilities();
+        assert!(capabilities
+            .iter()
+            .all(
+                |c| ArchiveFormat::from_path(Path::new(&format!("a.{}", c.extension))) == c.format
+            ));
+        assert!(capabilities
+            .iter()
+            .any(|c| c.format == ArchiveFormat::SevenZip && c.solid_block_size));
+
+        // 输出位于来源目录中时不打包自身
+        let output = src.join("self.zip");
+        create_archive(
+            &sources,
+            &output,
+            ArchiveFormat::Zip,
+            &Default::default(),
+            |_| {},
+        )
+        .unwrap();
+        create_archive(
+            &sources,
+            &output,
+            ArchiveFormat::Zip,
+            &Default::default(),
+            |_| {},
+        )
+        .unwrap();
+        let mut handler = create_handler(&output, ArchiveFormat::Zip, String::new());
+        assert!(handler
+            .entries()
+            .unwrap()
+            .all(|e| e.unwrap().path != "src/self.zip"));
+        assert!(create_archive(
+            &[src],
+            &dir.join("x.rar"),
+            ArchiveFormat::Rar,
+            &Default::default(),
+            |_| {}
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_create_deterministic() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("bin")).unwrap();
+        fs::write(src.join("a.txt"), "hello").unwrap();
+        fs::write(src.join("bin/run"), "#!/bin/sh\n").unwrap();
+        let sources = vec![src.clone()];
+        let options = CreateOptions {
+            deterministic: true,
+            source_date_epoch: Some(1_700_000_000),
+            ..Default::default()
+        };
+
+        #[cfg(unix)]
+        let set_modes = |a: u32, run: u32| {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(src.join("a.txt"), fs::Permissions::from_mode(a)).unwrap();
+            fs::set_permissions(src.join("bin/run"), fs::Permissions::from_mode(run)).unwrap();
+        };
+        for ext in ["zip", "tar.gz", "tar.xz", "7z"] {
+            let output = |n| dir.join(format!("{}.{}", n, ext));
+            let format = ArchiveFormat::from_path(&output(1));
+            let mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+            filetime::set_file_mtime(src.join("a.txt"), mtime).unwrap();
+            #[cfg(unix)]
+            set_modes(0o664, 0o775);
+            create_archive(&sources, &output(1), format, &options, |_| {}).unwrap();
+
+            // 修改时间与权限变化后输出不变
+            filetime::set_file_mtime(src.join("a.txt"), filetime::FileTime::now()).unwrap();
+            #[cfg(unix)]
+            set_modes(0o600, 0o700);
+            create_archive(&sources, &output(2), format, &options, |_| {}).unwrap();
+            assert_eq!(
+                fs::read(output(1)).unwrap(),
+                fs::read(output(2)).unwrap(),
+                "{}",
+                ext
+            );
+        }
+
+        let file = File::open(dir.join("1.tar.gz")).unwrap();
+        let raw = fs::read(dir.join("1.tar.gz")).unwrap();
+        // gzip 头部：时间为 0，系统字节为 255
+        assert_eq!(&raw[4..8], &[0, 0, 0, 0]);
+        assert_eq!(raw[9], 255);
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let mut headers = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let entry = entry.unwrap();
+            let header = entry.header();
+            assert_eq!(header.mtime().unwrap(), 1_700_000_000);
+            assert_eq!((header.uid().unwrap(), header.gid().unwrap()), (0, 0));
+            assert_eq!(header.username().unwrap(), Some(""));
+            headers.push((
+                entry.path().unwrap().to_string_lossy().to_string(),
+                header.mode().unwrap(),
+            ));
+        }
+        let mut expected = vec![
+            ("src/", 0o755),
+            ("src/a.txt", 0o644),
+            ("src/bin/", 0o755),
+            ("src/bin/run", 0o644),
+        ];
+        #[cfg(unix)]
+        {
+            expected[3].1 = 0o755;
+        }
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(name, mode)| (name.to_string(), mode))
+            .collect();
+        assert_eq!(headers, expected);
+
+        let encrypted = CreateOptions {
+            encryption: Some(crate::Encryption {
+                password: Zeroizing::new(String::from("p@ss")),
+                encrypt_header: false,
+            }),
+            ..options
+        };
+        let result = create_archive(
+            &sources,
+            &dir.join("e.zip"),
+            ArchiveFormat::Zip,
+            &encrypted,
+            |_| {},
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_create_encrypted() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let src = dir.join("合同");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("secret.txt"), "top secret").unwrap();
+        let sources = vec![src];
+
+        let encrypted = |encrypt_header| CreateOptions {
+            encryption: Some(crate::Encryption {
+                password: Zeroizing::new(String::from("p@ss")),
+                encrypt_header,
+            }),
+            ..Default::default()
+        };
+        for (name, encrypt_header) in [("a.zip", false), ("a.7z", false), ("h.7z", true)] {
+            let output = dir.join(name);
+            let format = ArchiveFormat::from_path(&output);
+            create_archive(
+                &sources,
+                &output,
+                format,
+                &encrypted(encrypt_header),
+                |_| {},
+            )
+            .unwrap();
+
+            // 7z 未加密头部时不需要密码即可列出条目
+            if format == ArchiveFormat::SevenZip {
+                let listed = create_handler(&output, format, String::new())
+                    .entries()
+                    .and_then(|mut e| e.try_fold(0, |n, e| e.map(|_| n + 1)));
+                assert_eq!(listed.is_ok(), !encrypt_header, "{}", name);
+            }
+
+            for (password, ok) in [("", false), ("wrong", false), ("p@ss", true)] {
+                let mut out = Vec::new();
+                let result = create_handler(&output, format, password.to_string()).read_entry(
+                    "合同/secret.txt",
+                    ByteRange::default(),
+                    &mut out,
+                );
+                match result {
+                    Ok(_) => assert!(ok && out == b"top secret", "{} {}", name, password),
+                    Err(ArchiveError::MsgError(e)) => assert!(!ok, "{} {}: {}", name, password, e),
+                }
+            }
+        }
+
+        let raw = fs::read(dir.join("a.zip")).unwrap();
+        assert!(!raw.windows(10).any(|w| w == b"top secret"));
+        // 加密头部后文件名不再以 UTF-16 明文出现
+        let name: Vec<u8> = "secret".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let contains_name = |file: &str| {
+            let raw = fs::read(dir.join(file)).unwrap();
+            raw.windows(name.len()).any(|w| w == name)
+        };
+        assert!(contains_name("a.7z"));
+        assert!(!contains_name("h.7z"));
+        assert!(create_archive(
+            &sources,
+            &dir.join("a.tar.gz"),
+            ArchiveFormat::TarGz,
+            &encrypted(false),
+            |_| {}
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_create_volumes() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let src = dir.join("data");
+        fs::create_dir_all(&src).unwrap();
+        // 不可压缩的内容，保证输出跨越多个分卷
+        let mut seed = 1u32;
+        let data: Vec<u8> = (0..200_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        fs::write(src.join("random.bin"), &data).unwrap();
+        let sources = vec![src];
+
+        const SIZE: u64 = 64 << 10;
+        for (name, password) in [("a.zip", ""), ("a.tar.gz", ""), ("h.7z", "p@ss")] {
+            let output = dir.join(name);
+            // 上次留下的分卷被替换或删除
+            for i in 0..9 {
+                fs::write(volume::volume_path(&output, i), "stale").unwrap();
+            }
+            let options = CreateOptions {
+                volume_size: Some(SIZE),
+                encryption: (!password.is_empty()).then(|| crate::Encryption {
+                    password: Zeroizing::new(password.to_string()),
+                    encrypt_header: true,
+                }),
+                ..Default::default()
+            };
+            let format = ArchiveFormat::from_path(&output);
+            create_archive(&sources, &output, format, &options, |_| {}).unwrap();
+            assert!(!output.exists());
+
+            let sizes: Vec<u64> = (0..)
+                .map(|i| volume::volume_path(&output, i))
+                .take_while(|path| path.exists())
+                .map(|path| fs::metadata(path).unwrap().len())
+                .collect();
+            assert_eq!(sizes.len(), 4, "{}", name);
+            assert!(sizes[..3].iter().all(|&size| size == SIZE), "{}", name);
+            assert!(sizes[3] > 0 && sizes[3] <= SIZE, "{}", name);
+
+            // 各卷直接拼接即为完整的压缩包
+            let joined = dir.join(format!("joined-{}", name));
+            let mut out = File::create(&joined).unwrap();
+            for i in 0..sizes.len() {
+                let mut volume = File::open(volume::volume_path(&output, i)).unwrap();
+                io::copy(&mut volume, &mut out).unwrap();
+            }
+            drop(out);
+            let mut read = Vec::new();
+            create_handler(&joined, format, password.to_string())
+                .read_entry("data/random.bin", ByteRange::default(), &mut read)
+                .unwrap();
+            assert!(read == data, "{}", name);
+        }
+
+        let options = CreateOptions {
+            volume_size: Some(0),
+            ..Default::default()
+        };
+        assert!(create_archive(
+            &sources,
+            &dir.join("b.zip"),
+            ArchiveFormat::Zip,
+            &options,
+            |_| {}
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,484 @@
+use base64::Engine;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::options::ExtractOptions;
+use super::restore;
+
+/// 扩展属性列表：(名称, 值)
+pub type Attrs = Vec<(String, Vec<u8>)>;
+
+const APPLEDOUBLE_MAGIC: u32 = 0x0005_1607;
+const APPLEDOUBLE_RESOURCE_FORK: u32 = 2;
+const APPLEDOUBLE_FINDER_INFO: u32 = 9;
+const FINDER_INFO_SIZE: usize = 32;
+const ATTR_MAGIC: &[u8; 4] = b"ATTR";
+const ATTR_HEADER_SIZE: usize = 36;
+
+/// 解压时恢复扩展属性、POSIX ACL 与 macOS AppleDouble 附属文件
+///
+/// 仅在 `ExtractOptions::restore_xattrs` 开启时生效。属性写入失败（文件系统不支持、
+/// 权限不足等）只记录警告，不中断解压。属性不写到符号链接上，也不经由链接写到目标目录之外。
+pub struct Xattrs {
+    root: PathBuf,
+    enabled: bool,
+    /// 是否恢复特权命名空间的属性，见 `ExtractOptions::restore_privileged_xattrs`
+    privileged: bool,
+    /// AppleDouble 附属文件，键为其描述的条目路径（压缩包内路径）
+    companions: HashMap<String, Vec<u8>>,
+}
+
+impl Xattrs {
+    pub fn new(root: &Path, options: &ExtractOptions) -> Self {
+        Xattrs {
+            root: root.to_path_buf(),
+            enabled: options.restore_xattrs,
+            privileged: options.restore_privileged_xattrs,
+            companions: HashMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 开启时，若条目为 AppleDouble 附属文件则返回其对应条目的路径
+    pub fn companion_target(&self, path: &str) -> Option<String> {
+        self.enabled.then(|| appledouble_target(path)).flatten()
+    }
+
+    /// 把属性写到已解压的条目上
+    pub fn apply(&self, path: &Path, attrs: &Attrs) {
+        if !self.enabled {
+            return;
+        }
+        if std::fs::symlink_metadata(path).map_or(true, |m| m.file_type().is_symlink()) {
+            return;
+        }
+        for (name, value) in attrs {
+            let name = host_name(name);
+            if !self.privileged && !unprivileged(&name) {
+                log::warn!("跳过特权命名空间的扩展属性 {}: {}", name, path.display());
+                continue;
+            }
+            if let Err(e) = set_attr(path, &name, value)
+                .or_else(|e| retry_writable(path, e, || set_attr(path, &name, value)))
+            {
+                log::warn!("恢复扩展属性 {} 失败 {}: {}", name, path.display(), e);
+            }
+        }
+    }
+
+    /// 记录 AppleDouble 附属文件，`target` 为其对应条目在压缩包内的路径；
+    /// 数据不是 AppleDouble 格式时原样返回，由调用方按普通文件处理
+    pub fn companion(&mut self, target: String, data: Vec<u8>) -> Option<Vec<u8>> {
+        if !is_appledouble(&data) {
+            return Some(data);
+        }
+        self.companions.insert(target, data);
+        None
+    }
+
+    /// 合并所有 AppleDouble 附属文件
+    pub fn finish(self) {
+        for (target, data) in &self.companions {
+            let path = match restore::safe_join(&self.root, target) {
+                Some(path) if self.contains_real(&path) => path,
+                _ => continue,
+            };
+            match parse_appledouble(data) {
+                Some(attrs) => self.apply(&path, &attrs),
+                None => log::warn!("无法解析 AppleDouble 文件: {}", target),
+            }
+        }
+    }
+
+    /// 附属文件在链接创建之后才合并，确认条目的上级目录没有经由链接指向目标目录之外
+    fn contains_real(&self, path: &Path) -> bool {
+        let real = |p: &Path| std::fs::canonicalize(p).ok();
+        match (path.parent().and_then(real), real(&self.root)) {
+            (Some(parent), Some(root)) => {
+                parent.starts_with(root) && path.symlink_metadata().is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 判断条目是否为 AppleDouble 附属文件，是则返回其对应条目的路径
+///
+/// 支持 zip 的 `__MACOSX/dir/._name` 与 tar 中并列的 `dir/._name` 两种形式。
+pub fn appledouble_target(path: &str) -> Option<String> {
+    let path = path.strip_prefix("__MACOSX/").unwrap_or(path);
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, path),
+    };
+    let name = name.strip_prefix("._").filter(|n| !n.is_empty())?;
+    Some(match dir {
+        Some(dir) => format!("{}/{}", dir, name),
+        None => name.to_string(),
+    })
+}
+
+/// 数据是否以 AppleDouble 魔数开头
+fn is_appledouble(data: &[u8]) -> bool {
+    data.len() >= 4 && be_u32(data, 0) == Some(APPLEDOUBLE_MAGIC)
+}
+
+/// 从 tar PAX 记录中读取扩展属性与 ACL
+///
+/// 支持 `SCHILY.xattr.*`、libarchive 的 `LIBARCHIVE.xattr.*`（URL 编码名称 + base64 值）
+/// 以及 `SCHILY.acl.access` / `SCHILY.acl.default`（仅 Linux）。
+pub fn from_pax<'a>(records: impl Iterator<Item = (&'a str, &'a [u8])>) -> Attrs {
+    let mut attrs = Attrs::new();
+    for (key, value) in records {
+        if let Some(name) = key.strip_prefix("SCHILY.xattr.") {
+            attrs.push((name.to_string(), value.to_vec()));
+        } else if let Some(name) = key.strip_prefix("LIBARCHIVE.xattr.") {
+            let value = std::str::from_utf8(value).ok().and_then(|v| {
+                base64::engine::general_purpose::STANDARD_NO_PAD
+                    .decode(v.trim_end().trim_end_matches('='))
+                    .ok()
+            });
+            if let (Some(name), Some(value)) = (url_decode(name), value) {
+                attrs.push((name, value));
+            }
+        } else if cfg!(target_os = "linux") {
+            let name = match key {
+                "SCHILY.acl.access" => "system.posix_acl_access",
+                "SCHILY.acl.default" => "system.posix_acl_default",
+                _ => continue,
+            };
+            match acl_from_text(&String::from_utf8_lossy(value)) {
+                Some(acl) => attrs.push((name.to_string(), acl)),
+                None => log::warn!("无法解析 ACL: {}", String::from_utf8_lossy(value)),
+            }
+        }
+    }
+    attrs
+}
+
+/// 解析 AppleDouble 文件：Finder 信息、资源分支以及 `ATTR` 段中的扩展属性
+pub fn parse_appledouble(data: &[u8]) -> Option<Attrs> {
+    if !is_appledouble(data) {
+        return None;
+    }
+    let count = be_u16(data, 24)? as usize;
+    let mut attrs = Attrs::new();
+    for i in 0..count {
+        let base = 26 + i * 12;
+        let id = be_u32(data, base)?;
+        let offset = be_u32(data, base + 4)? as usize;
+        let length = be_u32(data, base + 8)? as usize;
+        let body = data.get(offset..offset + length)?;
+        match id {
+            APPLEDOUBLE_RESOURCE_FORK if !body.is_empty() => {
+                attrs.push(("com.apple.ResourceFork".to_string(), body.to_vec()));
+            }
+            APPLEDOUBLE_FINDER_INFO => {
+                let finder_info = body.get(..FINDER_INFO_SIZE)?;
+                if finder_info.iter().any(|&b| b != 0) {
+                    attrs.push(("com.apple.FinderInfo".to_string(), finder_info.to_vec()));
+                }
+                // Finder 信息后跟 2 字节填充，再是 ATTR 头
+                attrs.extend(parse_attr_section(data, offset + FINDER_INFO_SIZE + 2));
+            }
+            _ => {}
+        }
+    }
+    Some(attrs)
+}
+
+fn parse_attr_section(data: &[u8], start: usize) -> Attrs {
+    let mut attrs = Attrs::new();
+    if data.get(start..start + 4) != Some(ATTR_MAGIC.as_slice()) {
+        return attrs;
+    }
+    let count = be_u16(data, start + ATTR_HEADER_SIZE - 2).unwrap_or(0) as usize;
+    let mut pos = start + ATTR_HEADER_SIZE;
+    for _ in 0..count {
+        let (offset, length, name_len) =
+            match (be_u32(data, pos), be_u32(data, pos + 4), data.get(pos + 10)) {
+                (Some(offset), Some(length), Some(&name_len)) => {
+                    (offset as usize, length as usize, name_len as usize)
+                }
+                _ => break,
+            };
+        let name = match data.get(pos + 11..pos + 11 + name_len) {
+            Some(name) => String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string(),
+            None => break,
+        };
+        if let Some(value) = data.get(offset..offset + length) {
+            attrs.push((name, value.to_vec()));
+        }
+        // 每项按 4 字节对齐
+        pos = (pos + 11 + name_len + 3) & !3;
+    }
+    attrs
+}
+
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+const ACL_XATTR_VERSION: u32 = 2;
+
+/// 把 ACL 文本（`user::rw-,user:alice:r--:1001,...`）转成 `system.posix_acl_*` 的二进制格式
+pub fn acl_from_text(text: &str) -> Option<Vec<u8>> {
+    let mut entries = Vec::new();
+    for line in text.split([',', '\n']) {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() < 3 {
+            return None;
+        }
+        let (tag_name, qualifier, perms) = (fields[0], fields[1], fields[2]);
+        let named = !qualifier.is_empty();
+        let tag: u16 = match (tag_name, named) {
+            ("user" | "u", false) => 0x01,
+            ("user" | "u", true) => 0x02,
+            ("group" | "g", false) => 0x04,
+            ("group" | "g", true) => 0x08,
+            ("mask" | "m", _) => 0x10,
+            ("other" | "o", _) => 0x20,
+            _ => return None,
+        };
+        let id = if named {
+            // star 在第四列附带数字 id
+            fields
+                .get(3)
+                .and_then(|id| id.parse().ok())
+                .or_else(|| qualifier.parse().ok())
+                .or_else(|| lookup_id(qualifier, tag == 0x02))?
+        } else {
+            ACL_UNDEFINED_ID
+        };
+        let perm = perms.chars().fold(0u16, |acc, c| match c {
+            'r' => acc | 4,
+            'w' => acc | 2,
+            'x' => acc | 1,
+            _ => acc,
+        });
+        entries.push((tag, perm, id));
+    }
+    // 内核要求按标签、再按 id 排序
+    entries.sort_by_key(|&(tag, _, id)| (tag, id));
+
+    let mut buf = ACL_XATTR_VERSION.to_le_bytes().to_vec();
+    for (tag, perm, id) in entries {
+        buf.extend_from_slice(&tag.to_le_bytes());
+        buf.extend_from_slice(&perm.to_le_bytes());
+        buf.extend_from_slice(&id.to_le_bytes());
+    }
+    Some(buf)
+}
+
+#[cfg(unix)]
+fn lookup_id(name: &str, is_user: bool) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: name 为合法的 C 字符串，返回的指针只在本次调用中读取
+    unsafe {
+        if is_user {
+            let pw = libc::getpwnam(name.as_ptr());
+            (!pw.is_null()).then(|| (*pw).pw_uid)
+        } else {
+            let gr = libc::getgrnam(name.as_ptr());
+            (!gr.is_null()).then(|| (*gr).gr_gid)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn lookup_id(_name: &str, _is_user: bool) -> Option<u32> {
+    None
+}
+
+/// 只读文件无法写入 `user.` 属性，临时加上属主写权限后重试
+#[cfg(unix)]
+fn retry_writable(
+    path: &Path,
+    err: std::io::Error,
+    f: impl FnOnce() -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let meta = std::fs::symlink_metadata(path)?;
+    if err.kind() != std::io::ErrorKind::PermissionDenied || meta.file_type().is_symlink() {
+        return Err(err);
+    }
+    let mode = meta.permissions().mode();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode | 0o200))?;
+    let result = f();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    result
+}
+
+#[cfg(not(unix))]
+fn retry_writable(
+    _path: &Path,
+    err: std::io::Error,
+    _f: impl FnOnce() -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    Err(err)
+}
+
+/// `xattr::set` 使用 `lsetxattr`，不跟随符号链接
+#[cfg(unix)]
+fn set_attr(path: &Path, name: &str, value: &[u8]) -> std::io::Result<()> {
+    xattr::set(path, name, value)
+}
+
+#[cfg(not(unix))]
+fn set_attr(_path: &Path, _name: &str, _value: &[u8]) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "当前平台不支持扩展属性",
+    ))
+}
+
+/// Linux 的属性名必须带命名空间，来自 macOS 的属性放到 `user.` 下
+#[cfg(target_os = "linux")]
+fn host_name(name: &str) -> String {
+    const NAMESPACES: [&str; 4] = ["user.", "trusted.", "security.", "system."];
+    if NAMESPACES.iter().any(|ns| name.starts_with(ns)) {
+        name.to_string()
+    } else {
+        format!("user.{}", name)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn host_name(name: &str) -> String {
+    name.to_string()
+}
+
+/// 无需特权即可设置的属性：`user.` 命名空间与 POSIX ACL；其他平台没有命名空间，全部放行
+fn unprivileged(name: &str) -> bool {
+    !cfg!(target_os = "linux")
+        || name.starts_with("user.")
+        || name == "system.posix_acl_access"
+        || name == "system.posix_acl_default"
+}
+
+fn url_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appledouble_target() {
+        assert_eq!(
+            appledouble_target("__MACOSX/dir/._a.txt"),
+            Some("dir/a.txt".to_string())
+        );
+        assert_eq!(appledouble_target("._a.txt"), Some("a.txt".to_string()));
+        assert_eq!(appledouble_target("dir/a.txt"), None);
+        assert_eq!(appledouble_target("dir/._"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_unprivileged() {
+        assert!(unprivileged(&host_name("com.apple.quarantine")));
+        assert!(unprivileged("user.mime_type"));
+        assert!(unprivileged("system.posix_acl_access"));
+        assert!(!unprivileged("security.capability"));
+        assert!(!unprivileged("trusted.overlay.opaque"));
+        assert!(!unprivileged("system.nfs4_acl"));
+    }
+
+    #[test]
+    fn test_acl_from_text() {
+        let acl =
+            acl_from_text("user::rw-,user:alice:r--:1001,group::r--,mask::r--,other::---").unwrap();
+        let mut expected = 2u32.to_le_bytes().to_vec();
+        for (tag, perm, id) in [
+            (0x01u16, 6u16, u32::MAX),
+            (0x02, 4, 1001),
+            (0x04, 4, u32::MAX),
+            (0x10, 4, u32::MAX),
+            (0x20, 0, u32::MAX),
+        ] {
+            expected.extend_from_slice(&tag.to_le_bytes());
+            expected.extend_from_slice(&perm.to_le_bytes());
+            expected.extend_from_slice(&id.to_le_bytes());
+        }
+        assert_eq!(acl, expected);
+        assert_eq!(acl_from_text("bogus::rw-"), None);
+    }
+
+    #[test]
+    fn test_parse_appledouble() {
+        // 头部 + 1 个 Finder 信息条目，后接 ATTR 段中的一个属性
+        let mut data = Vec::new();
+        data.extend_from_slice(&APPLEDOUBLE_MAGIC.to_be_bytes());
+        data.extend_from_slice(&0x0002_0000u32.to_be_bytes());
+        data.extend_from_slice(&[0u8; 16]);
+        data.extend_from_slice(&1u16.to_be_bytes());
+        let finder_offset = 26 + 12 + 2; // 条目表后补齐到 0x28
+        data.extend_from_slice(&APPLEDOUBLE_FINDER_INFO.to_be_bytes());
+        data.extend_from_slice(&(finder_offset as u32).to_be_bytes());
+        let length_pos = data.len();
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&[0u8; 2]);
+        assert_eq!(data.len(), finder_offset);
+
+        let mut finder_info = [0u8; 32];
+        finder_info[0..4].copy_from_slice(b"TEXT");
+        data.extend_from_slice(&finder_info);
+        data.extend_from_slice(&[0u8; 2]);
+
+        let attr_start = data.len();
+        let name = b"com.apple.quarantine\0";
+        let value = b"0081;abc";
+        let entry_len = (11 + name.len() + 3) & !3;
+        let value_offset = attr_start + ATTR_HEADER_SIZE + entry_len;
+        data.extend_from_slice(ATTR_MAGIC);
+        data.extend_from_slice(&[0u8; 30]);
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&(value_offset as u32).to_be_bytes());
+        data.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.push(name.len() as u8);
+        data.extend_from_slice(name);
+        data.resize(value_offset, 0);
+        data.extend_from_slice(value);
+        let total = (data.len() - finder_offset) as u32;
+        data[length_pos..length_pos + 4].copy_from_slice(&total.to_be_bytes());
+
+        let attrs = parse_appledouble(&data).unwrap();
+        assert_eq!(
+            attrs,
+            vec![
+                ("com.apple.FinderInfo".to_string(), finder_info.to_vec()),
+                ("com.apple.quarantine".to_string(), value.to_vec()),
+            ]
+        );
+    }
+}
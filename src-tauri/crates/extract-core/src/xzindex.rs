@@ -0,0 +1,282 @@
+//! xz 随机访问
+//!
+//! xz 文件末尾的索引记录了每个块的压缩与解压大小，多线程压缩（`xz -T`）会产生多个块。
+//! 从某个块开始解压时，用原流头、该块之后的所有块，加上重新生成的索引和流尾拼成一个
+//! 合法的 xz 流，交给普通的解码器即可。
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+const HEADER_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0];
+const FOOTER_MAGIC: [u8; 2] = [b'Y', b'Z'];
+/// 流头与流尾的长度
+const HEADER_SIZE: u64 = 12;
+
+/// 一个 xz 块
+#[derive(Debug, Clone)]
+pub(crate) struct XzBlock {
+    /// 块在文件中的位置
+    pub offset: u64,
+    /// 不含末尾填充的块大小
+    pub unpadded: u64,
+    /// 块解压后在整个流中的位置
+    pub output: u64,
+    pub uncompressed: u64,
+}
+
+/// 单个 xz 流的块布局
+#[derive(Debug, Clone)]
+pub(crate) struct XzLayout {
+    pub header: [u8; 12],
+    pub blocks: Vec<XzBlock>,
+}
+
+impl XzLayout {
+    /// 读取流头与末尾的索引；不是单个流（如多个流拼接）时返回 `None`
+    pub fn read<R: Read + Seek>(file: &mut R) -> io::Result<Option<Self>> {
+        let mut header = [0u8; 12];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        if header[..6] != HEADER_MAGIC {
+            return Ok(None);
+        }
+
+        // 跳过流尾之后的 4 字节对齐填充
+        let mut end = file.seek(SeekFrom::End(0))?;
+        let mut footer = [0u8; 12];
+        loop {
+            if end < 2 * HEADER_SIZE {
+                return Ok(None);
+            }
+            file.seek(SeekFrom::Start(end - HEADER_SIZE))?;
+            file.read_exact(&mut footer)?;
+            if footer[8..] == [0; 4] {
+                end -= 4;
+                continue;
+            }
+            break;
+        }
+        if footer[10..] != FOOTER_MAGIC || footer[8..10] != header[6..8] {
+            return Ok(None);
+        }
+        let backward = (u32::from_le_bytes(footer[4..8].try_into().unwrap()) as u64 + 1) * 4;
+        let index_start = match (end - HEADER_SIZE).checked_sub(backward) {
+            Some(start) if start >= HEADER_SIZE => start,
+            _ => return Ok(None),
+        };
+
+        let mut index = vec![0u8; backward as usize];
+        file.seek(SeekFrom::Start(index_start))?;
+        file.read_exact(&mut index)?;
+        let Some(records) = parse_index(&index) else {
+            return Ok(None);
+        };
+
+        let (mut offset, mut output) = (HEADER_SIZE, 0);
+        let mut blocks = Vec::with_capacity(records.len());
+        for (unpadded, uncompressed) in records {
+            blocks.push(XzBlock {
+                offset,
+                unpadded,
+                output,
+                uncompressed,
+            });
+            offset += pad4(unpadded);
+            output += uncompressed;
+        }
+        // 块必须正好铺满流头与索引之间
+        if offset != index_start {
+            return Ok(None);
+        }
+        Ok(Some(XzLayout { header, blocks }))
+    }
+
+    /// 包含 `offset` 的块
+    pub fn block_for(&self, offset: u64) -> Option<&XzBlock> {
+        let i = self.blocks.partition_point(|b| b.output <= offset);
+        self.blocks[..i].last()
+    }
+
+    /// 从 `block` 开始的合法 xz 流
+    pub fn stream_from<R: Read + Seek>(
+        &self,
+        mut file: R,
+        block: &XzBlock,
+    ) -> io::Result<impl Read> {
+        let rest: Vec<&XzBlock> = self
+            .blocks
+            .iter()
+            .filter(|b| b.offset >= block.offset)
+            .collect();
+        let end = rest
+            .last()
+            .map(|b| b.offset + pad4(b.unpadded))
+            .unwrap_or(block.offset);
+        file.seek(SeekFrom::Start(block.offset))?;
+
+        let mut tail = encode_index(&rest);
+        let backward = (tail.len() as u32 / 4 - 1).to_le_bytes();
+        let mut stored = Vec::with_capacity(6);
+        stored.extend_from_slice(&backward);
+        stored.extend_from_slice(&self.header[6..8]);
+        tail.extend_from_slice(&crc32fast::hash(&stored).to_le_bytes());
+        tail.extend_from_slice(&stored);
+        tail.extend_from_slice(&FOOTER_MAGIC);
+
+        Ok(Cursor::new(self.header)
+            .chain(file.take(end - block.offset))
+            .chain(Cursor::new(tail)))
+    }
+}
+
+/// LZMA2 过滤器的编号
+const FILTER_LZMA2: u64 = 0x21;
+
+/// 从文件开头的字节中读出第一个块的 LZMA2 字典大小，不是 xz 或读不出时返回 `None`
+pub(crate) fn lzma2_dict_size(head: &[u8]) -> Option<u32> {
+    if head.get(..6)? != HEADER_MAGIC {
+        return None;
+    }
+    // 块头的第一个字节为 (长度 / 4 - 1)，为 0 时表示没有块、直接是索引
+    let block = head.get(HEADER_SIZE as usize..)?;
+    let size = match *block.first()? {
+        0 => return None,
+        n => (n as usize + 1) * 4,
+    };
+    let block = block.get(..size)?;
+    let flags = *block.get(1)?;
+    let mut pos = 2;
+    // 可选的压缩大小与解压大小
+    for bit in [0x40, 0x80] {
+        if flags & bit != 0 {
+            read_varint(block, &mut pos)?;
+        }
+    }
+    for _ in 0..=(flags & 0x03) {
+        let id = read_varint(block, &mut pos)?;
+        let len = read_varint(block, &mut pos)? as usize;
+        let props = block.get(pos..pos.checked_add(len)?)?;
+        pos += len;
+        if id == FILTER_LZMA2 {
+            return match *props.first()? as u32 & 0x3F {
+                40 => Some(u32::MAX),
+                bits @ 0..=39 => Some((2 | (bits & 1)) << (bits / 2 + 11)),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+fn pad4(n: u64) -> u64 {
+    (n + 3) & !3
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// 解析索引，返回每个块的 (不含填充的大小, 解压大小)
+fn parse_index(index: &[u8]) -> Option<Vec<(u64, u64)>> {
+    let (body, crc) = index.split_at(index.len().checked_sub(4)?);
+    if index.first() != Some(&0) || crc32fast::hash(body).to_le_bytes() != crc {
+        return None;
+    }
+    let mut pos = 1;
+    let count = read_varint(body, &mut pos)?;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let unpadded = read_varint(body, &mut pos)?;
+        let uncompressed = read_varint(body, &mut pos)?;
+        records.push((unpadded, uncompressed));
+    }
+    Some(records)
+}
+
+fn encode_index(blocks: &[&XzBlock]) -> Vec<u8> {
+    let mut index = vec![0u8];
+    write_varint(&mut index, blocks.len() as u64);
+    for block in blocks {
+        write_varint(&mut index, block.unpadded);
+        write_varint(&mut index, block.uncompressed);
+    }
+    while index.len() % 4 != 0 {
+        index.push(0);
+    }
+    let crc = crc32fast::hash(&index);
+    index.extend_from_slice(&crc.to_le_bytes());
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use xz2::read::XzDecoder;
+    use xz2::stream::MtStreamBuilder;
+    use xz2::write::XzEncoder;
+
+    fn xz(data: &[u8], block_size: u64) -> Vec<u8> {
+        let stream = MtStreamBuilder::new()
+            .threads(2)
+            .block_size(block_size)
+            .preset(1)
+            .encoder()
+            .unwrap();
+        let mut encoder = XzEncoder::new_stream(Vec::new(), stream);
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_stream_from_block() {
+        let data: Vec<u8> = (0..1u32 << 20)
+            .flat_map(|i| (i % 251).to_le_bytes())
+            .collect();
+        let file = xz(&data, 1 << 20);
+
+        let layout = XzLayout::read(&mut Cursor::new(&file)).unwrap().unwrap();
+        assert_eq!(layout.blocks.len(), 4);
+        assert_eq!(
+            layout.blocks.iter().map(|b| b.uncompressed).sum::<u64>(),
+            data.len() as u64
+        );
+        assert_eq!(layout.block_for(0).unwrap().output, 0);
+        assert_eq!(layout.block_for((3 << 20) + 5).unwrap().output, 3 << 20);
+        // 预设 1 的字典为 1 MiB
+        assert_eq!(lzma2_dict_size(&file), Some(1 << 20));
+        assert_eq!(lzma2_dict_size(&data), None);
+
+        // 从每个块开始拼出的流都能完整解压并通过校验
+        for block in &layout.blocks {
+            let stream = layout.stream_from(Cursor::new(&file), block).unwrap();
+            let mut rest = Vec::new();
+            XzDecoder::new(stream).read_to_end(&mut rest).unwrap();
+            assert!(rest == data[block.output as usize..]);
+        }
+
+        // 多个流拼接、或不是 xz 时不使用块布局
+        let mut joined = file.clone();
+        joined.extend(xz(b"more", 1 << 20));
+        assert!(XzLayout::read(&mut Cursor::new(&joined)).unwrap().is_none());
+        assert!(XzLayout::read(&mut Cursor::new(&data[..64]))
+            .unwrap()
+            .is_none());
+    }
+}
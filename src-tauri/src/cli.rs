@@ -0,0 +1,446 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use extract_core::{
+    compare_archive, diff_archives, nested, open_handler, salvage_archive, ArchiveEntry,
+    ArchiveError, ArchiveFormat, ArchiveHandler, DiffEntry, DiffOptions, DiffStatus,
+    ExtractOptions, RecursiveExtract, SalvageOptions, TreeNode,
+};
+
+/// 命令行子命令，跳过开头的选项后第一个参数为这些名称时不启动窗口
+const SUBCOMMANDS: [&str; 8] = [
+    "list", "x", "test", "info", "compare", "diff", "repair", "help",
+];
+
+// 退出码：0 成功，2 参数错误（clap 默认）
+const EXIT_FAILURE: i32 = 1;
+const EXIT_TEST_FAILED: i32 = 3;
+const EXIT_PASSWORD: i32 = 4;
+const EXIT_UNSUPPORTED: i32 = 5;
+
+#[derive(Parser)]
+#[command(name = "extract", version, about = "查看、校验与解压压缩包")]
+struct Cli {
+    /// 以 JSON 输出结果
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 列出压缩包中的条目
+    List {
+        archive: PathBuf,
+        #[arg(short, long, default_value = "")]
+        password: String,
+    },
+    /// 解压到指定目录
+    X {
+        archive: PathBuf,
+        /// 目标目录
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(short, long, default_value = "")]
+        password: String,
+        /// 只解压匹配的条目（glob，可多次指定）
+        #[arg(long)]
+        include: Vec<String>,
+        /// 跳过匹配的条目（glob，可多次指定）
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// 把解压出的压缩包继续解压到同名目录
+        #[arg(short, long)]
+        recursive: bool,
+        /// 递归解压的最大层数
+        #[arg(long, default_value_t = 4, requires = "recursive")]
+        max_depth: usize,
+        /// 递归解压出的内容总大小上限（MiB）
+        #[arg(long, default_value_t = 8192, requires = "recursive")]
+        max_size: u64,
+        /// 递归解压后删除中间的压缩包
+        #[arg(long, requires = "recursive")]
+        delete_archives: bool,
+    },
+    /// 校验所有条目
+    Test {
+        archive: PathBuf,
+        #[arg(short, long, default_value = "")]
+        password: String,
+    },
+    /// 显示压缩包信息
+    Info {
+        archive: PathBuf,
+        #[arg(short, long, default_value = "")]
+        password: String,
+    },
+    /// 比较压缩包与目录：缺失、多出、内容或元数据不同的文件
+    Compare {
+        archive: PathBuf,
+        directory: PathBuf,
+        #[arg(short, long, default_value = "")]
+        password: String,
+    },
+    /// 比较两个压缩包：新增、删除、内容或元数据不同的条目
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        #[arg(long, default_value = "")]
+        old_password: String,
+        #[arg(long, default_value = "")]
+        new_password: String,
+        /// 输出内容不同的文本条目的统一格式 diff
+        #[arg(short, long)]
+        unified: bool,
+    },
+    /// 从损坏的压缩包中找回完好的条目（zip 与 tar）
+    Repair {
+        archive: PathBuf,
+        /// 把完好的条目解压到该目录
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 把完好的条目写入新的压缩包
+        #[arg(long)]
+        rebuild: Option<PathBuf>,
+        #[arg(short, long, default_value = "")]
+        password: String,
+    },
+}
+
+/// 写到标准输出；管道被关闭（如 `| head`）时静默忽略，不 panic
+macro_rules! out {
+    ($($arg:tt)*) => {{
+        let _ = writeln!(std::io::stdout(), $($arg)*);
+    }};
+}
+
+/// 命令行错误：退出码与提示信息
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl From<ArchiveError> for Failure {
+    fn from(e: ArchiveError) -> Self {
+        let ArchiveError::MsgError(message) = e;
+        let code = if message == "密码错误" {
+            EXIT_PASSWORD
+        } else {
+            EXIT_FAILURE
+        };
+        Failure { code, message }
+    }
+}
+
+/// 第一个参数为子命令时以命令行模式运行并返回退出码，否则返回 `None` 由调用方启动窗口
+///
+/// 全局选项可以写在子命令之前，如 `extract --json list a.zip`，判断时跳过开头以 `-` 起始的参数。
+pub fn run() -> Option<i32> {
+    let first = std::env::args().skip(1).find(|arg| !arg.starts_with('-'))?;
+    if !SUBCOMMANDS.contains(&first.as_str()) {
+        return None;
+    }
+    attach_console();
+
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return Some(e.exit_code());
+        }
+    };
+    Some(match execute(&cli) {
+        Ok(code) => code,
+        Err(failure) => {
+            if cli.json {
+                print_json(&json!({ "error": failure.message, "code": failure.code }));
+            } else {
+                eprintln!("错误: {}", failure.message);
+            }
+            failure.code
+        }
+    })
+}
+
+fn execute(cli: &Cli) -> Result<i32, Failure> {
+    match &cli.command {
+        Command::List { archive, password } => {
+            let mut handler = open(archive, password)?;
+            // JSON 模式读完再输出，中途出错时只输出错误，保证输出总是完整的 JSON
+            if cli.json {
+                let entries = handler.entries()?.collect::<Result<Vec<_>, _>>()?;
+                print_json(&entries);
+                return Ok(0);
+            }
+            // 文本模式边读边输出
+            let mut stdout = std::io::stdout().lock();
+            for entry in handler.entries()? {
+                print_entry(&mut stdout, &entry?);
+            }
+            Ok(0)
+        }
+        Command::X {
+            archive,
+            output,
+            password,
+            include,
+            exclude,
+            recursive,
+            max_depth,
+            max_size,
+            delete_archives,
+        } => {
+            let options = ExtractOptions {
+                include: include.clone(),
+                exclude: exclude.clone(),
+                recursive: recursive.then(|| RecursiveExtract {
+                    max_depth: *max_depth,
+                    max_size: max_size.saturating_mul(1 << 20),
+                    delete_archives: *delete_archives,
+                }),
+                ..Default::default()
+            };
+            open(archive, password)?.extract(output, &options)?;
+            if cli.json {
+                print_json(&json!({ "archive": archive, "output": output }));
+            }
+            Ok(0)
+        }
+        Command::Test { archive, password } => {
+            let report = open(archive, password)?.test()?;
+            if cli.json {
+                print_json(&report);
+            } else {
+                for failure in &report.failed {
+                    out!("失败  {}: {}", failure.path, failure.error);
+                }
+                out!(
+                    "已测试 {} 个条目，{} 个错误",
+                    report.tested,
+                    report.failed.len()
+                );
+            }
+            Ok(if report.is_ok() { 0 } else { EXIT_TEST_FAILED })
+        }
+        Command::Info { archive, password } => {
+            let info = open(archive, password)?.archive_info()?;
+            if cli.json {
+                print_json(&info);
+            } else {
+                out!("格式:     {:?}", info.format);
+                out!("条目:     {}", info.entry_count);
+                out!("压缩大小: {}", info.packed_size);
+                out!("原始大小: {}", info.unpacked_size);
+                out!("固实:     {}", info.is_solid);
+                out!("加密:     {}", info.is_encrypted);
+                out!("分卷:     {}", info.is_multi_volume);
+                if let Some(comment) = &info.comment {
+                    out!("注释:     {}", comment);
+                }
+            }
+            Ok(0)
+        }
+        Command::Compare {
+            archive,
+            directory,
+            password,
+        } => {
+            // 先检查文件与格式
+            drop(open(archive, password)?);
+            let report = compare_archive(&archive.to_string_lossy(), password.clone(), directory)?;
+            if cli.json {
+                print_json(&report);
+            } else {
+                for path in &report.missing {
+                    out!("缺失  {}", path);
+                }
+                for path in &report.extra {
+                    out!("多出  {}", path);
+                }
+                for modified in &report.modified {
+                    out!("修改  {}: {}", modified.path, modified.change);
+                }
+                for changed in &report.metadata_changed {
+                    let mut fields = Vec::new();
+                    if let Some([archive, disk]) = changed.mtime {
+                        fields.push(format!("修改时间 {} -> {}", archive, disk));
+                    }
+                    if let Some([archive, disk]) = changed.mode {
+                        fields.push(format!("权限 {:o} -> {:o}", archive, disk));
+                    }
+                    out!("属性  {}: {}", changed.path, fields.join(", "));
+                }
+                out!(
+                    "已比较 {} 个条目：{} 个缺失，{} 个多出，{} 个修改，{} 个属性不同",
+                    report.compared,
+                    report.missing.len(),
+                    report.extra.len(),
+                    report.modified.len(),
+                    report.metadata_changed.len()
+                );
+            }
+            Ok(if report.is_same() {
+                0
+            } else {
+                EXIT_TEST_FAILED
+            })
+        }
+        Command::Diff {
+            old,
+            new,
+            old_password,
+            new_password,
+            unified,
+        } => {
+            drop(open(old, old_password)?);
+            drop(open(new, new_password)?);
+            let options = DiffOptions {
+                unified: *unified,
+                ..Default::default()
+            };
+            let diff = diff_archives(
+                &old.to_string_lossy(),
+                old_password.clone(),
+                &new.to_string_lossy(),
+                new_password.clone(),
+                &options,
+            )?;
+            if cli.json {
+                print_json(&diff);
+            } else {
+                print_diff(&diff.tree);
+                out!(
+                    "新增 {}，删除 {}，修改 {}，属性不同 {}，相同 {}",
+                    diff.added,
+                    diff.removed,
+                    diff.changed,
+                    diff.metadata,
+                    diff.unchanged
+                );
+            }
+            Ok(if diff.is_same() { 0 } else { EXIT_TEST_FAILED })
+        }
+        Command::Repair {
+            archive,
+            output,
+            rebuild,
+            password,
+        } => {
+            if !archive.is_file() {
+                return Err(Failure {
+                    code: EXIT_FAILURE,
+                    message: format!("文件不存在: {}", archive.display()),
+                });
+            }
+            let options = SalvageOptions {
+                extract_to: output.clone(),
+                rebuild: rebuild.clone(),
+            };
+            let report = salvage_archive(archive, password, &options)?;
+            if cli.json {
+                print_json(&report);
+            } else {
+                if report.rebuilt_directory {
+                    out!("中央目录损坏，已通过扫描本地文件头重建");
+                }
+                for failure in &report.damaged {
+                    let path = match failure.path.as_str() {
+                        "" => "-",
+                        path => path,
+                    };
+                    out!("损坏  {}: {}", path, failure.error);
+                }
+                out!(
+                    "找回 {} 个条目，{} 处损坏，跳过 {} 字节",
+                    report.recovered,
+                    report.damaged.len(),
+                    report.skipped_bytes
+                );
+            }
+            Ok(if report.is_ok() { 0 } else { EXIT_TEST_FAILED })
+        }
+    }
+}
+
+/// `archive` 可以是 `outer.zip!/inner.tar.gz` 形式的虚拟路径
+fn open(archive: &Path, password: &str) -> Result<Box<dyn ArchiveHandler>, Failure> {
+    let virtual_path = nested::is_virtual(&archive.to_string_lossy());
+    if !virtual_path && !archive.is_file() {
+        return Err(Failure {
+            code: EXIT_FAILURE,
+            message: format!("文件不存在: {}", archive.display()),
+        });
+    }
+    let format = ArchiveFormat::from_path(archive);
+    if format == ArchiveFormat::Unknown {
+        return Err(Failure {
+            code: EXIT_UNSUPPORTED,
+            message: format!("不支持的格式: {}", archive.display()),
+        });
+    }
+    Ok(open_handler(
+        &archive.to_string_lossy(),
+        password.to_string(),
+    )?)
+}
+
+fn print_entry(out: &mut impl Write, entry: &ArchiveEntry) {
+    let suffix = if entry.is_dir { "/" } else { "" };
+    let _ = writeln!(
+        out,
+        "{:>12}  {:<19}  {}{}",
+        entry.size,
+        entry.modified.as_deref().unwrap_or("-"),
+        entry.path.trim_end_matches('/'),
+        suffix
+    );
+}
+
+/// 按目录顺序输出有变化的条目，类似 `git diff --name-status`
+fn print_diff(nodes: &[TreeNode<DiffEntry>]) {
+    for node in nodes {
+        let entry = node.item();
+        let status = match entry.status {
+            DiffStatus::Unchanged => None,
+            DiffStatus::Added => Some("A"),
+            DiffStatus::Removed => Some("D"),
+            DiffStatus::Changed => Some("M"),
+            DiffStatus::Metadata => Some("T"),
+        };
+        if let Some(status) = status {
+            match entry.changes.is_empty() {
+                true => out!("{}  {}", status, entry.path),
+                false => out!("{}  {} ({})", status, entry.path, entry.changes.join(", ")),
+            }
+            if let Some(unified) = &entry.unified {
+                out!("{}", unified.trim_end());
+            }
+        }
+        if entry.changed_below || entry.status != DiffStatus::Unchanged {
+            print_diff(node.children());
+        }
+    }
+}
+
+/// 发布版以 windows 子系统链接，没有控制台；从终端运行时连接到父进程的控制台输出结果
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: 没有父进程控制台（如从资源管理器启动）时调用失败，不影响后续执行
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(s) => out!("{}", s),
+        Err(e) => eprintln!("错误: {}", e),
+    }
+}
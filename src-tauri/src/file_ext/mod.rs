@@ -9,7 +9,7 @@ mod inspect;
 
 #[cfg(not(target_os = "macos"))]
 mod platform {
-    use crate::Inspect;
+    use super::inspect::Inspect;
     pub fn load(_inspect: Inspect) {}
 }
 
@@ -1,28 +1,40 @@
-use std::env;
-
 use tauri::{App, Manager};
+#[cfg(desktop)]
+use tauri_plugin_cli::CliExt;
 use tauri_plugin_deep_link::DeepLinkExt;
 
+pub mod cli;
 mod file_ext;
 mod unzip;
 
 #[tauri::command]
 fn run_args(app: tauri::AppHandle) -> Vec<String> {
     let mut args: Vec<String> = Vec::new();
-    if cfg!(target_os = "windows") {
-        args = env::args().skip(1).collect();
-    } else if cfg!(target_os = "macos") {
+    if cfg!(target_os = "macos") {
         args = match app.deep_link().get_current().unwrap_or_default() {
             Some(urls) => urls.iter().map(|url| url.to_string()).collect(),
             None => Vec::new(),
         }
+    } else {
+        // Windows/Linux 通过文件关联启动时，路径由 cli 插件的 `path` 参数传入
+        #[cfg(desktop)]
+        if let Ok(matches) = app.cli().matches() {
+            if let Some(serde_json::Value::String(path)) =
+                matches.args.get("path").map(|a| &a.value)
+            {
+                args.push(path.clone());
+            }
+        }
     }
     args
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_cli::init());
+    builder
         .plugin(
             tauri_plugin_log::Builder::new()
                 .target(tauri_plugin_log::Target::new(
@@ -32,9 +44,38 @@ pub fn run() {
         )
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_deep_link::init())
+        .manage(unzip::Sessions::default())
+        .manage(unzip::TempFiles::default())
+        .on_window_event(|window, event| {
+            // 解压窗口关闭时释放它打开的会话与临时文件
+            if let tauri::WindowEvent::Destroyed = event {
+                if window.label().starts_with("extract-") {
+                    window.state::<unzip::Sessions>().close_window(window.label());
+                    window.state::<unzip::TempFiles>().close_window(window.label());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
+            unzip::archive_open,
+            unzip::archive_list_dir,
+            unzip::archive_session_info,
+            unzip::archive_session_extract,
+            unzip::archive_session_read_entry,
+            unzip::archive_close,
+            unzip::archive_open_entry,
+            unzip::archive_write_back,
+            unzip::archive_session_edit,
             unzip::archive_list_files,
+            unzip::archive_stream_entries,
             unzip::archive_extract,
+            unzip::archive_info,
+            unzip::archive_read_entry,
+            unzip::archive_create,
+            unzip::archive_convert,
+            unzip::archive_compare,
+            unzip::archive_diff,
+            unzip::archive_salvage,
+            unzip::archive_create_formats,
             run_args
         ])
         .setup(|app| {
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `extract list|x|test|info ...` 以命令行模式运行，不启动窗口
+    if let Some(code) = app_lib::cli::run() {
+        std::process::exit(code);
+    }
     app_lib::run();
 }
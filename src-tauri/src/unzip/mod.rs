@@ -1,689 +1,408 @@
-use bzip2::read::BzDecoder;
-use extutil::ArchiveFormat;
-use flate2::read::GzDecoder;
-use itertools::Itertools;
-use plustree::TreeNode;
+// tauri 命令层，解压引擎位于 extract_core
+
+use extract_core::{
+    compare_archive, convert_archive, diff_archives, open_handler, preview, salvage_archive,
+    writer, ArchiveDiff, ArchiveEntry, ArchiveError, ArchiveFormat, ArchiveInfo, ArchiveSession,
+    ByteRange, CompareReport, CreateOptions, DiffOptions, DirPage, DirSort, EditOp, EntryPreview,
+    ExtractOptions, ResultR, SalvageOptions, SalvageReport, TreeNode, MAX_NESTED_DEPTH,
+    PREVIEW_LIMIT,
+};
 use serde::Serialize;
-use serde_json::json;
-use std::io::{BufReader, Read, Seek};
-use std::path::PathBuf;
-use std::{fs::File, path::Path};
-use tar::Archive;
-use unrar::error::UnrarError;
-use xz2::read::XzDecoder;
-use zip::result::ZipError;
-use zip::ZipArchive;
+use tauri::{Emitter, Manager, State, WebviewWindow};
 
-pub mod extutil;
-pub mod plustree;
+mod sessions;
+mod temp_files;
 
-#[derive(Debug, Serialize, Clone, Default)]
-pub struct ArchiveEntry {
-    pub name: String,
-    pub path: String,
-    pub parent_path: Option<String>,
-    pub size: u64,
-    pub is_dir: bool,
-    pub modified: Option<String>,
-}
-
-pub type ResultR<T, E = ArchiveError> = core::result::Result<T, E>;
+pub use sessions::Sessions;
+pub use temp_files::TempFiles;
 
-// -------------------------
-// 类型定义
-// -------------------------
+/// 流式列出时每批发送的默认条目数
+const ENTRY_BATCH_SIZE: usize = 500;
 
-/// 统一错误类型
-#[derive(Debug, thiserror::Error, Serialize)]
-pub enum ArchiveError {
-    #[error("Error: {0}")]
-    MsgError(String),
+/// `archive-entries` 事件的内容
+#[derive(Debug, Serialize, Clone)]
+pub struct EntryBatch {
+    /// 压缩包路径，用于区分同一窗口中的多个请求
+    path: String,
+    entries: Vec<ArchiveEntry>,
 }
 
-// -------------------------
-// 核心 Trait 设计
-// -------------------------
-
-pub trait ArchiveHandler {
-    /// 获取文件列表
-    fn list_files(&mut self) -> ResultR<Vec<TreeNode<ArchiveEntry>>>;
-    /// 执行解压
-    fn extract(&mut self, target_dir: &std::path::Path) -> ResultR<()>;
+/// `archive-create-progress` 事件的内容
+#[derive(Debug, Serialize, Clone)]
+pub struct CreateProgressEvent {
+    /// 输出路径，用于区分同一窗口中的多个请求
+    output: String,
+    #[serde(flatten)]
+    progress: writer::CreateProgress,
 }
 
-// -------------------------
-// 具体类型实现（空结构体示例）
-// -------------------------
-
-pub struct ZipHandler {
-    // 内部状态存储
-    archive_path: PathBuf,
-
+/// 打开压缩包并建立条目索引，返回会话 ID
+///
+/// 会话属于调用的窗口，窗口关闭时自动关闭。`path` 可以是 `outer.zip!/inner.tar.gz`
+/// 形式的虚拟路径，最多进入 `max_depth` 层（默认 [`MAX_NESTED_DEPTH`]）。
+#[tauri::command(async)]
+pub fn archive_open(
+    window: WebviewWindow,
+    sessions: State<'_, Sessions>,
+    path: String,
     password: String,
+    max_depth: Option<usize>,
+) -> ResultR<String> {
+    let cache_dir = index_cache_dir(&window);
+    let session = ArchiveSession::open_nested(
+        &path,
+        password,
+        cache_dir.as_deref(),
+        max_depth.unwrap_or(MAX_NESTED_DEPTH),
+    )?;
+    log::info!(
+        "archive_open:{:#?} {} entries",
+        session.format(),
+        session.index().len()
+    );
+    Ok(sessions.insert(window.label(), session))
 }
 
-impl ArchiveHandler for ZipHandler {
-    fn list_files(&mut self) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
-        let custom_error = |e: ZipError| match e {
-            zip::result::ZipError::FileNotFound => {
-                ArchiveError::MsgError(String::from("文件未找到"))
-            }
-            zip::result::ZipError::InvalidPassword => {
-                ArchiveError::MsgError(String::from("密码错误"))
-            }
-            zip::result::ZipError::UnsupportedArchive(estr) => match estr {
-                zip::result::ZipError::PASSWORD_REQUIRED => {
-                    ArchiveError::MsgError(String::from("密码错误"))
-                }
-                _ => ArchiveError::MsgError(String::from("打开文件错误")),
-            },
-            _ => ArchiveError::MsgError(String::from("打开文件错误")),
-        };
-        let path = &self.archive_path;
-        let file = File::open(path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
-        let mut archive = ZipArchive::new(file).map_err(custom_error)?;
-        let mut entries = Vec::new();
-
-        for i in 0..archive.len() {
-            let file = if self.password.is_empty() {
-                archive.by_index(i).map_err(custom_error)?
-            } else {
-                archive
-                    .by_index_decrypt(i, self.password.as_bytes())
-                    .map_err(custom_error)?
-            };
-
-            let full_path = String::from_utf8(file.name_raw().to_vec()).unwrap_or_default();
-            let is_dir = file.is_dir();
-            let modified = file.last_modified().map(|m| m.to_string());
-            let name = full_path.split('/').last().unwrap_or("").to_string();
-            // 使用 Path::new 创建一个 Path 对象
-            let path = Path::new(&full_path);
-            let mut parent_path = Option::None;
-            // 获取目录路径
-            if let Some(parent) = path.parent() {
-                parent_path = Some(
-                    parent
-                        .to_string_lossy()
-                        .to_string()
-                        .trim_end_matches('/')
-                        .to_string()
-                        + "/",
-                );
-            }
-            // 跳过 macOS 系统文件
-            if full_path.starts_with("__MACOSX") {
-                continue;
-            }
-            entries.push(ArchiveEntry {
-                name,
-                path: full_path.clone(),
-                parent_path,
-                size: if is_dir { 0 } else { file.size() },
-                is_dir,
-                modified,
-            });
-        }
-
-        let tree = plustree::TreeNode::build_tree(
-            entries,
-            String::from("/"),
-            |i| i.path.clone(),
-            |i| i.parent_path.clone().unwrap().clone(),
-        );
-        Ok(tree)
-    }
-
-    fn extract(&mut self, target_dir: &std::path::Path) -> ResultR<()> {
-        let custom_error = |e: ZipError| match e {
-            zip::result::ZipError::FileNotFound => {
-                ArchiveError::MsgError(String::from("文件未找到"))
-            }
-            zip::result::ZipError::InvalidPassword => {
-                ArchiveError::MsgError(String::from("密码错误"))
-            }
-            zip::result::ZipError::UnsupportedArchive(estr) => match estr {
-                zip::result::ZipError::PASSWORD_REQUIRED => {
-                    ArchiveError::MsgError(String::from("密码错误"))
-                }
-                _ => ArchiveError::MsgError(String::from("打开文件错误")),
-            },
-            _ => ArchiveError::MsgError(String::from("解压文件错误")),
-        };
-
-        let path = &self.archive_path;
-        let file = File::open(path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
-        let mut archive = ZipArchive::new(file).map_err(custom_error)?;
-
-        // 创建目标目录（如果不存在）
-        if !target_dir.exists() {
-            std::fs::create_dir_all(target_dir)
-                .map_err(|e| ArchiveError::MsgError(format!("创建目标目录失败: {}", e)))?;
-        }
-
-        // 遍历并解压所有文件
-        for i in 0..archive.len() {
-            let mut file = if self.password.is_empty() {
-                archive.by_index(i).map_err(custom_error)?
-            } else {
-                archive
-                    .by_index_decrypt(i, self.password.as_bytes())
-                    .map_err(custom_error)?
-            };
-
-            let full_path = String::from_utf8(file.name_raw().to_vec()).unwrap_or_default();
-
-            // 跳过 macOS 系统文件
-            if full_path.starts_with("__MACOSX") {
-                continue;
-            }
-
-            // 构建目标路径
-            let outpath = target_dir.join(Path::new(&full_path));
-
-            // 创建目录结构
-            if (*file.name()).ends_with('/') {
-                std::fs::create_dir_all(&outpath)
-                    .map_err(|e| ArchiveError::MsgError(format!("创建目录失败: {}", e)))?;
-            } else {
-                // 确保父目录存在
-                if let Some(parent) = outpath.parent() {
-                    if !parent.exists() {
-                        std::fs::create_dir_all(parent).map_err(|e| {
-                            ArchiveError::MsgError(format!("创建父目录失败: {}", e))
-                        })?;
-                    }
-                }
-
-                // 创建文件并写入内容
-                let mut outfile = std::fs::File::create(&outpath)
-                    .map_err(|e| ArchiveError::MsgError(format!("创建文件失败: {}", e)))?;
-
-                std::io::copy(&mut file, &mut outfile)
-                    .map_err(|e| ArchiveError::MsgError(format!("写入文件失败: {}", e)))?;
-            }
-
-            // 设置文件修改时间（如果可用）
-            #[cfg(unix)]
-            if let Some(mode) = file.unix_mode() {
-                use std::os::unix::fs::PermissionsExt;
-                if let Ok(metadata) = std::fs::metadata(&outpath) {
-                    let mut perms = metadata.permissions();
-                    perms.set_mode(mode);
-                    let _ = std::fs::set_permissions(&outpath, perms);
-                }
-            }
-        }
-
-        Ok(())
-    }
+/// tar 类压缩包的随机访问索引缓存在应用缓存目录中，再次打开时不必重新解压
+fn index_cache_dir(window: &WebviewWindow) -> Option<std::path::PathBuf> {
+    window
+        .path()
+        .app_cache_dir()
+        .ok()
+        .map(|dir| dir.join("index"))
 }
 
-// 其他格式类似
-
-pub struct TarHandler {
-    // 内部状态存储
-    archive_path: std::path::PathBuf,
-    password: String,
-    archive_format: ArchiveFormat,
+/// 分页列出会话中的一个目录（只含直接子条目）
+#[tauri::command(async)]
+pub fn archive_list_dir(
+    sessions: State<'_, Sessions>,
+    session: String,
+    dir_path: String,
+    offset: usize,
+    limit: usize,
+    sort: Option<DirSort>,
+) -> ResultR<DirPage> {
+    sessions
+        .get(&session)?
+        .list_dir(&dir_path, offset, limit, sort.unwrap_or_default())
 }
 
-impl ArchiveHandler for TarHandler {
-    fn list_files(&mut self) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
-        let file =
-            File::open(&self.archive_path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
-
-        // 根据不同格式创建对应的解码器
-        let reader: Box<dyn std::io::Read> = match self.archive_format {
-            ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
-            ArchiveFormat::TarXz => Box::new(XzDecoder::new(file)),
-            ArchiveFormat::TarBz2 => Box::new(BzDecoder::new(file)),
-            ArchiveFormat::Tar => Box::new(file),
-            _ => return Err(ArchiveError::MsgError("不支持的格式".to_string())),
-        };
-
-        let mut archive = Archive::new(reader);
-
-        let mut entries = Vec::new();
-
-        for entry in archive
-            .entries()
-            .map_err(|e| ArchiveError::MsgError(e.to_string()))?
-        {
-            let entry = entry.map_err(|e| {
-                println!("{:#?}", e);
-                ArchiveError::MsgError(e.to_string())
-            })?;
-            let path = entry
-                .path()
-                .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
-            let full_path = path.to_string_lossy().to_string();
-
-            // 跳过 macOS 系统文件
-            if full_path.starts_with("__MACOSX") {
-                continue;
-            }
-
-            let is_dir = entry.header().entry_type().is_dir();
-            let name = path
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_default();
-
-            let mut parent_path = None;
-            if let Some(parent) = path.parent() {
-                parent_path = Some(
-                    parent
-                        .to_string_lossy()
-                        .to_string()
-                        .trim_end_matches('/')
-                        .to_string()
-                        + "/",
-                );
-            }
-
-            entries.push(ArchiveEntry {
-                name,
-                path: full_path.clone(),
-                parent_path,
-                size: entry.header().size().unwrap_or(0),
-                is_dir,
-                modified: entry
-                    .header()
-                    .mtime()
-                    .map(|m| {
-                        chrono::DateTime::from_timestamp(m as i64, 0)
-                            .map_or(None, |t| Some(t.format("%Y-%m-%d %H:%M:%S").to_string()))
-                    })
-                    .unwrap_or(None),
-            });
-        }
-        println!("{:#?}", entries);
-
-        let tree = plustree::TreeNode::build_tree(
-            entries,
-            String::from("/"),
-            |i| i.path.clone(),
-            |i| i.parent_path.clone().unwrap_or_default(),
-        );
-        Ok(tree)
-    }
+#[tauri::command(async)]
+pub fn archive_session_info(
+    sessions: State<'_, Sessions>,
+    session: String,
+) -> ResultR<ArchiveInfo> {
+    sessions.get(&session)?.info()
+}
 
-    fn extract(&mut self, target_dir: &std::path::Path) -> ResultR<()> {
-        let file =
-            File::open(&self.archive_path).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+/// 用会话解压；传入 `password` 时改用该密码，成功后缓存到会话
+#[tauri::command(async)]
+pub fn archive_session_extract(
+    sessions: State<'_, Sessions>,
+    session: String,
+    target_path: String,
+    password: Option<String>,
+    options: Option<ExtractOptions>,
+) -> ResultR<()> {
+    let session = sessions.get(&session)?;
+    log::info!("archive_session_extract:{:#?}", session.format());
+    let options = options.unwrap_or_default();
+    session.with_handler(password, |handle| {
+        handle.extract(std::path::Path::new(&target_path), &options)
+    })
+}
 
-        let reader: Box<dyn std::io::Read> = match self.archive_format {
-            ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
-            ArchiveFormat::TarXz => Box::new(XzDecoder::new(file)),
-            ArchiveFormat::TarBz2 => Box::new(BzDecoder::new(file)),
-            ArchiveFormat::Tar => Box::new(file),
-            _ => return Err(ArchiveError::MsgError("不支持的格式".to_string())),
-        };
-        let mut archive = Archive::new(reader);
+/// 预览会话中的条目，`range` 为空时从头读取
+#[tauri::command(async)]
+pub fn archive_session_read_entry(
+    sessions: State<'_, Sessions>,
+    session: String,
+    entry: String,
+    range: Option<ByteRange>,
+) -> ResultR<EntryPreview> {
+    sessions
+        .get(&session)?
+        .preview(&entry, range.unwrap_or_default())
+}
 
-        archive
-            .unpack(target_dir)
-            .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+#[tauri::command(async)]
+pub fn archive_close(
+    sessions: State<'_, Sessions>,
+    temp_files: State<'_, TempFiles>,
+    session: String,
+) -> ResultR<()> {
+    sessions.remove(&session);
+    temp_files.close_session(&session);
+    Ok(())
+}
 
-        Ok(())
-    }
+/// 把条目解压到会话的临时目录并用系统默认程序打开，返回临时文件路径
+///
+/// 临时文件被修改时向窗口发送 `archive-entry-modified` 事件，可用
+/// `archive_write_back` 写回压缩包；窗口或会话关闭时删除临时文件。
+#[tauri::command(async)]
+pub fn archive_open_entry(
+    window: WebviewWindow,
+    sessions: State<'_, Sessions>,
+    temp_files: State<'_, TempFiles>,
+    session: String,
+    entry: String,
+) -> ResultR<String> {
+    let temp_dir = window
+        .path()
+        .temp_dir()
+        .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+    let opened = sessions.get(&session)?;
+    let file = temp_files.open(
+        window.app_handle(),
+        window.label(),
+        &temp_dir,
+        &session,
+        &opened,
+        &entry,
+    )?;
+    Ok(file.to_string_lossy().to_string())
 }
 
-pub struct SevenZipHandler {
-    // 内部状态存储
-    archive_path: std::path::PathBuf,
-    password: String,
+/// 把修改过的临时文件写回压缩包，并重新打开会话
+#[tauri::command(async)]
+pub fn archive_write_back(
+    window: WebviewWindow,
+    sessions: State<'_, Sessions>,
+    temp_files: State<'_, TempFiles>,
+    session: String,
+    entry: String,
+) -> ResultR<()> {
+    let file = temp_files.file(&session, &entry)?;
+    let opened = sessions.get(&session)?;
+    opened.replace_entry(&entry, &file)?;
+    log::info!("archive_write_back:{} {}", opened.path().display(), entry);
+    let reopened = opened.reopen(index_cache_dir(&window).as_deref())?;
+    sessions.replace(&session, reopened)
 }
 
-#[test]
-fn testf() -> () {
-    let path = std::path::Path::new(
-        "/Users/apple/Downloads/Compressed/时间序列分析——基于R（第2版）案例数据.zip",
+/// 按顺序添加、替换、删除或重命名会话中的条目，成功后重新打开会话
+#[tauri::command(async)]
+pub fn archive_session_edit(
+    window: WebviewWindow,
+    sessions: State<'_, Sessions>,
+    session: String,
+    ops: Vec<EditOp>,
+) -> ResultR<()> {
+    let opened = sessions.get(&session)?;
+    opened.edit(&ops)?;
+    log::info!(
+        "archive_session_edit:{} {} ops",
+        opened.path().display(),
+        ops.len()
     );
-    let mut handle = ZipHandler {
-        archive_path: path.to_path_buf(),
-        password: String::from("3"),
-    };
-
-    let ff = handle.list_files();
-    println!("{:#?}", ff);
+    let reopened = opened.reopen(index_cache_dir(&window).as_deref())?;
+    sessions.replace(&session, reopened)
 }
 
-impl ArchiveHandler for SevenZipHandler {
-    fn list_files(&mut self) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
-        let archive_path = &self.archive_path.clone().to_string_lossy().to_string();
-
-        let custom_err = |e: sevenz_rust::Error| {
-            if e.to_string().contains("Password") {
-                return ArchiveError::MsgError(String::from("密码错误"));
-            } else {
-                return ArchiveError::MsgError(format!("打开文件错误: {}", e));
-            }
-        };
-        let mut sz = sevenz_rust::SevenZReader::open(
-            archive_path,
-            self.password
-                .is_empty()
-                .then(|| sevenz_rust::Password::empty())
-                .unwrap_or(sevenz_rust::Password::from(self.password.as_str())),
-        )
-        .map_err(custom_err)?;
-        // 使用sevenz-rust库打开7z文件
-        let mut entries = Vec::new();
-
-        let mut add_entry = |entry: &sevenz_rust::SevenZArchiveEntry| {
-            // 遍历所有文件条目
-            let full_path = entry.name().to_string();
-
-            // 跳过 macOS 系统文件
-            // if full_path.starts_with("__MACOSX") {
-            //     return;
-            // }
-
-            let path = Path::new(&full_path);
-            let is_dir = entry.is_directory();
-            let name = path
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_default();
-
-            let mut parent_path = None;
-            if let Some(parent) = path.parent() {
-                parent_path = Some(
-                    parent
-                        .to_string_lossy()
-                        .to_string()
-                        .trim_end_matches('/')
-                        .to_string()
-                        + "/",
-                );
-            }
-
-            // 获取修改时间
-            let modified =
-                chrono::DateTime::from_timestamp(entry.last_modified_date().to_unix_time(), 0)
-                    .map_or(None, |t| Some(t.format("%Y-%m-%d %H:%M:%S").to_string()));
-
-            entries.push(ArchiveEntry {
-                name,
-                path: if is_dir {
-                    full_path.clone() + "/"
-                } else {
-                    full_path.clone()
-                },
-                parent_path,
-                size: entry.size(),
-                is_dir,
-                modified,
-            });
-        };
-        sz.for_each_entries(|entry, _reader| {
-            add_entry(entry);
-            Ok(true)
-        })
-        .map_err(custom_err)?;
-
-        println!("{:#?}", json!(entries));
-        // 构建缺失的文件夹
-        let parents_dir = entries
-            .clone()
-            .into_iter()
-            .map(|x| x.parent_path.unwrap())
-            .unique()
-            .collect::<Vec<_>>();
-
-        println!("parents_dir:{:#?}", json!(parents_dir));
-
-        parents_dir.iter().for_each(|x| {
-            let mut parent_path = None;
-            if let Some(parent) = Path::new(x).parent() {
-                parent_path = Some(
-                    parent
-                        .to_string_lossy()
-                        .to_string()
-                        .trim_end_matches('/')
-                        .to_string()
-                        + "/",
-                );
-            }
-
-            entries.push(ArchiveEntry {
-                name: String::from(""),
-                path: x.clone(),
-                parent_path: parent_path,
-                size: 0,
-                is_dir: true,
-                modified: None,
-            });
-        });
-
-        let mut entries: Vec<_> = entries
-            .into_iter()
-            .unique_by(|item| item.path.clone())
-            .collect();
+#[tauri::command(async)]
+pub fn archive_list_files(path: String, password: String) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
+    let format = ArchiveFormat::from_path(std::path::Path::new(&path));
+    log::info!("archive_list_files:{:#?}", format);
+    let mut handle = open_handler(&path, password)?;
+    handle.list_files()
+}
 
-        let tree = plustree::TreeNode::build_tree(
+/// 边读取边把条目分批推送到调用窗口（`archive-entries` 事件），发送完毕后返回条目总数
+#[tauri::command(async)]
+pub fn archive_stream_entries(
+    window: WebviewWindow,
+    path: String,
+    password: String,
+    batch_size: Option<usize>,
+) -> ResultR<u64> {
+    let format = ArchiveFormat::from_path(std::path::Path::new(&path));
+    log::info!("archive_stream_entries:{:#?}", format);
+    let mut handle = open_handler(&path, password)?;
+
+    let batch_size = batch_size.unwrap_or(ENTRY_BATCH_SIZE).max(1);
+    let emit = |entries: Vec<ArchiveEntry>| {
+        let batch = EntryBatch {
+            path: path.clone(),
             entries,
-            String::from("/"),
-            |i| i.path.clone(),
-            |i| i.parent_path.clone().unwrap_or_default(),
-        );
-        Ok(tree)
-    }
-
-    fn extract(&mut self, target_dir: &std::path::Path) -> ResultR<()> {
-        let archive_path = &self.archive_path.clone().to_string_lossy().to_string();
-        let custom_err = |e: sevenz_rust::Error| {
-            if e.to_string().contains("Password") {
-                return ArchiveError::MsgError(String::from("密码错误"));
-            } else {
-                return ArchiveError::MsgError(format!("打开文件错误: {}", e));
-            }
         };
+        window
+            .emit_to(window.label(), "archive-entries", batch)
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))
+    };
 
-        // sevenz_rustdefault_entry_extract_fn
-        sevenz_rust::decompress_file_with_password(
-            archive_path,
-            target_dir.to_str().unwrap(),
-            self.password
-                .is_empty()
-                .then(|| sevenz_rust::Password::empty())
-                .unwrap_or(sevenz_rust::Password::from(self.password.as_str())),
-        )
-        .map_err(custom_err)?;
-        Ok(())
+    let mut total = 0;
+    let mut batch = Vec::with_capacity(batch_size);
+    for entry in handle.entries()? {
+        batch.push(entry?);
+        total += 1;
+        if batch.len() == batch_size {
+            emit(std::mem::replace(
+                &mut batch,
+                Vec::with_capacity(batch_size),
+            ))?;
+        }
+    }
+    if !batch.is_empty() {
+        emit(batch)?;
     }
+    Ok(total)
 }
 
-pub struct RarHandler {
-    // 内部状态存储
-    archive_path: std::path::PathBuf,
-    password: String,
+#[tauri::command(async)]
+pub fn archive_info(path: String, password: String) -> ResultR<ArchiveInfo> {
+    let format = ArchiveFormat::from_path(std::path::Path::new(&path));
+    log::info!("archive_info:{:#?}", format);
+    let mut handle = open_handler(&path, password)?;
+    handle.archive_info()
 }
 
-impl ArchiveHandler for RarHandler {
-    fn list_files(&mut self) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
-        let archive_path = &self.archive_path.clone().to_string_lossy().to_string();
-        // 使用unrar库打开RAR文件
-        let archive = if self.password.is_empty() {
-            unrar::Archive::new(archive_path)
-        } else {
-            unrar::Archive::with_password(archive_path, &self.password)
-        };
-
-        let mut entries = Vec::new();
-
-        // 列出所有文件
-        match archive.open_for_listing() {
-            Ok(list) => {
-                for entry in list {
-                    match entry {
-                        Ok(entry) => {
-                            let full_path = &entry.filename;
-
-                            // 跳过 macOS 系统文件
-                            if full_path.starts_with("__MACOSX") {
-                                continue;
-                            }
-
-                            let path = Path::new(&full_path);
-                            let is_dir = entry.is_directory();
-                            let name = path
-                                .file_name()
-                                .map(|s| s.to_string_lossy().to_string())
-                                .unwrap_or_default();
-
-                            let mut parent_path = None;
-                            if let Some(parent) = path.parent() {
-                                parent_path = Some(
-                                    parent
-                                        .to_string_lossy()
-                                        .to_string()
-                                        .trim_end_matches('/')
-                                        .to_string()
-                                        + "/",
-                                );
-                            }
+#[tauri::command(async)]
+pub fn archive_extract(
+    path: String,
+    target_path: String,
+    password: String,
+    options: Option<ExtractOptions>,
+) -> ResultR<()> {
+    let target_path = std::path::Path::new(&target_path);
+    let format = ArchiveFormat::from_path(std::path::Path::new(&path));
+    log::info!("archive_extract:{:#?}", format);
+    let mut handle = open_handler(&path, password)?;
+    handle.extract(target_path, &options.unwrap_or_default())
+}
 
-                            // 转换时间格式
-                            let modified =
-                                chrono::DateTime::from_timestamp(entry.file_time as i64, 0)
-                                    .map_or(None, |dt| {
-                                        Some(dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                                    });
+/// 预览单个条目：读取内容或其中一段，识别类型并解码文本，单次最多返回 [`PREVIEW_LIMIT`] 字节
+#[tauri::command(async)]
+pub fn archive_read_entry(
+    path: String,
+    entry: String,
+    password: String,
+    range: Option<ByteRange>,
+) -> ResultR<EntryPreview> {
+    let format = ArchiveFormat::from_path(std::path::Path::new(&path));
+    log::info!("archive_read_entry:{:#?} {}", format, entry);
+    let mut handle = open_handler(&path, password)?;
+    preview::preview(
+        &entry,
+        range.unwrap_or_default(),
+        PREVIEW_LIMIT,
+        |range, out| handle.read_entry(&entry, range, out),
+    )
+}
 
-                            entries.push(ArchiveEntry {
-                                name,
-                                path: format!(
-                                    "{}{}",
-                                    full_path.clone().to_string_lossy().to_string(),
-                                    if is_dir { "/" } else { "" }
-                                ),
-                                parent_path,
-                                size: entry.unpacked_size,
-                                is_dir,
-                                modified,
-                            });
-                        }
-                        Err(e) => return Err(ArchiveError::MsgError(e.to_string())),
-                    }
-                }
+/// 把文件与文件夹打包为 `output`，进度以 `archive-create-progress` 事件推送到调用窗口
+///
+/// 未指定 `format` 时按 `output` 的扩展名选择格式。
+#[tauri::command(async)]
+pub fn archive_create(
+    window: WebviewWindow,
+    sources: Vec<String>,
+    output: String,
+    format: Option<ArchiveFormat>,
+    options: Option<CreateOptions>,
+) -> ResultR<()> {
+    let output_path = std::path::Path::new(&output);
+    let format = format.unwrap_or_else(|| ArchiveFormat::from_path(output_path));
+    log::info!("archive_create:{:#?} {} sources", format, sources.len());
+    let sources: Vec<std::path::PathBuf> = sources.iter().map(Into::into).collect();
+    writer::create_archive(
+        &sources,
+        output_path,
+        format,
+        &options.unwrap_or_default(),
+        |progress| {
+            let event = CreateProgressEvent {
+                output: output.clone(),
+                progress: progress.clone(),
+            };
+            if let Err(e) = window.emit_to(window.label(), "archive-create-progress", event) {
+                log::error!("emit archive-create-progress: {}", e);
             }
-            Err(e) => match e {
-                UnrarError { code, when } => match code {
-                    unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
-                        return Err(ArchiveError::MsgError(String::from("密码错误")))
-                    }
-                    _ => return Err(ArchiveError::MsgError(format!("发生错误: {}", e,))),
-                },
-            },
-        }
-
-        let tree = plustree::TreeNode::build_tree(
-            entries,
-            String::from("/"),
-            |i| i.path.clone(),
-            |i| i.parent_path.clone().unwrap_or_default(),
-        );
-        Ok(tree)
-    }
-
-    fn extract(&mut self, target_dir: &std::path::Path) -> ResultR<()> {
-        // // 使用unrar库解压文件
-
-        let archive_path = &self.archive_path.clone().to_string_lossy().to_string();
-        let archive = if self.password.is_empty() {
-            unrar::Archive::new(archive_path)
-        } else {
-            unrar::Archive::with_password(archive_path, &self.password)
-        };
-
-        let merr = |e: UnrarError| match e {
-            UnrarError { code, when } => match code {
-                unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword => {
-                    return ArchiveError::MsgError(String::from("密码错误"))
-                }
-                _ => return ArchiveError::MsgError(format!("发生错误: {}", e,)),
-            },
-        };
-
-        let mut archive = archive.open_for_processing().map_err(merr)?;
-        while let Some(header) = archive.read_header().map_err(merr)? {
-            let fname = header.entry().filename.to_string_lossy();
-
-            let is_file = header.entry().is_file();
-            let target_file = format!("{}/{}", target_dir.to_string_lossy().to_string(), fname);
+        },
+    )
+}
 
-            archive = if is_file {
-                // 跳过 macOS 系统文件
-                if fname.starts_with("__MACOSX") {
-                    header.skip().map_err(merr)?
-                } else {
-                    header.extract_to(target_file).map_err(merr)?
-                }
-            } else {
-                header.skip().map_err(merr)?
+/// 把压缩包转换为 `format` 格式的 `output`，条目边读边写，不解压到磁盘
+///
+/// `input` 可以是嵌套压缩包的虚拟路径，`password` 用于打开 `input`，输出的加密由 `options` 决定。
+/// 进度同样以 `archive-create-progress` 事件推送，未指定 `format` 时按 `output` 的扩展名选择格式。
+#[tauri::command(async)]
+pub fn archive_convert(
+    window: WebviewWindow,
+    input: String,
+    output: String,
+    format: Option<ArchiveFormat>,
+    options: Option<CreateOptions>,
+    password: String,
+) -> ResultR<()> {
+    let output_path = std::path::Path::new(&output);
+    let format = format.unwrap_or_else(|| ArchiveFormat::from_path(output_path));
+    log::info!("archive_convert:{} -> {:#?}", input, format);
+    convert_archive(
+        &input,
+        password,
+        output_path,
+        format,
+        &options.unwrap_or_default(),
+        |progress| {
+            let event = CreateProgressEvent {
+                output: output.clone(),
+                progress: progress.clone(),
             };
-        }
-        Ok(())
-    }
+            if let Err(e) = window.emit_to(window.label(), "archive-create-progress", event) {
+                log::error!("emit archive-create-progress: {}", e);
+            }
+        },
+    )
 }
 
-// -------------------------
-// 工厂模式设计
-// -------------------------
-
-pub fn create_handler(
-    path: &std::path::Path,
-    format: ArchiveFormat,
+/// 比较压缩包与目录，确认解压出的文件与压缩包仍然一致
+#[tauri::command(async)]
+pub fn archive_compare(
+    archive: String,
+    directory: String,
     password: String,
-) -> Box<dyn ArchiveHandler> {
-    match format {
-        ArchiveFormat::Zip => Box::new(ZipHandler {
-            archive_path: path.to_path_buf(),
-            password,
-        }),
-        ArchiveFormat::Tar
-        | ArchiveFormat::TarXz
-        | ArchiveFormat::TarGz
-        | ArchiveFormat::TarBz2
-        | ArchiveFormat::Gz
-        | ArchiveFormat::Bz2 => Box::new(TarHandler {
-            archive_path: path.to_path_buf(),
-            password,
-            archive_format: format,
-        }),
-        ArchiveFormat::SevenZip => Box::new(SevenZipHandler {
-            archive_path: path.to_path_buf(),
-            password,
-        }),
-        ArchiveFormat::Rar => Box::new(RarHandler {
-            archive_path: path.to_path_buf(),
-            password,
-        }),
-        _ => Box::new(ZipHandler {
-            archive_path: path.to_path_buf(),
-            password,
-        }),
-    }
+) -> ResultR<CompareReport> {
+    log::info!("archive_compare:{} <-> {}", archive, directory);
+    compare_archive(&archive, password, std::path::Path::new(&directory))
 }
 
+/// 比较两个压缩包，返回按目录组织的差异树
 #[tauri::command(async)]
-pub fn archive_list_files(path: String, password: String) -> ResultR<Vec<TreeNode<ArchiveEntry>>> {
-    let path = std::path::Path::new(&path);
-    let format = extutil::ArchiveFormat::from_path(path);
-    log::info!("archive_list_files:{:#?}", format);
-    let mut handle = create_handler(path, format, password);
-    handle.list_files()
+pub fn archive_diff(
+    old: String,
+    old_password: String,
+    new: String,
+    new_password: String,
+    options: Option<DiffOptions>,
+) -> ResultR<ArchiveDiff> {
+    log::info!("archive_diff:{} -> {}", old, new);
+    diff_archives(
+        &old,
+        old_password,
+        &new,
+        new_password,
+        &options.unwrap_or_default(),
+    )
 }
 
+/// 修复损坏的压缩包：找回完好的条目，按选项解压到目录或写入重建的压缩包
 #[tauri::command(async)]
-pub fn archive_extract(path: String, target_path: String, password: String) -> ResultR<()> {
-    let path = std::path::Path::new(&path);
-    let target_path = std::path::Path::new(&target_path);
-    let format = extutil::ArchiveFormat::from_path(path);
-    log::info!("archive_extract:{:#?}", format);
-    let mut handle = create_handler(path, format, password);
-    handle.extract(target_path)
+pub fn archive_salvage(
+    archive: String,
+    password: String,
+    options: Option<SalvageOptions>,
+) -> ResultR<SalvageReport> {
+    log::info!("archive_salvage:{}", archive);
+    salvage_archive(
+        std::path::Path::new(&archive),
+        &password,
+        &options.unwrap_or_default(),
+    )
+}
+
+/// 可以创建的格式及各自支持的压缩选项，创建对话框据此生成表单
+#[tauri::command]
+pub fn archive_create_formats() -> Vec<writer::FormatCapabilities> {
+    writer::format_capabilities()
 }
@@ -0,0 +1,71 @@
+// 已打开的压缩包会话，由 Tauri 托管，避免每次操作重新打开、解析与解密
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use extract_core::{ArchiveError, ArchiveSession, ResultR};
+
+struct Opened {
+    /// 打开会话的窗口
+    window: String,
+    session: Arc<ArchiveSession>,
+}
+
+#[derive(Default)]
+pub struct Sessions {
+    next_id: AtomicU64,
+    sessions: Mutex<HashMap<String, Opened>>,
+}
+
+impl Sessions {
+    pub fn insert(&self, window: &str, session: ArchiveSession) -> String {
+        let id = format!(
+            "session-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        let opened = Opened {
+            window: window.to_string(),
+            session: Arc::new(session),
+        };
+        self.lock().insert(id.clone(), opened);
+        id
+    }
+
+    /// 取出会话；正在进行的操作持有 `Arc`，会话被关闭后仍可完成
+    pub fn get(&self, id: &str) -> ResultR<Arc<ArchiveSession>> {
+        self.lock()
+            .get(id)
+            .map(|opened| opened.session.clone())
+            .ok_or_else(|| ArchiveError::MsgError(format!("会话不存在: {}", id)))
+    }
+
+    /// 用重新打开的会话替换，用于压缩包被修改之后
+    pub fn replace(&self, id: &str, session: ArchiveSession) -> ResultR<()> {
+        let mut sessions = self.lock();
+        let opened = sessions
+            .get_mut(id)
+            .ok_or_else(|| ArchiveError::MsgError(format!("会话不存在: {}", id)))?;
+        opened.session = Arc::new(session);
+        Ok(())
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    /// 关闭窗口打开的所有会话
+    pub fn close_window(&self, window: &str) {
+        self.lock().retain(|id, opened| {
+            let keep = opened.window != window;
+            if !keep {
+                log::info!("close {} with window {}", id, window);
+            }
+            keep
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Opened>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
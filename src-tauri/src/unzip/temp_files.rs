@@ -0,0 +1,252 @@
+// 用系统默认程序打开的条目：解压到会话的临时目录，监视修改以便写回压缩包
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use extract_core::{private_dir, ArchiveError, ArchiveSession, ByteRange, ResultR};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tempfile::TempDir;
+
+/// 检查临时文件是否被修改的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `archive-entry-modified` 事件的内容
+#[derive(Debug, Serialize, Clone)]
+pub struct EntryModified {
+    pub session: String,
+    pub entry: String,
+    /// 临时文件的路径
+    pub file: String,
+}
+
+struct SessionFiles {
+    /// 打开会话的窗口
+    window: String,
+    dir: PathBuf,
+    /// 条目到临时文件的映射
+    files: HashMap<String, PathBuf>,
+    /// 置位后监视线程退出
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+pub struct TempFiles {
+    sessions: Mutex<HashMap<String, SessionFiles>>,
+    /// 本进程私有的临时目录，各会话的目录都在其中；首次打开条目时创建
+    root: Mutex<Option<TempDir>>,
+}
+
+impl TempFiles {
+    /// 把条目解压到会话的临时目录并用系统默认程序打开，返回临时文件路径
+    ///
+    /// 会话的临时目录位于 `temp_dir` 下本进程私有的目录中，见 [`private_dir`]。
+    /// 已打开过的条目不再解压，以免覆盖尚未写回的修改。
+    pub fn open(
+        &self,
+        app: &AppHandle,
+        window: &str,
+        temp_dir: &Path,
+        id: &str,
+        session: &ArchiveSession,
+        entry: &str,
+    ) -> ResultR<PathBuf> {
+        let existing = self
+            .lock()
+            .get(id)
+            .and_then(|files| files.files.get(entry).cloned());
+        let file = match existing {
+            Some(file) if file.exists() => file,
+            _ => {
+                let file = self.extract(temp_dir, id, window, session, entry)?;
+                self.watch(app, window, id, entry, &file);
+                file
+            }
+        };
+        open_path(&file)?;
+        Ok(file)
+    }
+
+    /// 条目对应的临时文件
+    pub fn file(&self, id: &str, entry: &str) -> ResultR<PathBuf> {
+        self.lock()
+            .get(id)
+            .and_then(|files| files.files.get(entry).cloned())
+            .ok_or_else(|| ArchiveError::MsgError(format!("条目未打开: {}", entry)))
+    }
+
+    /// 停止监视并删除会话的临时目录
+    pub fn close_session(&self, id: &str) {
+        if let Some(files) = self.lock().remove(id) {
+            cleanup(files);
+        }
+    }
+
+    /// 清理窗口打开的所有会话的临时文件
+    pub fn close_window(&self, window: &str) {
+        let closed: Vec<SessionFiles> = {
+            let mut sessions = self.lock();
+            let ids: Vec<String> = sessions
+                .iter()
+                .filter(|(_, files)| files.window == window)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| sessions.remove(id)).collect()
+        };
+        closed.into_iter().for_each(cleanup);
+    }
+
+    fn extract(
+        &self,
+        temp_dir: &Path,
+        id: &str,
+        window: &str,
+        session: &ArchiveSession,
+        entry: &str,
+    ) -> ResultR<PathBuf> {
+        let dir = self.root(temp_dir)?.join(id);
+        // 只保留普通路径部分，防止条目路径跳出临时目录
+        let relative: PathBuf = Path::new(entry)
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        if relative.as_os_str().is_empty() {
+            return Err(ArchiveError::MsgError(format!("无效的条目: {}", entry)));
+        }
+        let file = dir.join(relative);
+        // 私有目录中只有本进程写入，可以直接创建上级目录
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        }
+        let mut out = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&file)
+            .map_err(|e| ArchiveError::MsgError(e.to_string()))?;
+        if let Err(e) = session.read_entry(entry, ByteRange::default(), &mut out) {
+            drop(out);
+            let _ = std::fs::remove_file(&file);
+            return Err(e);
+        }
+
+        self.lock()
+            .entry(id.to_string())
+            .or_insert_with(|| SessionFiles {
+                window: window.to_string(),
+                dir,
+                files: HashMap::new(),
+                stop: Arc::new(AtomicBool::new(false)),
+            })
+            .files
+            .insert(entry.to_string(), file.clone());
+        Ok(file)
+    }
+
+    /// 轮询临时文件的修改时间与大小，变化时通知窗口
+    fn watch(&self, app: &AppHandle, window: &str, id: &str, entry: &str, file: &Path) {
+        let Some(stop) = self.lock().get(id).map(|files| files.stop.clone()) else {
+            return;
+        };
+        let (app, window, file) = (app.clone(), window.to_string(), file.to_path_buf());
+        let payload = EntryModified {
+            session: id.to_string(),
+            entry: entry.to_string(),
+            file: file.to_string_lossy().to_string(),
+        };
+        thread::spawn(move || {
+            let mut last = stamp(&file);
+            while !stop.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+                let current = stamp(&file);
+                if current.is_some() && current != last {
+                    last = current;
+                    if let Err(e) = app.emit_to(&window, "archive-entry-modified", payload.clone())
+                    {
+                        log::error!("emit archive-entry-modified: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    /// 本进程私有的临时目录，不存在时在 `temp_dir` 中创建
+    fn root(&self, temp_dir: &Path) -> ResultR<PathBuf> {
+        let mut root = self.root.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(root) = root.as_ref() {
+            return Ok(root.path().to_path_buf());
+        }
+        let dir = private_dir(temp_dir, "extract-open-")
+            .map_err(|e| ArchiveError::MsgError(format!("创建临时目录失败: {}", e)))?;
+        let path = dir.path().to_path_buf();
+        *root = Some(dir);
+        Ok(path)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, SessionFiles>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn stamp(file: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(file).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn cleanup(files: SessionFiles) {
+    files.stop.store(true, Ordering::Relaxed);
+    if let Err(e) = std::fs::remove_dir_all(&files.dir) {
+        log::warn!("remove {}: {}", files.dir.display(), e);
+    }
+}
+
+/// 用系统默认程序打开文件
+///
+/// 路径中含有压缩包作者可控的条目名，不能经过 cmd.exe：`x&calc.exe` 之类的名称会被当作命令执行。
+#[cfg(target_os = "windows")]
+fn open_path(path: &Path) -> ResultR<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let file: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let operation: Vec<u16> = "open\0".encode_utf16().collect();
+    // SAFETY: 两个字符串都以 NUL 结尾，并在调用期间有效
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            operation.as_ptr(),
+            file.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // 返回值大于 32 表示成功，否则为错误码
+    match result as isize {
+        code if code > 32 => Ok(()),
+        code => Err(ArchiveError::MsgError(format!(
+            "无法打开文件: 错误码 {}",
+            code
+        ))),
+    }
+}
+
+/// 用系统默认程序打开文件，路径作为单独的参数传给 open / xdg-open，不经过 shell
+#[cfg(not(target_os = "windows"))]
+fn open_path(path: &Path) -> ResultR<()> {
+    #[cfg(target_os = "macos")]
+    let status = std::process::Command::new("open").arg(path).status();
+    #[cfg(not(target_os = "macos"))]
+    let status = std::process::Command::new("xdg-open").arg(path).status();
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(ArchiveError::MsgError(format!("无法打开文件: {}", status))),
+        Err(e) => Err(ArchiveError::MsgError(format!("无法打开文件: {}", e))),
+    }
+}
@@ -1,13 +1,29 @@
 import { useEffect, useState } from "react";
 import {
-  archiveListFiles,
-  archiveExtract,
-  ArchiveEntry,
-  TreeNode,
+  archiveOpen,
+  archiveClose,
+  archiveSessionExtract,
+  archiveOpenEntry,
+  archiveWriteBack,
+  archiveSessionEdit,
+  archiveSalvage,
+  archiveCompare,
+  archiveDiff,
+  ArchiveDiff,
+  CompareReport,
+  onEntryModified,
+  EditOp,
+  EntryModified,
+  NESTED_SEPARATOR,
 } from "./commands";
 
 import FileTree from "./components/FileTree";
 import PasswordDialog from "./components/PasswordDialog";
+import PreviewDialog from "./components/PreviewDialog";
+import WriteBackDialog from "./components/WriteBackDialog";
+import CreateDialog from "./components/CreateDialog";
+import CompareDialog from "./components/CompareDialog";
+import DiffDialog from "./components/DiffDialog";
 import { run_args } from "./commands";
 import "./App.css";
 
@@ -16,8 +32,11 @@ import { getCurrent } from "@tauri-apps/plugin-deep-link";
 import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { attachConsole } from "@tauri-apps/plugin-log";
 import { listen } from "@tauri-apps/api/event";
-import { open } from "@tauri-apps/plugin-dialog";
-import { Button } from "@heroui/react";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { Button, Checkbox } from "@heroui/react";
+
+// 可以修复的格式：zip 与 tar 系列
+const REPAIRABLE = /\.(zip|tar|tar\.(gz|bz2|xz|zst)|tgz|tbz2?|tzst)$/i;
 
 function App() {
   const [v, setV] = useState("");
@@ -27,8 +46,22 @@ function App() {
   const [passwordRequire, setPasswordRequire] = useState(false);
   const [extractPasswordRequire, setExtractPasswordRequire] = useState(false);
   const [password, setPassword] = useState("");
-  const [zipList, setZipList] = useState<TreeNode<ArchiveEntry>[]>([]);
+  const [session, setSession] = useState("");
+  // 正在预览的条目
+  const [previewEntry, setPreviewEntry] = useState<string>();
+  // 被系统程序修改、等待确认写回的条目
+  const [modified, setModified] = useState<EntryModified>();
+  // 写回后压缩包内容变化，用于刷新文件树
+  const [treeVersion, setTreeVersion] = useState(0);
   const [extractPath, setExtractPath] = useState("");
+  // 把解压出的压缩包继续解压
+  const [recursive, setRecursive] = useState(false);
+  const [createOpen, setCreateOpen] = useState(false);
+  const [convertOpen, setConvertOpen] = useState(false);
+  const [compareDir, setCompareDir] = useState("");
+  const [compareReport, setCompareReport] = useState<CompareReport>();
+  const [diffWith, setDiffWith] = useState("");
+  const [diff, setDiff] = useState<ArchiveDiff>();
   const [zipFiles, setZipFiles] = useState<string[]>([]);
 
   const _extensions = [
@@ -38,6 +71,7 @@ function App() {
     "tar",
     "tar.gz",
     "tar.xz",
+    "tar.zst",
     "tar.bz2",
     "tbz2",
     "tbz",
@@ -139,6 +173,7 @@ function App() {
             "tar",
             "tar.gz",
             "tar.xz",
+            "tar.zst",
             "tar.gz",
             "tar.bz2",
             "tbz2",
@@ -159,14 +194,25 @@ function App() {
 
   const loadList = (filePath: string, password: string) => {
     setValue(filePath);
-    archiveListFiles(filePath, password)
-      .then((res) => {
-        setZipList([...res]);
-        console.log(JSON.stringify(res, null, 2));
+    archiveOpen(filePath, password)
+      .then((id) => {
+        // 关闭上一个压缩包的会话
+        setSession((prev) => {
+          if (prev) {
+            archiveClose(prev);
+          }
+          return id;
+        });
       })
       .catch((err) => {
         if (err.MsgError == "密码错误") {
           setPasswordRequire(true);
+        } else if (
+          REPAIRABLE.test(filePath) &&
+          !filePath.includes(NESTED_SEPARATOR) &&
+          confirm(`打开失败：${err.MsgError}\n尝试修复并找回完好的条目？`)
+        ) {
+          salvage(filePath, password);
         } else {
           alert(JSON.stringify(err));
           console.log("err :", err);
@@ -174,6 +220,42 @@ function App() {
       });
   };
 
+  // 修复损坏的压缩包：完好的条目解压到选择的目录，可同时写入重建的压缩包
+  const salvage = async (filePath: string, password: string) => {
+    const dir = await open({ multiple: false, directory: true });
+    if (!dir) {
+      return;
+    }
+    let rebuild: string | null = null;
+    if (confirm("同时写入重建的压缩包？")) {
+      const name = filePath.slice(filePath.lastIndexOf("/") + 1);
+      rebuild = await save({ defaultPath: `${dir}/fixed-${name}` });
+    }
+    setUzloading(true);
+    archiveSalvage(filePath, password, {
+      extract_to: dir,
+      rebuild: rebuild ?? undefined,
+    })
+      .then((report) => {
+        const damaged = report.damaged
+          .slice(0, 20)
+          .map((d) => `${d.path || "-"}: ${d.error}`);
+        alert(
+          [
+            `找回 ${report.recovered} 个条目，${report.damaged.length} 处损坏`,
+            ...damaged,
+          ].join("\n")
+        );
+      })
+      .catch((err) => {
+        alert(JSON.stringify(err));
+        console.log("err :", err);
+      })
+      .finally(() => {
+        setUzloading(false);
+      });
+  };
+
   const handlePasswordConfirm = (password: string) => {
     loadList(v, password);
     setPassword(password);
@@ -186,11 +268,14 @@ function App() {
     extract(extractPath, password);
   };
 
-  const extract = (extractPath: string, password: string) => {
+  // 密码在打开时已验证并缓存到会话，只有解压时才需要密码的压缩包才会再次传入
+  const extract = (extractPath: string, password?: string) => {
     setUzloading(true);
     setTimeout(() => {
       new Promise(async (ok, _reject) => {
-        archiveExtract(v, extractPath, password)
+        archiveSessionExtract(session, extractPath, password, {
+          recursive: recursive ? {} : null,
+        })
           .then(ok)
           .catch((err) => {
             if (err.MsgError == "密码错误") {
@@ -215,13 +300,119 @@ function App() {
 
     if (file) {
       setExtractPath(file);
-      extract(file, password);
+      extract(file);
+    }
+  };
+
+  // 比较压缩包与解压出的目录
+  const compare = async () => {
+    const dir = await open({ multiple: false, directory: true });
+    if (!dir) {
+      return;
+    }
+    setUzloading(true);
+    archiveCompare(v, dir, password)
+      .then((report) => {
+        setCompareDir(dir);
+        setCompareReport(report);
+      })
+      .catch((err) => {
+        alert(JSON.stringify(err));
+        console.log("err :", err);
+      })
+      .finally(() => {
+        setUzloading(false);
+      });
+  };
+
+  // 与另一个压缩包比较，当前压缩包作为旧版本
+  const diffArchive = async () => {
+    const file = await open({ multiple: false, directory: false });
+    if (!file) {
+      return;
+    }
+    setUzloading(true);
+    archiveDiff(v, password, file, "", { unified: true })
+      .then((diff) => {
+        setDiffWith(file);
+        setDiff(diff);
+      })
+      .catch((err) => {
+        alert(JSON.stringify(err));
+        console.log("err :", err);
+      })
+      .finally(() => {
+        setUzloading(false);
+      });
+  };
+
+  // 进入压缩包中的压缩包
+  const enterArchive = (entry: string) => {
+    loadList(v + NESTED_SEPARATOR + entry, password);
+  };
+
+  // 回到外一层压缩包
+  const leaveArchive = () => {
+    loadList(v.slice(0, v.lastIndexOf(NESTED_SEPARATOR)), password);
+  };
+
+  const openEntry = (entry: string) => {
+    // 双击的第一次单击会打开预览
+    setPreviewEntry(undefined);
+    archiveOpenEntry(session, entry).catch((err) => {
+      alert(JSON.stringify(err));
+      console.log("err :", err);
+    });
+  };
+
+  const writeBack = (modified: EntryModified) => {
+    setModified(undefined);
+    archiveWriteBack(modified.session, modified.entry)
+      .then(() => setTreeVersion((v) => v + 1))
+      .catch((err) => {
+        alert(JSON.stringify(err));
+        console.log("err :", err);
+      });
+  };
+
+  const editArchive = (ops: EditOp[]) => {
+    archiveSessionEdit(session, ops)
+      .then(() => setTreeVersion((v) => v + 1))
+      .catch((err) => {
+        alert(JSON.stringify(err));
+        console.log("err :", err);
+      });
+  };
+
+  // 把文件或文件夹加入压缩包根目录
+  const addToArchive = async (directory: boolean) => {
+    const selected = await open({ multiple: true, directory });
+    if (selected?.length) {
+      editArchive(selected.map((source) => ({ op: "add", source })));
+    }
+  };
+
+  const renameEntry = (path: string) => {
+    const from = path.replace(/\/$/, "");
+    const to = prompt("新的路径", from)?.trim();
+    if (to && to !== from) {
+      editArchive([{ op: "rename", from, to }]);
+    }
+  };
+
+  const deleteEntry = (path: string) => {
+    if (confirm(`从压缩包中删除 ${path}？`)) {
+      editArchive([{ op: "delete", entry: path }]);
     }
   };
 
   useEffect(() => {
     cli_run_args();
     listenFileDrop();
+    const unlisten = onEntryModified(setModified);
+    return () => {
+      unlisten.then((f) => f());
+    };
   }, []);
   return (
     <div className="p-4">
@@ -237,9 +428,42 @@ function App() {
         onConfirm={handleExtractPasswordConfirm}
       />
 
+      {session && (
+        <PreviewDialog
+          session={session}
+          entry={previewEntry}
+          onClose={() => setPreviewEntry(undefined)}
+        />
+      )}
+
+      <WriteBackDialog
+        modified={modified}
+        onClose={() => setModified(undefined)}
+        onConfirm={writeBack}
+      />
+
+      <CreateDialog isOpen={createOpen} onClose={() => setCreateOpen(false)} />
+      <CreateDialog
+        isOpen={convertOpen}
+        onClose={() => setConvertOpen(false)}
+        convertFrom={v}
+        convertPassword={password}
+      />
+      <CompareDialog
+        directory={compareDir}
+        report={compareReport}
+        onClose={() => setCompareReport(undefined)}
+      />
+      <DiffDialog
+        old={v}
+        new={diffWith}
+        diff={diff}
+        onClose={() => setDiff(undefined)}
+      />
+
       <div className="flex align-center items-end gap-2">
         <Button
-          isDisabled={!zipList.length}
+          isDisabled={!session}
           color="primary"
           onPress={zip_to}
           variant="bordered"
@@ -247,10 +471,60 @@ function App() {
         >
           解压到
         </Button>
+        <Checkbox isSelected={recursive} onValueChange={setRecursive}>
+          递归解压
+        </Checkbox>
+        <Button variant="bordered" onPress={() => setCreateOpen(true)}>
+          压缩
+        </Button>
+        {session && (
+          <Button variant="bordered" onPress={() => setConvertOpen(true)}>
+            转换格式
+          </Button>
+        )}
+        {session && (
+          <Button variant="bordered" onPress={compare} isLoading={uzLoading}>
+            比较目录
+          </Button>
+        )}
+        {session && (
+          <Button
+            variant="bordered"
+            onPress={diffArchive}
+            isLoading={uzLoading}
+          >
+            比较压缩包
+          </Button>
+        )}
+        {session && !v.includes(NESTED_SEPARATOR) && (
+          <>
+            <Button variant="bordered" onPress={() => addToArchive(false)}>
+              添加文件
+            </Button>
+            <Button variant="bordered" onPress={() => addToArchive(true)}>
+              添加文件夹
+            </Button>
+          </>
+        )}
+        {v.includes(NESTED_SEPARATOR) && (
+          <Button variant="bordered" onPress={leaveArchive}>
+            返回上层
+          </Button>
+        )}
       </div>
       <div className="mt-2">
-        {!!zipList.length ? (
-          <FileTree width={ww} height={hh} data={zipList} />
+        {session ? (
+          <FileTree
+            key={treeVersion}
+            width={ww}
+            height={hh}
+            session={session}
+            onPreview={setPreviewEntry}
+            onOpen={openEntry}
+            onEnter={enterArchive}
+            onRename={v.includes(NESTED_SEPARATOR) ? undefined : renameEntry}
+            onDelete={v.includes(NESTED_SEPARATOR) ? undefined : deleteEntry}
+          />
         ) : (
           <div
             style={{ width: ww, height: hh }}
@@ -260,7 +534,7 @@ function App() {
               <span className="p-5  z-10">
                 拖拽文件放入
                 <span className="p-5 opacity-1">
-                  支持zip、tar(gz、xz、bz2)、rar、7z
+                  支持zip、tar(gz、xz、bz2、zst)、rar、7z
                 </span>
               </span>
             </div>
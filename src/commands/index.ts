@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 
 // 类型定义
 export interface ArchiveEntry {
@@ -8,14 +9,232 @@ export interface ArchiveEntry {
   size: number;
   is_dir: boolean;
   modified: string | null;
+  /** 条目本身是压缩包，可用 `外层!/条目` 形式的虚拟路径打开 */
+  is_archive: boolean;
 }
 
+/** 虚拟路径中分隔压缩包与其中条目的标记 */
+export const NESTED_SEPARATOR = "!/";
+
+export interface ArchiveBlock {
+  entry_count: number;
+  packed_size: number;
+  unpacked_size: number;
+  methods: string[];
+}
+
+export interface ArchiveInfo {
+  format: string;
+  comment: string | null;
+  entry_count: number;
+  packed_size: number;
+  unpacked_size: number;
+  is_zip64: boolean;
+  is_multi_volume: boolean;
+  is_solid: boolean;
+  is_locked: boolean;
+  is_encrypted: boolean;
+  blocks: ArchiveBlock[];
+}
+
+export interface ExtractOptions {
+  restore_mtime?: boolean;
+  restore_atime?: boolean;
+  restore_permissions?: boolean;
+  umask?: number;
+  keep_special_bits?: boolean;
+  restore_ownership?: boolean;
+  links?: LinkPolicy;
+  restore_xattrs?: boolean;
+  /** 同时恢复 trusted.、security. 等特权命名空间的属性，默认只恢复 user. 属性与 ACL */
+  restore_privileged_xattrs?: boolean;
+  include?: string[];
+  exclude?: string[];
+  /** 把解压出的压缩包继续解压到同名目录 */
+  recursive?: RecursiveExtract | null;
+}
+
+export interface RecursiveExtract {
+  /** 最多继续解压的层数，默认 4 */
+  max_depth?: number;
+  /** 递归解压出的内容总大小上限（字节），默认 8 GiB */
+  max_size?: number;
+  /** 解压成功后删除中间的压缩包 */
+  delete_archives?: boolean;
+}
+
+export type LinkPolicy =
+  | "materialize"
+  | "skip"
+  | "copy_target"
+  | "refuse_escaping";
+
 export interface TreeNode<T> {
   item: T;
   children: TreeNode<T>[] | null;
 }
 
+export interface DirItem extends ArchiveEntry {
+  /** 直接子条目数 */
+  child_count: number;
+  /** 其下所有文件数 */
+  file_count: number;
+  /** 其下所有文件的总大小 */
+  total_size: number;
+}
+
+export interface DirPage {
+  path: string;
+  total: number;
+  offset: number;
+  items: DirItem[];
+}
+
+export interface DirSort {
+  key?: "name" | "size" | "modified";
+  descending?: boolean;
+}
+
+/** 条目内容中的一段，length 为空表示到末尾 */
+export interface ByteRange {
+  offset?: number;
+  length?: number | null;
+}
+
+export interface EntryPreview {
+  path: string;
+  /** 条目的完整大小 */
+  size: number;
+  offset: number;
+  /** 本次返回的字节数 */
+  length: number;
+  mime: string;
+  /** 能解码为文本时的内容 */
+  text?: string | null;
+  encoding?: string | null;
+  /** 非文本内容的 base64 */
+  data?: string | null;
+  /** 之后还有内容未返回 */
+  truncated: boolean;
+}
+
+/** 用系统程序打开的条目被修改，`archive-entry-modified` 事件的内容 */
+export interface EntryModified {
+  session: string;
+  entry: string;
+  /** 临时文件的路径 */
+  file: string;
+}
+
 // 命令调用函数
+/** 打开压缩包并建立索引，返回会话 ID */
+export async function archiveOpen(
+  path: string,
+  password: string,
+  maxDepth?: number
+): Promise<string> {
+  return invoke<string>("archive_open", { path, password, maxDepth });
+}
+
+/** 分页列出会话中的一个目录，根目录为 "/" */
+export async function archiveListDir(
+  session: string,
+  dirPath: string,
+  offset: number,
+  limit: number,
+  sort?: DirSort
+): Promise<DirPage> {
+  return invoke<DirPage>("archive_list_dir", {
+    session,
+    dirPath,
+    offset,
+    limit,
+    sort,
+  });
+}
+
+/** 会话对应压缩包的信息，首次读取后缓存 */
+export async function archiveSessionInfo(session: string): Promise<ArchiveInfo> {
+  return invoke<ArchiveInfo>("archive_session_info", { session });
+}
+
+/** 用会话解压；传入 password 时改用该密码，成功后缓存到会话 */
+export async function archiveSessionExtract(
+  session: string,
+  targetPath: string,
+  password?: string,
+  options?: ExtractOptions
+): Promise<void> {
+  return invoke<void>("archive_session_extract", {
+    session,
+    targetPath,
+    password,
+    options,
+  });
+}
+
+/** 预览会话中的条目，单次最多返回 1 MiB */
+export async function archiveSessionReadEntry(
+  session: string,
+  entry: string,
+  range?: ByteRange
+): Promise<EntryPreview> {
+  return invoke<EntryPreview>("archive_session_read_entry", {
+    session,
+    entry,
+    range,
+  });
+}
+
+/** 把条目解压到临时目录并用系统默认程序打开，返回临时文件路径 */
+export async function archiveOpenEntry(
+  session: string,
+  entry: string
+): Promise<string> {
+  return invoke<string>("archive_open_entry", { session, entry });
+}
+
+/** 把修改过的临时文件写回压缩包，会话随之刷新 */
+export async function archiveWriteBack(
+  session: string,
+  entry: string
+): Promise<void> {
+  return invoke<void>("archive_write_back", { session, entry });
+}
+
+/** 对压缩包的一项修改，压缩包内的路径使用 `/` 分隔 */
+export type EditOp =
+  /** 把磁盘上的文件或文件夹加入 dest 目录（默认根目录），替换同名条目 */
+  | { op: "add"; source: string; dest?: string }
+  | { op: "replace"; entry: string; source: string }
+  /** 目录连同其中的全部条目 */
+  | { op: "delete"; entry: string }
+  /** 目录连同其中的全部条目；目标已存在时被覆盖 */
+  | { op: "rename"; from: string; to: string };
+
+/** 按顺序修改会话中的压缩包，全部成功后才替换原文件，会话随之刷新 */
+export async function archiveSessionEdit(
+  session: string,
+  ops: EditOp[]
+): Promise<void> {
+  return invoke<void>("archive_session_edit", { session, ops });
+}
+
+/** 监听当前窗口中打开的条目被修改，返回取消监听的函数 */
+export async function onEntryModified(
+  handler: (modified: EntryModified) => void
+): Promise<() => void> {
+  return getCurrentWebviewWindow().listen<EntryModified>(
+    "archive-entry-modified",
+    (event) => handler(event.payload)
+  );
+}
+
+/** 关闭会话；窗口关闭时其会话会自动关闭 */
+export async function archiveClose(session: string): Promise<void> {
+  return invoke<void>("archive_close", { session });
+}
+
 export async function archiveListFiles(
   path: string,
   password: string
@@ -26,12 +245,355 @@ export async function archiveListFiles(
   });
 }
 
+export interface EntryBatch {
+  path: string;
+  entries: ArchiveEntry[];
+}
+
+/** 边读取边分批接收条目，返回条目总数 */
+export async function archiveStreamEntries(
+  path: string,
+  password: string,
+  onBatch: (entries: ArchiveEntry[]) => void,
+  batchSize?: number
+): Promise<number> {
+  const unlisten = await getCurrentWebviewWindow().listen<EntryBatch>(
+    "archive-entries",
+    (event) => {
+      if (event.payload.path === path) {
+        onBatch(event.payload.entries);
+      }
+    }
+  );
+  try {
+    return await invoke<number>("archive_stream_entries", {
+      path,
+      password,
+      batchSize,
+    });
+  } finally {
+    unlisten();
+  }
+}
+
 export async function archiveExtract(
   path: string,
   targetPath: string,
+  password: string,
+  options?: ExtractOptions
+): Promise<void> {
+  return invoke<void>("archive_extract", {
+    path,
+    targetPath,
+    password,
+    options,
+  });
+}
+
+export async function archiveInfo(
+  path: string,
   password: string
+): Promise<ArchiveInfo> {
+  return invoke<ArchiveInfo>("archive_info", { path, password });
+}
+
+/** 预览单个条目，单次最多返回 1 MiB */
+export async function archiveReadEntry(
+  path: string,
+  entry: string,
+  password: string,
+  range?: ByteRange
+): Promise<EntryPreview> {
+  return invoke<EntryPreview>("archive_read_entry", {
+    path,
+    entry,
+    password,
+    range,
+  });
+}
+
+/** 可以创建的格式，与 `ArchiveFormat` 的序列化名称一致 */
+export type CreateFormat =
+  | "Zip"
+  | "Tar"
+  | "TarGz"
+  | "TarBz2"
+  | "TarXz"
+  | "TarZst"
+  | "SevenZip";
+
+export type ZipMethod = "deflate" | "store" | "bzip2" | "zstd";
+
+export type CompressionLevel =
+  | "store"
+  | "fastest"
+  | "fast"
+  | "normal"
+  | "maximum"
+  | "ultra";
+
+export interface CompressionOptions {
+  /** 默认 normal */
+  level?: CompressionLevel;
+  /** zip 条目的压缩方式，默认 deflate */
+  method?: ZipMethod;
+  /** 7z 与 xz 的 LZMA2 字典大小（字节），为空时按压缩级别 */
+  dictionary_size?: number | null;
+  /** 7z 固实块的大小（字节），为空时不使用固实压缩 */
+  solid_block_size?: number | null;
+  /** xz 与 zstd 的压缩线程数，0 表示按 CPU 核数，默认 1 */
+  threads?: number;
+}
+
+/** 某种格式支持的创建选项 */
+export interface FormatCapabilities {
+  format: CreateFormat;
+  /** 默认扩展名，不含开头的 `.` */
+  extension: string;
+  /** 可选的压缩级别，为空时不压缩 */
+  levels: CompressionLevel[];
+  /** 可选的压缩方式，只有 zip 不为空 */
+  methods: ZipMethod[];
+  dictionary_size: boolean;
+  solid_block_size: boolean;
+  threads: boolean;
+  encryption: boolean;
+  encrypt_header: boolean;
+}
+
+export interface CreateOptions {
+  compression?: CompressionOptions;
+  /** 可重复构建：相同的输入总是得到相同的字节，不能与加密同时使用 */
+  deterministic?: boolean;
+  /** 可重复构建时条目的修改时间（unix 秒），默认读取 `SOURCE_DATE_EPOCH` */
+  source_date_epoch?: number | null;
+  /** 加密，只有 zip 与 7z 支持 */
+  encryption?: Encryption | null;
+  /** 分卷大小（字节），输出为 `output.001`、`output.002`……，为空时不分卷 */
+  volume_size?: number | null;
+}
+
+export interface Encryption {
+  password: string;
+  /** 7z 同时加密文件名 */
+  encrypt_header?: boolean;
+}
+
+export interface CreateProgress {
+  output: string;
+  /** 正在写入的条目 */
+  entry: string;
+  entries_done: number;
+  entries_total: number;
+  bytes_done: number;
+  bytes_total: number;
+}
+
+/** 把文件与文件夹打包为 `output`，未指定格式时按扩展名选择 */
+export async function archiveCreate(
+  sources: string[],
+  output: string,
+  format?: CreateFormat,
+  options?: CreateOptions,
+  onProgress?: (progress: CreateProgress) => void
+): Promise<void> {
+  const unlisten = await getCurrentWebviewWindow().listen<CreateProgress>(
+    "archive-create-progress",
+    (event) => {
+      if (event.payload.output === output) {
+        onProgress?.(event.payload);
+      }
+    }
+  );
+  try {
+    return await invoke<void>("archive_create", {
+      sources,
+      output,
+      format,
+      options,
+    });
+  } finally {
+    unlisten();
+  }
+}
+
+/**
+ * 把压缩包（可以是嵌套压缩包的虚拟路径）转换为另一种格式，条目边读边写，不解压到磁盘
+ *
+ * `password` 用于打开 `input`，输出的加密由 `options.encryption` 决定；
+ * 进度同样通过 `archive-create-progress` 事件推送。
+ */
+export async function archiveConvert(
+  input: string,
+  output: string,
+  password: string,
+  format?: CreateFormat,
+  options?: CreateOptions,
+  onProgress?: (progress: CreateProgress) => void
 ): Promise<void> {
-  return invoke<void>("archive_extract", { path, targetPath, password });
+  const unlisten = await getCurrentWebviewWindow().listen<CreateProgress>(
+    "archive-create-progress",
+    (event) => {
+      if (event.payload.output === output) {
+        onProgress?.(event.payload);
+      }
+    }
+  );
+  try {
+    return await invoke<void>("archive_convert", {
+      input,
+      output,
+      format,
+      options,
+      password,
+    });
+  } finally {
+    unlisten();
+  }
+}
+
+/** 内容不同之处，archive 为压缩包中的值，disk 为目录中的值 */
+export type Change =
+  | { kind: "kind"; archive: string; disk: string }
+  | { kind: "size"; archive: number; disk: number }
+  /** 值为十六进制的 SHA-256 */
+  | { kind: "content"; archive: string; disk: string }
+  | { kind: "link_target"; archive: string; disk: string }
+  | { kind: "unreadable"; error: string };
+
+/** 压缩包与目录的比较结果，各列表按路径排序 */
+export interface CompareReport {
+  compared: number;
+  /** 压缩包中有、目录中没有 */
+  missing: string[];
+  /** 目录中有、压缩包中没有 */
+  extra: string[];
+  modified: { path: string; change: Change }[];
+  /** 各字段为 [压缩包中的值, 目录中的值]，相同时为 null */
+  metadata_changed: {
+    path: string;
+    mtime: [number, number] | null;
+    mode: [number, number] | null;
+  }[];
+}
+
+/** 比较压缩包与目录，确认解压出的文件与压缩包仍然一致 */
+export async function archiveCompare(
+  archive: string,
+  directory: string,
+  password: string
+): Promise<CompareReport> {
+  return invoke<CompareReport>("archive_compare", {
+    archive,
+    directory,
+    password,
+  });
+}
+
+export type DiffStatus =
+  | "unchanged"
+  | "added"
+  | "removed"
+  | "changed"
+  | "metadata";
+
+/** 条目在一个压缩包中的信息 */
+export interface EntryDigest {
+  kind: "file" | "dir" | "symlink";
+  size: number;
+  crc32: number;
+  /** 十六进制的 SHA-256，目录与符号链接为空 */
+  sha256: string;
+  mtime: number;
+  mode: number;
+  link_target: string | null;
+}
+
+export interface DiffEntry {
+  name: string;
+  /** 目录以 / 结尾 */
+  path: string;
+  parent_path: string;
+  is_dir: boolean;
+  status: DiffStatus;
+  /** 不同的属性：kind、size、crc32、sha256、link_target、mtime、mode */
+  changes: string[];
+  old: EntryDigest | null;
+  new: EntryDigest | null;
+  /** 目录下有变化的条目 */
+  changed_below: boolean;
+  /** 内容不同的文本条目的统一格式 diff */
+  unified: string | null;
+}
+
+export interface ArchiveDiff {
+  added: number;
+  removed: number;
+  changed: number;
+  metadata: number;
+  unchanged: number;
+  tree: TreeNode<DiffEntry>[];
+}
+
+export interface DiffOptions {
+  /** 为内容不同的文本条目生成统一格式的 diff */
+  unified?: boolean;
+  /** diff 的上下文行数，默认 3 */
+  context?: number;
+  /** 超过该大小（字节）的条目不生成 diff，默认 1 MiB */
+  text_limit?: number;
+}
+
+/** 比较两个压缩包，返回按目录组织的差异树 */
+export async function archiveDiff(
+  old: string,
+  oldPassword: string,
+  newArchive: string,
+  newPassword: string,
+  options?: DiffOptions
+): Promise<ArchiveDiff> {
+  return invoke<ArchiveDiff>("archive_diff", {
+    old,
+    oldPassword,
+    new: newArchive,
+    newPassword,
+    options,
+  });
+}
+
+/** 修复选项，都为空时只检查哪些条目完好 */
+export interface SalvageOptions {
+  /** 把完好的条目解压到该目录 */
+  extract_to?: string;
+  /** 把完好的条目写入新的压缩包，格式与原压缩包相同 */
+  rebuild?: string;
+}
+
+export interface SalvageReport {
+  recovered: number;
+  /** 头部损坏、无法得知名称时 path 为空 */
+  damaged: { path: string; error: string }[];
+  /** zip 的中央目录损坏，条目由扫描本地文件头得到 */
+  rebuilt_directory: boolean;
+  skipped_bytes: number;
+}
+
+/** 从损坏的压缩包中找回完好的条目，支持 zip 与 tar */
+export async function archiveSalvage(
+  archive: string,
+  password: string,
+  options?: SalvageOptions
+): Promise<SalvageReport> {
+  return invoke<SalvageReport>("archive_salvage", {
+    archive,
+    password,
+    options,
+  });
+}
+
+/** 可以创建的格式及各自支持的压缩选项 */
+export async function archiveCreateFormats(): Promise<FormatCapabilities[]> {
+  return invoke<FormatCapabilities[]>("archive_create_formats");
 }
 
 export async function run_args(): Promise<string[]> {
@@ -0,0 +1,115 @@
+import {
+  Button,
+  Modal,
+  ModalContent,
+  ModalHeader,
+  ModalBody,
+  ModalFooter,
+} from "@heroui/react";
+import { Change, CompareReport } from "../commands";
+
+interface CompareDialogProps {
+  /** 比较的目录 */
+  directory: string;
+  /** 比较结果，为空时关闭 */
+  report?: CompareReport;
+  onClose: () => void;
+}
+
+const describe = (change: Change) => {
+  switch (change.kind) {
+    case "kind":
+      return `类型不同 ${change.archive} → ${change.disk}`;
+    case "size":
+      return `大小不同 ${change.archive} → ${change.disk}`;
+    case "content":
+      return "内容不同";
+    case "link_target":
+      return `链接目标不同 ${change.archive} → ${change.disk}`;
+    case "unreadable":
+      return `无法读取: ${change.error}`;
+  }
+};
+
+const formatTime = (seconds: number) =>
+  new Date(seconds * 1000).toLocaleString();
+
+const Section = ({ title, items }: { title: string; items: string[] }) =>
+  items.length == 0 ? null : (
+    <div>
+      <div className="font-bold">
+        {title}（{items.length}）
+      </div>
+      <ul className="text-sm break-all">
+        {items.map((item) => (
+          <li key={item}>{item}</li>
+        ))}
+      </ul>
+    </div>
+  );
+
+const CompareDialog = ({ directory, report, onClose }: CompareDialogProps) => {
+  const same =
+    report &&
+    report.missing.length +
+      report.extra.length +
+      report.modified.length +
+      report.metadata_changed.length ==
+      0;
+
+  return (
+    <Modal
+      isOpen={!!report}
+      onClose={onClose}
+      size="3xl"
+      scrollBehavior="inside"
+    >
+      <ModalContent>
+        <ModalHeader className="break-all">比较 {directory}</ModalHeader>
+        <ModalBody>
+          {report && (
+            <>
+              <div>
+                已比较 {report.compared} 个条目
+                {same && "，目录与压缩包一致"}
+              </div>
+              <Section title="缺失" items={report.missing} />
+              <Section title="多出" items={report.extra} />
+              <Section
+                title="内容不同"
+                items={report.modified.map(
+                  (m) => `${m.path}：${describe(m.change)}`
+                )}
+              />
+              <Section
+                title="属性不同"
+                items={report.metadata_changed.map((m) =>
+                  [
+                    m.path,
+                    m.mtime &&
+                      `修改时间 ${formatTime(m.mtime[0])} → ${formatTime(
+                        m.mtime[1]
+                      )}`,
+                    m.mode &&
+                      `权限 ${m.mode[0].toString(8)} → ${m.mode[1].toString(
+                        8
+                      )}`,
+                  ]
+                    .filter(Boolean)
+                    .join("  ")
+                )}
+              />
+            </>
+          )}
+        </ModalBody>
+        <ModalFooter>
+          <Button color="primary" onPress={onClose}>
+            关闭
+          </Button>
+        </ModalFooter>
+      </ModalContent>
+    </Modal>
+  );
+};
+
+export default CompareDialog;
@@ -0,0 +1,356 @@
+import { useEffect, useState } from "react";
+import {
+  Button,
+  Checkbox,
+  Input,
+  Modal,
+  ModalContent,
+  ModalHeader,
+  ModalBody,
+  ModalFooter,
+  Progress,
+  Select,
+  SelectItem,
+} from "@heroui/react";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import {
+  archiveConvert,
+  archiveCreate,
+  archiveCreateFormats,
+  CompressionLevel,
+  CreateFormat,
+  CreateProgress,
+  FormatCapabilities,
+  ZipMethod,
+} from "../commands";
+
+const LEVEL_LABELS: Record<CompressionLevel, string> = {
+  store: "仅存储",
+  fastest: "极速压缩",
+  fast: "快速压缩",
+  normal: "标准压缩",
+  maximum: "最大压缩",
+  ultra: "极限压缩",
+};
+
+const METHOD_LABELS: Record<ZipMethod, string> = {
+  deflate: "Deflate",
+  store: "不压缩",
+  bzip2: "BZip2",
+  zstd: "Zstandard",
+};
+
+const MB = 1 << 20;
+
+/** 字典大小，空字符串表示按压缩级别 */
+const DICTIONARY_SIZES = [1, 4, 16, 32, 64, 256].map((n) => n * MB);
+
+/** 固实块大小，空字符串表示不使用固实压缩 */
+const SOLID_BLOCK_SIZES = [16, 64, 256, 1024, 4096].map((n) => n * MB);
+
+/** 分卷大小，与 7-Zip 的预设一致；空字符串表示不分卷 */
+const VOLUME_SIZES: [number, string][] = [
+  [10 * MB, "10 MB"],
+  [100 * MB, "100 MB"],
+  [700 * MB, "700 MB - CD"],
+  [4092 * MB, "4092 MB - FAT32"],
+  [4480 * MB, "4480 MB - DVD"],
+];
+
+const formatSize = (size: number) =>
+  size >= 1024 * MB ? `${size / 1024 / MB} GB` : `${size / MB} MB`;
+
+interface CreateDialogProps {
+  isOpen: boolean;
+  onClose: () => void;
+  /** 设置时把该压缩包转换为所选格式，不再选择文件 */
+  convertFrom?: string;
+  /** 打开 `convertFrom` 使用的密码 */
+  convertPassword?: string;
+}
+
+const CreateDialog = ({
+  isOpen,
+  onClose,
+  convertFrom,
+  convertPassword = "",
+}: CreateDialogProps) => {
+  const [sources, setSources] = useState<string[]>([]);
+  const [formats, setFormats] = useState<FormatCapabilities[]>([]);
+  const [format, setFormat] = useState<CreateFormat>("Zip");
+  const [level, setLevel] = useState<CompressionLevel>("normal");
+  const [method, setMethod] = useState<ZipMethod>("deflate");
+  const [dictionarySize, setDictionarySize] = useState("");
+  const [solidBlockSize, setSolidBlockSize] = useState("");
+  const [threads, setThreads] = useState("1");
+  const [deterministic, setDeterministic] = useState(false);
+  const [volumeSize, setVolumeSize] = useState("");
+  const [password, setPassword] = useState("");
+  const [encryptHeader, setEncryptHeader] = useState(false);
+  const [progress, setProgress] = useState<CreateProgress>();
+  const [creating, setCreating] = useState(false);
+
+  useEffect(() => {
+    archiveCreateFormats().then(setFormats).catch(console.error);
+  }, []);
+
+  const capabilities = formats.find((f) => f.format === format);
+
+  const changeFormat = (next: CreateFormat) => {
+    setFormat(next);
+    const levels = formats.find((f) => f.format === next)?.levels ?? [];
+    if (levels.length && !levels.includes(level)) {
+      setLevel(levels.includes("normal") ? "normal" : levels[0]);
+    }
+  };
+
+  const addSources = async (directory: boolean) => {
+    const selected = await open({ multiple: true, directory });
+    if (selected) {
+      setSources((prev) => [
+        ...prev,
+        ...selected.filter((path) => !prev.includes(path)),
+      ]);
+    }
+  };
+
+  const handleClose = () => {
+    if (creating) {
+      return;
+    }
+    setSources([]);
+    setPassword("");
+    setProgress(undefined);
+    onClose();
+  };
+
+  // 加密使用随机的盐，输出无法重复
+  const canEncrypt = !!capabilities?.encryption && !deterministic;
+
+  const create = async () => {
+    if (!capabilities) {
+      return;
+    }
+    const { extension } = capabilities;
+    const output = await save({
+      filters: [{ name: extension, extensions: [extension] }],
+    });
+    if (!output) {
+      return;
+    }
+    setCreating(true);
+    const encryption =
+      password && canEncrypt
+        ? {
+            password,
+            encrypt_header: capabilities.encrypt_header && encryptHeader,
+          }
+        : null;
+    const compression = {
+      level: capabilities.levels.length ? level : undefined,
+      method: capabilities.methods.length ? method : undefined,
+      dictionary_size:
+        capabilities.dictionary_size && dictionarySize
+          ? Number(dictionarySize)
+          : null,
+      solid_block_size:
+        capabilities.solid_block_size && solidBlockSize
+          ? Number(solidBlockSize)
+          : null,
+      threads: capabilities.threads ? Number(threads) || 0 : undefined,
+    };
+    const options = {
+      compression,
+      deterministic,
+      encryption,
+      volume_size: volumeSize ? Number(volumeSize) : null,
+    };
+    (convertFrom
+      ? archiveConvert(
+          convertFrom,
+          output,
+          convertPassword,
+          format,
+          options,
+          setProgress
+        )
+      : archiveCreate(sources, output, format, options, setProgress)
+    )
+      .then(() => {
+        alert(convertFrom ? "转换完成" : "压缩完成");
+        setCreating(false);
+        handleClose();
+      })
+      .catch((err) => {
+        alert(JSON.stringify(err));
+        console.log("err :", err);
+        setCreating(false);
+      });
+  };
+
+  return (
+    <Modal isOpen={isOpen} onClose={handleClose} size="2xl">
+      <ModalContent>
+        <ModalHeader>{convertFrom ? "转换格式" : "创建压缩包"}</ModalHeader>
+        <ModalBody>
+          {convertFrom ? (
+            <p className="text-sm break-all">{convertFrom}</p>
+          ) : (
+            <>
+              <div className="flex gap-2">
+                <Button variant="bordered" onPress={() => addSources(false)}>
+                  添加文件
+                </Button>
+                <Button variant="bordered" onPress={() => addSources(true)}>
+                  添加文件夹
+                </Button>
+              </div>
+              <ul className="max-h-48 overflow-auto text-sm">
+                {sources.map((source) => (
+                  <li key={source} className="break-all">
+                    {source}
+                  </li>
+                ))}
+              </ul>
+            </>
+          )}
+          <Select
+            label="格式"
+            selectedKeys={[format]}
+            onChange={(e) =>
+              e.target.value && changeFormat(e.target.value as CreateFormat)
+            }
+          >
+            {formats.map((f) => (
+              <SelectItem key={f.format}>{f.extension}</SelectItem>
+            ))}
+          </Select>
+          {capabilities && capabilities.levels.length > 0 && (
+            <Select
+              label="压缩级别"
+              selectedKeys={[level]}
+              onChange={(e) =>
+                e.target.value && setLevel(e.target.value as CompressionLevel)
+              }
+            >
+              {capabilities.levels.map((l) => (
+                <SelectItem key={l}>{LEVEL_LABELS[l]}</SelectItem>
+              ))}
+            </Select>
+          )}
+          {capabilities && capabilities.methods.length > 0 && (
+            <Select
+              label="压缩方式"
+              selectedKeys={[method]}
+              onChange={(e) =>
+                e.target.value && setMethod(e.target.value as ZipMethod)
+              }
+            >
+              {capabilities.methods.map((m) => (
+                <SelectItem key={m}>{METHOD_LABELS[m]}</SelectItem>
+              ))}
+            </Select>
+          )}
+          {capabilities?.dictionary_size && (
+            <Select
+              label="字典大小"
+              placeholder="按压缩级别"
+              selectedKeys={dictionarySize ? [dictionarySize] : []}
+              onChange={(e) => setDictionarySize(e.target.value)}
+            >
+              {DICTIONARY_SIZES.map((size) => (
+                <SelectItem key={String(size)}>{formatSize(size)}</SelectItem>
+              ))}
+            </Select>
+          )}
+          {capabilities?.solid_block_size && (
+            <Select
+              label="固实块大小"
+              placeholder="非固实"
+              selectedKeys={solidBlockSize ? [solidBlockSize] : []}
+              onChange={(e) => setSolidBlockSize(e.target.value)}
+            >
+              {SOLID_BLOCK_SIZES.map((size) => (
+                <SelectItem key={String(size)}>{formatSize(size)}</SelectItem>
+              ))}
+            </Select>
+          )}
+          {capabilities?.threads && (
+            <Input
+              type="number"
+              label="线程数"
+              description="0 表示按 CPU 核数"
+              min={0}
+              value={threads}
+              onValueChange={setThreads}
+            />
+          )}
+          <Select
+            label="分卷大小"
+            placeholder="不分卷"
+            description="输出为 .001、.002……，可用 7-Zip 或 WinRAR 打开第一卷"
+            selectedKeys={volumeSize ? [volumeSize] : []}
+            onChange={(e) => setVolumeSize(e.target.value)}
+          >
+            {VOLUME_SIZES.map(([size, label]) => (
+              <SelectItem key={String(size)}>{label}</SelectItem>
+            ))}
+          </Select>
+          <Checkbox isSelected={deterministic} onValueChange={setDeterministic}>
+            可重复构建（固定时间与权限，相同输入得到相同文件）
+          </Checkbox>
+          <Input
+            type="password"
+            label="密码"
+            placeholder={
+              canEncrypt
+                ? "留空则不加密"
+                : deterministic
+                ? "可重复构建时不能加密"
+                : "该格式不支持加密"
+            }
+            isDisabled={!canEncrypt}
+            value={password}
+            onValueChange={setPassword}
+          />
+          {capabilities?.encrypt_header && canEncrypt && (
+            <Checkbox
+              isSelected={encryptHeader}
+              onValueChange={setEncryptHeader}
+            >
+              加密文件名
+            </Checkbox>
+          )}
+          {progress && (
+            <Progress
+              label={progress.entry}
+              // tar 类格式转换时事先不知道总数
+              isIndeterminate={!progress.entries_total}
+              value={
+                progress.bytes_total
+                  ? (progress.bytes_done / progress.bytes_total) * 100
+                  : (progress.entries_done / progress.entries_total) * 100
+              }
+              showValueLabel
+            />
+          )}
+        </ModalBody>
+        <ModalFooter>
+          <Button color="danger" variant="light" onPress={handleClose}>
+            取消
+          </Button>
+          <Button
+            color="primary"
+            isDisabled={!convertFrom && sources.length === 0}
+            isLoading={creating}
+            onPress={create}
+          >
+            {convertFrom ? "转换" : "压缩"}
+          </Button>
+        </ModalFooter>
+      </ModalContent>
+    </Modal>
+  );
+};
+
+export default CreateDialog;
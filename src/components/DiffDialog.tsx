@@ -0,0 +1,172 @@
+import { useMemo, useState } from "react";
+import { Tree } from "react-arborist";
+import {
+  Button,
+  Checkbox,
+  Modal,
+  ModalContent,
+  ModalHeader,
+  ModalBody,
+  ModalFooter,
+} from "@heroui/react";
+import { ArchiveDiff, DiffEntry, DiffStatus, TreeNode } from "../commands";
+
+interface DiffDialogProps {
+  /** 旧、新压缩包的路径 */
+  old: string;
+  new: string;
+  /** 比较结果，为空时关闭 */
+  diff?: ArchiveDiff;
+  onClose: () => void;
+}
+
+interface DiffNode {
+  id: string;
+  name: string;
+  entry: DiffEntry;
+  children?: DiffNode[];
+}
+
+const STATUS: Record<DiffStatus, { label: string; color: string }> = {
+  unchanged: { label: "", color: "" },
+  added: { label: "新增", color: "text-green-600" },
+  removed: { label: "删除", color: "text-red-600" },
+  changed: { label: "修改", color: "text-orange-500" },
+  metadata: { label: "属性", color: "text-blue-500" },
+};
+
+// 转换为 react-arborist 的节点，onlyChanged 时去掉没有变化的条目
+function toNodes(
+  nodes: TreeNode<DiffEntry>[],
+  onlyChanged: boolean
+): DiffNode[] {
+  return nodes
+    .filter(
+      ({ item }) =>
+        !onlyChanged || item.status != "unchanged" || item.changed_below
+    )
+    .map(({ item, children }) => ({
+      id: item.path,
+      name: item.name,
+      entry: item,
+      children: item.is_dir
+        ? toNodes(children ?? [], onlyChanged)
+        : undefined,
+    }));
+}
+
+const DiffDialog = ({ old, new: newPath, diff, onClose }: DiffDialogProps) => {
+  const [onlyChanged, setOnlyChanged] = useState(true);
+  const [selected, setSelected] = useState<DiffEntry>();
+  const data = useMemo(
+    () => toNodes(diff?.tree ?? [], onlyChanged),
+    [diff, onlyChanged]
+  );
+
+  const close = () => {
+    setSelected(undefined);
+    onClose();
+  };
+
+  return (
+    <Modal isOpen={!!diff} onClose={close} size="5xl" scrollBehavior="inside">
+      <ModalContent>
+        <ModalHeader className="flex flex-col break-all text-sm">
+          <span>旧：{old}</span>
+          <span>新：{newPath}</span>
+        </ModalHeader>
+        <ModalBody>
+          {diff && (
+            <div className="flex gap-4 items-center">
+              <span>
+                新增 {diff.added}，删除 {diff.removed}，修改 {diff.changed}
+                ，属性不同 {diff.metadata}，相同 {diff.unchanged}
+              </span>
+              <Checkbox isSelected={onlyChanged} onValueChange={setOnlyChanged}>
+                只显示变化
+              </Checkbox>
+            </div>
+          )}
+          <Tree
+            data={data}
+            width="100%"
+            height={360}
+            indent={20}
+            rowHeight={28}
+            openByDefault={true}
+          >
+            {({ node, style }) => {
+              const entry = node.data.entry;
+              const status = STATUS[entry.status];
+              return (
+                <div
+                  style={style}
+                  className={`flex gap-2 px-2 cursor-pointer items-center ${
+                    selected?.path == entry.path ? "bg-blue-100" : ""
+                  }`}
+                  onClick={() =>
+                    node.isLeaf ? setSelected(entry) : node.toggle()
+                  }
+                >
+                  <span className={status.color}>
+                    {entry.is_dir ? `${node.data.name}/` : node.data.name}
+                  </span>
+                  <span className={`text-xs ${status.color}`}>
+                    {status.label}
+                  </span>
+                  {entry.changes.length > 0 && (
+                    <span className="text-xs text-gray-500 ml-auto">
+                      {entry.changes.join(", ")}
+                    </span>
+                  )}
+                </div>
+              );
+            }}
+          </Tree>
+          {selected && (
+            <div className="text-sm break-all">
+              <div className="font-bold">{selected.path}</div>
+              {[selected.old, selected.new].map(
+                (digest, i) =>
+                  digest && (
+                    <div key={i}>
+                      {i == 0 ? "旧" : "新"}：{digest.size} 字节，crc32{" "}
+                      {digest.crc32.toString(16).padStart(8, "0")}，修改于{" "}
+                      {new Date(digest.mtime * 1000).toLocaleString()}，权限{" "}
+                      {digest.mode.toString(8)}
+                      {digest.link_target && `，链接到 ${digest.link_target}`}
+                    </div>
+                  )
+              )}
+              {selected.unified && (
+                <pre className="mt-2 p-2 bg-gray-100 overflow-auto text-xs">
+                  {selected.unified.split("\n").map((line, i) => (
+                    <div
+                      key={i}
+                      className={
+                        line.startsWith("+") && !line.startsWith("+++")
+                          ? "text-green-700"
+                          : line.startsWith("-") && !line.startsWith("---")
+                          ? "text-red-700"
+                          : ""
+                      }
+                    >
+                      {line}
+                    </div>
+                  ))}
+                </pre>
+              )}
+            </div>
+          )}
+        </ModalBody>
+        <ModalFooter>
+          <Button color="primary" onPress={close}>
+            关闭
+          </Button>
+        </ModalFooter>
+      </ModalContent>
+    </Modal>
+  );
+};
+
+export default DiffDialog;
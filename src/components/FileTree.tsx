@@ -1,14 +1,16 @@
+import { useEffect, useRef, useState } from "react";
 import { Tree } from "react-arborist";
+import { archiveListDir, DirItem } from "../commands";
 import close from "../assets/close.svg";
 import open from "../assets/open.svg";
 import file from "../assets/file.svg";
-// import {
-//   Dropdown,
-//   DropdownTrigger,
-//   DropdownMenu,
-//   DropdownItem,
-//   Chip,
-// } from "@heroui/react";
+import {
+  Dropdown,
+  DropdownTrigger,
+  DropdownMenu,
+  DropdownItem,
+  Chip,
+} from "@heroui/react";
 
 interface FileNode {
   id: string;
@@ -16,48 +18,128 @@ interface FileNode {
   path: string;
   size: number;
   is_dir: boolean;
-  modified?: string;
+  modified?: string | null;
+  file_count?: number;
+  is_archive?: boolean;
+  /** "加载更多" 占位节点，点击时从 offset 继续加载所在目录 */
+  more?: { dir: string; offset: number };
   children?: FileNode[];
 }
 
-// 转换数据结构
-function transformData(data: any[]): FileNode[] {
-  // 先转换数据结构
-  const transformedData = data.map((node) => ({
-    id: node.item.path,
-    name: node.item.name || node.item.path.split("/").slice(-2)[0],
-    path: node.item.path,
-    size: node.item.size,
-    is_dir: node.item.is_dir,
-    modified: node.item.modified,
-    children: node.children ? transformData(node.children) : undefined,
-  }));
+/** 每次向后端请求的条目数 */
+const PAGE_SIZE = 200;
+const ROOT = "/";
 
-  // 排序：目录(is_dir=true)排在前面，然后按照modified时间最新的排序
-  return transformedData.sort((a, b) => {
-    // 首先按照是否为目录排序（目录排在前面）
-    if (a.is_dir && !b.is_dir) return -1;
-    if (!a.is_dir && b.is_dir) return 1;
+function toNode(item: DirItem): FileNode {
+  return {
+    id: item.path,
+    name: item.name || item.path.split("/").slice(-2)[0],
+    path: item.path,
+    size: item.total_size,
+    is_dir: item.is_dir,
+    modified: item.modified,
+    file_count: item.file_count,
+    is_archive: item.is_archive,
+    // 目录先给空数组，展开时再加载
+    children: item.is_dir ? [] : undefined,
+  };
+}
 
-    // 然后按照modified时间排序（最新的排在前面）
-    if (a.modified && b.modified) {
-      return new Date(b.modified).getTime() - new Date(a.modified).getTime();
+// 替换目录 dir 的子节点
+function updateChildren(
+  nodes: FileNode[],
+  dir: string,
+  update: (children: FileNode[]) => FileNode[]
+): FileNode[] {
+  if (dir === ROOT) {
+    return update(nodes);
+  }
+  return nodes.map((node) => {
+    if (node.id === dir) {
+      return { ...node, children: update(node.children || []) };
+    }
+    if (node.children?.length && dir.startsWith(node.id)) {
+      return { ...node, children: updateChildren(node.children, dir, update) };
     }
-    // 如果没有modified时间，保持原有顺序
-    return 0;
+    return node;
   });
 }
 
+function formatSize(size: number) {
+  return size > 1024 * 1024
+    ? `${(size / (1024 * 1024)).toFixed(2)} MB`
+    : `${(size / 1024).toFixed(2)} KB`;
+}
+
 function FileTree({
-  data,
+  session,
   width,
   height,
+  onPreview,
+  onOpen,
+  onEnter,
+  onRename,
+  onDelete,
 }: {
-  data: any[];
+  session: string;
   width?: number | string;
   height?: number;
+  /** 点击文件时预览 */
+  onPreview?: (path: string) => void;
+  /** 双击文件时用系统默认程序打开 */
+  onOpen?: (path: string) => void;
+  /** 点击压缩包条目时进入 */
+  onEnter?: (path: string) => void;
+  /** 重命名条目，目录连同其中的条目 */
+  onRename?: (path: string) => void;
+  /** 删除条目，目录连同其中的条目 */
+  onDelete?: (path: string) => void;
 }) {
-  const treeData = transformData(data);
+  const [treeData, setTreeData] = useState<FileNode[]>([]);
+  // 已加载过的目录
+  const loaded = useRef(new Set<string>());
+
+  const loadPage = (dir: string, offset: number) => {
+    loaded.current.add(dir);
+    archiveListDir(session, dir, offset, PAGE_SIZE)
+      .then((page) => {
+        const items = page.items.map(toNode);
+        const next = offset + items.length;
+        if (next < page.total) {
+          items.push({
+            id: `${dir}\0more`,
+            name: `加载更多（剩余 ${page.total - next} 项）`,
+            path: dir,
+            size: 0,
+            is_dir: false,
+            more: { dir, offset: next },
+          });
+        }
+        setTreeData((nodes) =>
+          updateChildren(nodes, dir, (children) => [
+            ...(offset ? children.filter((n) => !n.more) : []),
+            ...items,
+          ])
+        );
+      })
+      .catch((err) => {
+        loaded.current.delete(dir);
+        console.log("err :", err);
+      });
+  };
+
+  useEffect(() => {
+    loaded.current = new Set();
+    setTreeData([]);
+    loadPage(ROOT, 0);
+  }, [session]);
+
+  const onToggle = (id: string) => {
+    if (!loaded.current.has(id)) {
+      loadPage(id, 0);
+    }
+  };
+
   return (
     <Tree
       data={treeData}
@@ -67,12 +149,29 @@ function FileTree({
       padding={32}
       rowHeight={32}
       openByDefault={false} // 添加这行，默认折叠所有节点
+      onToggle={onToggle}
     >
       {({ node, style, dragHandle }) => (
         <div
           style={style}
           ref={dragHandle}
-          onClick={() => !node.isLeaf && node.toggle()}
+          onClick={() => {
+            const more = node.data.more;
+            if (more) {
+              loadPage(more.dir, more.offset);
+            } else if (!node.isLeaf) {
+              node.toggle();
+            } else if (node.data.is_archive && onEnter) {
+              onEnter(node.data.path);
+            } else {
+              onPreview?.(node.data.path);
+            }
+          }}
+          onDoubleClick={() => {
+            if (node.isLeaf && !node.data.more) {
+              onOpen?.(node.data.path);
+            }
+          }}
           className={`flex gap-2 px-2 cursor-pointer justify-items-stretch items-center ${
             node.isSelected ? "bg-blue-100" : ""
           } ${!node.isLeaf ? "hover:bg-gray-100" : ""}`}
@@ -103,12 +202,23 @@ function FileTree({
             </span>
           </span>
           {node.data.is_dir && (
-            <span className="text-[12px] m-1 text-gray-500 ml-auto">
-              {node.data.modified?.replace(new Date().getFullYear() + "-", "")}
+            <span className="text-xs text-gray-500 ml-auto">
+              <span className="text-[12px] m-1 text-gray-500 ml-auto">
+                {node.data.modified?.replace(
+                  new Date().getFullYear() + "-",
+                  ""
+                )}
+              </span>
+              <span className="text-[12px] m-1 text-gray-500 ml-auto">
+                {node.data.file_count} 个文件
+              </span>
+              <span className="text-[12px] m-1 text-blue-500 ml-auto">
+                {formatSize(node.data.size)}
+              </span>
             </span>
           )}
 
-          {!node.data.is_dir && (
+          {!node.data.is_dir && !node.data.more && (
             <span className="text-xs text-gray-500 ml-auto">
               <span className="text-[12px] m-1 text-gray-500 ml-auto">
                 {node.data.modified?.replace(
@@ -117,20 +227,32 @@ function FileTree({
                 )}
               </span>
               <span className="text-[12px] m-1 text-blue-500 ml-auto">
-                {node.data.size > 1024 * 1024
-                  ? `${(node.data.size / (1024 * 1024)).toFixed(2)} MB`
-                  : `${(node.data.size / 1024).toFixed(2)} KB`}
+                {formatSize(node.data.size)}
               </span>
             </span>
           )}
-          {/* <Dropdown>
-            <DropdownTrigger>
-              <Chip size="sm">...</Chip>
-            </DropdownTrigger>
-            <DropdownMenu aria-label="Static Actions">
-              <DropdownItem key="new">解压</DropdownItem>
-            </DropdownMenu>
-          </Dropdown> */}
+          {!node.data.more && (onRename || onDelete) && (
+            <Dropdown>
+              <DropdownTrigger>
+                <Chip size="sm" onClick={(e) => e.stopPropagation()}>
+                  ...
+                </Chip>
+              </DropdownTrigger>
+              <DropdownMenu
+                aria-label="条目操作"
+                onAction={(key) =>
+                  key === "rename"
+                    ? onRename?.(node.data.path)
+                    : onDelete?.(node.data.path)
+                }
+              >
+                <DropdownItem key="rename">重命名</DropdownItem>
+                <DropdownItem key="delete" className="text-danger">
+                  删除
+                </DropdownItem>
+              </DropdownMenu>
+            </Dropdown>
+          )}
         </div>
       )}
     </Tree>
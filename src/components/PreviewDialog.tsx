@@ -0,0 +1,100 @@
+import { useEffect, useState } from "react";
+import {
+  Button,
+  Modal,
+  ModalContent,
+  ModalHeader,
+  ModalBody,
+  ModalFooter,
+} from "@heroui/react";
+import { archiveSessionReadEntry, EntryPreview } from "../commands";
+
+interface PreviewDialogProps {
+  session: string;
+  /** 要预览的条目，为空时关闭 */
+  entry?: string;
+  onClose: () => void;
+}
+
+const PreviewDialog = ({ session, entry, onClose }: PreviewDialogProps) => {
+  const [preview, setPreview] = useState<EntryPreview>();
+  const [error, setError] = useState("");
+  const [loading, setLoading] = useState(false);
+
+  const load = (offset: number) => {
+    if (!entry) return;
+    setLoading(true);
+    archiveSessionReadEntry(session, entry, { offset })
+      .then((next) => {
+        // 文本继续加载时拼接到已有内容之后
+        setPreview((prev) =>
+          offset && prev?.text != null && next.text != null
+            ? {
+                ...next,
+                offset: prev.offset,
+                length: prev.length + next.length,
+                text: prev.text + next.text,
+              }
+            : next
+        );
+      })
+      .catch((err) => {
+        setError(err.MsgError ?? String(err));
+      })
+      .finally(() => setLoading(false));
+  };
+
+  useEffect(() => {
+    setPreview(undefined);
+    setError("");
+    load(0);
+  }, [session, entry]);
+
+  return (
+    <Modal
+      isOpen={!!entry}
+      onClose={onClose}
+      size="4xl"
+      scrollBehavior="inside"
+    >
+      <ModalContent>
+        <ModalHeader className="break-all">{entry}</ModalHeader>
+        <ModalBody>
+          {error && <div className="text-danger">{error}</div>}
+          {preview?.text != null && (
+            <pre className="text-xs whitespace-pre-wrap break-all">
+              {preview.text}
+            </pre>
+          )}
+          {preview?.data != null &&
+            (preview.mime.startsWith("image/") && !preview.truncated ? (
+              <img
+                className="max-w-full"
+                src={`data:${preview.mime};base64,${preview.data}`}
+              />
+            ) : (
+              <div className="text-gray-500">
+                无法预览的文件类型：{preview.mime}
+              </div>
+            ))}
+        </ModalBody>
+        <ModalFooter>
+          {preview?.text != null && preview.truncated && (
+            <Button
+              variant="light"
+              isLoading={loading}
+              onPress={() => load(preview.offset + preview.length)}
+            >
+              加载更多
+            </Button>
+          )}
+          <Button color="primary" onPress={onClose}>
+            关闭
+          </Button>
+        </ModalFooter>
+      </ModalContent>
+    </Modal>
+  );
+};
+
+export default PreviewDialog;
@@ -0,0 +1,48 @@
+import {
+  Button,
+  Modal,
+  ModalContent,
+  ModalHeader,
+  ModalBody,
+  ModalFooter,
+} from "@heroui/react";
+import { EntryModified } from "../commands";
+
+interface WriteBackDialogProps {
+  /** 被修改的条目，为空时关闭 */
+  modified?: EntryModified;
+  onClose: () => void;
+  onConfirm: (modified: EntryModified) => void;
+}
+
+const WriteBackDialog = ({
+  modified,
+  onClose,
+  onConfirm,
+}: WriteBackDialogProps) => {
+  return (
+    <Modal isOpen={!!modified} onClose={onClose}>
+      <ModalContent>
+        <ModalHeader>文件已修改</ModalHeader>
+        <ModalBody>
+          <span className="break-all">
+            {modified?.entry} 已被修改，是否写回压缩包？
+          </span>
+        </ModalBody>
+        <ModalFooter>
+          <Button color="danger" variant="light" onPress={onClose}>
+            忽略
+          </Button>
+          <Button
+            color="primary"
+            onPress={() => modified && onConfirm(modified)}
+          >
+            写回
+          </Button>
+        </ModalFooter>
+      </ModalContent>
+    </Modal>
+  );
+};
+
+export default WriteBackDialog;